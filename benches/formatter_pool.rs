@@ -0,0 +1,65 @@
+// Copyright (c) 2025 Kirky.X
+//
+// Licensed under the MIT License
+// See LICENSE file in the project root for full license information.
+
+//! Persistent formatter benchmarks
+//! Compares spawning prettier per file with keeping it running in the pool
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use std::sync::Arc;
+use tempfile::TempDir;
+use zenith::config::types::AppConfig;
+use zenith::core::traits::Zenith;
+use zenith::internal::{BackupService, HashCache, PrettierZenith, ZenithRegistry, ZenithService};
+
+/// Check 500 JS files; skipped when prettier is not installed.
+fn bench_prettier_500_js_files(c: &mut Criterion) {
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    if !rt.block_on(PrettierZenith.is_available()) {
+        return;
+    }
+
+    let temp_dir = TempDir::new().unwrap();
+    for i in 0..500 {
+        std::fs::write(
+            temp_dir.path().join(format!("file_{}.js", i)),
+            format!(
+                "const  value{} = {{a:1,b:[1,2,3]}}\nfunction f(){{return value{}}}\n",
+                i, i
+            ),
+        )
+        .unwrap();
+    }
+
+    let registry = Arc::new(ZenithRegistry::new());
+    registry.register(Arc::new(PrettierZenith));
+    let paths = vec![temp_dir.path().to_string_lossy().into_owned()];
+
+    let mut group = c.benchmark_group("prettier_500_js_files");
+    group.sample_size(10);
+
+    for (id, parallel_formatters) in [("per_file", false), ("pooled", true)] {
+        let mut config = AppConfig::default();
+        config.global.cache_enabled = false;
+        config.global.parallel_formatters = parallel_formatters;
+        group.bench_function(id, |b| {
+            b.iter(|| {
+                let service = ZenithService::new(
+                    config.clone(),
+                    registry.clone(),
+                    Arc::new(BackupService::new(config.backup.clone(), None)),
+                    Arc::new(HashCache::new()),
+                    true,
+                );
+                rt.block_on(service.format_paths(black_box(paths.clone())))
+                    .unwrap()
+            });
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_prettier_500_js_files);
+criterion_main!(benches);
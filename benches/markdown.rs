@@ -0,0 +1,55 @@
+// Copyright (c) 2025 Kirky.X
+//
+// Licensed under the MIT License
+// See LICENSE file in the project root for full license information.
+
+//! Markdown code block benchmarks
+//! Compares one rustfmt process per block with the batched invocation
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use zenith::internal::MarkdownZenith;
+
+/// A large document: mostly valid items, with the occasional bare statement
+/// that rustfmt rejects on its own.
+const LARGE_DOCUMENT: &str = include_str!("../tests/fixtures/markdown/large.md");
+
+/// Bodies of the document's ```rust blocks
+fn rust_blocks(document: &str) -> Vec<String> {
+    let mut blocks = Vec::new();
+    let mut current: Option<String> = None;
+    for line in document.lines() {
+        match current.as_mut() {
+            None if line.trim() == "```rust" => current = Some(String::new()),
+            Some(_) if line.trim() == "```" => blocks.extend(current.take()),
+            Some(block) => {
+                block.push_str(line);
+                block.push('\n');
+            }
+            None => {}
+        }
+    }
+    blocks
+}
+
+fn bench_markdown_rust_blocks(c: &mut Criterion) {
+    let blocks = rust_blocks(LARGE_DOCUMENT);
+    let mut group = c.benchmark_group("markdown_rust_blocks");
+    group.sample_size(10);
+
+    group.bench_function("per_block", |b| {
+        b.iter(|| {
+            for block in &blocks {
+                MarkdownZenith::format_rust_snippets(black_box(std::slice::from_ref(block)));
+            }
+        });
+    });
+
+    group.bench_function("batched", |b| {
+        b.iter(|| MarkdownZenith::format_rust_snippets(black_box(&blocks)));
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_markdown_rust_blocks);
+criterion_main!(benches);
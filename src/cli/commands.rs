@@ -6,8 +6,9 @@
 //! 命令行命令定义模块。
 //! 使用 `clap` 库定义程序的子命令及其参数。
 
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
 
 /// Zenith 命令行主结构体。
 #[derive(Parser)]
@@ -21,9 +22,45 @@ pub struct Cli {
     #[arg(short, long, env = "ZENITH_CONFIG")]
     pub config: Option<PathBuf>,
 
+    /// 启用配置文件中 `[profiles.<name>]` 定义的配置方案。可以通过环境变量 `ZENITH_PROFILE` 设置。
+    #[arg(long, env = "ZENITH_PROFILE", global = true)]
+    pub profile: Option<String>,
+
     /// 日志级别（debug, info, warn, error）。默认为 `info`。
     #[arg(short = 'L', long, env = "ZENITH_LOG_LEVEL", default_value = "info")]
     pub log_level: String,
+
+    /// 日志格式：`text` 为可读文本，`json` 为每行一个 JSON 对象，便于日志采集。
+    #[arg(long, env = "ZENITH_LOG_FORMAT", value_enum, default_value_t = LogFormat::Text)]
+    pub log_format: LogFormat,
+
+    /// 将日志写入该文件（追加），而不是标准错误输出。
+    #[arg(long, env = "ZENITH_LOG_FILE")]
+    pub log_file: Option<PathBuf>,
+
+    /// 安静模式：不输出执行摘要和逐个文件的信息，只输出错误。
+    #[arg(short, long, global = true)]
+    pub quiet: bool,
+
+    /// 禁用彩色输出。设置了 `NO_COLOR` 环境变量或标准输出不是终端时也会自动禁用。
+    #[arg(long, global = true)]
+    pub no_color: bool,
+}
+
+/// 日志输出格式。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    /// 可读文本。
+    Text,
+    /// 每行一个 JSON 对象。
+    Json,
+}
+
+/// 检查模式结果的 CI 注解格式。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum AnnotationFormat {
+    /// GitHub Actions 工作流命令（`::error file=...::`）。
+    Github,
 }
 
 /// 支持的子命令列表。
@@ -43,8 +80,17 @@ pub enum Commands {
         #[arg(long)]
         no_backup: bool,
 
-        /// 并发工作线程数。
-        #[arg(short, long)]
+        /// 备份目录（覆盖配置中的 `backup.dir`）。
+        #[arg(long, value_name = "PATH")]
+        backup_dir: Option<PathBuf>,
+
+        /// 追加到本次备份会话 ID 后的标签，如 `backup_20250101_120000_pre-refactor`，
+        /// 便于之后在 `list-backups` 中查找。
+        #[arg(long, value_name = "NAME")]
+        backup_label: Option<String>,
+
+        /// 并发工作线程数；`auto` 根据待处理文件所用格式化工具的类型选择。
+        #[arg(short, long, value_name = "N|auto", value_parser = crate::config::types::parse_workers)]
         workers: Option<usize>,
 
         /// 运行在检查模式（dry-run），不修改文件内容。
@@ -54,8 +100,127 @@ pub enum Commands {
         /// 启用文件监听模式，监控文件变化并自动格式化。
         #[arg(long)]
         watch: bool,
+
+        /// 仅规范化空白字符（行尾空白、文件末尾换行），不调用语言格式化工具。
+        #[arg(long)]
+        whitespace_only: bool,
+
+        /// 严格模式：显式指定的文件没有匹配的格式化工具时视为失败并以非零状态码退出。
+        #[arg(long)]
+        strict: bool,
+
+        /// 在执行摘要后列出没有格式化工具的文件类型及数量
+        #[arg(long)]
+        report_unsupported: bool,
+
+        /// 在执行摘要后输出耗时统计 (平均值、p95/p99、标准差等)。
+        #[arg(long)]
+        metrics: bool,
+
+        /// 在执行摘要后按格式化工具分组统计文件数、修改数和失败数。
+        #[arg(long)]
+        by_formatter: bool,
+
+        /// 若已安装的工具版本与 `zenith.lock` 不一致，则直接失败而非仅警告。
+        #[arg(long)]
+        locked: bool,
+
+        /// 任一文件出现格式化工具或 IO 错误时立即停止处理剩余文件，并以状态码 2 退出。
+        /// 检查模式下需要格式化的文件不算错误。
+        #[arg(long)]
+        fail_fast: bool,
+
+        /// 让支持的格式化工具（目前为 prettier）以常驻进程运行并在文件间复用，
+        /// 不支持的工具仍逐个文件启动。
+        #[arg(long)]
+        parallel_formatters: bool,
+
+        /// 遍历目录时包含隐藏文件和目录（如 `.github/workflows`）。
+        #[arg(long)]
+        include_hidden: bool,
+
+        /// 遍历目录的最大深度，1 表示只处理目录下的直接子文件（覆盖配置中的 `global.max_depth`）。
+        #[arg(long, value_name = "N")]
+        max_depth: Option<usize>,
+
+        /// 对格式化后的内容再格式化一次，若第二次仍有改动则发出警告，用于发现不收敛的格式化工具。
+        /// 只复查发生改动的文件。
+        #[arg(long)]
+        verify_idempotent: bool,
+
+        /// 遍历目录时只处理指定格式化工具（如 `rust`、`prettier`）负责的扩展名，可重复指定。
+        /// 显式传入的文件不受影响。
+        #[arg(long = "lang", value_name = "NAME")]
+        languages: Vec<String>,
+
+        /// 格式化后对写入了改动的文件执行 `git add`，用于 pre-commit 钩子。
+        /// 不在 git 仓库中时仅给出警告；不能与 `--check` 或 `--watch` 同时使用。
+        #[arg(long, conflicts_with_all = ["check", "watch"])]
+        stage: bool,
+
+        /// 只格式化指定的行范围 `起始行:结束行`（从 1 开始，包含两端），用于格式化编辑器中的选区。
+        /// 目前 rust 和 clang-format 支持范围，其他格式化工具仍格式化整个文件。
+        #[arg(long, value_name = "START:END", value_parser = crate::utils::range::parse_line_range)]
+        lines: Option<(usize, usize)>,
+
+        /// 检查模式下为每个需要格式化的文件输出 CI 注解，使其显示在 PR 中。
+        /// 在 GitHub Actions 中运行（`GITHUB_ACTIONS=true`）时自动启用 `github`。
+        #[arg(long, value_enum, value_name = "FORMAT", requires = "check")]
+        annotate: Option<AnnotationFormat>,
+
+        /// 将格式化结果写入该目录（按相对当前目录的路径保持目录结构），不修改原文件，也不创建备份。
+        #[arg(long, value_name = "DIR", conflicts_with_all = ["check", "watch", "stage"])]
+        out_dir: Option<PathBuf>,
+
+        /// 限定格式化的总耗时（如 `90s`、`5m`、`1h`，不带单位时为秒）。超时后不再开始新文件，
+        /// 正在处理的文件照常完成，其余文件报告为未处理（`Skipped: time budget exceeded`）。
+        #[arg(long, value_name = "DURATION", value_parser = parse_duration, conflicts_with = "watch")]
+        time_budget: Option<Duration>,
     },
 
+    /// 检查归档文件（.zip/.tar/.tar.gz）中的文件格式，不解压到磁盘。
+    /// 有文件处理失败，或未指定 `--output` 且有文件需要格式化时，以非零状态码退出。
+    Archive {
+        /// 要检查的归档文件。
+        archive: PathBuf,
+
+        /// 将格式化后的内容写入新的归档文件，格式由扩展名决定。
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// 在当前目录生成带注释的默认配置文件 `zenith.toml`。
+    Init {
+        /// 覆盖已存在的 `zenith.toml`。
+        #[arg(long)]
+        force: bool,
+
+        /// 同时创建配置目录及其中的 `plugins` 子目录（默认为 `.zenith/plugins`）。
+        #[arg(long)]
+        config_dir: bool,
+    },
+
+    /// 输出配置文件的 JSON Schema，可供编辑器补全和校验 `zenith.toml`。
+    ConfigSchema,
+
+    /// 以 JSON 输出合并默认值、配置文件、环境变量和项目级配置后实际生效的配置。
+    /// MCP API 密钥会被隐去。
+    ConfigShow {
+        /// 从该目录向上查找项目级配置（默认为当前目录）。
+        #[arg(default_value = ".")]
+        dir: PathBuf,
+    },
+
+    /// 显示配置目录中持久化文件缓存的统计信息。
+    CacheStats {
+        /// 以 JSON 格式输出。
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// 删除持久化的文件缓存，之后所有文件都会被重新处理。
+    CacheClear,
+
     /// 检查系统环境。
     Doctor {
         /// 是否输出详细信息。
@@ -63,13 +228,26 @@ pub enum Commands {
         verbose: bool,
     },
 
+    /// 生成 `zenith.lock`，记录当前各格式化工具的版本。
+    Lock,
+
     /// 列出所有可用的备份。
     ListBackups,
 
     /// 从备份中恢复文件。
     Recover {
-        /// 要恢复的备份 ID。
-        backup_id: String,
+        /// 要恢复的备份 ID。使用 `--since`/`--until` 时省略。
+        #[arg(required_unless_present_any = ["since", "until"], conflicts_with_all = ["since", "until"])]
+        backup_id: Option<String>,
+
+        /// 批量恢复：该时间之后创建的所有备份会话（RFC 3339、`YYYY-MM-DD HH:MM:SS` 或 `YYYY-MM-DD`，本地时间）。
+        /// 同一文件取时间窗口内最新会话中的版本。
+        #[arg(long, value_parser = parse_time)]
+        since: Option<SystemTime>,
+
+        /// 批量恢复：该时间之前创建的所有备份会话，格式同 `--since`（默认为现在）。
+        #[arg(long, value_parser = parse_time)]
+        until: Option<SystemTime>,
 
         /// 恢复的目标目录（默认为当前目录）。
         #[arg(short, long)]
@@ -81,6 +259,10 @@ pub enum Commands {
         /// 备份保留天数。
         #[arg(short, long, default_value = "7")]
         days: u32,
+
+        /// 按天数清理后最多保留的备份会话数（覆盖配置中的 `backup.max_sessions`）。
+        #[arg(long, value_parser = parse_max_sessions)]
+        max_sessions: Option<usize>,
     },
 
     /// 启动 MCP (Model Context Protocol) 服务。
@@ -93,3 +275,63 @@ pub enum Commands {
     /// 自动回滚到最新的备份。
     AutoRollback,
 }
+
+/// 解析 `--max-sessions`：须为正整数，与配置中的 `backup.max_sessions` 一致。
+fn parse_max_sessions(value: &str) -> Result<usize, String> {
+    match value.trim().parse::<usize>() {
+        Ok(0) => Err("须为正整数，不限制时请省略".to_string()),
+        Ok(sessions) => Ok(sessions),
+        Err(e) => Err(format!("无法解析会话数 '{}'：{}", value, e)),
+    }
+}
+
+/// 解析命令行中的时长：数字加可选单位 `ms`、`s`、`m`、`h`，不带单位时为秒。
+fn parse_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let invalid = || {
+        format!(
+            "无法解析时长 '{}'，请使用如 500ms、90s、5m、1h 的格式",
+            value
+        )
+    };
+    let number: u64 = number.parse().map_err(|_| invalid())?;
+    let duration = match unit.trim() {
+        "ms" => Duration::from_millis(number),
+        "" | "s" => Duration::from_secs(number),
+        "m" => Duration::from_secs(number.saturating_mul(60)),
+        "h" => Duration::from_secs(number.saturating_mul(3600)),
+        _ => return Err(invalid()),
+    };
+    if duration.is_zero() {
+        return Err("时长必须大于 0".to_string());
+    }
+    Ok(duration)
+}
+
+/// 解析命令行中的时间点：RFC 3339，或本地时间 `YYYY-MM-DD HH:MM:SS` / `YYYY-MM-DD`。
+fn parse_time(value: &str) -> Result<SystemTime, String> {
+    use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime};
+
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Ok(time.into());
+    }
+    let naive = NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S")
+        .or_else(|_| {
+            NaiveDate::parse_from_str(value, "%Y-%m-%d").map(|date| date.and_time(NaiveTime::MIN))
+        })
+        .map_err(|_| {
+            format!(
+                "无法解析时间 '{}'，请使用 RFC 3339 或 YYYY-MM-DD[ HH:MM:SS]",
+                value
+            )
+        })?;
+    naive
+        .and_local_timezone(Local)
+        .earliest()
+        .map(Into::into)
+        .ok_or_else(|| format!("本地时间 '{}' 不存在", value))
+}
@@ -6,15 +6,21 @@
 //! 配置缓存模块。
 //! 用于缓存项目级的配置，以避免频繁的文件系统查找。
 
-use crate::config::{load_config_with_project_discovery, types::AppConfig};
+use crate::config::discovery::{discover_project_config, DEFAULT_PROJECT_MARKERS};
+use crate::config::{add_with_profile, environment, types::AppConfig};
 use crate::error::{Result, ZenithError};
+use config::{Config, File};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 
 /// 项目级配置缓存。
+///
+/// 缓存的是与应用级配置合并后的结果，因此同一个缓存应始终配合同一份应用级配置使用。
 pub struct ConfigCache {
-    /// 缓存映射：目录路径 -> 该目录对应的项目配置。
+    /// 缓存映射：项目目录路径 -> 合并了该目录项目配置的完整配置。
     cache: HashMap<PathBuf, AppConfig>,
+    /// 内置列表之外的项目根目录标识文件。
+    project_markers: Vec<String>,
 }
 
 impl ConfigCache {
@@ -22,9 +28,17 @@ impl ConfigCache {
     pub fn new() -> Self {
         Self {
             cache: HashMap::new(),
+            project_markers: Vec::new(),
         }
     }
 
+    /// 在内置列表之外，把 `markers` 也视为项目根目录标识，
+    /// 并让项目级配置的查找止于含有它们的目录。
+    pub fn with_project_markers(mut self, markers: Vec<String>) -> Self {
+        self.project_markers = markers;
+        self
+    }
+
     /// 获取指定文件路径的配置，如果需要则执行项目级自动发现。
     ///
     /// # 参数
@@ -38,85 +52,52 @@ impl ConfigCache {
     ) -> Result<AppConfig> {
         // 通过查找项目配置文件来确定该文件所属的项目目录
         let project_dir = self.find_project_directory(file_path)?;
-
-        // 检查是否已有缓存
-        if let Some(cached_config) = self.cache.get(&project_dir) {
-            // 与应用级配置合并，确保应用级设置得以保留
-            return Ok(self.merge_configs(app_config, cached_config));
+        if let Some(config) = self.cache.get(&project_dir) {
+            return Ok(config.clone());
         }
 
-        // 执行项目级配置自动发现并加载
-        let project_config = load_config_with_project_discovery(None, Some(file_path))?;
-
-        // 存入缓存
-        self.cache.insert(project_dir, project_config.clone());
-
-        // 与应用级配置合并
-        Ok(self.merge_configs(app_config, &project_config))
+        // 没有缓存时执行项目级配置自动发现，加载后与应用级配置合并
+        let config = match discover_project_config(file_path, &self.project_markers)? {
+            Some(path) => {
+                let project_config = Config::builder()
+                    .add_source(File::from(path).required(false))
+                    .build()
+                    .map_err(|e| ZenithError::Config(e.to_string()))?;
+                self.merge_configs(app_config, &project_config)?
+            }
+            None => app_config.clone(),
+        };
+        self.cache.insert(project_dir, config.clone());
+        Ok(config)
     }
 
-    /// 将应用级配置与项目级配置合并（项目级配置优先级更高）。
-    fn merge_configs(&self, app_config: &AppConfig, project_config: &AppConfig) -> AppConfig {
-        // Create a new config with app-level settings as base and project settings overriding them
-        AppConfig {
-            global: if project_config.global.log_level != app_config.global.log_level
-                || project_config.global.backup_enabled != app_config.global.backup_enabled
-                || project_config.global.recursive != app_config.global.recursive
-                || project_config.global.cache_enabled != app_config.global.cache_enabled
-                || project_config.global.config_dir != app_config.global.config_dir
-            {
-                project_config.global.clone()
-            } else {
-                app_config.global.clone()
-            },
-            zeniths: if !project_config.zeniths.is_empty() {
-                // If project has specific zenith settings, use them; otherwise use app settings
-                project_config.zeniths.clone()
-            } else {
-                app_config.zeniths.clone()
-            },
-            backup: if project_config.backup.dir != app_config.backup.dir
-                || project_config.backup.retention_days != app_config.backup.retention_days
-            {
-                project_config.backup.clone()
-            } else {
-                app_config.backup.clone()
-            },
-            concurrency: if project_config.concurrency.workers != app_config.concurrency.workers
-                || project_config.concurrency.batch_size != app_config.concurrency.batch_size
-            {
-                project_config.concurrency.clone()
-            } else {
-                app_config.concurrency.clone()
-            },
-            limits: if project_config.limits.max_file_size_mb != app_config.limits.max_file_size_mb
-                || project_config.limits.max_memory_mb != app_config.limits.max_memory_mb
-            {
-                project_config.limits.clone()
-            } else {
-                app_config.limits.clone()
-            },
-            mcp: if project_config.mcp.enabled != app_config.mcp.enabled
-                || project_config.mcp.host != app_config.mcp.host
-                || project_config.mcp.port != app_config.mcp.port
-                || project_config.mcp.auth_enabled != app_config.mcp.auth_enabled
-                || project_config.mcp.users.len() != app_config.mcp.users.len()
-            {
-                project_config.mcp.clone()
-            } else {
-                app_config.mcp.clone()
-            },
-            security: if !project_config.security.allowed_plugin_commands.is_empty()
-                || project_config.security.allow_absolute_paths
-                    != app_config.security.allow_absolute_paths
-                || project_config.security.allow_relative_paths
-                    != app_config.security.allow_relative_paths
-            {
-                project_config.security.clone()
-            } else {
-                app_config.security.clone()
-            },
-        }
+    /// 将项目级配置逐项叠加到应用级配置之上。
+    ///
+    /// 只有项目配置文件中实际写出的配置项（包括嵌套表中的单个字段）覆盖应用级配置，
+    /// 其余沿用应用级的值。项目配置中的配置方案随后叠加，环境变量的优先级仍然最高。
+    fn merge_configs(&self, app_config: &AppConfig, project_config: &Config) -> Result<AppConfig> {
+        let config_error = |e: config::ConfigError| ZenithError::Config(e.to_string());
+        let base = Config::try_from(app_config).map_err(config_error)?;
+        let profile = app_config
+            .profile
+            .clone()
+            .or_else(|| project_config.get_string("profile").ok());
+
+        let builder = add_with_profile(
+            Config::builder().add_source(base),
+            project_config.clone(),
+            profile.as_deref(),
+            false,
+        )?;
+        let mut config: AppConfig = builder
+            .add_source(environment())
+            .build()
+            .map_err(config_error)?
+            .try_deserialize()
+            .map_err(config_error)?;
+        config.profile = app_config.profile.clone();
+        config.validate()?;
+        Ok(config)
     }
 
     /// Find the project directory for a given file by looking for configuration files
@@ -126,37 +107,12 @@ impl ConfigCache {
             .ok_or_else(|| ZenithError::Config("Invalid file path".to_string()))?
             .to_path_buf();
 
-        // Common project markers to identify project boundaries
-        let project_markers = [
-            ".git",
-            "Cargo.toml",
-            "package.json",
-            "pom.xml",
-            "build.gradle",
-            "CMakeLists.txt",
-            "Makefile",
-            ".svn",
-            ".hg",
-            ".project",
-            ".vscode",
-            ".idea",
-            "requirements.txt",
-            "setup.py",
-            "pyproject.toml",
-            "Gemfile",
-            "composer.json",
-            "mix.exs",
-            "build.sbt",
-            "go.mod",
-            ".zenith.toml",
-            "zenith.toml",
-            ".prettierrc",
-            ".eslintrc",
-            ".stylelintrc",
-            ".clang-format",
-            ".rustfmt.toml",
-            ".editorconfig",
-        ];
+        // Common project markers to identify project boundaries, plus the configured ones
+        let project_markers: Vec<&str> = DEFAULT_PROJECT_MARKERS
+            .iter()
+            .copied()
+            .chain(self.project_markers.iter().map(String::as_str))
+            .collect();
 
         // Traverse up the directory tree looking for project markers
         loop {
@@ -192,6 +148,7 @@ impl Default for ConfigCache {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::types::{LineEnding, ZenithSettings};
     use std::fs;
     use tempfile::TempDir;
 
@@ -208,6 +165,80 @@ mod tests {
         assert_eq!(config.global.log_level, "info"); // Default value
     }
 
+    /// A project directory holding `zenith.toml` with `content`, and a file in it
+    fn project_with_config(content: &str) -> (TempDir, PathBuf) {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("zenith.toml"), content).unwrap();
+        let test_file = temp_dir.path().join("main.rs");
+        fs::write(&test_file, "fn main() {}").unwrap();
+        (temp_dir, test_file)
+    }
+
+    #[test]
+    fn test_partial_project_override_keeps_app_fields() {
+        let (_temp_dir, test_file) = project_with_config(
+            "[global]\nbackup_enabled = false\n\n[limits]\nmax_file_size_mb = 5\n",
+        );
+        let mut app_config = AppConfig::default();
+        app_config.global.log_level = "debug".to_string();
+        app_config.global.line_ending = LineEnding::Crlf;
+        app_config.limits.max_memory_mb = 64;
+        app_config.backup.retention_days = 30;
+
+        let config = ConfigCache::new()
+            .get_config_for_file(&app_config, &test_file)
+            .unwrap();
+
+        assert!(!config.global.backup_enabled);
+        assert_eq!(config.global.log_level, "debug");
+        assert_eq!(config.global.line_ending, LineEnding::Crlf);
+        assert_eq!(config.limits.max_file_size_mb, 5);
+        assert_eq!(config.limits.max_memory_mb, 64);
+        assert_eq!(config.backup.retention_days, 30);
+    }
+
+    #[test]
+    fn test_project_override_merges_nested_tables() {
+        let (_temp_dir, test_file) =
+            project_with_config("[zeniths.rust]\nargs = [\"--edition\", \"2021\"]\n");
+        let mut app_config = AppConfig::default();
+        app_config.zeniths.insert(
+            "rust".to_string(),
+            ZenithSettings {
+                config_path: Some("rustfmt.toml".to_string()),
+                ..Default::default()
+            },
+        );
+        app_config
+            .zeniths
+            .insert("python".to_string(), ZenithSettings::default());
+
+        let config = ConfigCache::new()
+            .get_config_for_file(&app_config, &test_file)
+            .unwrap();
+
+        let rust = &config.zeniths["rust"];
+        assert_eq!(rust.args, ["--edition", "2021"]);
+        assert_eq!(rust.config_path.as_deref(), Some("rustfmt.toml"));
+        assert!(config.zeniths.contains_key("python"));
+    }
+
+    #[test]
+    fn test_explicit_project_value_wins_even_when_default() {
+        // Set explicitly to the default value, which the app config differs from
+        let (_temp_dir, test_file) = project_with_config("[global]\nlog_level = \"info\"\n");
+        let mut app_config = AppConfig::default();
+        app_config.global.log_level = "debug".to_string();
+        app_config.global.cache_enabled = false;
+
+        let config = ConfigCache::new()
+            .get_config_for_file(&app_config, &test_file)
+            .unwrap();
+
+        assert_eq!(config.global.log_level, "info");
+        assert!(!config.global.cache_enabled);
+    }
+
     #[test]
     fn test_find_project_directory() {
         let cache = ConfigCache::new();
@@ -226,6 +257,25 @@ mod tests {
         assert_eq!(project_dir, temp_dir.path());
     }
 
+    #[test]
+    fn test_find_project_directory_with_custom_marker() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join(".myteam"), "").unwrap();
+        let nested = temp_dir.path().join("services").join("api");
+        fs::create_dir_all(&nested).unwrap();
+        let test_file = nested.join("main.rs");
+        fs::write(&test_file, "fn main() {}").unwrap();
+
+        let project_dir = ConfigCache::new()
+            .find_project_directory(&test_file)
+            .unwrap();
+        assert_eq!(project_dir, nested);
+
+        let cache = ConfigCache::new().with_project_markers(vec![".myteam".to_string()]);
+        let project_dir = cache.find_project_directory(&test_file).unwrap();
+        assert_eq!(project_dir, temp_dir.path());
+    }
+
     #[test]
     fn test_find_project_directory_no_marker() {
         let cache = ConfigCache::new();
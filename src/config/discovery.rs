@@ -10,6 +10,38 @@ use crate::error::{Result, ZenithError};
 use crate::utils::directory::traverse_upwards;
 use std::path::{Path, PathBuf};
 
+/// 内置的项目根目录标识文件，`global.project_markers` 在此基础上追加。
+pub const DEFAULT_PROJECT_MARKERS: &[&str] = &[
+    ".git",
+    "Cargo.toml",
+    "package.json",
+    "pom.xml",
+    "build.gradle",
+    "CMakeLists.txt",
+    "Makefile",
+    ".svn",
+    ".hg",
+    ".project",
+    ".vscode",
+    ".idea",
+    "requirements.txt",
+    "setup.py",
+    "pyproject.toml",
+    "Gemfile",
+    "composer.json",
+    "mix.exs",
+    "build.sbt",
+    "go.mod",
+    ".zenith.toml",
+    "zenith.toml",
+    ".prettierrc",
+    ".eslintrc",
+    ".stylelintrc",
+    ".clang-format",
+    ".rustfmt.toml",
+    ".editorconfig",
+];
+
 /// 项目级配置文件的候选列表。
 const PROJECT_CONFIG_FILES: &[&str] = &[
     ".zenith.toml",
@@ -44,7 +76,8 @@ const PROJECT_CONFIG_FILES: &[&str] = &[
 fn get_formatter_config_files(formatter_name: &str) -> &'static [&'static str] {
     match formatter_name {
         "rust" => &[".rustfmt.toml", "rustfmt.toml"],
-        "javascript" | "typescript" | "json" | "html" | "css" | "less" | "scss" | "graphql" => &[
+        "prettier" | "javascript" | "typescript" | "json" | "html" | "css" | "less" | "scss"
+        | "graphql" => &[
             ".prettierrc",
             ".prettierrc.json",
             ".prettierrc.yaml",
@@ -52,13 +85,16 @@ fn get_formatter_config_files(formatter_name: &str) -> &'static [&'static str] {
             ".prettierrc.js",
         ],
         "python" => &[
+            "ruff.toml",
+            ".ruff.toml",
             ".black",
             "pyproject.toml",
             "setup.cfg",
             ".flake8",
             "pycodestyle.cfg",
         ],
-        "java" => &[".google-java-format", "google-java-format.properties"],
+        "java" | "google-java-format" => &[".google-java-format", "google-java-format.properties"],
+        "clang-format" => &[".clang-format", "_clang-format"],
         "c" | "cpp" | "c++" => &[
             ".clang-format",
             "_clang-format",
@@ -93,39 +129,52 @@ fn get_formatter_config_files(formatter_name: &str) -> &'static [&'static str] {
             ".prettierrc.yml",
             ".prettierrc.js",
         ],
-        "toml" => &[".taplo.toml", "taplo.toml"],
         _ => &[],
     }
 }
 
 /// 发现指定文件所属项目的配置。
 ///
+/// 自文件所在目录向上查找；遇到含有 `project_markers` 中任一文件的目录时，
+/// 视其为项目根目录，不再继续向上。
+///
 /// # 参数
 ///
 /// * `file_path` - 文件的路径。
+/// * `project_markers` - 用户配置的额外项目根目录标识文件。
 ///
 /// # 返回值
 ///
 /// 如果找到项目配置文件，返回其 `PathBuf`，否则返回 `None`。
-pub fn discover_project_config(file_path: &Path) -> Result<Option<PathBuf>> {
-    let start_dir = if file_path.is_dir() {
+pub fn discover_project_config(
+    file_path: &Path,
+    project_markers: &[String],
+) -> Result<Option<PathBuf>> {
+    // 目录需要先检查自身，文件从其所在目录开始
+    let start = if file_path.is_dir() {
         file_path
     } else {
-        file_path.parent().ok_or_else(|| {
-            ZenithError::Config(format!("无法获取文件 {} 的父目录", file_path.display()))
-        })?
+        file_path
+            .parent()
+            .ok_or_else(|| ZenithError::Config("Invalid file path".to_string()))?
     };
 
-    // 向上遍历目录查找配置文件
-    traverse_upwards(start_dir, |dir| {
-        for config_file in PROJECT_CONFIG_FILES {
-            let config_path = dir.join(config_file);
-            if config_path.exists() {
-                return Some(config_path);
-            }
+    for dir in start.ancestors() {
+        if let Some(config_path) = PROJECT_CONFIG_FILES
+            .iter()
+            .map(|config_file| dir.join(config_file))
+            .find(|config_path| config_path.exists())
+        {
+            return Ok(Some(config_path));
         }
-        None
-    })
+        if project_markers
+            .iter()
+            .any(|marker| dir.join(marker).exists())
+        {
+            break;
+        }
+    }
+    Ok(None)
 }
 
 /// 发现特定格式化工具的配置。
@@ -147,12 +196,8 @@ pub fn discover_formatter_config(
         return Ok(None);
     }
 
-    let start_dir = file_path.parent().ok_or_else(|| {
-        ZenithError::Config(format!("无法获取文件 {} 的父目录", file_path.display()))
-    })?;
-
-    // 向上遍历目录查找工具特定的配置文件
-    traverse_upwards(start_dir, |dir| {
+    // 向上遍历目录查找工具特定的配置文件（从文件所在目录开始）
+    traverse_upwards(file_path, |dir| {
         for config_file in config_files {
             let config_path = dir.join(config_file);
             if config_path.exists() {
@@ -163,6 +208,43 @@ pub fn discover_formatter_config(
     })
 }
 
+/// 从距离文件最近的 `Cargo.toml` 中检测 Rust edition。
+///
+/// 通过 `edition.workspace = true` 继承的 edition 会继续向上查找工作区根目录的
+/// `[workspace.package]`；未声明 edition 的包按 Cargo 的规则视为 2015。
+///
+/// # 返回值
+///
+/// 如果找到可确定 edition 的清单文件，返回 edition 字符串，否则返回 `None`。
+pub fn discover_rust_edition(file_path: &Path) -> Result<Option<String>> {
+    let mut edition = None;
+    // traverse_upwards 从起点的父目录开始，传入文件本身即可覆盖其所在目录
+    traverse_upwards(file_path, |dir| {
+        let manifest_path = dir.join("Cargo.toml");
+        let manifest: toml::Value = std::fs::read_to_string(&manifest_path).ok()?.parse().ok()?;
+        edition = manifest_edition(&manifest);
+        edition.as_ref().map(|_| manifest_path)
+    })?;
+    Ok(edition)
+}
+
+fn manifest_edition(manifest: &toml::Value) -> Option<String> {
+    if let Some(package) = manifest.get("package") {
+        match package.get("edition") {
+            Some(toml::Value::String(edition)) => return Some(edition.clone()),
+            // `edition.workspace = true`，由工作区根目录决定
+            Some(_) => {}
+            None => return Some("2015".into()),
+        }
+    }
+    manifest
+        .get("workspace")?
+        .get("package")?
+        .get("edition")?
+        .as_str()
+        .map(String::from)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -175,7 +257,7 @@ mod tests {
         let test_file = temp_dir.path().join("test.txt");
         fs::write(&test_file, "test content").unwrap();
 
-        let result = discover_project_config(&test_file).unwrap();
+        let result = discover_project_config(&test_file, &[]).unwrap();
         assert!(result.is_none());
     }
 
@@ -189,11 +271,36 @@ mod tests {
         fs::create_dir(temp_dir.path().join("subdir")).unwrap();
         fs::write(&test_file, "test content").unwrap();
 
-        let result = discover_project_config(&test_file).unwrap();
+        let result = discover_project_config(&test_file, &[]).unwrap();
         assert!(result.is_some());
         assert_eq!(result.unwrap(), config_file);
     }
 
+    #[test]
+    fn test_discover_project_config_stops_at_custom_marker() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("zenith.toml"), "").unwrap();
+        let project = temp_dir.path().join("team");
+        fs::create_dir_all(project.join("src")).unwrap();
+        fs::write(project.join(".myteam"), "").unwrap();
+        let test_file = project.join("src").join("main.rs");
+        fs::write(&test_file, "fn main() {}").unwrap();
+
+        // 没有自定义标识时会找到上层目录的配置
+        assert_eq!(
+            discover_project_config(&test_file, &[]).unwrap(),
+            Some(temp_dir.path().join("zenith.toml"))
+        );
+        let markers = vec![".myteam".to_string()];
+        assert_eq!(discover_project_config(&test_file, &markers).unwrap(), None);
+
+        fs::write(project.join(".zenith.toml"), "").unwrap();
+        assert_eq!(
+            discover_project_config(&test_file, &markers).unwrap(),
+            Some(project.join(".zenith.toml"))
+        );
+    }
+
     #[test]
     fn test_discover_formatter_config_rust() {
         let temp_dir = TempDir::new().unwrap();
@@ -209,6 +316,66 @@ mod tests {
         assert_eq!(result.unwrap(), config_file);
     }
 
+    #[test]
+    fn test_discover_rust_edition() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(
+            temp_dir.path().join("Cargo.toml"),
+            "[package]\nname = \"demo\"\nversion = \"0.1.0\"\nedition = \"2018\"\n",
+        )
+        .unwrap();
+        let test_file = temp_dir.path().join("src").join("main.rs");
+        fs::create_dir(temp_dir.path().join("src")).unwrap();
+        fs::write(&test_file, "fn main() {}").unwrap();
+
+        let edition = discover_rust_edition(&test_file).unwrap();
+        assert_eq!(edition.as_deref(), Some("2018"));
+    }
+
+    #[test]
+    fn test_discover_rust_edition_from_workspace() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(
+            temp_dir.path().join("Cargo.toml"),
+            "[workspace]\nmembers = [\"member\"]\n\n[workspace.package]\nedition = \"2024\"\n",
+        )
+        .unwrap();
+        let member = temp_dir.path().join("member");
+        fs::create_dir_all(member.join("src")).unwrap();
+        fs::write(
+            member.join("Cargo.toml"),
+            "[package]\nname = \"member\"\nedition.workspace = true\n",
+        )
+        .unwrap();
+        let test_file = member.join("src").join("lib.rs");
+        fs::write(&test_file, "").unwrap();
+
+        let edition = discover_rust_edition(&test_file).unwrap();
+        assert_eq!(edition.as_deref(), Some("2024"));
+    }
+
+    #[test]
+    fn test_discover_rust_edition_without_manifest() {
+        let temp_dir = TempDir::new().unwrap();
+        let test_file = temp_dir.path().join("main.rs");
+        fs::write(&test_file, "fn main() {}").unwrap();
+
+        // 临时目录之上可能存在其他清单，因此只校验调用成功
+        assert!(discover_rust_edition(&test_file).is_ok());
+    }
+
+    #[test]
+    fn test_discover_formatter_config_in_same_directory() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_file = temp_dir.path().join(".clang-format");
+        fs::write(&config_file, "BasedOnStyle: LLVM").unwrap();
+        let test_file = temp_dir.path().join("main.c");
+        fs::write(&test_file, "int main() {}").unwrap();
+
+        let result = discover_formatter_config(&test_file, "clang-format").unwrap();
+        assert_eq!(result, Some(config_file));
+    }
+
     #[test]
     fn test_discover_formatter_config_javascript() {
         let temp_dir = TempDir::new().unwrap();
@@ -0,0 +1,241 @@
+// Copyright (c) 2025 Kirky.X
+//
+// Licensed under the MIT License
+// See LICENSE file in the project root for full license information.
+
+//! 格式化工具版本锁定模块。
+//! 负责生成与校验 `zenith.lock` 文件，记录每个格式化工具解析到的版本，
+//! 以便在团队成员与 CI 之间获得可复现的格式化结果。
+
+use crate::error::{Result, ZenithError};
+use crate::utils::environment::ToolStatus;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+/// 锁文件的默认文件名。
+pub const LOCK_FILE_NAME: &str = "zenith.lock";
+
+/// 当前锁文件格式版本。
+const LOCK_FILE_VERSION: u32 = 1;
+
+/// `zenith.lock` 文件内容。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LockFile {
+    /// 锁文件格式版本。
+    pub version: u32,
+    /// 工具名称 -> 锁定的版本信息。
+    #[serde(default)]
+    pub tools: BTreeMap<String, String>,
+}
+
+/// 已安装工具与锁文件之间的差异。
+#[derive(Debug, Clone, PartialEq)]
+pub enum LockDrift {
+    /// 已安装版本与锁定版本不一致。
+    Changed {
+        tool: String,
+        locked: String,
+        installed: String,
+    },
+    /// 锁文件中记录的工具当前不可用。
+    Missing { tool: String, locked: String },
+    /// 当前可用的工具未记录在锁文件中。
+    Unlocked { tool: String, installed: String },
+}
+
+impl fmt::Display for LockDrift {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LockDrift::Changed {
+                tool,
+                locked,
+                installed,
+            } => write!(
+                f,
+                "{}: locked '{}', installed '{}'",
+                tool, locked, installed
+            ),
+            LockDrift::Missing { tool, locked } => {
+                write!(f, "{}: locked '{}', but not installed", tool, locked)
+            }
+            LockDrift::Unlocked { tool, installed } => {
+                write!(f, "{}: installed '{}', but not locked", tool, installed)
+            }
+        }
+    }
+}
+
+impl LockFile {
+    /// 根据环境检查结果生成锁文件，仅记录可用且能获取版本的工具。
+    pub fn from_statuses(statuses: &[ToolStatus]) -> Self {
+        let tools = statuses
+            .iter()
+            .filter(|status| status.available)
+            .filter_map(|status| {
+                status
+                    .version
+                    .as_ref()
+                    .map(|version| (status.name.clone(), version.clone()))
+            })
+            .collect();
+
+        Self {
+            version: LOCK_FILE_VERSION,
+            tools,
+        }
+    }
+
+    /// 从磁盘读取锁文件。
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        let lock: LockFile = toml::from_str(&content)?;
+
+        if lock.version != LOCK_FILE_VERSION {
+            return Err(ZenithError::Config(format!(
+                "Unsupported lock file version {} in {}",
+                lock.version,
+                path.display()
+            )));
+        }
+
+        Ok(lock)
+    }
+
+    /// 将锁文件写入磁盘。
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let content = toml::to_string_pretty(self)
+            .map_err(|e| ZenithError::Config(format!("Failed to serialize lock file: {}", e)))?;
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+
+    /// 将当前环境与锁文件进行比对，返回所有差异。
+    pub fn verify(&self, statuses: &[ToolStatus]) -> Vec<LockDrift> {
+        let mut drifts = Vec::new();
+
+        for (tool, locked) in &self.tools {
+            let installed = statuses
+                .iter()
+                .find(|status| &status.name == tool && status.available)
+                .and_then(|status| status.version.as_ref());
+
+            match installed {
+                Some(installed) if installed != locked => drifts.push(LockDrift::Changed {
+                    tool: tool.clone(),
+                    locked: locked.clone(),
+                    installed: installed.clone(),
+                }),
+                Some(_) => {}
+                None => drifts.push(LockDrift::Missing {
+                    tool: tool.clone(),
+                    locked: locked.clone(),
+                }),
+            }
+        }
+
+        for status in statuses.iter().filter(|status| status.available) {
+            if let Some(installed) = &status.version {
+                if !self.tools.contains_key(&status.name) {
+                    drifts.push(LockDrift::Unlocked {
+                        tool: status.name.clone(),
+                        installed: installed.clone(),
+                    });
+                }
+            }
+        }
+
+        drifts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn status(name: &str, version: Option<&str>) -> ToolStatus {
+        ToolStatus {
+            name: name.to_string(),
+            available: version.is_some(),
+            version: version.map(|v| v.to_string()),
+            min_version: None,
+            category: "Other".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_lock_generation_skips_missing_tools() {
+        let statuses = vec![
+            status("rustfmt", Some("rustfmt 1.7.0-stable")),
+            status("prettier", None),
+        ];
+
+        let lock = LockFile::from_statuses(&statuses);
+        assert_eq!(lock.version, LOCK_FILE_VERSION);
+        assert_eq!(lock.tools.len(), 1);
+        assert_eq!(
+            lock.tools.get("rustfmt").map(String::as_str),
+            Some("rustfmt 1.7.0-stable")
+        );
+    }
+
+    #[test]
+    fn test_lock_save_and_load_roundtrip() {
+        let temp_dir = TempDir::new().unwrap();
+        let lock_path = temp_dir.path().join(LOCK_FILE_NAME);
+
+        let lock = LockFile::from_statuses(&[status("rustfmt", Some("rustfmt 1.7.0"))]);
+        lock.save(&lock_path).unwrap();
+
+        let loaded = LockFile::load(&lock_path).unwrap();
+        assert_eq!(loaded, lock);
+    }
+
+    #[test]
+    fn test_lock_verify_no_drift() {
+        let statuses = vec![status("rustfmt", Some("rustfmt 1.7.0"))];
+        let lock = LockFile::from_statuses(&statuses);
+        assert!(lock.verify(&statuses).is_empty());
+    }
+
+    #[test]
+    fn test_lock_verify_detects_drift() {
+        let lock = LockFile::from_statuses(&[
+            status("rustfmt", Some("rustfmt 1.7.0")),
+            status("shfmt", Some("v3.8.0")),
+        ]);
+
+        let current = vec![
+            status("rustfmt", Some("rustfmt 1.8.0")),
+            status("shfmt", None),
+            status("prettier", Some("3.3.3")),
+        ];
+
+        let drifts = lock.verify(&current);
+        assert_eq!(drifts.len(), 3);
+        assert!(drifts.contains(&LockDrift::Changed {
+            tool: "rustfmt".into(),
+            locked: "rustfmt 1.7.0".into(),
+            installed: "rustfmt 1.8.0".into(),
+        }));
+        assert!(drifts.contains(&LockDrift::Missing {
+            tool: "shfmt".into(),
+            locked: "v3.8.0".into(),
+        }));
+        assert!(drifts.contains(&LockDrift::Unlocked {
+            tool: "prettier".into(),
+            installed: "3.3.3".into(),
+        }));
+    }
+
+    #[test]
+    fn test_lock_load_rejects_unknown_version() {
+        let temp_dir = TempDir::new().unwrap();
+        let lock_path = temp_dir.path().join(LOCK_FILE_NAME);
+        std::fs::write(&lock_path, "version = 99\n[tools]\n").unwrap();
+
+        assert!(LockFile::load(&lock_path).is_err());
+    }
+}
@@ -8,13 +8,17 @@
 
 pub mod cache;
 pub mod discovery;
+pub mod lock;
 pub mod types;
 
-use self::types::AppConfig;
+use self::types::{AppConfig, AUTO_WORKERS};
 use crate::error::{Result, ZenithError};
-use config::{Config, Environment, File};
+use config::builder::DefaultState;
+use config::{Config, ConfigBuilder, Environment, File, FileFormat};
+use std::collections::HashMap;
 use std::path::PathBuf;
 
+use self::cache::ConfigCache;
 use self::discovery::discover_project_config;
 use std::path::Path;
 
@@ -28,19 +32,48 @@ use std::path::Path;
 ///
 /// 返回解析后的 `AppConfig` 结构体。
 pub fn load_config(path: Option<PathBuf>) -> Result<AppConfig> {
-    load_config_with_project_discovery(path, None)
+    load_config_with_profile(path, None)
+}
+
+/// 选择配置方案的环境变量。
+pub const PROFILE_ENV: &str = "ZENITH_PROFILE";
+
+/// 加载配置，并应用 `[profiles.<name>]` 中的配置方案。
+///
+/// 未指定 `profile` 时依次使用 `ZENITH_PROFILE` 和配置文件顶层的 `profile`。
+/// 方案中的设置覆盖配置文件，但环境变量的优先级仍然最高。
+/// 指定的方案不存在时返回错误。
+pub fn load_config_with_profile(path: Option<PathBuf>, profile: Option<&str>) -> Result<AppConfig> {
+    build_config(path, None, &[], profile, true)
 }
 
 /// 加载配置，并支持可选的项目级配置自动发现。
 ///
+/// 项目配置中没有 `profile` 对应的方案时忽略该方案，不报错。
+///
 /// # 参数
 ///
 /// * `app_config_path` - 应用级配置文件路径。
 /// * `file_path` - 正在处理的文件路径，用于向上查找项目配置。
+/// * `project_markers` - 额外的项目根目录标识文件，查找止于含有它们的目录。
+/// * `profile` - 启用的配置方案名称。
 pub fn load_config_with_project_discovery(
     app_config_path: Option<PathBuf>,
     file_path: Option<&Path>,
+    project_markers: &[String],
+    profile: Option<&str>,
+) -> Result<AppConfig> {
+    build_config(app_config_path, file_path, project_markers, profile, false)
+}
+
+fn build_config(
+    app_config_path: Option<PathBuf>,
+    file_path: Option<&Path>,
+    project_markers: &[String],
+    profile: Option<&str>,
+    require_profile: bool,
 ) -> Result<AppConfig> {
+    let config_error = |e: config::ConfigError| ZenithError::Config(e.to_string());
     let mut builder = Config::builder();
 
     // 1. 加载默认值 (由结构体的 Default 实现处理)
@@ -58,27 +91,195 @@ pub fn load_config_with_project_discovery(
 
     // 3. 如果提供了文件路径，则尝试发现项目级配置
     if let Some(file_path) = file_path {
-        if let Some(project_config_path) = discover_project_config(file_path)? {
+        if let Some(project_config_path) = discover_project_config(file_path, project_markers)? {
             builder = builder.add_source(File::from(project_config_path).required(false));
         }
     }
 
-    // 4. 从环境变量加载 (最高优先级)
-    // 环境变量前缀为 ZENITH_，例如 ZENITH_GLOBAL_LOG_LEVEL
-    builder = builder.add_source(Environment::with_prefix("ZENITH").separator("_"));
+    let files = builder.build().map_err(config_error)?;
+    let profile = profile
+        .map(str::to_string)
+        .or_else(|| std::env::var(PROFILE_ENV).ok().filter(|p| !p.is_empty()))
+        .or_else(|| files.get_string("profile").ok());
 
-    let config = builder
-        .build()
-        .map_err(|e| ZenithError::Config(e.to_string()))?;
+    // 4. 在配置文件之上叠加所选配置方案
+    let builder = add_with_profile(
+        Config::builder(),
+        files,
+        profile.as_deref(),
+        require_profile,
+    )?;
 
-    config
+    // 5. 从环境变量加载 (最高优先级)
+    let mut config: AppConfig = builder
+        .add_source(environment())
+        .build()
+        .map_err(config_error)?
         .try_deserialize()
-        .map_err(|e| ZenithError::Config(e.to_string()))
+        .map_err(config_error)?;
+    config.profile = profile;
+    config.validate()?;
+    Ok(config)
+}
+
+/// 覆盖配置的环境变量来源，优先级高于所有配置文件。
+///
+/// 前缀与各层键名之间以双下划线分隔，例如 `ZENITH__GLOBAL__LOG_LEVEL` 对应
+/// `global.log_level`，`ZENITH__CONCURRENCY__WORKERS` 对应 `concurrency.workers`。
+/// 键名本身含有单下划线，因此不能用单下划线分隔；`ZENITH_LOG_LEVEL` 等
+/// 命令行参数使用的环境变量也因此不会被当作配置项。
+pub(crate) fn environment() -> Environment {
+    Environment::with_prefix("ZENITH").separator("__")
+}
+
+/// 把 `files` 加入 `builder`，并在其上叠加其中 `[profiles.<profile>]` 的配置方案。
+///
+/// 方案不存在时，`require_profile` 为真则返回错误，否则忽略该方案。
+pub(crate) fn add_with_profile(
+    builder: ConfigBuilder<DefaultState>,
+    files: Config,
+    profile: Option<&str>,
+    require_profile: bool,
+) -> Result<ConfigBuilder<DefaultState>> {
+    let Some(name) = profile else {
+        return Ok(builder.add_source(files));
+    };
+    match files.get::<toml::Table>(&format!("profiles.{}", name)) {
+        Ok(overrides) => {
+            let overrides =
+                toml::to_string(&overrides).map_err(|e| ZenithError::Config(e.to_string()))?;
+            Ok(builder
+                .add_source(files)
+                .add_source(File::from_str(&overrides, FileFormat::Toml)))
+        }
+        Err(_) if require_profile => {
+            let mut available: Vec<String> = files
+                .get_table("profiles")
+                .map(|profiles| profiles.into_keys().collect())
+                .unwrap_or_default();
+            available.sort();
+            Err(ZenithError::Config(format!(
+                "Unknown profile '{}', available: {}",
+                name,
+                if available.is_empty() {
+                    "none".to_string()
+                } else {
+                    available.join(", ")
+                }
+            )))
+        }
+        Err(_) => Ok(builder.add_source(files)),
+    }
+}
+
+/// 导出 `AppConfig` 的 JSON Schema，供编辑器补全和校验 `zenith.toml`。
+pub fn config_schema() -> serde_json::Value {
+    serde_json::to_value(schemars::schema_for!(AppConfig))
+        .expect("a JSON Schema always serializes to JSON")
+}
+
+/// `zenith init` 生成的配置文件名。
+pub const DEFAULT_CONFIG_FILE: &str = "zenith.toml";
+
+/// 生成带注释的默认配置文件内容：`AppConfig::default()` 序列化为 TOML，
+/// 每个配置项前附上 JSON Schema 中对应字段的说明。
+pub fn default_config_toml() -> Result<String> {
+    let mut config = AppConfig::default();
+    // 默认线程数取决于当前机器的 CPU 数，写入文件时改用 auto，便于提交到仓库
+    config.concurrency.workers = AUTO_WORKERS;
+    let body = toml::to_string_pretty(&config).map_err(|e| ZenithError::Config(e.to_string()))?;
+    let schema = config_schema();
+    let mut descriptions = HashMap::new();
+    collect_descriptions(&schema, &schema, "", &mut descriptions);
+
+    let mut output = String::from(
+        "# Zenith 配置文件，由 `zenith init` 生成。\n\
+         # 除 `workers = \"auto\"` 外均为默认值；完整的字段说明可通过 `zenith config-schema` 查看。\n",
+    );
+    let mut section = String::new();
+    for line in body.lines() {
+        let trimmed = line.trim();
+        let key = if let Some(name) = trimmed.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            section = name.to_string();
+            if !output.ends_with("\n\n") {
+                output.push('\n');
+            }
+            Some(section.clone())
+        } else {
+            trimmed
+                .split_once(" = ")
+                .map(|(key, _)| match section.as_str() {
+                    "" => key.to_string(),
+                    section => format!("{}.{}", section, key),
+                })
+        };
+        if let Some(description) = key.and_then(|key| descriptions.get(&key)) {
+            // 多行文档注释在 Schema 中以空格连接，中文标点后的空格是多余的
+            let description = description.replace("， ", "，").replace("。 ", "。");
+            for comment in description.lines().map(str::trim) {
+                if comment.is_empty() {
+                    output.push_str("#\n");
+                } else {
+                    output.push_str(&format!("# {}\n", comment));
+                }
+            }
+        }
+        output.push_str(line);
+        output.push('\n');
+    }
+    Ok(output)
+}
+
+/// 收集 `object` 各属性（及其引用的定义中的嵌套属性）的说明，键为以点分隔的路径。
+fn collect_descriptions(
+    root: &serde_json::Value,
+    object: &serde_json::Value,
+    prefix: &str,
+    descriptions: &mut HashMap<String, String>,
+) {
+    let Some(properties) = object.get("properties").and_then(|p| p.as_object()) else {
+        return;
+    };
+    for (name, property) in properties {
+        let path = if prefix.is_empty() {
+            name.clone()
+        } else {
+            format!("{}.{}", prefix, name)
+        };
+        if let Some(description) = property.get("description").and_then(|d| d.as_str()) {
+            descriptions.insert(path.clone(), description.to_string());
+        }
+        // 带说明的结构体字段以 `allOf: [{ "$ref": ... }]` 的形式引用定义
+        let reference = property
+            .get("$ref")
+            .or_else(|| property.pointer("/allOf/0/$ref"))
+            .and_then(|r| r.as_str())
+            .and_then(|r| r.strip_prefix("#/definitions/"));
+        if let Some(definition) =
+            reference.and_then(|r| root.pointer(&format!("/definitions/{}", r)))
+        {
+            collect_descriptions(root, definition, &path, descriptions);
+        }
+    }
+}
+
+/// 加载在 `dir` 下实际生效的配置：默认值、配置文件、配置方案、环境变量，
+/// 再合并从 `dir` 向上发现的项目级配置。
+pub fn load_effective_config(
+    app_config_path: Option<PathBuf>,
+    profile: Option<&str>,
+    dir: &Path,
+) -> Result<AppConfig> {
+    let app_config = load_config_with_profile(app_config_path, profile)?;
+    ConfigCache::new()
+        .with_project_markers(app_config.global.project_markers.clone())
+        .get_config_for_file(&app_config, dir)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::types::GlobalConfig;
 
     #[test]
     fn test_load_config_with_valid_file() {
@@ -141,6 +342,124 @@ batch_size = 50
         }
     }
 
+    #[test]
+    fn test_config_schema_describes_sections() {
+        let schema = config_schema();
+        let properties = schema["properties"].as_object().unwrap();
+        for section in ["global", "zeniths", "backup", "mcp", "overrides"] {
+            assert!(properties.contains_key(section), "missing {}", section);
+        }
+        assert_eq!(
+            schema["definitions"]["GlobalConfig"]["properties"]["log_level"]["default"],
+            "info"
+        );
+    }
+
+    #[test]
+    fn test_load_effective_config_merges_project_config() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            temp_dir.path().join("zenith.toml"),
+            "[backup]\nretention_days = 30\n",
+        )
+        .unwrap();
+
+        let config = load_effective_config(None, None, temp_dir.path()).unwrap();
+        assert_eq!(config.backup.retention_days, 30);
+    }
+
+    #[test]
+    fn test_environment_overrides_nested_fields() {
+        let vars = [
+            ("ZENITH__GLOBAL__LOG_LEVEL", "debug"),
+            ("ZENITH__GLOBAL__BACKUP_ENABLED", "false"),
+            ("ZENITH__CONCURRENCY__WORKERS", "3"),
+            ("ZENITH__LIMITS__MAX_FILE_SIZE_MB", "5"),
+            // 命令行参数使用的环境变量不属于配置
+            ("ZENITH_LOG_LEVEL", "trace"),
+            ("ZENITH_PROFILE", "ci"),
+        ]
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect();
+
+        let config: AppConfig = Config::builder()
+            .add_source(File::from_str(
+                "[global]\nlog_level = \"warn\"\n\n[concurrency]\nworkers = 8\n",
+                FileFormat::Toml,
+            ))
+            .add_source(environment().source(Some(vars)))
+            .build()
+            .unwrap()
+            .try_deserialize()
+            .unwrap();
+
+        assert_eq!(config.global.log_level, "debug");
+        assert!(!config.global.backup_enabled);
+        assert_eq!(config.concurrency.workers, 3);
+        assert_eq!(config.limits.max_file_size_mb, 5);
+        assert!(config.global.recursive);
+        assert_eq!(config.profile, None);
+    }
+
+    #[test]
+    fn test_load_config_with_profile() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let config_path = temp_dir.path().join("zenith.toml");
+        std::fs::write(
+            &config_path,
+            r#"
+[global]
+log_level = "debug"
+
+[backup]
+retention_days = 14
+
+[profiles.ci.backup]
+retention_days = 30
+
+[profiles.local.global]
+log_level = "warn"
+"#,
+        )
+        .unwrap();
+
+        let config = load_config_with_profile(Some(config_path.clone()), Some("ci")).unwrap();
+        assert_eq!(config.profile.as_deref(), Some("ci"));
+        assert_eq!(config.backup.retention_days, 30);
+        // 方案未覆盖的设置仍来自配置文件
+        assert_eq!(config.global.log_level, "debug");
+
+        let config = load_config_with_profile(Some(config_path.clone()), Some("local")).unwrap();
+        assert_eq!(config.backup.retention_days, 14);
+        assert_eq!(config.global.log_level, "warn");
+
+        let err = load_config_with_profile(Some(config_path), Some("strict")).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Configuration error: Unknown profile 'strict', available: ci, local"
+        );
+    }
+
+    #[test]
+    fn test_load_config_rejects_invalid_values() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let config_path = temp_dir.path().join("zenith.toml");
+        let error = |content: &str| {
+            std::fs::write(&config_path, content).unwrap();
+            load_config(Some(config_path.clone()))
+                .unwrap_err()
+                .to_string()
+        };
+
+        let message = error("[global]\nlog_level = \"loud\"\n");
+        assert!(message.contains("global.log_level"), "{}", message);
+        let message = error("[limits]\nmax_file_size_mb = 0\n");
+        assert!(message.contains("limits.max_file_size_mb"), "{}", message);
+        let message = error("[concurrency]\nworkers = 0\n");
+        assert!(message.contains("workers"), "{}", message);
+    }
+
     #[test]
     fn test_load_config_with_invalid_file() {
         let temp_dir = tempfile::tempdir().unwrap();
@@ -157,4 +476,18 @@ backup_enabled = true
         let result = load_config(Some(config_path));
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_default_config_toml_is_documented_and_loadable() {
+        let text = default_config_toml().unwrap();
+        assert!(text.contains("# 全局配置项。\n[global]\n"));
+        assert!(text.contains("# 备份保留天数。\nretention_days = 7\n"));
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let config_path = temp_dir.path().join(DEFAULT_CONFIG_FILE);
+        std::fs::write(&config_path, text).unwrap();
+        let config = load_config(Some(config_path)).unwrap();
+        assert_eq!(config.backup.retention_days, 7);
+        assert_eq!(config.global.config_dir, GlobalConfig::default().config_dir);
+    }
 }
@@ -6,12 +6,14 @@
 //! 配置类型定义模块。
 //! 包含 Zenith 应用的所有配置结构体及其默认值实现。
 
+use crate::error::ZenithError;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::path::PathBuf;
 
 /// Zenith 应用的主配置结构体。
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, JsonSchema)]
 pub struct AppConfig {
     /// 全局配置项。
     #[serde(default)]
@@ -34,10 +36,111 @@ pub struct AppConfig {
     /// 安全相关配置。
     #[serde(default)]
     pub security: SecurityConfig,
+    /// 空白字符规范化配置。
+    #[serde(default)]
+    pub normalize: NormalizeConfig,
+    /// 扩展名（不含点）到格式化工具名称的映射，优先于按扩展名注册的默认工具。
+    /// 值为 `"none"` 时跳过该扩展名的文件。
+    #[serde(default)]
+    pub overrides: HashMap<String, String>,
+    /// 启用的配置方案，对应 `[profiles.<name>]`；也可通过 `--profile` 或 `ZENITH_PROFILE` 指定。
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+}
+
+/// 显示配置时替换密钥的占位符。
+const REDACTED: &str = "<redacted>";
+
+/// `global.log_level` 可用的取值。
+const LOG_LEVELS: &[&str] = &["trace", "debug", "info", "warn", "error"];
+
+/// `mcp.users` 中可用的角色。
+const MCP_USER_ROLES: &[&str] = &["admin", "user", "readonly"];
+
+impl AppConfig {
+    /// 隐去 MCP API 密钥后的副本，用于打印配置。
+    pub fn redacted(&self) -> Self {
+        let mut config = self.clone();
+        if config.mcp.api_key.is_some() {
+            config.mcp.api_key = Some(REDACTED.into());
+        }
+        for user in &mut config.mcp.users {
+            user.api_key = REDACTED.into();
+        }
+        config
+    }
+
+    /// 检查配置取值是否有意义，如数值范围和枚举取值。
+    ///
+    /// 返回的 `ZenithError::Config` 指明出错的配置项及其可用取值。
+    pub fn validate(&self) -> crate::error::Result<()> {
+        let invalid = |key: &str, message: String| {
+            Err(ZenithError::Config(format!(
+                "配置项 {} 无效：{}",
+                key, message
+            )))
+        };
+
+        if !LOG_LEVELS.contains(&self.global.log_level.to_lowercase().as_str()) {
+            return invalid(
+                "global.log_level",
+                format!(
+                    "'{}'，可用取值为 {}",
+                    self.global.log_level,
+                    LOG_LEVELS.join(", ")
+                ),
+            );
+        }
+        if self.concurrency.batch_size == 0 {
+            return invalid("concurrency.batch_size", "须为正整数".into());
+        }
+        if self.limits.max_file_size_mb == 0 {
+            return invalid("limits.max_file_size_mb", "须为正整数".into());
+        }
+        if self.backup.max_sessions == Some(0) {
+            return invalid("backup.max_sessions", "须为正整数，不限制时请省略".into());
+        }
+        for (index, user) in self.mcp.users.iter().enumerate() {
+            if user.api_key.trim().is_empty() {
+                return invalid(&format!("mcp.users[{}].api_key", index), "不能为空".into());
+            }
+            if !MCP_USER_ROLES.contains(&user.role.as_str()) {
+                return invalid(
+                    &format!("mcp.users[{}].role", index),
+                    format!("'{}'，可用取值为 {}", user.role, MCP_USER_ROLES.join(", ")),
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// 检查 `concurrency.formatter_limits` 的键是否都是 `formatters` 中的格式化工具名称。
+    ///
+    /// 外部插件在加载配置之后才注册，因此不在 [`Self::validate`] 中检查。
+    pub fn validate_formatter_limits(&self, formatters: &[String]) -> crate::error::Result<()> {
+        let mut unknown: Vec<&String> = self
+            .concurrency
+            .formatter_limits
+            .keys()
+            .filter(|name| !formatters.contains(name))
+            .collect();
+        unknown.sort();
+        match unknown.first() {
+            Some(name) => Err(ZenithError::Config(format!(
+                "配置项 concurrency.formatter_limits 无效：未知的格式化工具 '{}'，可用取值为 {}",
+                name,
+                formatters.join(", ")
+            ))),
+            None => Ok(()),
+        }
+    }
 }
 
+/// `overrides` 中用于禁用某个扩展名的特殊值。
+pub const DISABLED_OVERRIDE: &str = "none";
+
 /// 全局通用配置。
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct GlobalConfig {
     /// 是否启用自动备份。
     #[serde(default = "default_true")]
@@ -54,6 +157,61 @@ pub struct GlobalConfig {
     /// 配置文件和插件的存放目录。
     #[serde(default = "default_config_dir")]
     pub config_dir: String,
+    /// 显式指定的文件没有匹配的格式化工具时是否视为失败。
+    #[serde(default)]
+    pub fail_on_unsupported: bool,
+    /// 是否让支持的格式化工具（目前为 prettier）以常驻进程运行，在多个文件间复用，
+    /// 避免逐个文件启动进程的开销。不支持的工具仍逐个文件启动。
+    #[serde(default)]
+    pub parallel_formatters: bool,
+    /// 遍历目录时是否包含隐藏文件和目录（如 `.github/`）。`.git` 以及备份、配置目录始终跳过。
+    #[serde(default)]
+    pub include_hidden: bool,
+    /// 遍历目录的最大深度：1 表示只处理目录下的直接子文件。未设置时不限制。
+    #[serde(default)]
+    pub max_depth: Option<usize>,
+    /// 遍历目录时是否跟随符号链接。默认不跟随，且跳过解析到根目录之外的符号链接。
+    #[serde(default)]
+    pub follow_symlinks: bool,
+    /// 文件开头带有 UTF-8 BOM 时，格式化前去掉 BOM，格式化后是否重新加上。
+    #[serde(default = "default_true")]
+    pub preserve_bom: bool,
+    /// 格式化后输出的换行符：`lf`、`crlf`，或 `preserve` 沿用原文件中占多数的换行符。
+    #[serde(default)]
+    pub line_ending: LineEnding,
+    /// 格式化后文件末尾的换行：`ensure` 补上、`remove` 去掉，`preserve` 保留格式化工具的输出。
+    #[serde(default)]
+    pub final_newline: FinalNewline,
+    /// 额外的项目根目录标识文件（如 `.myteam`），在内置列表之外识别项目边界。
+    /// 项目级配置的查找也止于含有这些文件的目录。
+    #[serde(default)]
+    pub project_markers: Vec<String>,
+}
+
+/// 格式化结果使用的换行符。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum LineEnding {
+    /// 统一为 `\n`。
+    Lf,
+    /// 统一为 `\r\n`。
+    Crlf,
+    /// 沿用原文件中占多数的换行符；原文件没有换行时保留格式化工具的输出。
+    #[default]
+    Preserve,
+}
+
+/// 格式化结果末尾的换行处理。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum FinalNewline {
+    /// 非空文件以换行结尾；空文件保持为空。
+    Ensure,
+    /// 去掉末尾的所有换行。
+    Remove,
+    /// 保留格式化工具的输出。
+    #[default]
+    Preserve,
 }
 
 impl Default for GlobalConfig {
@@ -64,12 +222,21 @@ impl Default for GlobalConfig {
             recursive: true,
             cache_enabled: true,
             config_dir: default_config_dir(),
+            fail_on_unsupported: false,
+            parallel_formatters: false,
+            include_hidden: false,
+            max_depth: None,
+            follow_symlinks: false,
+            preserve_bom: true,
+            line_ending: LineEnding::Preserve,
+            final_newline: FinalNewline::Preserve,
+            project_markers: Vec::new(),
         }
     }
 }
 
 /// 单个格式化工具的设置。
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ZenithSettings {
     /// 是否启用该格式化工具。
     #[serde(default = "default_true")]
@@ -79,6 +246,12 @@ pub struct ZenithSettings {
     /// 是否使用默认规则。
     #[serde(default = "default_true")]
     pub use_default: bool,
+    /// 传递给格式化工具的附加选项（如 INI 的 `sort_keys`）。
+    #[serde(default)]
+    pub options: serde_json::Value,
+    /// 追加到内置格式化工具命令行的参数（如 `["--edition", "2021"]`）。
+    #[serde(default)]
+    pub args: Vec<String>,
 }
 
 impl Default for ZenithSettings {
@@ -87,12 +260,14 @@ impl Default for ZenithSettings {
             enabled: default_true(),
             config_path: None,
             use_default: default_true(),
+            options: serde_json::Value::Null,
+            args: Vec::new(),
         }
     }
 }
 
 /// 备份功能配置。
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct BackupConfig {
     /// 备份文件存放目录。
     #[serde(default = "default_backup_dir")]
@@ -100,6 +275,16 @@ pub struct BackupConfig {
     /// 备份保留天数。
     #[serde(default = "default_retention_days")]
     pub retention_days: u32,
+    /// 最多保留的备份会话数。按天数清理后，仅保留最新的 N 个会话；未设置时不限制。
+    #[serde(default)]
+    pub max_sessions: Option<usize>,
+    /// 按内容去重：文件内容只在 `objects/<blake3>` 中保存一份，
+    /// 各会话仅记录路径到哈希的引用。
+    #[serde(default)]
+    pub dedup: bool,
+    /// 格式化成功结束后自动按 `retention_days` 和 `max_sessions` 清理旧的备份会话。
+    #[serde(default)]
+    pub auto_clean_on_exit: bool,
 }
 
 impl Default for BackupConfig {
@@ -107,19 +292,31 @@ impl Default for BackupConfig {
         Self {
             dir: default_backup_dir(),
             retention_days: default_retention_days(),
+            max_sessions: None,
+            dedup: false,
+            auto_clean_on_exit: false,
         }
     }
 }
 
 /// 并发执行配置。
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ConcurrencyConfig {
-    /// 并行工作的线程数。
-    #[serde(default = "default_workers")]
+    /// 并行工作的线程数；配置为 `"auto"` 时由服务按所选格式化工具的类型决定。
+    #[serde(
+        default = "default_workers",
+        deserialize_with = "deserialize_workers",
+        serialize_with = "serialize_workers"
+    )]
+    #[schemars(with = "WorkersValue")]
     pub workers: usize,
     /// 批量处理的文件数量。
     #[serde(default = "default_batch_size")]
     pub batch_size: usize,
+    /// 各格式化工具同时运行的最大数量，以格式化工具名称为键（如 `rust = 2`），
+    /// 仍受 `workers` 总数限制；未列出的工具不单独限制。
+    #[serde(default)]
+    pub formatter_limits: HashMap<String, usize>,
 }
 
 impl Default for ConcurrencyConfig {
@@ -127,19 +324,84 @@ impl Default for ConcurrencyConfig {
         Self {
             workers: default_workers(),
             batch_size: default_batch_size(),
+            formatter_limits: HashMap::new(),
         }
     }
 }
 
+/// `workers = "auto"` 对应的取值：运行时根据待处理文件的格式化工具选择线程数。
+pub const AUTO_WORKERS: usize = 0;
+
+/// `workers` 的配置形式：具体数量或 `"auto"`。
+/// 环境变量覆盖的值总是字符串，因此数字字符串同样接受。
+#[derive(Deserialize, JsonSchema)]
+#[serde(untagged)]
+enum WorkersValue {
+    Count(usize),
+    Keyword(String),
+}
+
+fn deserialize_workers<'de, D>(deserializer: D) -> std::result::Result<usize, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    match WorkersValue::deserialize(deserializer)? {
+        // 0 是 auto 的内部取值，在配置中没有意义
+        WorkersValue::Count(0) => parse_workers("0").map_err(serde::de::Error::custom),
+        WorkersValue::Count(count) => Ok(count),
+        WorkersValue::Keyword(keyword) => parse_workers(&keyword).map_err(serde::de::Error::custom),
+    }
+}
+
+fn serialize_workers<S>(workers: &usize, serializer: S) -> std::result::Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    if *workers == AUTO_WORKERS {
+        serializer.serialize_str("auto")
+    } else {
+        serializer.serialize_u64(*workers as u64)
+    }
+}
+
+/// 解析 `workers` 的取值：`auto`（不区分大小写）返回 [`AUTO_WORKERS`]，
+/// 否则须为正整数。
+pub fn parse_workers(value: &str) -> std::result::Result<usize, String> {
+    let value = value.trim();
+    if value.eq_ignore_ascii_case("auto") {
+        return Ok(AUTO_WORKERS);
+    }
+    match value.parse::<usize>() {
+        Ok(0) | Err(_) => Err(format!(
+            "无效的 workers 取值 '{}'，请使用正整数或 auto",
+            value
+        )),
+        Ok(count) => Ok(count),
+    }
+}
+
 /// 资源与文件限制配置。
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct LimitsConfig {
     /// 允许处理的最大文件大小 (MB)。
     #[serde(default = "default_max_file_size_mb")]
     pub max_file_size_mb: u64,
-    /// 允许使用的最大内存 (MB)。
+    /// 允许使用的最大内存 (MB)，0 表示不限制。同时处理的文件内容总量不超过该值；
+    /// 进程常驻内存超过该值时暂停开始新文件，直到内存回落。
     #[serde(default = "default_max_memory_mb")]
     pub max_memory_mb: u64,
+    /// 外部格式化工具出现临时性失败时的最大重试次数，0 表示不重试。
+    #[serde(default)]
+    pub formatter_retries: u32,
+    /// 首次重试前的等待时间 (ms)，之后每次重试翻倍。
+    #[serde(default = "default_formatter_retry_delay_ms")]
+    pub formatter_retry_delay_ms: u64,
+    /// 超过该大小 (MB) 的文件以流的方式交给格式化工具，不整体读入内存。
+    #[serde(default = "default_stream_threshold_mb")]
+    pub stream_threshold_mb: u64,
+    /// 在文件开头的多少 KB 中查找 NUL 字节；找到时视为二进制文件并跳过，0 表示不检查。
+    #[serde(default = "default_binary_check_kb")]
+    pub binary_check_kb: u64,
 }
 
 impl Default for LimitsConfig {
@@ -147,12 +409,77 @@ impl Default for LimitsConfig {
         Self {
             max_file_size_mb: default_max_file_size_mb(),
             max_memory_mb: default_max_memory_mb(),
+            formatter_retries: 0,
+            formatter_retry_delay_ms: default_formatter_retry_delay_ms(),
+            stream_threshold_mb: default_stream_threshold_mb(),
+            binary_check_kb: default_binary_check_kb(),
+        }
+    }
+}
+
+/// 外部格式化工具的重试策略，由 [`LimitsConfig`] 生成。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetryPolicy {
+    /// 最大重试次数。
+    pub retries: u32,
+    /// 首次重试前的等待时间 (ms)。
+    pub base_delay_ms: u64,
+}
+
+/// 单次重试等待时间的上限 (ms)。
+const MAX_RETRY_DELAY_MS: u64 = 10_000;
+
+impl RetryPolicy {
+    /// 根据资源限制配置创建重试策略。
+    pub fn from_limits(limits: &LimitsConfig) -> Self {
+        Self {
+            retries: limits.formatter_retries,
+            base_delay_ms: limits.formatter_retry_delay_ms,
+        }
+    }
+
+    /// 第 `attempt` 次重试（从 0 开始）前的等待时间，按指数退避增长。
+    pub fn delay(&self, attempt: u32) -> std::time::Duration {
+        let factor = 1u64.checked_shl(attempt).unwrap_or(u64::MAX);
+        let millis = self
+            .base_delay_ms
+            .saturating_mul(factor)
+            .min(MAX_RETRY_DELAY_MS);
+        std::time::Duration::from_millis(millis)
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            retries: 0,
+            base_delay_ms: default_formatter_retry_delay_ms(),
+        }
+    }
+}
+
+/// 空白字符规范化配置（`[normalize]`）。
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct NormalizeConfig {
+    /// 是否删除行尾空白字符。
+    #[serde(default = "default_true")]
+    pub trim_trailing_whitespace: bool,
+    /// 是否保证文件以单个换行符结尾。
+    #[serde(default = "default_true")]
+    pub insert_final_newline: bool,
+}
+
+impl Default for NormalizeConfig {
+    fn default() -> Self {
+        Self {
+            trim_trailing_whitespace: true,
+            insert_final_newline: true,
         }
     }
 }
 
 /// MCP (Model Context Protocol) 服务配置。
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct McpConfig {
     /// 是否启用 MCP 服务。
     #[serde(default = "default_mcp_enabled")]
@@ -175,10 +502,13 @@ pub struct McpConfig {
     /// 用户列表及其角色。
     #[serde(default)]
     pub users: Vec<McpUser>,
+    /// 是否记录每个请求的方法、耗时和状态码。
+    #[serde(default)]
+    pub request_logging: bool,
 }
 
 /// 插件安全配置。
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SecurityConfig {
     /// 允许的插件命令白名单。
     #[serde(default)]
@@ -189,6 +519,14 @@ pub struct SecurityConfig {
     /// 是否允许插件使用相对路径。
     #[serde(default = "default_allow_relative_paths")]
     pub allow_relative_paths: bool,
+    /// 允许格式化的根目录列表，为空时不做限制。
+    #[serde(default)]
+    pub allowed_roots: Vec<PathBuf>,
+    /// 格式化不受信任的代码时启用：不再查找项目中的格式化工具配置文件，让支持的格式化工具
+    /// 忽略项目配置，并拒绝加载会执行代码的配置文件（如 `.prettierrc.js`）。
+    /// 项目级配置无法关闭此选项。
+    #[serde(default)]
+    pub sandbox_external_config: bool,
 }
 
 impl Default for SecurityConfig {
@@ -197,18 +535,23 @@ impl Default for SecurityConfig {
             allowed_plugin_commands: Vec::new(),
             allow_absolute_paths: default_allow_absolute_paths(),
             allow_relative_paths: default_allow_relative_paths(),
+            allowed_roots: Vec::new(),
+            sandbox_external_config: false,
         }
     }
 }
 
 /// MCP 用户信息。
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct McpUser {
     /// 用户 API 密钥。
     pub api_key: String,
     /// 用户角色（例如 admin, user）。
     #[serde(default = "default_mcp_user_role")]
     pub role: String,
+    /// 该用户可以格式化的根目录；请求中的路径必须位于其中之一。为空时不限制。
+    #[serde(default)]
+    pub allowed_paths: Vec<PathBuf>,
 }
 
 fn default_mcp_user_role() -> String {
@@ -225,6 +568,7 @@ impl Default for McpConfig {
             api_key: None,
             allowed_origins: default_mcp_allowed_origins(),
             users: vec![],
+            request_logging: false,
         }
     }
 }
@@ -238,6 +582,19 @@ pub struct ZenithConfig {
     pub use_default_rules: bool,
     /// 特定于某个格式化工具的 JSON 配置。
     pub zenith_specific: serde_json::Value,
+    /// 外部工具失败时的重试策略。
+    #[serde(default)]
+    pub retry: RetryPolicy,
+    /// 只格式化的行范围（从 1 开始，包含两端）。不支持范围的格式化工具仍格式化整个文件。
+    #[serde(default)]
+    pub range: Option<(usize, usize)>,
+    /// 是否让格式化工具忽略项目中的配置文件，并拒绝会执行代码的配置文件
+    /// （来自 `security.sandbox_external_config`）。
+    #[serde(default)]
+    pub sandbox_config: bool,
+    /// 格式化结果使用的换行符（来自 `global.line_ending`），由 Zenith 服务在格式化后统一转换。
+    #[serde(default)]
+    pub line_ending: LineEnding,
 }
 
 impl Default for ZenithConfig {
@@ -246,6 +603,10 @@ impl Default for ZenithConfig {
             custom_config_path: None,
             use_default_rules: true,
             zenith_specific: serde_json::Value::Null,
+            retry: RetryPolicy::default(),
+            range: None,
+            sandbox_config: false,
+            line_ending: LineEnding::Preserve,
         }
     }
 }
@@ -265,12 +626,159 @@ pub struct FormatResult {
     pub formatted_size: u64,
     /// 执行耗时 (毫秒)。
     pub duration_ms: u64,
+    /// 处理该文件的格式化工具名称；未选中格式化工具时为空。
+    pub formatter: Option<String>,
     /// 错误信息（如果失败）。
     pub error: Option<String>,
+    /// 结构化的错误类别，与 `error` 同时设置，便于脚本按类型处理。
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_kind: Option<FormatError>,
+    /// 不影响执行结果的警告，例如格式化工具的输出不稳定。
+    pub warning: Option<String>,
+}
+
+/// 单个文件格式化失败的类别。
+///
+/// 序列化为 `{"kind": "timeout", "message": "..."}`；`Display` 输出消息本身，
+/// 没有消息时输出类别说明，因此与原有的字符串错误保持一致。
+/// `unsupported_extension` 和 `no_formatter` 的 `message` 是扩展名（不含点）。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "message", rename_all = "snake_case")]
+pub enum FormatError {
+    /// 文件被跳过（二进制文件、格式化工具未安装等）。
+    Unsupported(Option<String>),
+    /// 没有格式化工具处理该扩展名，文件被跳过。
+    UnsupportedExtension(String),
+    /// 显式指定的文件没有格式化工具处理该扩展名，且启用了 `fail_on_unsupported`。
+    NoFormatter(String),
+    /// 格式化工具未安装。
+    ToolNotFound(Option<String>),
+    /// 格式化工具超时。
+    Timeout(Option<String>),
+    /// 没有读写文件的权限。
+    PermissionDenied(Option<String>),
+    /// 格式化工具拒绝了输入，通常是语法错误，或格式化结果未通过校验。
+    SyntaxInvalid(Option<String>),
+    /// 写回文件失败。
+    WriteFailed(Option<String>),
+    /// 创建备份失败，文件未被修改。
+    BackupFailed(Option<String>),
+    /// 文件超过大小限制。
+    TooLarge(Option<String>),
+    /// 因前面的失败而被取消。
+    Cancelled(Option<String>),
+    /// 超出 `--time-budget` 而未处理。
+    TimeBudgetExceeded,
+    /// 其他错误。
+    Other(Option<String>),
+}
+
+impl FormatError {
+    /// 附带的消息（如果有）。
+    pub fn message(&self) -> Option<&str> {
+        match self {
+            Self::UnsupportedExtension(_) | Self::NoFormatter(_) | Self::TimeBudgetExceeded => None,
+            Self::Unsupported(message)
+            | Self::ToolNotFound(message)
+            | Self::Timeout(message)
+            | Self::PermissionDenied(message)
+            | Self::SyntaxInvalid(message)
+            | Self::WriteFailed(message)
+            | Self::BackupFailed(message)
+            | Self::TooLarge(message)
+            | Self::Cancelled(message)
+            | Self::Other(message) => message.as_deref(),
+        }
+    }
+
+    fn description(&self) -> &'static str {
+        match self {
+            Self::Unsupported(_) => "Unsupported file",
+            Self::UnsupportedExtension(_) => "Unsupported file type",
+            Self::NoFormatter(_) => "No formatter for file type",
+            Self::ToolNotFound(_) => "Formatter not installed",
+            Self::Timeout(_) => "Formatter timed out",
+            Self::PermissionDenied(_) => "Permission denied",
+            Self::SyntaxInvalid(_) => "Formatter rejected the input",
+            Self::WriteFailed(_) => "Write failed",
+            Self::BackupFailed(_) => "Backup failed",
+            Self::TooLarge(_) => "File too large",
+            Self::Cancelled(_) => "Cancelled",
+            Self::TimeBudgetExceeded => "Skipped: time budget exceeded",
+            Self::Other(_) => "Formatting failed",
+        }
+    }
+}
+
+impl std::fmt::Display for FormatError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnsupportedExtension(ext) => write!(f, "Skipped: .{} not supported", ext),
+            Self::NoFormatter(ext) => write!(f, "Unsupported file type: .{} has no formatter", ext),
+            _ => f.write_str(self.message().unwrap_or_else(|| self.description())),
+        }
+    }
+}
+
+impl From<&ZenithError> for FormatError {
+    fn from(error: &ZenithError) -> Self {
+        let message = Some(error.to_string());
+        match error {
+            ZenithError::UnsupportedExtension(_) | ZenithError::ZenithSkipped { .. } => {
+                Self::Unsupported(message)
+            }
+            ZenithError::ToolNotFound { .. } => Self::ToolNotFound(message),
+            ZenithError::FormatterTimeout { .. } | ZenithError::PluginTimeout { .. } => {
+                Self::Timeout(message)
+            }
+            ZenithError::PermissionDenied { .. } => Self::PermissionDenied(message),
+            ZenithError::Io(e) if e.kind() == std::io::ErrorKind::PermissionDenied => {
+                Self::PermissionDenied(message)
+            }
+            ZenithError::ZenithFailed { .. } => Self::SyntaxInvalid(message),
+            ZenithError::BackupFailed(_) => Self::BackupFailed(message),
+            ZenithError::FileTooLarge { .. } => Self::TooLarge(message),
+            _ => Self::Other(message),
+        }
+    }
+}
+
+impl FormatResult {
+    /// `file_path` 失败的结果。
+    pub fn failed(file_path: PathBuf, error: FormatError) -> Self {
+        let mut result = Self {
+            file_path,
+            ..Default::default()
+        };
+        result.fail(error);
+        result
+    }
+
+    /// 记录失败：`error` 保存可读消息，`error_kind` 保存结构化类别。
+    pub fn fail(&mut self, error: FormatError) {
+        self.error = Some(error.to_string());
+        self.error_kind = Some(error);
+    }
+
+    /// 是否为真正的失败（格式化工具或 IO 出错）。
+    ///
+    /// 被跳过或取消的文件以及检查模式下需要格式化的文件都不算失败。
+    pub fn is_failure(&self) -> bool {
+        !self.success
+            && !matches!(
+                self.error_kind,
+                Some(
+                    FormatError::Unsupported(_)
+                        | FormatError::UnsupportedExtension(_)
+                        | FormatError::Cancelled(_)
+                        | FormatError::TimeBudgetExceeded
+                )
+            )
+    }
 }
 
 /// 性能指标统计。
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Default, PartialEq)]
 pub struct PerformanceMetrics {
     /// 处理的文件总数。
     pub total_files: usize,
@@ -288,6 +796,82 @@ pub struct PerformanceMetrics {
     pub std_deviation_ms: f64,
 }
 
+impl PerformanceMetrics {
+    /// 根据格式化结果中的 `duration_ms` 汇总性能指标。
+    ///
+    /// 分位数通过对耗时排序后按最近秩 (nearest-rank) 取值。
+    pub fn from_results(results: &[FormatResult]) -> Self {
+        if results.is_empty() {
+            return Self::default();
+        }
+
+        let mut durations: Vec<u64> = results.iter().map(|r| r.duration_ms).collect();
+        durations.sort_unstable();
+
+        let count = durations.len();
+        let avg = durations.iter().sum::<u64>() as f64 / count as f64;
+        let variance = durations
+            .iter()
+            .map(|&d| (d as f64 - avg).powi(2))
+            .sum::<f64>()
+            / count as f64;
+
+        let percentile = |p: f64| -> f64 {
+            let rank = ((p * count as f64).ceil() as usize).clamp(1, count);
+            durations[rank - 1] as f64
+        };
+
+        Self {
+            total_files: count,
+            p95_duration_ms: percentile(0.95),
+            p99_duration_ms: percentile(0.99),
+            avg_duration_ms: avg,
+            min_duration_ms: durations[0],
+            max_duration_ms: durations[count - 1],
+            std_deviation_ms: variance.sqrt(),
+        }
+    }
+}
+
+/// 因没有匹配的格式化工具而被跳过的文件类型统计。
+#[derive(Debug, Clone, Serialize, Default, PartialEq)]
+pub struct UnsupportedReport {
+    /// 扩展名（不含点）到被跳过文件数量的映射。
+    pub extensions: BTreeMap<String, usize>,
+}
+
+impl UnsupportedReport {
+    /// 从格式化结果中汇总没有格式化工具处理的文件，包括 `fail_on_unsupported` 下算作失败的文件。
+    pub fn from_results(results: &[FormatResult]) -> Self {
+        let mut extensions = BTreeMap::new();
+        for ext in results.iter().filter_map(|r| match &r.error_kind {
+            Some(FormatError::UnsupportedExtension(ext) | FormatError::NoFormatter(ext)) => {
+                Some(ext)
+            }
+            _ => None,
+        }) {
+            *extensions.entry(ext.clone()).or_insert(0) += 1;
+        }
+        Self { extensions }
+    }
+
+    /// 是否没有被跳过的文件。
+    pub fn is_empty(&self) -> bool {
+        self.extensions.is_empty()
+    }
+
+    /// 按文件数量降序（数量相同时按扩展名）排列的统计项。
+    pub fn sorted(&self) -> Vec<(&str, usize)> {
+        let mut entries: Vec<(&str, usize)> = self
+            .extensions
+            .iter()
+            .map(|(ext, count)| (ext.as_str(), *count))
+            .collect();
+        entries.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        entries
+    }
+}
+
 // 默认值助手函数
 fn default_true() -> bool {
     true
@@ -315,6 +899,18 @@ fn default_max_memory_mb() -> u64 {
     100 // 根据 PRD，默认限制为 100MB
 }
 
+fn default_formatter_retry_delay_ms() -> u64 {
+    100
+}
+
+fn default_stream_threshold_mb() -> u64 {
+    4
+}
+
+fn default_binary_check_kb() -> u64 {
+    8
+}
+
 fn default_config_dir() -> String {
     ".zenith".into()
 }
@@ -351,6 +947,82 @@ fn default_allow_relative_paths() -> bool {
 mod tests {
     use super::*;
 
+    /// `validate` 的错误信息
+    fn validation_error(config: &AppConfig) -> String {
+        match config.validate() {
+            Err(ZenithError::Config(message)) => message,
+            other => panic!("expected a config error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_validate_rejects_nonsensical_values() {
+        assert!(AppConfig::default().validate().is_ok());
+
+        let mut config = AppConfig::default();
+        config.global.log_level = "verbose".into();
+        let message = validation_error(&config);
+        assert!(message.contains("global.log_level"), "{}", message);
+        assert!(
+            message.contains("trace, debug, info, warn, error"),
+            "{}",
+            message
+        );
+        config.global.log_level = "WARN".into();
+        assert!(config.validate().is_ok());
+
+        let mut config = AppConfig::default();
+        config.concurrency.batch_size = 0;
+        assert!(validation_error(&config).contains("concurrency.batch_size"));
+
+        let mut config = AppConfig::default();
+        config.limits.max_file_size_mb = 0;
+        assert!(validation_error(&config).contains("limits.max_file_size_mb"));
+
+        let mut config = AppConfig::default();
+        config.backup.max_sessions = Some(0);
+        assert!(validation_error(&config).contains("backup.max_sessions"));
+
+        let mut config = AppConfig::default();
+        config.mcp.users = vec![McpUser {
+            api_key: "key".into(),
+            role: "owner".into(),
+            allowed_paths: vec![],
+        }];
+        assert!(validation_error(&config).contains("mcp.users[0].role"));
+        config.mcp.users[0].role = "readonly".into();
+        config.mcp.users[0].api_key = " ".into();
+        assert!(validation_error(&config).contains("mcp.users[0].api_key"));
+    }
+
+    #[test]
+    fn test_formatter_limits_must_name_registered_formatters() {
+        let formatters = vec!["prettier".to_string(), "rust".to_string()];
+        let mut config = AppConfig::default();
+        config.concurrency.formatter_limits.insert("rust".into(), 2);
+        assert!(config.validate_formatter_limits(&formatters).is_ok());
+
+        config
+            .concurrency
+            .formatter_limits
+            .insert("rustfmt".into(), 2);
+        match config.validate_formatter_limits(&formatters) {
+            Err(ZenithError::Config(message)) => {
+                assert!(message.contains("'rustfmt'"), "{}", message);
+                assert!(message.contains("prettier, rust"), "{}", message);
+            }
+            other => panic!("expected a config error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_zero_workers_is_rejected() {
+        let result: std::result::Result<ConcurrencyConfig, _> = toml::from_str("workers = 0");
+        assert!(result.unwrap_err().to_string().contains("正整数或 auto"));
+        let config: ConcurrencyConfig = toml::from_str("workers = \"auto\"").unwrap();
+        assert_eq!(config.workers, AUTO_WORKERS);
+    }
+
     #[test]
     fn test_global_config_defaults() {
         let config = GlobalConfig::default();
@@ -374,11 +1046,24 @@ mod tests {
         assert_eq!(config.batch_size, 100);
     }
 
+    #[test]
+    fn test_workers_accepts_auto() {
+        let config: ConcurrencyConfig = toml::from_str("workers = \"auto\"").unwrap();
+        assert_eq!(config.workers, AUTO_WORKERS);
+        let config: ConcurrencyConfig = toml::from_str("workers = 3").unwrap();
+        assert_eq!(config.workers, 3);
+        assert!(toml::from_str::<ConcurrencyConfig>("workers = \"many\"").is_err());
+        assert_eq!(parse_workers("AUTO"), Ok(AUTO_WORKERS));
+        assert!(parse_workers("0").is_err());
+    }
+
     #[test]
     fn test_limits_config_defaults() {
         let config = LimitsConfig::default();
         assert_eq!(config.max_file_size_mb, 10);
         assert_eq!(config.max_memory_mb, 100);
+        assert_eq!(config.stream_threshold_mb, 4);
+        assert_eq!(config.binary_check_kb, 8);
     }
 
     #[test]
@@ -388,4 +1073,145 @@ mod tests {
         assert!(config.use_default);
         assert_eq!(config.config_path, None);
     }
+
+    #[test]
+    fn test_performance_metrics_from_results() {
+        let results: Vec<FormatResult> = (1..=100)
+            .map(|ms| FormatResult {
+                duration_ms: ms,
+                ..Default::default()
+            })
+            .collect();
+
+        let metrics = PerformanceMetrics::from_results(&results);
+        assert_eq!(metrics.total_files, 100);
+        assert_eq!(metrics.min_duration_ms, 1);
+        assert_eq!(metrics.max_duration_ms, 100);
+        assert_eq!(metrics.p95_duration_ms, 95.0);
+        assert_eq!(metrics.p99_duration_ms, 99.0);
+        assert_eq!(metrics.avg_duration_ms, 50.5);
+        assert!((metrics.std_deviation_ms - 28.866).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_performance_metrics_empty_results() {
+        let metrics = PerformanceMetrics::from_results(&[]);
+        assert_eq!(metrics, PerformanceMetrics::default());
+    }
+
+    #[test]
+    fn test_unsupported_report_from_results() {
+        let skipped = |error: FormatError| FormatResult::failed(PathBuf::new(), error);
+        let results = vec![
+            skipped(FormatError::UnsupportedExtension("xyz".into())),
+            skipped(FormatError::UnsupportedExtension("lock".into())),
+            // fail_on_unsupported 下算作失败的文件同样计入
+            skipped(FormatError::NoFormatter("xyz".into())),
+            skipped(FormatError::Unsupported(Some(
+                "Skipped: binary file".into(),
+            ))),
+            skipped(FormatError::Unsupported(Some("No extension".into()))),
+            FormatResult {
+                success: true,
+                ..Default::default()
+            },
+        ];
+
+        let report = UnsupportedReport::from_results(&results);
+        assert_eq!(report.extensions.len(), 2);
+        assert_eq!(report.extensions["xyz"], 2);
+        assert_eq!(report.extensions["lock"], 1);
+        assert_eq!(report.sorted(), vec![("xyz", 2), ("lock", 1)]);
+        assert_eq!(
+            serde_json::to_value(&report).unwrap(),
+            serde_json::json!({ "extensions": { "lock": 1, "xyz": 2 } })
+        );
+        assert!(UnsupportedReport::from_results(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_format_result_is_failure() {
+        let failed = FormatResult::failed(
+            PathBuf::new(),
+            FormatError::SyntaxInvalid(Some("rustfmt failed".into())),
+        );
+        let skipped = FormatResult::failed(
+            PathBuf::new(),
+            FormatError::UnsupportedExtension("xyz".into()),
+        );
+        let cancelled = FormatResult::failed(PathBuf::new(), FormatError::Cancelled(None));
+        let strict = FormatResult::failed(PathBuf::new(), FormatError::NoFormatter("xyz".into()));
+        let needs_formatting = FormatResult {
+            success: true,
+            changed: true,
+            ..Default::default()
+        };
+
+        assert!(failed.is_failure());
+        assert!(!skipped.is_failure());
+        assert_eq!(
+            skipped.error.as_deref(),
+            Some("Skipped: .xyz not supported")
+        );
+        assert!(!cancelled.is_failure());
+        assert!(strict.is_failure());
+        assert_eq!(
+            strict.error.as_deref(),
+            Some("Unsupported file type: .xyz has no formatter")
+        );
+        assert!(!needs_formatting.is_failure());
+    }
+
+    #[test]
+    fn test_format_error_kinds() {
+        let timeout = FormatError::from(&ZenithError::FormatterTimeout {
+            tool: "shfmt".into(),
+            seconds: 5,
+        });
+        assert_eq!(
+            timeout,
+            FormatError::Timeout(Some("Formatter 'shfmt' timed out after 5 seconds".into()))
+        );
+        assert_eq!(
+            serde_json::to_value(&timeout).unwrap(),
+            serde_json::json!({
+                "kind": "timeout",
+                "message": "Formatter 'shfmt' timed out after 5 seconds"
+            })
+        );
+        assert!(matches!(
+            FormatError::from(&ZenithError::ToolNotFound {
+                tool: "rustfmt".into()
+            }),
+            FormatError::ToolNotFound(_)
+        ));
+        assert!(matches!(
+            FormatError::from(&ZenithError::ZenithFailed {
+                name: "rust".into(),
+                reason: "expected `;`".into()
+            }),
+            FormatError::SyntaxInvalid(_)
+        ));
+
+        // Display 保留原有的字符串错误，没有消息时输出类别说明
+        assert_eq!(FormatError::WriteFailed(None).to_string(), "Write failed");
+        assert_eq!(
+            serde_json::to_value(FormatError::UnsupportedExtension("xyz".into())).unwrap(),
+            serde_json::json!({ "kind": "unsupported_extension", "message": "xyz" })
+        );
+        assert_eq!(
+            serde_json::to_value(FormatError::TimeBudgetExceeded).unwrap(),
+            serde_json::json!({ "kind": "time_budget_exceeded" })
+        );
+        assert_eq!(
+            FormatError::TimeBudgetExceeded.to_string(),
+            "Skipped: time budget exceeded"
+        );
+        let mut result = FormatResult::default();
+        result.fail(FormatError::Unsupported(Some(
+            "Skipped: binary file".into(),
+        )));
+        assert_eq!(result.error.as_deref(), Some("Skipped: binary file"));
+        assert!(!result.is_failure());
+    }
 }
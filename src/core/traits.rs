@@ -5,8 +5,10 @@
 
 use crate::config::types::ZenithConfig;
 use crate::error::Result;
+use crate::zeniths::pool::FormatterPool;
 use async_trait::async_trait;
 use std::path::Path;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 
 #[async_trait]
 pub trait Zenith: Send + Sync {
@@ -18,8 +20,82 @@ pub trait Zenith: Send + Sync {
         0
     }
 
+    /// Oldest tool version this formatter is known to work with, as
+    /// reported by `zenith doctor`.
+    fn min_version(&self) -> Option<&str> {
+        None
+    }
+
+    /// Whether the external tool behind this formatter is installed.
+    /// Built-in formatters are always available.
+    async fn is_available(&self) -> bool {
+        true
+    }
+
+    /// Whether formatting spawns an external process, which makes the
+    /// formatter wait-bound rather than CPU-bound. Used to pick the worker
+    /// count for `workers = "auto"`.
+    fn spawns_process(&self) -> bool {
+        false
+    }
+
+    /// Whether `ZenithConfig::range` limits the changes this formatter
+    /// makes; formatters that return `false` format the whole file.
+    fn supports_range(&self) -> bool {
+        false
+    }
+
+    /// Whether this formatter takes content that is not UTF-8 text, such as
+    /// binary data or legacy encodings. Other formatters never see files
+    /// with NUL bytes or invalid UTF-8, and get UTF-8 content without a BOM.
+    fn handles_binary(&self) -> bool {
+        false
+    }
+
+    /// Whether this formatter handles `path`, given the file's first line
+    /// when it could be read. Only consulted for files whose extension no
+    /// formatter registered, such as extensionless scripts with a shebang.
+    fn supports_path(&self, path: &Path, _first_line: Option<&str>) -> bool {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| self.extensions().contains(&ext))
+    }
+
     async fn format(&self, content: &[u8], path: &Path, config: &ZenithConfig) -> Result<Vec<u8>>;
 
+    /// Format `content` through a persistent process from `pool`, used when
+    /// `global.parallel_formatters` is set. The default ignores the pool and
+    /// calls [`Zenith::format`].
+    async fn format_pooled(
+        &self,
+        content: &[u8],
+        path: &Path,
+        config: &ZenithConfig,
+        _pool: &FormatterPool,
+    ) -> Result<Vec<u8>> {
+        self.format(content, path, config).await
+    }
+
+    /// Format everything read from `input` into `output`. Used for files
+    /// above `LimitsConfig::stream_threshold_mb`.
+    ///
+    /// The default buffers the whole input and calls [`Zenith::format`];
+    /// formatters backed by a stdio tool override it to pipe the file
+    /// through without holding it in memory.
+    async fn format_stream(
+        &self,
+        input: &mut (dyn AsyncRead + Unpin + Send),
+        output: &mut (dyn AsyncWrite + Unpin + Send),
+        path: &Path,
+        config: &ZenithConfig,
+    ) -> Result<()> {
+        let mut content = Vec::new();
+        input.read_to_end(&mut content).await?;
+        let formatted = self.format(&content, path, config).await?;
+        output.write_all(&formatted).await?;
+        Ok(())
+    }
+
     async fn validate(&self, _content: &[u8]) -> Result<bool> {
         Ok(true)
     }
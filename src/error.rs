@@ -56,6 +56,10 @@ pub enum ZenithError {
     #[error("Path traversal attempt detected: {0}")]
     PathTraversal(PathBuf),
 
+    /// 路径不在允许的根目录范围内（安全检查）。
+    #[error("Path is outside the allowed roots: {0}")]
+    OutsideAllowedRoot(PathBuf),
+
     /// 备份功能已禁用。
     #[error("Backup is disabled")]
     BackupDisabled,
@@ -99,6 +103,42 @@ pub enum ZenithError {
         required: String,
         actual: String,
     },
+
+    /// 已安装的工具版本与 `zenith.lock` 不一致。
+    #[error("Lock file mismatch: {0}")]
+    LockMismatch(String),
+
+    /// `overrides` 中指定的格式化工具未注册。
+    #[error("Override for .{ext} refers to unknown formatter '{name}'")]
+    UnknownFormatter { ext: String, name: String },
+
+    /// 读取或写入归档文件失败。
+    #[error("Archive error: {0}")]
+    Archive(String),
+
+    /// `--lang` 指定的格式化工具未注册。
+    #[error("Unknown formatter '{name}', available: {available}")]
+    UnknownLanguage { name: String, available: String },
+
+    /// 执行 git 命令失败。
+    #[error("Git error: {0}")]
+    Git(String),
+
+    /// 格式化工具对非空输入返回了空输出，视为失败以免清空文件。
+    #[error("Formatter '{tool}' produced empty output for non-empty input")]
+    FormatterEmptyOutput { tool: String },
+
+    /// 格式化工具超时未完成。
+    #[error("Formatter '{tool}' timed out after {seconds} seconds")]
+    FormatterTimeout { tool: String, seconds: u64 },
+
+    /// 外部插件超时未完成，进程已被终止。
+    #[error("Plugin '{name}' timed out after {seconds} seconds")]
+    PluginTimeout { name: String, seconds: u64 },
+
+    /// 格式化器无法无损重写该文件，文件保持原样并按跳过处理。
+    #[error("Skipped: {name} {reason}")]
+    ZenithSkipped { name: String, reason: String },
 }
 
 /// Zenith 库通用的 `Result` 类型。
@@ -174,6 +214,25 @@ mod tests {
         assert!(format!("{}", error).contains("Path traversal attempt detected"));
     }
 
+    #[test]
+    fn test_outside_allowed_root_error() {
+        let path = PathBuf::from("/etc/hosts");
+        let error = ZenithError::OutsideAllowedRoot(path);
+        assert!(format!("{}", error).contains("outside the allowed roots"));
+    }
+
+    #[test]
+    fn test_unknown_formatter_error() {
+        let error = ZenithError::UnknownFormatter {
+            ext: "ts".to_string(),
+            name: "deno".to_string(),
+        };
+        assert_eq!(
+            format!("{}", error),
+            "Override for .ts refers to unknown formatter 'deno'"
+        );
+    }
+
     #[test]
     fn test_version_incompatible_error() {
         let error = ZenithError::VersionIncompatible {
@@ -186,4 +245,46 @@ mod tests {
         assert!(format!("{}", error).contains(">= 2.0.0"));
         assert!(format!("{}", error).contains("1.5.0"));
     }
+
+    #[test]
+    fn test_formatter_empty_output_error() {
+        let error = ZenithError::FormatterEmptyOutput {
+            tool: "prettier".to_string(),
+        };
+        assert_eq!(
+            format!("{}", error),
+            "Formatter 'prettier' produced empty output for non-empty input"
+        );
+    }
+
+    #[test]
+    fn test_formatter_timeout_error() {
+        let error = ZenithError::FormatterTimeout {
+            tool: "shfmt".to_string(),
+            seconds: 5,
+        };
+        assert_eq!(
+            format!("{}", error),
+            "Formatter 'shfmt' timed out after 5 seconds"
+        );
+    }
+
+    #[test]
+    fn test_unknown_language_error() {
+        let error = ZenithError::UnknownLanguage {
+            name: "cobol".to_string(),
+            available: "rust, yaml".to_string(),
+        };
+        assert_eq!(
+            format!("{}", error),
+            "Unknown formatter 'cobol', available: rust, yaml"
+        );
+    }
+
+    #[test]
+    fn test_lock_mismatch_error() {
+        let error = ZenithError::LockMismatch("rustfmt: locked '1.7.0', installed '1.8.0'".into());
+        assert!(format!("{}", error).contains("Lock file mismatch"));
+        assert!(format!("{}", error).contains("rustfmt"));
+    }
 }
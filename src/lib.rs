@@ -22,17 +22,19 @@ pub(crate) mod mcp;
 pub(crate) mod services;
 
 pub use mcp::protocol::{
-    FileFormatResult, FormatParams, FormatResponseData, JsonRpcError, JsonRpcRequest,
-    JsonRpcResponse, RecoverParams, RecoverResponseData,
+    FileFormatResult, FormatContentParams, FormatContentResponseData, FormatParams,
+    FormatResponseData, FormatSummaryData, JsonRpcError, JsonRpcRequest, JsonRpcResponse,
+    RecoverParams, RecoverResponseData, ReloadPluginsResponseData,
 };
 
 #[doc(hidden)]
 pub mod internal {
-    pub use crate::cli::commands::{Cli, Commands};
+    pub use crate::cli::commands::{AnnotationFormat, Cli, Commands, LogFormat};
     pub use crate::config::load_config;
     pub use crate::mcp::server::McpServer;
     pub use crate::plugins::PluginLoader;
     pub use crate::services::formatter::ZenithService;
+    pub use crate::services::progress::{ProgressBarReporter, ProgressReporter};
     pub use crate::services::watch::{FileWatcher, WatchConfig};
     pub use crate::storage::backup::BackupService;
     pub use crate::storage::cache::HashCache;
@@ -41,6 +43,10 @@ pub mod internal {
 
     #[cfg(feature = "c")]
     pub use crate::zeniths::impls::c_zenith::ClangZenith;
+    #[cfg(feature = "go")]
+    pub use crate::zeniths::impls::go_zenith::GoZenith;
+    #[cfg(feature = "graphql")]
+    pub use crate::zeniths::impls::graphql_zenith::GraphqlZenith;
     #[cfg(feature = "ini")]
     pub use crate::zeniths::impls::ini_zenith::IniZenith;
     #[cfg(feature = "java")]
@@ -55,6 +61,12 @@ pub mod internal {
     pub use crate::zeniths::impls::rust_zenith::RustZenith;
     #[cfg(feature = "shell")]
     pub use crate::zeniths::impls::shell_zenith::ShellZenith;
+    #[cfg(feature = "sql")]
+    pub use crate::zeniths::impls::sql_zenith::SqlZenith;
     #[cfg(feature = "toml")]
     pub use crate::zeniths::impls::toml_zenith::TomlZenith;
+    #[cfg(feature = "vue")]
+    pub use crate::zeniths::impls::vue_zenith::VueZenith;
+    #[cfg(feature = "yaml")]
+    pub use crate::zeniths::impls::yaml_zenith::YamlZenith;
 }
@@ -8,20 +8,37 @@
 
 use clap::Parser;
 use colored::*;
+use std::collections::BTreeMap;
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 use tracing::{error, info, warn, Level};
-use zenith::config::load_config;
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+use zenith::config::lock::{LockFile, LOCK_FILE_NAME};
+use zenith::config::types::{
+    AppConfig, FormatError, PerformanceMetrics, UnsupportedReport, AUTO_WORKERS,
+};
+use zenith::config::{
+    config_schema, default_config_toml, load_config_with_profile, load_effective_config,
+    DEFAULT_CONFIG_FILE,
+};
 use zenith::error::Result;
 use zenith::internal::{
-    BackupService, Cli, Commands, EnvironmentChecker, FileWatcher, HashCache, McpServer,
-    PluginLoader, WatchConfig, ZenithRegistry, ZenithService,
+    AnnotationFormat, BackupService, Cli, Commands, EnvironmentChecker, FileWatcher, HashCache,
+    LogFormat, McpServer, PluginLoader, ProgressBarReporter, WatchConfig, ZenithRegistry,
+    ZenithService,
 };
 use zenith::plugins::loader::PluginSecurityConfig;
 use zenith::prelude::FormatResult;
+use zenith::utils::{annotations, git};
 
 #[cfg(feature = "c")]
 use zenith::internal::ClangZenith;
+#[cfg(feature = "go")]
+use zenith::internal::GoZenith;
+#[cfg(feature = "graphql")]
+use zenith::internal::GraphqlZenith;
 #[cfg(feature = "ini")]
 use zenith::internal::IniZenith;
 #[cfg(feature = "java")]
@@ -36,8 +53,14 @@ use zenith::internal::PythonZenith;
 use zenith::internal::RustZenith;
 #[cfg(feature = "shell")]
 use zenith::internal::ShellZenith;
+#[cfg(feature = "sql")]
+use zenith::internal::SqlZenith;
 #[cfg(feature = "toml")]
 use zenith::internal::TomlZenith;
+#[cfg(feature = "vue")]
+use zenith::internal::VueZenith;
+#[cfg(feature = "yaml")]
+use zenith::internal::YamlZenith;
 
 /// 程序的入口点。
 ///
@@ -48,18 +71,49 @@ use zenith::internal::TomlZenith;
 async fn main() -> Result<()> {
     let cli = Cli::parse();
 
-    // 设置日志级别
+    // 设置日志级别，安静模式下只保留错误
     let log_level = match cli.log_level.to_lowercase().as_str() {
+        _ if cli.quiet => Level::ERROR,
         "debug" => Level::DEBUG,
         "warn" => Level::WARN,
         "error" => Level::ERROR,
         _ => Level::INFO,
     };
+    let quiet = cli.quiet;
+
+    // 按 --no-color、NO_COLOR 和是否为终端决定是否输出颜色
+    let use_color = !cli.no_color
+        && std::env::var_os("NO_COLOR").is_none_or(|v| v.is_empty())
+        && std::io::stdout().is_terminal();
+    if !use_color {
+        colored::control::set_override(false);
+    }
 
-    tracing_subscriber::fmt().with_max_level(log_level).init();
+    // 日志写到 stderr 或 --log-file，保证 config-schema 等命令的 stdout 可以直接重定向使用
+    let log_writer = match &cli.log_file {
+        Some(path) => {
+            let file = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)?;
+            BoxMakeWriter::new(std::sync::Mutex::new(file))
+        }
+        None => BoxMakeWriter::new(std::io::stderr),
+    };
+    let subscriber = tracing_subscriber::fmt()
+        .with_max_level(log_level)
+        .with_writer(log_writer);
+    match cli.log_format {
+        LogFormat::Text => subscriber
+            .with_ansi(use_color && cli.log_file.is_none())
+            .init(),
+        LogFormat::Json => subscriber.json().init(),
+    }
 
     // 加载配置文件
-    let mut config = load_config(cli.config)?;
+    let config_path = cli.config.clone();
+    let profile = cli.profile.clone();
+    let mut config = load_config_with_profile(cli.config, cli.profile.as_deref())?;
 
     // 初始化插件加载器，应用安全配置
     let security_config = PluginSecurityConfig {
@@ -71,13 +125,17 @@ async fn main() -> Result<()> {
 
     // 从配置目录加载外部插件
     let plugins_dir = std::path::Path::new(&config.global.config_dir).join("plugins");
-    if let Err(e) = plugin_loader.load_plugins_from_dir(&plugins_dir).await {
-        error!("加载外部插件失败: {}", e);
-    } else {
-        info!(
+    match plugin_loader.load_plugins_from_dir(&plugins_dir).await {
+        Ok(failures) if failures.is_empty() => info!(
             "外部插件加载完成，共 {} 个插件",
             plugin_loader.list_plugins().len()
-        );
+        ),
+        Ok(failures) => warn!(
+            "外部插件加载完成，共 {} 个插件，{} 个插件加载失败",
+            plugin_loader.list_plugins().len(),
+            failures.len()
+        ),
+        Err(e) => error!("加载外部插件失败: {}", e),
     }
 
     // 初始化统一注册中心
@@ -91,7 +149,10 @@ async fn main() -> Result<()> {
     registry.register(Arc::new(PythonZenith));
 
     #[cfg(feature = "markdown")]
-    registry.register(Arc::new(MarkdownZenith));
+    {
+        MarkdownZenith::validate_regexes()?;
+        registry.register(Arc::new(MarkdownZenith));
+    }
 
     #[cfg(feature = "prettier")]
     registry.register(Arc::new(PrettierZenith));
@@ -111,12 +172,28 @@ async fn main() -> Result<()> {
     #[cfg(feature = "shell")]
     registry.register(Arc::new(ShellZenith));
 
+    #[cfg(feature = "graphql")]
+    registry.register(Arc::new(GraphqlZenith));
+
+    #[cfg(feature = "go")]
+    registry.register(Arc::new(GoZenith));
+
+    #[cfg(feature = "yaml")]
+    registry.register(Arc::new(YamlZenith));
+
+    #[cfg(feature = "sql")]
+    registry.register(Arc::new(SqlZenith));
+
+    #[cfg(feature = "vue")]
+    registry.register(Arc::new(VueZenith::new(Arc::downgrade(&registry))));
+
     // 注册已加载的外部插件
     for plugin_info in plugin_loader.list_plugins() {
         if let Some(plugin) = plugin_loader.get_plugin(&plugin_info.name) {
             registry.register(plugin);
         }
     }
+    config.validate_formatter_limits(&registry.names())?;
 
     // 根据命令执行相应的逻辑
     match cli.command {
@@ -124,10 +201,55 @@ async fn main() -> Result<()> {
             paths,
             recursive,
             no_backup,
+            backup_dir,
+            backup_label,
             workers,
             check,
             watch,
+            whitespace_only,
+            strict,
+            report_unsupported,
+            metrics,
+            by_formatter,
+            locked,
+            fail_fast,
+            parallel_formatters,
+            include_hidden,
+            max_depth,
+            verify_idempotent,
+            languages,
+            stage,
+            lines,
+            annotate,
+            out_dir,
+            time_budget,
         } => {
+            // 校验工具版本是否与 zenith.lock 一致
+            let lock_path = std::path::Path::new(LOCK_FILE_NAME);
+            if lock_path.exists() {
+                let lock = LockFile::load(lock_path)?;
+                let drifts = lock.verify(&EnvironmentChecker::check_all(registry.clone()).await);
+                if !drifts.is_empty() {
+                    for drift in &drifts {
+                        warn!("工具版本与 {} 不一致: {}", LOCK_FILE_NAME, drift);
+                        println!(
+                            "{}",
+                            format!("警告: 工具版本与 {} 不一致: {}", LOCK_FILE_NAME, drift)
+                                .yellow()
+                        );
+                    }
+                    if locked {
+                        let details: Vec<String> = drifts.iter().map(|d| d.to_string()).collect();
+                        return Err(zenith::error::ZenithError::LockMismatch(details.join("; ")));
+                    }
+                }
+            } else if locked {
+                return Err(zenith::error::ZenithError::LockMismatch(format!(
+                    "{} not found",
+                    LOCK_FILE_NAME
+                )));
+            }
+
             // 更新全局配置
             if recursive {
                 config.global.recursive = true;
@@ -135,9 +257,24 @@ async fn main() -> Result<()> {
             if no_backup {
                 config.global.backup_enabled = false;
             }
+            if let Some(dir) = backup_dir {
+                config.backup.dir = dir.to_string_lossy().into_owned();
+            }
             if let Some(w) = workers {
                 config.concurrency.workers = w;
             }
+            if strict {
+                config.global.fail_on_unsupported = true;
+            }
+            if parallel_formatters {
+                config.global.parallel_formatters = true;
+            }
+            if include_hidden {
+                config.global.include_hidden = true;
+            }
+            if max_depth.is_some() {
+                config.global.max_depth = max_depth;
+            }
 
             let mode_str = if check {
                 "检查模式 (CHECK MODE)"
@@ -146,21 +283,44 @@ async fn main() -> Result<()> {
             } else {
                 "写入模式 (WRITE MODE)"
             };
+            let workers_str = match config.concurrency.workers {
+                AUTO_WORKERS => "auto".to_string(),
+                workers => workers.to_string(),
+            };
             info!(
                 "正在启动 Zenith，模式：{}，工作线程数：{}...",
-                mode_str, config.concurrency.workers
+                mode_str, workers_str
             );
 
             // 初始化服务组件
-            let backup_service = Arc::new(BackupService::new(config.backup.clone()));
-            let hash_cache = Arc::new(HashCache::new());
-            let service = Arc::new(ZenithService::new(
+            let backup_service = Arc::new(BackupService::new(
+                config.backup.clone(),
+                backup_label.as_deref(),
+            ));
+            let hash_cache = if watch {
+                load_persistent_cache(&config).await
+            } else {
+                Arc::new(HashCache::new())
+            };
+            let mut service = ZenithService::new(
                 config.clone(),
-                registry,
+                registry.clone(),
                 backup_service,
                 hash_cache,
                 check,
-            ));
+            )
+            .with_whitespace_only(whitespace_only)
+            .with_fail_fast(fail_fast)
+            .with_verify_idempotent(verify_idempotent)
+            .with_range(lines)
+            .with_out_dir(out_dir)
+            .with_time_budget(time_budget)
+            .with_languages(&languages)?;
+            // 进度条只在交互式终端的一次性格式化中显示
+            if !watch && !quiet && std::io::stderr().is_terminal() {
+                service = service.with_progress(Arc::new(ProgressBarReporter::new()));
+            }
+            let service = Arc::new(service);
 
             // 如果是监听模式，启动文件监听
             if watch {
@@ -177,10 +337,12 @@ async fn main() -> Result<()> {
                 // 统计初始格式化结果
                 let total = initial_results.len();
                 let changed = initial_results.iter().filter(|r| r.changed).count();
-                println!(
-                    "\n{}",
-                    format!("初始格式化完成: {} 个文件中 {} 个已修改", total, changed).green()
-                );
+                if !quiet {
+                    println!(
+                        "\n{}",
+                        format!("初始格式化完成: {} 个文件中 {} 个已修改", total, changed).green()
+                    );
+                }
 
                 // 设置文件监听
                 let watch_config = WatchConfig {
@@ -189,7 +351,10 @@ async fn main() -> Result<()> {
                     recursive,
                 };
 
-                let mut watcher = match FileWatcher::new(watch_config, service.clone()) {
+                // 插件目录中的配置文件变化时重新加载插件
+                let mut watcher = match FileWatcher::new(watch_config, service.clone())
+                    .and_then(|w| w.with_plugin_reload(plugin_loader, registry.clone()))
+                {
                     Ok(w) => w,
                     Err(e) => {
                         error!("创建文件监听器失败: {}", e);
@@ -202,26 +367,48 @@ async fn main() -> Result<()> {
                     "正在监听 {} 个路径，按 Ctrl+C 停止...",
                     watcher.watched_paths()
                 );
-                println!("\n{}", "监听中... (按 Ctrl+C 停止)".cyan());
+                if !quiet {
+                    println!("\n{}", "监听中... (按 Ctrl+C 停止)".cyan());
+                }
 
-                // 启动监听循环
+                // 启动监听循环，收到 Ctrl+C 后处理完当前文件、写入缓存再退出
+                let formatted = Arc::new(AtomicUsize::new(0));
+                let modified = Arc::new(AtomicUsize::new(0));
+                let (formatted_count, modified_count) = (formatted.clone(), modified.clone());
                 watcher
-                    .start(move |path| {
+                    .start_until(ctrl_c(), move |path| {
                         let service = service.clone();
+                        let formatted = formatted_count.clone();
+                        let modified = modified_count.clone();
                         async move {
                             // 检查文件是否需要格式化
                             if !service.is_cached(&path).await {
                                 let result = service.format_file(path).await;
+                                formatted.fetch_add(1, Ordering::Relaxed);
                                 if result.changed {
-                                    println!(
-                                        "{}",
-                                        format!("  已格式化: {}", result.file_path.display())
-                                            .green()
-                                    );
+                                    modified.fetch_add(1, Ordering::Relaxed);
+                                    if !quiet {
+                                        println!(
+                                            "{}",
+                                            format!("  已格式化: {}", result.file_path.display())
+                                                .green()
+                                        );
+                                    }
+                                    if let Some(warning) = &result.warning {
+                                        println!(
+                                            "{}",
+                                            format!(
+                                                "  警告: {} -> {}",
+                                                result.file_path.display(),
+                                                warning
+                                            )
+                                            .yellow()
+                                        );
+                                    }
                                 } else if result.success {
                                     tracing::debug!("文件无需格式化: {:?}", result.file_path);
                                 } else if let Some(err) = &result.error {
-                                    if !err.starts_with("Skipped") {
+                                    if result.is_failure() {
                                         println!(
                                             "{}",
                                             format!(
@@ -243,11 +430,26 @@ async fn main() -> Result<()> {
                                     formatted_size: 0,
                                     duration_ms: 0,
                                     error: None,
+                                    error_kind: None,
+                                    formatter: None,
+                                    warning: None,
                                 }
                             }
                         }
                     })
                     .await;
+
+                if !quiet {
+                    println!(
+                        "\n{}",
+                        format!(
+                            "已停止监听: 共处理 {} 个文件，其中 {} 个已修改",
+                            formatted.load(Ordering::Relaxed),
+                            modified.load(Ordering::Relaxed)
+                        )
+                        .green()
+                    );
+                }
             } else {
                 // 非监听模式，一次性格式化
                 let string_paths: Vec<String> = paths
@@ -262,24 +464,121 @@ async fn main() -> Result<()> {
                 let changed = results.iter().filter(|r| r.changed).count();
                 let failed = total - success;
 
-                println!("\n{}", "执行摘要:".bold().underline());
-                println!("  文件总数: {}", total);
-                println!("  格式化成功: {}", success.to_string().green());
-                println!("  已修改:     {}", changed.to_string().yellow());
-                println!("  失败:       {}", failed.to_string().red());
+                if !quiet {
+                    println!("\n{}", "执行摘要:".bold().underline());
+                    println!("  文件总数: {}", total);
+                    println!("  格式化成功: {}", success.to_string().green());
+                    println!("  已修改:     {}", changed.to_string().yellow());
+                    println!("  失败:       {}", failed.to_string().red());
+                }
+
+                if by_formatter && !quiet {
+                    print_formatter_breakdown(&results);
+                }
 
                 // 打印失败详情
                 if failed > 0 {
                     println!("\n{}", "失败详情:".red().bold());
                     for res in results.iter().filter(|r| !r.success) {
                         if let Some(err) = &res.error {
-                            if !err.starts_with("Skipped") {
+                            if res.is_failure() {
                                 println!("  {} -> {}", res.file_path.display(), err);
                             }
                         }
                     }
                 }
 
+                // 打印警告（如 --verify-idempotent 发现的不稳定格式化工具）
+                let warned: Vec<_> = results.iter().filter(|r| r.warning.is_some()).collect();
+                if !warned.is_empty() {
+                    println!("\n{}", "警告:".yellow().bold());
+                    for res in warned {
+                        if let Some(warning) = &res.warning {
+                            println!("  {} -> {}", res.file_path.display(), warning);
+                        }
+                    }
+                }
+
+                // 超出 --time-budget 后未处理的文件
+                let over_budget = results
+                    .iter()
+                    .filter(|r| r.error_kind == Some(FormatError::TimeBudgetExceeded))
+                    .count();
+                if over_budget > 0 {
+                    println!(
+                        "\n{}",
+                        format!(
+                            "已超出时间预算 (--time-budget)：{} 个文件未处理。",
+                            over_budget
+                        )
+                        .yellow()
+                    );
+                }
+
+                // 将写入了改动的文件加入 git 暂存区
+                if stage {
+                    stage_changed_files(&results).await;
+                }
+
+                // 打印不支持的文件类型
+                if report_unsupported {
+                    let report = UnsupportedReport::from_results(&results);
+                    println!("\n{}", "不支持的文件类型:".bold().underline());
+                    if report.is_empty() {
+                        println!("  无");
+                    }
+                    for (ext, count) in report.sorted() {
+                        println!("  .{:<12} {}", ext, count);
+                    }
+                }
+
+                // 打印性能统计
+                if metrics {
+                    let stats = PerformanceMetrics::from_results(&results);
+                    println!("\n{}", "性能统计:".bold().underline());
+                    println!("  文件数:   {}", stats.total_files);
+                    println!("  平均耗时: {:.2} ms", stats.avg_duration_ms);
+                    println!(
+                        "  最小/最大: {} ms / {} ms",
+                        stats.min_duration_ms, stats.max_duration_ms
+                    );
+                    println!("  p95:      {:.2} ms", stats.p95_duration_ms);
+                    println!("  p99:      {:.2} ms", stats.p99_duration_ms);
+                    println!("  标准差:   {:.2} ms", stats.std_deviation_ms);
+                }
+
+                // 输出 CI 注解；在 GitHub Actions 中检查时自动启用
+                let annotate = annotate.or_else(|| {
+                    (check && annotations::running_in_github_actions())
+                        .then_some(AnnotationFormat::Github)
+                });
+                if annotate == Some(AnnotationFormat::Github) {
+                    for annotation in annotations::github_annotations(&results) {
+                        println!("{}", annotation);
+                    }
+                }
+
+                // fail-fast 模式下出现错误时，剩余文件已被跳过，以状态码 2 退出
+                if fail_fast && results.iter().any(|r| r.is_failure()) {
+                    println!(
+                        "\n{}",
+                        "已在首个错误后停止处理剩余文件 (--fail-fast)。".red()
+                    );
+                    std::process::exit(2);
+                }
+
+                // 严格模式下存在失败（包括不支持的文件）时以非零状态码退出
+                if config.global.fail_on_unsupported {
+                    let hard_failures = results.iter().filter(|r| r.is_failure()).count();
+                    if hard_failures > 0 {
+                        println!(
+                            "\n{}",
+                            format!("严格模式：{} 个文件处理失败。", hard_failures).red()
+                        );
+                        std::process::exit(1);
+                    }
+                }
+
                 // 如果是检查模式且有文件需要格式化，则以非零状态码退出
                 if check && changed > 0 {
                     println!("\n{}", "检查失败：部分文件需要格式化。".red());
@@ -287,13 +586,127 @@ async fn main() -> Result<()> {
                 }
             }
         }
+        Commands::Archive { archive, output } => {
+            info!("正在检查归档文件: {}", archive.display());
+            let service = ZenithService::new(
+                config.clone(),
+                registry,
+                Arc::new(BackupService::new(config.backup.clone(), None)),
+                Arc::new(HashCache::new()),
+                true,
+            );
+            let results = service
+                .format_archive_to(&archive, output.as_deref())
+                .await?;
+
+            let changed: Vec<_> = results.iter().filter(|r| r.changed).collect();
+            let failed: Vec<_> = results.iter().filter(|r| r.is_failure()).collect();
+            if !quiet {
+                println!("\n{}", "执行摘要:".bold().underline());
+                println!("  文件总数: {}", results.len());
+                println!("  需要格式化: {}", changed.len().to_string().yellow());
+                println!("  失败:       {}", failed.len().to_string().red());
+                for res in &changed {
+                    println!(
+                        "  {}",
+                        format!("需要格式化: {}", res.file_path.display()).yellow()
+                    );
+                }
+            }
+            for res in &failed {
+                if let Some(err) = &res.error {
+                    println!("  {} -> {}", res.file_path.display(), err);
+                }
+            }
+            if let Some(output) = &output {
+                if !quiet {
+                    println!("{}", format!("已写入 {}", output.display()).green());
+                }
+            }
+
+            // 写出新归档时，需要格式化的文件已被修正，只有失败才算错误
+            if !failed.is_empty() || (output.is_none() && !changed.is_empty()) {
+                std::process::exit(1);
+            }
+        }
+        Commands::Init { force, config_dir } => {
+            let path = std::path::Path::new(DEFAULT_CONFIG_FILE);
+            if path.exists() && !force {
+                let msg = format!("{} 已存在，使用 --force 覆盖。", DEFAULT_CONFIG_FILE);
+                error!("{}", msg);
+                eprintln!("{}", msg.red());
+                std::process::exit(1);
+            }
+            std::fs::write(path, default_config_toml()?)?;
+            println!("{}", format!("已写入 {}", DEFAULT_CONFIG_FILE).green());
+            info!("已生成默认配置文件 {}", DEFAULT_CONFIG_FILE);
+
+            if config_dir {
+                let plugins_dir = std::path::Path::new(&config.global.config_dir).join("plugins");
+                std::fs::create_dir_all(&plugins_dir)?;
+                println!("{}", format!("已创建 {}", plugins_dir.display()).green());
+            }
+        }
+        Commands::ConfigSchema => {
+            println!("{}", serde_json::to_string_pretty(&config_schema())?);
+        }
+        Commands::ConfigShow { dir } => {
+            let effective = load_effective_config(config_path, profile.as_deref(), &dir)?;
+            println!("{}", serde_json::to_string_pretty(&effective.redacted())?);
+        }
+        Commands::CacheStats { json } => {
+            let mut cache = HashCache::with_cache_dir(config.global.config_dir.clone().into());
+            cache.load().await?;
+            let stats = cache.stats().await;
+            let cache_file = cache.cache_file().unwrap_or_default();
+            if json {
+                let report = serde_json::json!({
+                    "cache_file": cache_file,
+                    "exists": cache_file.exists(),
+                    "entries": stats.entries,
+                    "valid_entries": stats.valid_entries,
+                    "expired_entries": stats.expired_entries,
+                    "average_age_secs": stats.average_age.map(|age| age.as_secs_f64()),
+                });
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                println!("缓存文件: {}", cache_file.display());
+                println!("条目总数: {}", stats.entries);
+                println!("有效条目: {}", stats.valid_entries);
+                println!("过期条目: {}", stats.expired_entries);
+                match stats.average_age {
+                    Some(age) => println!("平均缓存时长: {}s", age.as_secs()),
+                    None => println!("平均缓存时长: -"),
+                }
+            }
+        }
+        Commands::CacheClear => {
+            let cache = HashCache::with_cache_dir(config.global.config_dir.clone().into());
+            let cache_file = cache.cache_file().unwrap_or_default();
+            if cache.clear_persisted().await? {
+                let msg = format!("已删除缓存文件: {}", cache_file.display());
+                println!("{}", msg.green());
+                info!("{}", msg);
+            } else {
+                println!("未发现缓存文件: {}", cache_file.display());
+            }
+        }
         Commands::Doctor { verbose } => {
             info!("正在检查系统环境...");
-            let results = EnvironmentChecker::check_all(registry);
+            let results = EnvironmentChecker::check_all(registry).await;
             let summary = EnvironmentChecker::print_results(&results, verbose);
 
             println!();
 
+            if summary.outdated_tools > 0 {
+                let msg = format!(
+                    "警告: {} 个工具低于最低版本要求，格式化结果可能与预期不一致。",
+                    summary.outdated_tools
+                );
+                warn!("{}", msg);
+                println!("{}", msg.yellow());
+            }
+
             if summary.missing_tools > 0 {
                 let msg = format!(
                     "警告: 缺失 {} 个工具。某些格式化功能可能无法正常工作。",
@@ -307,24 +720,40 @@ async fn main() -> Result<()> {
                 info!("环境检查完成，所有工具均可用");
             }
         }
+        Commands::Lock => {
+            info!("正在生成 {}...", LOCK_FILE_NAME);
+            let results = EnvironmentChecker::check_all(registry).await;
+            let lock = LockFile::from_statuses(&results);
+            lock.save(std::path::Path::new(LOCK_FILE_NAME))?;
+
+            let msg = format!(
+                "已写入 {}，共锁定 {} 个工具。",
+                LOCK_FILE_NAME,
+                lock.tools.len()
+            );
+            println!("{}", msg.green());
+            info!("{}", msg);
+        }
         Commands::ListBackups => {
-            let backup_service = BackupService::new(config.backup.clone());
+            let backup_service = BackupService::new(config.backup.clone(), None);
             match backup_service.list_backups().await {
                 Ok(backups) => {
                     if backups.is_empty() {
                         println!("未发现备份。");
                     } else {
                         println!(
-                            "{:<30} | {:<20} | {:<10}",
-                            "备份 ID (Backup ID)", "创建时间", "大小"
+                            "{:<30} | {:<20} | {:<20} | {:<10}",
+                            "备份 ID (Backup ID)", "标签", "创建时间", "大小"
                         );
-                        println!("{:-<30}-|-{:-<20}-|-{:-<10}", "", "", "");
+                        println!("{:-<30}-|-{:-<20}-|-{:-<20}-|-{:-<10}", "", "", "", "");
                         for (id, time, size) in backups {
                             let datetime: chrono::DateTime<chrono::Local> = time.into();
                             let size_mb = size as f64 / 1024.0 / 1024.0;
+                            let label = BackupService::session_label(&id).unwrap_or("-");
                             println!(
-                                "{:<30} | {:<20} | {:.2} MB",
+                                "{:<30} | {:<20} | {:<20} | {:.2} MB",
                                 id,
+                                label,
                                 datetime.format("%Y-%m-%d %H:%M"),
                                 size_mb
                             );
@@ -334,11 +763,32 @@ async fn main() -> Result<()> {
                 Err(e) => error!("列出备份失败: {}", e),
             }
         }
-        Commands::Recover { backup_id, target } => {
-            info!("正在恢复备份 '{}'...", backup_id);
-            let backup_service = BackupService::new(config.backup.clone());
-            println!("正在恢复备份 '{}'...", backup_id);
-            match backup_service.recover(&backup_id, target).await {
+        Commands::Recover {
+            backup_id,
+            target,
+            since,
+            until,
+        } => {
+            let backup_service = BackupService::new(config.backup.clone(), None);
+            let result = match backup_id {
+                Some(backup_id) => {
+                    info!("正在恢复备份 '{}'...", backup_id);
+                    println!("正在恢复备份 '{}'...", backup_id);
+                    backup_service.recover(&backup_id, target).await
+                }
+                None => {
+                    info!("正在按时间范围批量恢复备份...");
+                    println!("正在按时间范围批量恢复备份...");
+                    backup_service
+                        .recover_range(
+                            since.unwrap_or(std::time::UNIX_EPOCH),
+                            until.unwrap_or_else(std::time::SystemTime::now),
+                            target,
+                        )
+                        .await
+                }
+            };
+            match result {
                 Ok(count) => {
                     let msg = format!("成功恢复 {} 个文件。", count);
                     println!("{}", msg.green());
@@ -350,9 +800,13 @@ async fn main() -> Result<()> {
                 }
             }
         }
-        Commands::CleanBackups { days } => {
+        Commands::CleanBackups { days, max_sessions } => {
             info!("正在清理 {} 天前的备份...", days);
-            let backup_service = BackupService::new(config.backup.clone());
+            let mut backup_config = config.backup.clone();
+            if max_sessions.is_some() {
+                backup_config.max_sessions = max_sessions;
+            }
+            let backup_service = BackupService::new(backup_config, None);
             println!("正在清理 {} 天前的备份...", days);
             match backup_service.clean_backups(days).await {
                 Ok(count) => {
@@ -371,14 +825,15 @@ async fn main() -> Result<()> {
                 .parse()
                 .map_err(|_| zenith::error::ZenithError::Config("无效的地址".into()))?;
 
-            let hash_cache = Arc::new(HashCache::new());
-            let server = McpServer::new(config, registry, hash_cache);
+            let hash_cache = load_persistent_cache(&config).await;
+            let server =
+                McpServer::new(config, registry, hash_cache).with_plugin_loader(plugin_loader);
             server.run(socket_addr).await?;
         }
         Commands::AutoRollback => {
             info!("正在启动自动回滚到最新备份...");
 
-            let backup_service = Arc::new(BackupService::new(config.backup.clone()));
+            let backup_service = Arc::new(BackupService::new(config.backup.clone(), None));
             let hash_cache = Arc::new(HashCache::new());
             let service = ZenithService::new(config, registry, backup_service, hash_cache, false);
 
@@ -404,3 +859,94 @@ async fn main() -> Result<()> {
 
     Ok(())
 }
+
+/// 为长时间运行的模式（监听、MCP 服务）创建持久化的哈希缓存。
+///
+/// 缓存保存在配置目录中，启动时读取上次退出时写入的内容；
+/// 未启用缓存时返回仅存在于内存中的缓存。
+async fn load_persistent_cache(config: &AppConfig) -> Arc<HashCache> {
+    if !config.global.cache_enabled {
+        return Arc::new(HashCache::new());
+    }
+
+    let mut cache = HashCache::with_cache_dir(config.global.config_dir.clone().into());
+    if let Err(e) = cache.load().await {
+        warn!("读取缓存失败，将使用空缓存: {}", e);
+    }
+    Arc::new(cache)
+}
+
+/// 按格式化工具分组输出文件数、修改数和失败数（`--by-formatter`）。
+///
+/// 没有选中格式化工具的文件（如不支持的文件）不计入。
+fn print_formatter_breakdown(results: &[FormatResult]) {
+    let mut groups: BTreeMap<&str, (usize, usize, usize)> = BTreeMap::new();
+    for result in results {
+        if let Some(formatter) = &result.formatter {
+            let (files, changed, failed) = groups.entry(formatter).or_default();
+            *files += 1;
+            *changed += usize::from(result.changed);
+            *failed += usize::from(result.is_failure());
+        }
+    }
+
+    println!("\n{}", "按格式化工具:".bold().underline());
+    if groups.is_empty() {
+        println!("  无");
+    }
+    for (formatter, (files, changed, failed)) in groups {
+        println!(
+            "  {:<20} 文件: {:<6} 已修改: {:<6} 失败: {}",
+            formatter, files, changed, failed
+        );
+    }
+}
+
+/// 对写入了改动的文件执行 `git add`（`--stage`）。
+///
+/// 不在 git 仓库中或 `git add` 失败时只给出警告，不影响格式化结果。
+async fn stage_changed_files(results: &[FormatResult]) {
+    let changed: Vec<_> = results
+        .iter()
+        .filter(|r| r.success && r.changed)
+        .map(|r| r.file_path.clone())
+        .collect();
+    if changed.is_empty() {
+        return;
+    }
+
+    let cwd = match std::env::current_dir() {
+        Ok(cwd) => cwd,
+        Err(e) => {
+            warn!("无法获取当前目录，跳过暂存: {}", e);
+            return;
+        }
+    };
+    if !git::is_work_tree(&cwd).await {
+        warn!("当前目录不在 git 仓库中，跳过 --stage");
+        println!(
+            "{}",
+            "警告: 当前目录不在 git 仓库中，未暂存任何文件。".yellow()
+        );
+        return;
+    }
+    match git::stage(&cwd, &changed).await {
+        Ok(()) => println!(
+            "{}",
+            format!("已暂存 {} 个格式化后的文件。", changed.len()).green()
+        ),
+        Err(e) => {
+            warn!("暂存格式化后的文件失败: {}", e);
+            println!("{}", format!("警告: 暂存文件失败: {}", e).yellow());
+        }
+    }
+}
+
+/// 等待 Ctrl+C 信号。
+async fn ctrl_c() {
+    if let Err(e) = tokio::signal::ctrl_c().await {
+        warn!("无法监听 Ctrl+C 信号: {}", e);
+        std::future::pending::<()>().await;
+    }
+    info!("收到 Ctrl+C，正在停止...");
+}
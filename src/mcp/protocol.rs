@@ -3,7 +3,9 @@
 // Licensed under the MIT License
 // See LICENSE file in the project root for full license information.
 
+use crate::config::types::{FormatError, UnsupportedReport};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::PathBuf;
 
 #[derive(Debug, Deserialize)]
@@ -36,6 +38,9 @@ pub struct FormatParams {
     #[serde(default)]
     pub backup: bool,
     pub workers: Option<usize>,
+    /// Report which files would change without writing them or taking a backup
+    #[serde(default)]
+    pub check: bool,
 }
 
 #[derive(Debug, Serialize)]
@@ -45,15 +50,52 @@ pub struct FormatResponseData {
     pub failed_files: usize,
     pub backup_id: Option<String>,
     pub duration_ms: u64,
+    pub unsupported: UnsupportedReport,
+    /// Milliseconds spent on the files of each formatter, summed over the batch
+    pub per_formatter_ms: HashMap<String, u64>,
     pub results: Vec<FileFormatResult>,
 }
 
+/// Final event of a `/format/stream` response
+#[derive(Debug, Serialize)]
+pub struct FormatSummaryData {
+    pub total_files: usize,
+    pub formatted_files: usize,
+    pub failed_files: usize,
+    pub backup_id: Option<String>,
+    pub duration_ms: u64,
+}
+
 #[derive(Debug, Serialize)]
 pub struct FileFormatResult {
     pub path: PathBuf,
     pub success: bool,
     pub changed: bool,
+    /// Name of the formatter that handled the file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub formatter: Option<String>,
     pub error: Option<String>,
+    /// Category of `error`, for clients that act on the kind of failure
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_kind: Option<FormatError>,
+}
+
+/// Parameters of `format_content`, which formats text sent in the request
+#[derive(Debug, Deserialize)]
+pub struct FormatContentParams {
+    /// Selects the formatter and the project config; the file is not read
+    pub path: PathBuf,
+    pub content: String,
+    /// Lines `[start, end]` to format, 1-based and inclusive
+    #[serde(default)]
+    pub range: Option<(usize, usize)>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FormatContentResponseData {
+    pub content: String,
+    pub changed: bool,
+    pub duration_ms: u64,
 }
 
 #[derive(Debug, Deserialize)]
@@ -67,3 +109,10 @@ pub struct RecoverResponseData {
     pub restored_files: usize,
     pub duration_ms: u64,
 }
+
+/// Result of `reload_plugins`
+#[derive(Debug, Serialize)]
+pub struct ReloadPluginsResponseData {
+    /// Names of the plugins loaded after the rescan, sorted
+    pub plugins: Vec<String>,
+}
@@ -3,27 +3,40 @@
 // Licensed under the MIT License
 // See LICENSE file in the project root for full license information.
 
-use crate::config::types::AppConfig;
+use crate::config::types::{AppConfig, FormatResult, UnsupportedReport};
 use crate::mcp::protocol::*;
+use crate::plugins::loader::PluginLoader;
 use crate::services::formatter::ZenithService;
 use crate::storage::backup::BackupService;
 use crate::storage::cache::HashCache;
+use crate::utils::path::{validate_path, validate_within_roots};
 use crate::zeniths::registry::ZenithRegistry;
 use axum::{
     extract::State,
-    http::{HeaderMap, StatusCode},
-    routing::post,
+    http::{header, HeaderMap, HeaderValue, Method, StatusCode},
+    response::sse::{Event, KeepAlive, Sse},
+    routing::{get, post},
     Json, Router,
 };
+use futures::stream::{self, BoxStream, StreamExt};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::future::Future;
 use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::net::TcpListener;
-use tracing::{info, warn};
+use tokio::sync::Mutex;
+use tower_http::cors::{AllowOrigin, CorsLayer};
+use tower_http::trace::{DefaultOnResponse, TraceLayer};
+use tower_http::LatencyUnit;
+use tracing::{info, warn, Level};
 
 pub struct McpServer {
     config: AppConfig,
     registry: Arc<ZenithRegistry>,
     hash_cache: Arc<HashCache>,
+    plugin_loader: Option<Arc<Mutex<PluginLoader>>>,
 }
 
 impl McpServer {
@@ -36,30 +49,112 @@ impl McpServer {
             config,
             registry,
             hash_cache,
+            plugin_loader: None,
         }
     }
 
-    pub async fn run(&self, addr: SocketAddr) -> crate::error::Result<()> {
+    /// Let admins rescan the loader's plugins directory with `reload_plugins`
+    pub fn with_plugin_loader(mut self, plugin_loader: PluginLoader) -> Self {
+        self.plugin_loader = Some(Arc::new(Mutex::new(plugin_loader)));
+        self
+    }
+
+    /// Build the HTTP router with all MCP endpoints and the auth middleware
+    pub fn router(&self) -> Router {
         let app_state = Arc::new(AppState {
             config: self.config.clone(),
             registry: self.registry.clone(),
             hash_cache: self.hash_cache.clone(),
+            plugin_loader: self.plugin_loader.clone(),
         });
 
-        let app = Router::new()
+        // Routes added after the auth layer are reachable without credentials
+        let router = Router::new()
             .route("/", post(handle_json_rpc))
+            .route("/format/stream", post(handle_format_stream))
             .route_layer(axum::middleware::from_fn_with_state(
                 app_state.clone(),
                 auth_middleware,
             ))
-            .with_state(app_state);
+            .route("/health", get(handle_health))
+            .with_state(app_state)
+            .layer(self.cors_layer());
+
+        if !self.config.mcp.request_logging {
+            return router;
+        }
+        router.layer(
+            TraceLayer::new_for_http()
+                .make_span_with(|request: &axum::extract::Request| {
+                    tracing::info_span!(
+                        "mcp_request",
+                        method = %request.method(),
+                        uri = %request.uri(),
+                        rpc_method = tracing::field::Empty,
+                    )
+                })
+                .on_response(
+                    DefaultOnResponse::new()
+                        .level(Level::INFO)
+                        .latency_unit(LatencyUnit::Millis),
+                ),
+        )
+    }
+
+    /// CORS policy from `allowed_origins`; `"*"` allows any origin
+    ///
+    /// Preflight requests are answered here, before authentication.
+    fn cors_layer(&self) -> CorsLayer {
+        let origins = &self.config.mcp.allowed_origins;
+        let allow_origin = if origins.iter().any(|origin| origin == "*") {
+            AllowOrigin::any()
+        } else {
+            AllowOrigin::list(origins.iter().filter_map(|origin| {
+                HeaderValue::from_str(origin)
+                    .inspect_err(|_| warn!("Ignoring invalid CORS origin: {}", origin))
+                    .ok()
+            }))
+        };
+        CorsLayer::new()
+            .allow_origin(allow_origin)
+            .allow_methods([Method::GET, Method::POST])
+            .allow_headers([header::AUTHORIZATION, header::CONTENT_TYPE])
+    }
+
+    /// Serve until Ctrl+C is pressed
+    pub async fn run(&self, addr: SocketAddr) -> crate::error::Result<()> {
+        self.run_until(addr, async {
+            if let Err(e) = tokio::signal::ctrl_c().await {
+                warn!("Failed to listen for Ctrl+C: {}", e);
+                std::future::pending::<()>().await;
+            }
+            info!("Received Ctrl+C, shutting down MCP server");
+        })
+        .await
+    }
+
+    /// Serve until `shutdown` resolves.
+    ///
+    /// New connections are refused once the signal arrives, requests already
+    /// in flight are allowed to finish, and the hash cache is flushed to disk
+    /// before returning.
+    pub async fn run_until<S>(&self, addr: SocketAddr, shutdown: S) -> crate::error::Result<()>
+    where
+        S: Future<Output = ()> + Send + 'static,
+    {
+        let app = self.router();
 
         info!(
             "MCP Server listening on {} (auth: {})",
             addr, self.config.mcp.auth_enabled
         );
         let listener = TcpListener::bind(addr).await?;
-        axum::serve(listener, app).await?;
+        axum::serve(listener, app)
+            .with_graceful_shutdown(shutdown)
+            .await?;
+
+        self.hash_cache.save().await?;
+        info!("MCP Server stopped");
         Ok(())
     }
 }
@@ -68,6 +163,7 @@ struct AppState {
     config: AppConfig,
     registry: Arc<ZenithRegistry>,
     hash_cache: Arc<HashCache>,
+    plugin_loader: Option<Arc<Mutex<PluginLoader>>>,
 }
 
 #[derive(Debug, Clone)]
@@ -75,6 +171,8 @@ struct AppState {
 struct UserContext {
     api_key: String,
     role: String,
+    /// Roots the user's `format` paths must lie in; empty means anywhere
+    allowed_paths: Vec<PathBuf>,
 }
 
 async fn auth_middleware(
@@ -89,6 +187,7 @@ async fn auth_middleware(
         let user_context = UserContext {
             api_key: "[auth-disabled]".into(),
             role: "user".into(),
+            allowed_paths: Vec::new(),
         };
         request.extensions_mut().insert(user_context);
         return Ok(next.run(request).await);
@@ -110,12 +209,25 @@ async fn auth_middleware(
                         let user_context = UserContext {
                             api_key: user.api_key.clone(),
                             role: user.role.clone(),
+                            allowed_paths: user.allowed_paths.clone(),
                         };
                         request.extensions_mut().insert(user_context);
                         return Ok(next.run(request).await);
                     }
                 }
 
+                // The top-level api_key acts as an implicit admin user
+                let master_key = state.config.mcp.api_key.as_deref();
+                if master_key.is_some_and(|key| !key.is_empty() && key == token) {
+                    let user_context = UserContext {
+                        api_key: token.to_string(),
+                        role: "admin".into(),
+                        allowed_paths: Vec::new(),
+                    };
+                    request.extensions_mut().insert(user_context);
+                    return Ok(next.run(request).await);
+                }
+
                 warn!("Invalid or unknown authorization token in request");
                 return Err(StatusCode::UNAUTHORIZED);
             }
@@ -133,16 +245,70 @@ async fn auth_middleware(
 fn check_method_permission(method: &str, role: &str) -> bool {
     match role {
         "admin" => true,
-        "user" => matches!(method, "format" | "recover"),
-        "readonly" => method == "format",
+        "user" => matches!(method, "format" | "format_content" | "recover"),
+        "readonly" => matches!(method, "format" | "format_content"),
         _ => false,
     }
 }
 
+/// Reject paths outside the user's `allowed_paths`
+///
+/// Paths are checked for `..` segments first, then resolved, so neither
+/// traversal nor symlinks lead out of an allowed root.
+fn check_path_permission(
+    user_context: &UserContext,
+    paths: &[PathBuf],
+) -> Result<(), JsonRpcError> {
+    if user_context.allowed_paths.is_empty() {
+        return Ok(());
+    }
+    for path in paths {
+        validate_path(path)
+            .and_then(|()| validate_within_roots(path, &user_context.allowed_paths))
+            .map_err(|e| JsonRpcError {
+                code: 1006,
+                message: format!("Permission denied: {}", e),
+            })?;
+    }
+    Ok(())
+}
+
+/// Liveness probe for load balancers and orchestrators; needs no auth
+async fn handle_health() -> Json<serde_json::Value> {
+    Json(serde_json::json!({ "status": "ok" }))
+}
+
+/// A request without an `id` is a notification: the method still runs, but
+/// per JSON-RPC 2.0 no response object is sent back, only `204 No Content`
 async fn handle_json_rpc(
     axum::extract::State(state): axum::extract::State<Arc<AppState>>,
     user_context: Option<axum::Extension<UserContext>>,
     Json(req): Json<JsonRpcRequest>,
+) -> axum::response::Response {
+    use axum::response::IntoResponse;
+
+    // Shows up in the request log when `request_logging` is enabled
+    tracing::Span::current().record("rpc_method", req.method.as_str());
+
+    let notification = req.id.is_none();
+    let method = req.method.clone();
+    let Json(response) = dispatch_json_rpc(state, user_context, req).await;
+    if !notification {
+        return Json(response).into_response();
+    }
+    if let Some(error) = &response.error {
+        warn!(
+            "Notification '{}' failed ({}): {}",
+            method, error.code, error.message
+        );
+    }
+    StatusCode::NO_CONTENT.into_response()
+}
+
+async fn dispatch_json_rpc(
+    state: Arc<AppState>,
+    user_context: Option<axum::Extension<UserContext>>,
+    req: JsonRpcRequest,
 ) -> Json<JsonRpcResponse<serde_json::Value>> {
     let user_context = match user_context {
         Some(ctx) => ctx.0,
@@ -175,8 +341,10 @@ async fn handle_json_rpc(
     }
 
     let response = match req.method.as_str() {
-        "format" => handle_format(state, req.params).await,
-        "recover" => handle_recover(state, req.params).await,
+        "format" => handle_format(state, &user_context, req.params).await,
+        "format_content" => handle_format_content(state, &user_context, req.params).await,
+        "recover" => handle_recover(state, &user_context, req.params).await,
+        "reload_plugins" => handle_reload_plugins(state).await,
         _ => Err(JsonRpcError {
             code: -32601,
             message: "Method not found".into(),
@@ -201,6 +369,7 @@ async fn handle_json_rpc(
 
 async fn handle_format(
     state: Arc<AppState>,
+    user_context: &UserContext,
     params: Option<serde_json::Value>,
 ) -> Result<serde_json::Value, JsonRpcError> {
     let params: FormatParams = serde_json::from_value(params.unwrap_or(serde_json::Value::Null))
@@ -208,27 +377,14 @@ async fn handle_format(
             code: -32602,
             message: "Invalid params".into(),
         })?;
+    check_path_permission(user_context, &params.paths)?;
 
-    let mut config = state.config.clone();
-    config.global.recursive = params.recursive;
-    config.global.backup_enabled = params.backup;
-    if let Some(w) = params.workers {
-        config.concurrency.workers = w;
-    }
-
-    let backup_service = Arc::new(BackupService::new(config.backup.clone()));
-    let service = ZenithService::new(
-        config,
-        state.registry.clone(),
-        backup_service.clone(),
-        state.hash_cache.clone(),
-        false,
-    );
+    let (service, backup_service) = build_format_service(&state, &params);
 
     let start = std::time::Instant::now();
     let string_paths: Vec<String> = params
         .paths
-        .into_iter()
+        .iter()
         .map(|p| p.to_string_lossy().into_owned())
         .collect();
     let results = service
@@ -248,15 +404,19 @@ async fn handle_format(
         total_files: total,
         formatted_files: success,
         failed_files: failed,
-        backup_id: Some(backup_service.get_session_id().to_string()),
+        backup_id: backup_id(&backup_service, &params),
         duration_ms: duration,
+        unsupported: UnsupportedReport::from_results(&results),
+        per_formatter_ms: per_formatter_ms(&results),
         results: results
             .into_iter()
             .map(|r| FileFormatResult {
                 path: r.file_path,
                 success: r.success,
                 changed: r.changed,
+                formatter: r.formatter,
                 error: r.error,
+                error_kind: r.error_kind,
             })
             .collect(),
     };
@@ -267,8 +427,196 @@ async fn handle_format(
     })
 }
 
+/// Total time spent per formatter, from the formatter recorded on each result
+fn per_formatter_ms(results: &[FormatResult]) -> HashMap<String, u64> {
+    let mut totals = HashMap::new();
+    for result in results {
+        if let Some(formatter) = &result.formatter {
+            *totals.entry(formatter.clone()).or_insert(0) += result.duration_ms;
+        }
+    }
+    totals
+}
+
+/// Create a formatting service for a single `format` request
+fn build_format_service(
+    state: &AppState,
+    params: &FormatParams,
+) -> (ZenithService, Arc<BackupService>) {
+    let mut config = state.config.clone();
+    config.global.recursive = params.recursive;
+    config.global.backup_enabled = params.backup && !params.check;
+    if let Some(w) = params.workers {
+        config.concurrency.workers = w;
+    }
+
+    let backup_service = Arc::new(BackupService::new(config.backup.clone(), None));
+    let service = ZenithService::new(
+        config,
+        state.registry.clone(),
+        backup_service.clone(),
+        state.hash_cache.clone(),
+        params.check,
+    );
+    (service, backup_service)
+}
+
+/// Session a `format` request backed files up to; check mode takes no backup
+fn backup_id(backup_service: &BackupService, params: &FormatParams) -> Option<String> {
+    (!params.check).then(|| backup_service.get_session_id().to_string())
+}
+
+/// Stream `format` results as Server-Sent Events.
+///
+/// Emits one `result` event per file as it completes, then a `summary` event.
+/// If the request fails before any file is processed, a single `error` event
+/// carrying a `JsonRpcError` is sent instead.
+async fn handle_format_stream(
+    State(state): State<Arc<AppState>>,
+    user_context: Option<axum::Extension<UserContext>>,
+    Json(params): Json<FormatParams>,
+) -> Result<Sse<BoxStream<'static, Result<Event, Infallible>>>, StatusCode> {
+    let user_context = user_context
+        .map(|ctx| ctx.0)
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+    if !check_method_permission("format", &user_context.role) {
+        warn!(
+            "Permission denied for format stream with role '{}'",
+            user_context.role
+        );
+        return Err(StatusCode::FORBIDDEN);
+    }
+    if let Err(error) = check_path_permission(&user_context, &params.paths) {
+        let event = json_event("error", &error);
+        return Ok(Sse::new(stream::iter(vec![Ok(event)]).boxed()));
+    }
+
+    let (service, backup_service) = build_format_service(&state, &params);
+    let backup_id = backup_id(&backup_service, &params);
+    let start = std::time::Instant::now();
+    let string_paths: Vec<String> = params
+        .paths
+        .into_iter()
+        .map(|p| p.to_string_lossy().into_owned())
+        .collect();
+
+    let receiver = match service.format_paths_streaming(string_paths).await {
+        Ok(receiver) => receiver,
+        Err(e) => {
+            let error = JsonRpcError {
+                code: 1003,
+                message: e.to_string(),
+            };
+            let event = json_event("error", &error);
+            return Ok(Sse::new(stream::iter(vec![Ok(event)]).boxed()));
+        }
+    };
+
+    let stream = stream::unfold(
+        (Some(receiver), 0usize, 0usize),
+        move |(receiver, total, success)| {
+            let backup_id = backup_id.clone();
+            async move {
+                let mut receiver = receiver?;
+                match receiver.recv().await {
+                    Some(result) => {
+                        let success = success + usize::from(result.success);
+                        let event = json_event(
+                            "result",
+                            &FileFormatResult {
+                                path: result.file_path,
+                                success: result.success,
+                                changed: result.changed,
+                                formatter: result.formatter,
+                                error: result.error,
+                                error_kind: result.error_kind,
+                            },
+                        );
+                        Some((Ok(event), (Some(receiver), total + 1, success)))
+                    }
+                    None => {
+                        let summary = FormatSummaryData {
+                            total_files: total,
+                            formatted_files: success,
+                            failed_files: total - success,
+                            backup_id,
+                            duration_ms: start.elapsed().as_millis() as u64,
+                        };
+                        Some((Ok(json_event("summary", &summary)), (None, total, success)))
+                    }
+                }
+            }
+        },
+    );
+
+    Ok(Sse::new(stream.boxed()).keep_alive(KeepAlive::default()))
+}
+
+/// Build a named SSE event with a JSON payload
+fn json_event<T: serde::Serialize>(name: &str, data: &T) -> Event {
+    Event::default()
+        .event(name)
+        .json_data(data)
+        .unwrap_or_else(|_| Event::default().event("error").data("Serialization error"))
+}
+
+/// Format text sent in the request without touching the file system
+async fn handle_format_content(
+    state: Arc<AppState>,
+    user_context: &UserContext,
+    params: Option<serde_json::Value>,
+) -> Result<serde_json::Value, JsonRpcError> {
+    let params: FormatContentParams =
+        serde_json::from_value(params.unwrap_or(serde_json::Value::Null))
+            .ok()
+            .filter(|p: &FormatContentParams| {
+                p.range
+                    .is_none_or(|(start, end)| start >= 1 && start <= end)
+            })
+            .ok_or_else(|| JsonRpcError {
+                code: -32602,
+                message: "Invalid params".into(),
+            })?;
+    // The path picks the project config, so it must be one the user may format
+    check_path_permission(user_context, std::slice::from_ref(&params.path))?;
+
+    let service = ZenithService::new(
+        state.config.clone(),
+        state.registry.clone(),
+        Arc::new(BackupService::new(state.config.backup.clone(), None)),
+        state.hash_cache.clone(),
+        true,
+    )
+    .with_range(params.range);
+
+    let start = std::time::Instant::now();
+    let (formatted, changed) = service
+        .format_content(&params.path, params.content.as_bytes())
+        .await
+        .map_err(|e| JsonRpcError {
+            code: 1003,
+            message: e.to_string(),
+        })?;
+
+    let response = FormatContentResponseData {
+        content: String::from_utf8_lossy(&formatted).into_owned(),
+        changed,
+        duration_ms: start.elapsed().as_millis() as u64,
+    };
+
+    serde_json::to_value(response).map_err(|_| JsonRpcError {
+        code: -32603,
+        message: "Serialization error".into(),
+    })
+}
+
+/// Restore a backup session into `target`, or the working directory
+///
+/// For users with `allowed_paths` the target must lie within them, since
+/// restoring writes every file of the session.
 async fn handle_recover(
     state: Arc<AppState>,
+    user_context: &UserContext,
     params: Option<serde_json::Value>,
 ) -> Result<serde_json::Value, JsonRpcError> {
     let params: RecoverParams = serde_json::from_value(params.unwrap_or(serde_json::Value::Null))
@@ -277,7 +625,16 @@ async fn handle_recover(
         message: "Invalid params".into(),
     })?;
 
-    let backup_service = BackupService::new(state.config.backup.clone());
+    let target = match &params.target {
+        Some(target) => target.clone(),
+        None => std::env::current_dir().map_err(|e| JsonRpcError {
+            code: 1004,
+            message: format!("Cannot determine current directory: {}", e),
+        })?,
+    };
+    check_path_permission(user_context, &[target])?;
+
+    let backup_service = BackupService::new(state.config.backup.clone(), None);
 
     let start = std::time::Instant::now();
     let count = backup_service
@@ -299,3 +656,28 @@ async fn handle_recover(
         message: "Serialization error".into(),
     })
 }
+
+/// Rescan the plugins directory and update the shared registry; admin only
+async fn handle_reload_plugins(state: Arc<AppState>) -> Result<serde_json::Value, JsonRpcError> {
+    let Some(plugin_loader) = &state.plugin_loader else {
+        return Err(JsonRpcError {
+            code: -32603,
+            message: "Plugin reloading is not available".into(),
+        });
+    };
+
+    let plugins = plugin_loader
+        .lock()
+        .await
+        .reload(&state.registry)
+        .await
+        .map_err(|e| JsonRpcError {
+            code: -32603,
+            message: e.to_string(),
+        })?;
+
+    serde_json::to_value(ReloadPluginsResponseData { plugins }).map_err(|_| JsonRpcError {
+        code: -32603,
+        message: "Serialization error".into(),
+    })
+}
@@ -9,19 +9,25 @@
 //! - Validation of plugin commands to ensure they exist and are executable
 //! - Integration with the main Zenith registry system
 //! - Error handling for plugin loading and execution
+use crate::config::cache::ConfigCache;
 use crate::config::types::ZenithConfig;
 use crate::core::traits::Zenith;
 use crate::error::{Result, ZenithError};
 use crate::plugins::types::PluginInfo;
 use crate::utils::path::sanitize_path_for_log;
+use crate::zeniths::common::reject_empty_output;
+use crate::zeniths::registry::ZenithRegistry;
+use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::process::Stdio;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use tokio::fs;
-use tokio::io::AsyncWriteExt;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::process::Command;
+use tokio::time::timeout;
 use tracing::{debug, error, info, warn};
 
 #[cfg(test)]
@@ -45,7 +51,91 @@ mod tests {
 
         // This should not fail even if the directory is empty
         let result = loader.load_plugins_from_dir(temp_dir.path()).await;
-        assert!(result.is_ok());
+        assert!(result.unwrap().is_empty());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_list_files_register_every_enabled_plugin() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::write(
+            temp_dir.path().join("formatters.toml"),
+            r#"
+[[plugins]]
+name = "zig-fmt"
+command = "cat"
+args = []
+extensions = ["zig"]
+enabled = true
+
+[[plugins]]
+name = "nim-fmt"
+command = "cat"
+args = []
+extensions = ["nim"]
+enabled = true
+
+[[plugins]]
+name = "odin-fmt"
+command = "cat"
+args = []
+extensions = ["odin"]
+enabled = true
+"#,
+        )
+        .unwrap();
+        std::fs::write(
+            temp_dir.path().join("more.json"),
+            r#"[
+                {"name": "gleam-fmt", "command": "cat", "args": [], "extensions": ["gleam"], "enabled": true},
+                {"name": "off", "command": "cat", "args": [], "extensions": ["v"], "enabled": false}
+            ]"#,
+        )
+        .unwrap();
+
+        let mut loader = PluginLoader::new();
+        let failures = loader.load_plugins_from_dir(temp_dir.path()).await.unwrap();
+        assert!(failures.is_empty());
+
+        let registry = ZenithRegistry::new();
+        for plugin in loader.list_plugins() {
+            registry.register(loader.get_plugin(&plugin.name).unwrap());
+        }
+        for (ext, name) in [
+            ("zig", "zig-fmt"),
+            ("nim", "nim-fmt"),
+            ("odin", "odin-fmt"),
+            ("gleam", "gleam-fmt"),
+        ] {
+            assert_eq!(registry.get_by_extension(ext).unwrap().name(), name);
+        }
+        assert!(loader.get_plugin("off").is_none());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_load_plugins_reports_malformed_configs() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::write(
+            temp_dir.path().join("valid.json"),
+            r#"{"name": "valid", "command": "cat", "args": [], "extensions": ["rb"], "enabled": true}"#,
+        )
+        .unwrap();
+        let malformed = temp_dir.path().join("malformed.json");
+        std::fs::write(&malformed, r#"{"name": "malformed", "command": "cat","#).unwrap();
+        std::fs::write(
+            temp_dir.path().join("disabled.json"),
+            r#"{"name": "off", "command": "cat", "args": [], "extensions": ["php"], "enabled": false}"#,
+        )
+        .unwrap();
+
+        let mut loader = PluginLoader::new();
+        let failures = loader.load_plugins_from_dir(temp_dir.path()).await.unwrap();
+        assert!(loader.get_plugin("valid").is_some());
+        assert_eq!(loader.list_plugins().len(), 1);
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].0, malformed);
+        assert!(matches!(failures[0].1, ZenithError::Serialization(_)));
     }
 
     #[tokio::test]
@@ -82,7 +172,7 @@ mod tests {
         file.write_all(config_content.as_bytes()).unwrap();
 
         let loader = PluginLoader::new();
-        let result = loader.load_plugin_from_config(config_file).await;
+        let result = loader.load_plugins_from_config(config_file).await;
 
         match result {
             Err(ZenithError::PluginDisabled { name }) => {
@@ -92,6 +182,192 @@ mod tests {
         }
     }
 
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_reload_picks_up_new_and_removed_configs() {
+        let temp_dir = TempDir::new().unwrap();
+        let plugin_config = |name: &str, ext: &str| {
+            format!(
+                r#"{{"name": "{}", "command": "cat", "args": [], "extensions": ["{}"], "enabled": true}}"#,
+                name, ext
+            )
+        };
+        std::fs::write(
+            temp_dir.path().join("first.json"),
+            plugin_config("first", "rb"),
+        )
+        .unwrap();
+
+        let registry = ZenithRegistry::new();
+        let mut loader = PluginLoader::new();
+        loader.load_plugins_from_dir(temp_dir.path()).await.unwrap();
+        for plugin in loader.list_plugins() {
+            registry.register(loader.get_plugin(&plugin.name).unwrap());
+        }
+        assert!(registry.get_by_name("first").is_some());
+        assert!(registry.get_by_name("second").is_none());
+
+        std::fs::write(
+            temp_dir.path().join("second.json"),
+            plugin_config("second", "php"),
+        )
+        .unwrap();
+        let names = loader.reload(&registry).await.unwrap();
+        assert_eq!(names, ["first", "second"]);
+        assert!(registry.get_by_name("first").is_some());
+        assert_eq!(registry.get_by_extension("php").unwrap().name(), "second");
+
+        std::fs::remove_file(temp_dir.path().join("first.json")).unwrap();
+        assert_eq!(loader.reload(&registry).await.unwrap(), ["second"]);
+        assert!(registry.get_by_name("first").is_none());
+        assert!(registry.get_by_extension("rb").is_none());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_plugin_with_unmapped_extension_routes_by_it() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::write(
+            temp_dir.path().join("zig.json"),
+            r#"{"name": "zig-fmt", "command": "cat", "args": [], "extensions": ["zig"], "enabled": true}"#,
+        )
+        .unwrap();
+        std::fs::write(
+            temp_dir.path().join("dotted.json"),
+            r#"{"name": "dotted", "command": "cat", "args": [], "extensions": [".odin"], "enabled": true}"#,
+        )
+        .unwrap();
+
+        let mut loader = PluginLoader::new();
+        loader.load_plugins_from_dir(temp_dir.path()).await.unwrap();
+        assert!(loader.get_plugin("dotted").is_none());
+
+        let registry = ZenithRegistry::new();
+        registry.register(loader.get_plugin("zig-fmt").unwrap());
+        assert_eq!(registry.get_by_extension("zig").unwrap().name(), "zig-fmt");
+        assert!(registry.get_by_extension("unknown").is_none());
+        assert!(std::ptr::eq(
+            intern_extension("zig"),
+            intern_extension("zig")
+        ));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_args_substitute_filepath_and_ext() {
+        let plugin = ExternalZenith::new(
+            "echo-path".to_string(),
+            "echo".to_string(),
+            vec![
+                "--stdin-filepath".to_string(),
+                "{filepath}".to_string(),
+                "--parser={ext}".to_string(),
+            ],
+            vec!["zig".to_string()],
+        );
+
+        let temp_dir = TempDir::new().unwrap();
+        let file = temp_dir.path().join("main.zig");
+        let output = plugin
+            .format(b"", &file, &ZenithConfig::default())
+            .await
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            format!("--stdin-filepath {} --parser=zig\n", file.display())
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_success_exit_codes() {
+        let config: ExternalPluginConfig = serde_json::from_str(
+            r#"{"name": "exit-one", "command": "sh", "args": ["-c", "cat; echo oops >&2; exit 1"], "extensions": ["txt"], "enabled": true, "success_exit_codes": [0, 1]}"#,
+        )
+        .unwrap();
+        let plugin = ExternalZenith::from_config(&config);
+        let output = plugin
+            .format(b"kept", Path::new("a.txt"), &ZenithConfig::default())
+            .await
+            .unwrap();
+        assert_eq!(output, b"kept");
+
+        let strict = ExternalZenith::from_config(&config).with_success_exit_codes(vec![0]);
+        let err = strict
+            .format(b"kept", Path::new("a.txt"), &ZenithConfig::default())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("exited with code 1: oops"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_hanging_plugin_is_killed_on_timeout() {
+        let temp_dir = TempDir::new().unwrap();
+        let pid_file = temp_dir.path().join("pid");
+        let config: ExternalPluginConfig = serde_json::from_str(&format!(
+            r#"{{"name": "hang", "command": "sh", "args": ["-c", "echo $$ > '{}'; exec sleep 30"], "extensions": ["txt"], "enabled": true, "timeout_seconds": 1}}"#,
+            pid_file.display()
+        ))
+        .unwrap();
+
+        let started = std::time::Instant::now();
+        let err = ExternalZenith::from_config(&config)
+            .format(
+                b"",
+                &temp_dir.path().join("a.txt"),
+                &ZenithConfig::default(),
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ZenithError::PluginTimeout { seconds: 1, .. }));
+        assert!(started.elapsed() < std::time::Duration::from_secs(10));
+
+        // `kill -0` still succeeds for an unreaped zombie
+        let pid = std::fs::read_to_string(&pid_file).unwrap();
+        let alive = std::process::Command::new("kill")
+            .args(["-0", pid.trim()])
+            .stderr(Stdio::null())
+            .status()
+            .unwrap()
+            .success();
+        assert!(!alive);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_env_and_cwd_reach_the_command() {
+        let temp_dir = TempDir::new().unwrap();
+        let project = temp_dir.path().join("project");
+        std::fs::create_dir_all(project.join("src")).unwrap();
+        std::fs::write(project.join("package.json"), "{}").unwrap();
+        let file = project.join("src").join("app.txt");
+
+        let config: ExternalPluginConfig = serde_json::from_str(
+            r#"{"name": "env", "command": "sh", "args": ["-c", "printf '%s|' \"$PLUGIN_STYLE\"; pwd"], "extensions": ["txt"], "enabled": true, "env": {"PLUGIN_STYLE": "compact"}}"#,
+        )
+        .unwrap();
+        let output = ExternalZenith::from_config(&config)
+            .format(b"", &file, &ZenithConfig::default())
+            .await
+            .unwrap();
+        let project = project.canonicalize().unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            format!("compact|{}\n", project.display())
+        );
+
+        let output = ExternalZenith::from_config(&config)
+            .with_cwd(Some(temp_dir.path().to_path_buf()))
+            .format(b"", &file, &ZenithConfig::default())
+            .await
+            .unwrap();
+        assert!(String::from_utf8(output).unwrap().ends_with(&format!(
+            "{}\n",
+            temp_dir.path().canonicalize().unwrap().display()
+        )));
+    }
+
     #[tokio::test]
     async fn test_external_zenith_creation() {
         let external_plugin = ExternalZenith::new(
@@ -111,12 +387,36 @@ mod tests {
 pub struct ExternalPluginConfig {
     pub name: String,
     pub command: String,
+    /// Arguments passed to `command`; `{filepath}` and `{ext}` are replaced
+    /// with the path and extension of the file being formatted
     pub args: Vec<String>,
     pub extensions: Vec<String>,
     pub enabled: bool,
+    /// Exit codes that mean the command succeeded; stdout is used as the
+    /// formatted content for any of them
+    #[serde(default = "default_success_exit_codes")]
+    pub success_exit_codes: Vec<i32>,
+    /// Extra environment variables for `command`
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    /// Working directory for `command`; defaults to the project directory
+    /// of the file being formatted
+    #[serde(default)]
+    pub cwd: Option<String>,
+    /// Seconds before a running command is killed; `0` disables the timeout
+    #[serde(default = "default_plugin_timeout_seconds")]
+    pub timeout_seconds: u64,
+}
+
+fn default_success_exit_codes() -> Vec<i32> {
+    vec![0]
+}
+
+fn default_plugin_timeout_seconds() -> u64 {
+    30
 }
 
-/// Configuration for a list of plugins (TOML array format)
+/// Configuration for a list of plugins (`plugins` array in TOML or JSON)
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ExternalPluginConfigList {
     pub plugins: Vec<ExternalPluginConfig>,
@@ -147,6 +447,8 @@ impl Default for PluginSecurityConfig {
 pub struct PluginLoader {
     loaded_plugins: HashMap<String, Arc<dyn Zenith>>,
     security_config: PluginSecurityConfig,
+    /// Directory scanned by the last `load_plugins_from_dir`, rescanned by `reload`
+    plugins_dir: Option<PathBuf>,
 }
 
 impl PluginLoader {
@@ -154,6 +456,7 @@ impl PluginLoader {
         Self {
             loaded_plugins: HashMap::new(),
             security_config: PluginSecurityConfig::default(),
+            plugins_dir: None,
         }
     }
 
@@ -161,6 +464,7 @@ impl PluginLoader {
         Self {
             loaded_plugins: HashMap::new(),
             security_config,
+            plugins_dir: None,
         }
     }
 
@@ -248,13 +552,48 @@ impl PluginLoader {
         Ok(())
     }
 
+    /// Extensions are matched against `Path::extension`, so they must be
+    /// non-empty and hold no dots or path separators
+    fn validate_plugin_extensions(config: &ExternalPluginConfig) -> Result<()> {
+        if config.extensions.is_empty() {
+            return Err(ZenithError::PluginValidationError {
+                name: config.name.clone(),
+                error: "No extensions declared".to_string(),
+            });
+        }
+        for ext in &config.extensions {
+            if ext.is_empty()
+                || ext.contains(['.', '/', '\\'])
+                || ext.chars().any(char::is_whitespace)
+            {
+                return Err(ZenithError::PluginValidationError {
+                    name: config.name.clone(),
+                    error: format!(
+                        "Invalid extension '{}': use the bare extension, e.g. \"zig\" for .zig files",
+                        ext
+                    ),
+                });
+            }
+        }
+        Ok(())
+    }
+
     /// Load plugins from a directory by scanning plugin configuration files
-    pub async fn load_plugins_from_dir<P: AsRef<Path>>(&mut self, dir: P) -> Result<()> {
+    ///
+    /// A config that fails to load does not stop the others; each failure is
+    /// logged and returned with the config's path. Disabled plugins are
+    /// skipped and not counted as failures.
+    pub async fn load_plugins_from_dir<P: AsRef<Path>>(
+        &mut self,
+        dir: P,
+    ) -> Result<Vec<(PathBuf, ZenithError)>> {
         let dir = dir.as_ref();
+        self.plugins_dir = Some(dir.to_path_buf());
+        let mut failures = Vec::new();
 
         // Check if directory exists
         if !dir.exists() {
-            return Ok(());
+            return Ok(failures);
         }
 
         let mut entries = fs::read_dir(dir).await?;
@@ -267,105 +606,143 @@ impl PluginLoader {
                 .extension()
                 .is_some_and(|ext| ext == "json" || ext == "toml")
             {
-                match self.load_plugin_from_config(&path).await {
-                    Ok(plugin) => {
-                        self.register_plugin(plugin);
+                match self.load_plugins_from_config(&path).await {
+                    Ok(plugins) => {
+                        for plugin in plugins {
+                            self.register_plugin(plugin);
+                        }
                     }
+                    Err(ZenithError::PluginDisabled { .. }) => {}
                     Err(e) => {
-                        eprintln!("Failed to load plugin from {:?}: {}", path, e);
+                        warn!(
+                            "Failed to load plugin from {}: {}",
+                            sanitize_path_for_log(&path),
+                            e
+                        );
+                        failures.push((path, e));
                     }
                 }
             }
         }
 
-        Ok(())
+        Ok(failures)
+    }
+
+    /// Directory the plugins were loaded from, if any
+    pub fn plugins_dir(&self) -> Option<&Path> {
+        self.plugins_dir.as_deref()
     }
 
-    /// Load a single plugin from its configuration file
-    /// Supports both single plugin (JSON or TOML) and plugin list (TOML array) formats
-    async fn load_plugin_from_config<P: AsRef<Path>>(
+    /// Rescan the plugins directory and apply the result to `registry`
+    ///
+    /// Plugins whose configuration disappeared are unregistered; new and
+    /// changed ones replace what was registered under their name. Returns
+    /// the names of the plugins now loaded, sorted.
+    pub async fn reload(&mut self, registry: &ZenithRegistry) -> Result<Vec<String>> {
+        let Some(dir) = self.plugins_dir.clone() else {
+            return Ok(Vec::new());
+        };
+
+        let previous = std::mem::take(&mut self.loaded_plugins);
+        // Configs that fail to load are logged and dropped like removed ones
+        if let Err(e) = self.load_plugins_from_dir(&dir).await {
+            self.loaded_plugins = previous;
+            return Err(e);
+        }
+
+        for name in previous.keys() {
+            if !self.loaded_plugins.contains_key(name) {
+                registry.unregister(name);
+            }
+        }
+        for plugin in self.loaded_plugins.values() {
+            registry.register(plugin.clone());
+        }
+
+        let mut names: Vec<String> = self.loaded_plugins.keys().cloned().collect();
+        names.sort();
+        info!(
+            "Reloaded {} plugins from {}",
+            names.len(),
+            sanitize_path_for_log(&dir)
+        );
+        Ok(names)
+    }
+
+    /// Load the plugins defined in a configuration file
+    ///
+    /// A file holds a single plugin (JSON or TOML) or a list of them: a JSON
+    /// array, or a `plugins` array in JSON or TOML. Every enabled plugin in a
+    /// list is loaded; if one of them fails validation the whole file fails.
+    /// A file whose plugins are all disabled returns `PluginDisabled`.
+    async fn load_plugins_from_config<P: AsRef<Path>>(
         &self,
         config_path: P,
-    ) -> Result<Arc<dyn Zenith>> {
+    ) -> Result<Vec<Arc<dyn Zenith>>> {
         let config_path = config_path.as_ref();
         let sanitized_path = sanitize_path_for_log(config_path);
-        info!("Loading plugin from: {}", sanitized_path);
+        info!("Loading plugins from: {}", sanitized_path);
 
         let config_content = fs::read_to_string(config_path).await?;
-
-        // Check if this is a TOML file that might contain a list of plugins
-        if config_path.extension().is_some_and(|ext| ext == "toml") {
-            // Try to parse as a list of plugins first
-            if let Ok(config_list) = toml::from_str::<ExternalPluginConfigList>(&config_content) {
-                if !config_list.plugins.is_empty() {
-                    info!(
-                        "Found {} plugins in list format from: {}",
-                        config_list.plugins.len(),
-                        sanitized_path
-                    );
-
-                    // Load the first enabled plugin from the list
-                    for config in &config_list.plugins {
-                        if config.enabled {
-                            debug!(
-                                "Loading plugin from list: name={}, extensions={:?}",
-                                config.name, config.extensions
-                            );
-
-                            self.validate_plugin_config(config).await?;
-
-                            let external_plugin = ExternalZenith::new(
-                                config.name.clone(),
-                                config.command.clone(),
-                                config.args.clone(),
-                                config.extensions.to_vec(),
-                            );
-
-                            info!("Successfully loaded plugin: {}", external_plugin.name());
-                            return Ok(Arc::new(external_plugin));
-                        }
-                    }
-
-                    // All plugins are disabled
-                    let first_disabled_name = config_list
-                        .plugins
-                        .first()
-                        .map(|p| p.name.clone())
-                        .unwrap_or_else(|| "unknown".to_string());
-                    return Err(ZenithError::PluginDisabled {
-                        name: first_disabled_name,
-                    });
-                }
-            }
+        let configs = Self::parse_plugin_configs(config_path, &config_content)?;
+        if configs.len() > 1 {
+            info!(
+                "Found {} plugins in list format from: {}",
+                configs.len(),
+                sanitized_path
+            );
         }
 
-        // Try to parse as single plugin config (JSON or TOML)
-        let config: ExternalPluginConfig =
-            if config_path.extension().is_some_and(|ext| ext == "json") {
-                serde_json::from_str(&config_content)?
-            } else {
-                toml::from_str(&config_content)?
-            };
+        let mut plugins: Vec<Arc<dyn Zenith>> = Vec::new();
+        for config in &configs {
+            if !config.enabled {
+                warn!("Plugin '{}' is disabled, skipping", config.name);
+                continue;
+            }
+            debug!(
+                "Loading plugin: name={}, extensions={:?}",
+                config.name, config.extensions
+            );
 
-        debug!(
-            "Parsed plugin config: name={}, extensions={:?}",
-            config.name, config.extensions
-        );
+            // Validate the plugin configuration
+            self.validate_plugin_config(config).await?;
 
-        if !config.enabled {
-            warn!("Plugin '{}' is disabled, skipping", config.name);
-            return Err(ZenithError::PluginDisabled { name: config.name });
+            let external_plugin = ExternalZenith::from_config(config);
+            info!("Successfully loaded plugin: {}", external_plugin.name());
+            plugins.push(Arc::new(external_plugin));
         }
 
-        // Validate the plugin configuration
-        self.validate_plugin_config(&config).await?;
+        match configs.first() {
+            Some(first) if plugins.is_empty() => Err(ZenithError::PluginDisabled {
+                name: first.name.clone(),
+            }),
+            _ => Ok(plugins),
+        }
+    }
 
-        // Create an external plugin instance
-        let external_plugin =
-            ExternalZenith::new(config.name, config.command, config.args, config.extensions);
+    /// Plugin configs in a file, in the single or list form
+    fn parse_plugin_configs(
+        config_path: &Path,
+        config_content: &str,
+    ) -> Result<Vec<ExternalPluginConfig>> {
+        if config_path.extension().is_some_and(|ext| ext == "json") {
+            // Dispatch on the shape first so parse errors point at the right form
+            let value: serde_json::Value = serde_json::from_str(config_content)?;
+            return Ok(match value {
+                serde_json::Value::Array(_) => serde_json::from_value(value)?,
+                serde_json::Value::Object(ref map) if map.contains_key("plugins") => {
+                    serde_json::from_value::<ExternalPluginConfigList>(value)?.plugins
+                }
+                _ => vec![serde_json::from_value(value)?],
+            });
+        }
 
-        info!("Successfully loaded plugin: {}", external_plugin.name());
-        Ok(Arc::new(external_plugin))
+        let value: toml::Table = toml::from_str(config_content)?;
+        Ok(if value.contains_key("plugins") {
+            value.try_into::<ExternalPluginConfigList>()?.plugins
+        } else {
+            vec![value.try_into()?]
+        })
     }
 
     /// Validate plugin configuration and check if the command exists and is executable
@@ -373,6 +750,7 @@ impl PluginLoader {
         // Security validation first
         self.validate_command_security(&config.command)?;
         self.validate_plugin_arguments(&config.args)?;
+        Self::validate_plugin_extensions(config)?;
         info!("Validating plugin '{}'", config.name);
 
         // Check if the command exists
@@ -462,43 +840,23 @@ impl Default for PluginLoader {
     }
 }
 
-/// A mapping of common extensions to static string slices
-static EXTENSION_MAP: &[(&str, &str)] = &[
-    ("js", "js"),
-    ("jsx", "jsx"),
-    ("ts", "ts"),
-    ("tsx", "tsx"),
-    ("rs", "rs"),
-    ("py", "py"),
-    ("java", "java"),
-    ("cpp", "cpp"),
-    ("c", "c"),
-    ("h", "h"),
-    ("hpp", "hpp"),
-    ("html", "html"),
-    ("css", "css"),
-    ("json", "json"),
-    ("yaml", "yaml"),
-    ("yml", "yml"),
-    ("toml", "toml"),
-    ("md", "md"),
-    ("txt", "txt"),
-    ("xml", "xml"),
-    ("ini", "ini"),
-    ("sh", "sh"),
-    ("bash", "bash"),
-    ("sql", "sql"),
-    ("go", "go"),
-    ("rb", "rb"),
-    ("php", "php"),
-];
-
-fn get_static_extension(ext: &str) -> &'static str {
-    EXTENSION_MAP
-        .iter()
-        .find(|(key, _)| *key == ext)
-        .map(|(_, static_ext)| *static_ext)
-        .unwrap_or("unknown")
+/// Extensions declared by plugins, leaked once each so `Zenith::extensions`
+/// can hand out `&'static str`; reloading plugins reuses the same strings
+static INTERNED_EXTENSIONS: Lazy<Mutex<HashSet<&'static str>>> =
+    Lazy::new(|| Mutex::new(HashSet::new()));
+
+fn intern_extension(ext: &str) -> &'static str {
+    let mut interned = INTERNED_EXTENSIONS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    match interned.get(ext) {
+        Some(existing) => existing,
+        None => {
+            let leaked: &'static str = Box::leak(ext.to_string().into_boxed_str());
+            interned.insert(leaked);
+            leaked
+        }
+    }
 }
 
 /// Plugin implementation for external tools
@@ -508,6 +866,10 @@ pub struct ExternalZenith {
     command: String,
     args: Vec<String>,
     extensions: Vec<&'static str>,
+    success_exit_codes: Vec<i32>,
+    env: HashMap<String, String>,
+    cwd: Option<PathBuf>,
+    timeout_seconds: Option<u64>,
     resolved_command_path: Option<PathBuf>,
 }
 
@@ -521,7 +883,7 @@ impl ExternalZenith {
     ) -> Self {
         let extensions: Vec<&'static str> = extension_strings
             .iter()
-            .map(|ext| get_static_extension(ext))
+            .map(|ext| intern_extension(ext))
             .collect();
 
         Self {
@@ -529,10 +891,77 @@ impl ExternalZenith {
             command,
             args,
             extensions,
+            success_exit_codes: default_success_exit_codes(),
+            env: HashMap::new(),
+            cwd: None,
+            timeout_seconds: Some(default_plugin_timeout_seconds()),
             resolved_command_path: None,
         }
     }
 
+    /// Plugin described by a validated configuration file
+    pub fn from_config(config: &ExternalPluginConfig) -> Self {
+        Self::new(
+            config.name.clone(),
+            config.command.clone(),
+            config.args.clone(),
+            config.extensions.clone(),
+        )
+        .with_success_exit_codes(config.success_exit_codes.clone())
+        .with_env(config.env.clone())
+        .with_cwd(config.cwd.as_ref().map(PathBuf::from))
+        .with_timeout((config.timeout_seconds > 0).then_some(config.timeout_seconds))
+    }
+
+    /// Kill the command after `seconds`; `None` waits indefinitely
+    pub fn with_timeout(mut self, seconds: Option<u64>) -> Self {
+        self.timeout_seconds = seconds;
+        self
+    }
+
+    /// Set extra environment variables for the command
+    pub fn with_env(mut self, env: HashMap<String, String>) -> Self {
+        self.env = env;
+        self
+    }
+
+    /// Run the command in `cwd` instead of the file's project directory
+    pub fn with_cwd(mut self, cwd: Option<PathBuf>) -> Self {
+        self.cwd = cwd;
+        self
+    }
+
+    /// Directory to run the command in for `path`: the configured `cwd`, or
+    /// the project directory found by walking up from the file
+    fn working_dir(&self, path: &Path) -> Option<PathBuf> {
+        if let Some(cwd) = &self.cwd {
+            return Some(cwd.clone());
+        }
+        ConfigCache::new()
+            .find_project_directory(path)
+            .ok()
+            .filter(|dir| !dir.as_os_str().is_empty())
+    }
+
+    /// Treat these exit codes as success instead of only `0`
+    pub fn with_success_exit_codes(mut self, codes: Vec<i32>) -> Self {
+        self.success_exit_codes = codes;
+        self
+    }
+
+    /// The configured arguments with `{filepath}` and `{ext}` replaced for `path`
+    fn expand_args(&self, path: &Path) -> Vec<String> {
+        let filepath = path.to_string_lossy();
+        let ext = path
+            .extension()
+            .map(|ext| ext.to_string_lossy())
+            .unwrap_or_default();
+        self.args
+            .iter()
+            .map(|arg| arg.replace("{filepath}", &filepath).replace("{ext}", &ext))
+            .collect()
+    }
+
     #[allow(dead_code)]
     async fn resolve_command_path(&mut self) -> Result<PathBuf> {
         if let Some(ref path) = self.resolved_command_path {
@@ -589,22 +1018,25 @@ impl Zenith for ExternalZenith {
         &self.extensions
     }
 
+    fn spawns_process(&self) -> bool {
+        true
+    }
+
     async fn format(
         &self,
         content: &[u8],
-        _path: &std::path::Path,
+        path: &std::path::Path,
         _config: &ZenithConfig,
     ) -> Result<Vec<u8>> {
-        debug!(
-            "Executing plugin '{}' with args: {:?}",
-            self.name, self.args
-        );
+        // The command may run in another directory, so it gets an absolute path
+        let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+        let args = self.expand_args(&path);
+        debug!("Executing plugin '{}' with args: {:?}", self.name, args);
 
         let mut cmd = Command::new(&self.command);
-
-        // Add the configured arguments
-        for arg in &self.args {
-            cmd.arg(arg);
+        cmd.args(&args).envs(&self.env);
+        if let Some(dir) = self.working_dir(&path) {
+            cmd.current_dir(dir);
         }
 
         cmd.stdin(Stdio::piped())
@@ -619,34 +1051,79 @@ impl Zenith for ExternalZenith {
             }
         })?;
 
-        // Write content to stdin
-        if let Some(mut stdin) = child.stdin.take() {
-            stdin.write_all(content).await.map_err(|e| {
-                error!("Failed to write to plugin '{}' stdin: {}", self.name, e);
-                ZenithError::PluginError {
-                    name: self.name.clone(),
-                    error: e.to_string(),
+        // Feed stdin while draining stdout/stderr so neither side blocks on a
+        // full pipe, and keep the child owned here so it can be killed
+        let mut stdin = child.stdin.take();
+        let mut stdout = child.stdout.take();
+        let mut stderr = child.stderr.take();
+        let run = async {
+            let feed = async {
+                if let Some(mut stdin) = stdin.take() {
+                    stdin.write_all(content).await?;
+                    // Drop stdin to signal EOF
                 }
-            })?;
-            // Drop stdin to signal EOF
-            drop(stdin);
-        }
-
-        let output = child.wait_with_output().await.map_err(|e| {
-            error!("Failed to wait for plugin '{}': {}", self.name, e);
+                Ok::<_, std::io::Error>(())
+            };
+            let mut stdout_buf = Vec::new();
+            let mut stderr_buf = Vec::new();
+            let read_stdout = async {
+                if let Some(stdout) = stdout.as_mut() {
+                    stdout.read_to_end(&mut stdout_buf).await?;
+                }
+                Ok::<_, std::io::Error>(())
+            };
+            let read_stderr = async {
+                if let Some(stderr) = stderr.as_mut() {
+                    stderr.read_to_end(&mut stderr_buf).await?;
+                }
+                Ok::<_, std::io::Error>(())
+            };
+            let (_, _, _, status) = tokio::try_join!(feed, read_stdout, read_stderr, child.wait())?;
+            Ok::<_, std::io::Error>(std::process::Output {
+                status,
+                stdout: stdout_buf,
+                stderr: stderr_buf,
+            })
+        };
+        let result = match self.timeout_seconds {
+            Some(seconds) => match timeout(Duration::from_secs(seconds), run).await {
+                Ok(result) => result,
+                Err(_) => {
+                    warn!(
+                        "Plugin '{}' timed out after {} seconds, killing it",
+                        self.name, seconds
+                    );
+                    // kill() also waits, so the process does not linger as a zombie
+                    if let Err(e) = child.kill().await {
+                        warn!("Failed to kill plugin '{}': {}", self.name, e);
+                    }
+                    return Err(ZenithError::PluginTimeout {
+                        name: self.name.clone(),
+                        seconds,
+                    });
+                }
+            },
+            None => run.await,
+        };
+        let output = result.map_err(|e| {
+            error!("Failed to run plugin '{}': {}", self.name, e);
             ZenithError::PluginError {
                 name: self.name.clone(),
                 error: e.to_string(),
             }
         })?;
 
-        if output.status.success() {
+        if output
+            .status
+            .code()
+            .is_some_and(|code| self.success_exit_codes.contains(&code))
+        {
             debug!(
                 "Plugin '{}' executed successfully, output size: {} bytes",
                 self.name,
                 output.stdout.len()
             );
-            Ok(output.stdout)
+            reject_empty_output(&self.name, content, output.stdout)
         } else {
             let stderr = String::from_utf8_lossy(&output.stderr);
             error!(
@@ -655,9 +1132,16 @@ impl Zenith for ExternalZenith {
                 output.status.code(),
                 stderr
             );
+            let status = match output.status.code() {
+                Some(code) => format!("exited with code {}", code),
+                None => "was terminated by a signal".to_string(),
+            };
             Err(ZenithError::PluginError {
                 name: self.name.clone(),
-                error: stderr.to_string(),
+                error: match stderr.trim() {
+                    "" => status,
+                    stderr => format!("{}: {}", status, stderr),
+                },
             })
         }
     }
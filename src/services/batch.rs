@@ -1,90 +1,355 @@
-use crate::config::types::FormatResult;
+use crate::config::types::{FormatError, FormatResult};
+use futures::future::BoxFuture;
 use std::path::PathBuf;
-use std::sync::Arc;
-use tokio::sync::Semaphore;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use sysinfo::{ProcessRefreshKind, System};
+use tokio::sync::{mpsc, OwnedSemaphorePermit, Semaphore};
+use tracing::{debug, warn};
+
+/// Granularity of memory budget permits (1 KiB)
+const BUDGET_UNIT_BYTES: u64 = 1024;
+
+/// How long a throttled file waits before memory is sampled again
+const MEMORY_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Shared byte budget bounding how much file content is in flight at once
+///
+/// Each file acquires permits proportional to its size before it is read and
+/// releases them once formatting completes. A file larger than the whole
+/// budget takes all of it, so it runs alone instead of being rejected.
+#[derive(Clone)]
+pub struct MemoryBudget {
+    semaphore: Arc<Semaphore>,
+    capacity_units: u32,
+}
+
+impl MemoryBudget {
+    /// Create a budget of `max_bytes` bytes
+    pub fn new(max_bytes: u64) -> Self {
+        let capacity_units = max_bytes
+            .div_ceil(BUDGET_UNIT_BYTES)
+            .clamp(1, u32::MAX as u64) as u32;
+        Self {
+            semaphore: Arc::new(Semaphore::new(capacity_units as usize)),
+            capacity_units,
+        }
+    }
+
+    /// Create a budget from a `LimitsConfig::max_memory_mb` value
+    pub fn from_megabytes(max_memory_mb: u64) -> Self {
+        Self::new(max_memory_mb.saturating_mul(1024 * 1024))
+    }
+
+    /// Total budget in bytes
+    #[cfg(test)]
+    pub fn capacity_bytes(&self) -> u64 {
+        self.capacity_units as u64 * BUDGET_UNIT_BYTES
+    }
+
+    /// Bytes not currently reserved by in-flight files
+    #[cfg(test)]
+    pub fn available_bytes(&self) -> u64 {
+        self.semaphore.available_permits() as u64 * BUDGET_UNIT_BYTES
+    }
+
+    /// Reserve room for `bytes` of content, waiting until enough is released
+    pub async fn acquire(&self, bytes: u64) -> Option<OwnedSemaphorePermit> {
+        let units = bytes
+            .div_ceil(BUDGET_UNIT_BYTES)
+            .clamp(1, self.capacity_units as u64) as u32;
+        self.semaphore.clone().acquire_many_owned(units).await.ok()
+    }
+}
+
+/// Source of resident memory samples, in bytes; `None` when unavailable
+type MemorySampler = Arc<dyn Fn() -> Option<u64> + Send + Sync>;
+
+/// Holds back new files while the process's resident memory exceeds a limit
+///
+/// Memory is sampled each time a file is about to start, and again every
+/// [`MEMORY_POLL_INTERVAL`] while it waits. Waiting only helps while other
+/// files are in flight and can release memory, so with none in flight the
+/// file starts regardless.
+#[derive(Clone)]
+pub struct MemoryMonitor {
+    limit_bytes: u64,
+    sampler: MemorySampler,
+    in_flight: Arc<AtomicUsize>,
+    throttled: Arc<AtomicUsize>,
+}
+
+impl MemoryMonitor {
+    /// Monitor the resident memory of this process against `limit_bytes`
+    pub fn new(limit_bytes: u64) -> Self {
+        let system = Mutex::new(System::new());
+        let pid = sysinfo::get_current_pid().ok();
+        Self::with_sampler(
+            limit_bytes,
+            Arc::new(move || {
+                let pid = pid?;
+                let mut system = system.lock().ok()?;
+                system.refresh_process_specifics(pid, ProcessRefreshKind::new().with_memory());
+                system.process(pid).map(|process| process.memory())
+            }),
+        )
+    }
+
+    /// Create a monitor from a `LimitsConfig::max_memory_mb` value
+    pub fn from_megabytes(max_memory_mb: u64) -> Self {
+        Self::new(max_memory_mb.saturating_mul(1024 * 1024))
+    }
+
+    /// Monitor with memory read from `sampler` instead of the process
+    pub fn with_sampler(limit_bytes: u64, sampler: MemorySampler) -> Self {
+        Self {
+            limit_bytes,
+            sampler,
+            in_flight: Arc::new(AtomicUsize::new(0)),
+            throttled: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// How many files have had to wait for memory to drop
+    #[cfg(test)]
+    pub fn throttle_count(&self) -> usize {
+        self.throttled.load(Ordering::SeqCst)
+    }
+
+    /// Wait until a file may start; it counts as in flight until the guard
+    /// is dropped
+    pub async fn admit(&self) -> InFlightGuard {
+        let mut waited = false;
+        while self.in_flight.load(Ordering::SeqCst) > 0 {
+            match (self.sampler)() {
+                Some(rss) if rss > self.limit_bytes => {
+                    if !waited {
+                        waited = true;
+                        self.throttled.fetch_add(1, Ordering::SeqCst);
+                        warn!(
+                            "Memory usage {} MB exceeds max_memory_mb ({} MB), waiting before starting more files",
+                            rss / (1024 * 1024),
+                            self.limit_bytes / (1024 * 1024)
+                        );
+                    }
+                    tokio::time::sleep(MEMORY_POLL_INTERVAL).await;
+                }
+                _ => break,
+            }
+        }
+        if waited {
+            debug!("Memory usage back under max_memory_mb, resuming");
+        }
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        InFlightGuard(self.in_flight.clone())
+    }
+}
+
+/// A file admitted by [`MemoryMonitor::admit`] that has not finished yet
+pub struct InFlightGuard(Arc<AtomicUsize>);
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Shared flag that stops a batch from starting any more files
+///
+/// Files that are already being processed run to completion, so cancelling
+/// never leaves a half-written file behind.
+#[derive(Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// Waits for any slot a file needs besides a worker, such as a per-formatter
+/// permit, which is held until the file is done
+pub type Admission =
+    Arc<dyn Fn(PathBuf) -> BoxFuture<'static, Option<OwnedSemaphorePermit>> + Send + Sync>;
 
 /// Batch processing optimizer for efficient file processing
+#[derive(Clone)]
 pub struct BatchOptimizer {
-    batch_size: usize,
     workers: usize,
+    memory_budget: Option<MemoryBudget>,
+    memory_monitor: Option<MemoryMonitor>,
+    cancel: Option<CancelToken>,
+    deadline: Option<Instant>,
+    admission: Option<Admission>,
 }
 
 impl BatchOptimizer {
     /// Create a new batch optimizer with the given configuration
-    pub fn new(batch_size: usize, workers: usize) -> Self {
+    pub fn new(workers: usize) -> Self {
         Self {
-            batch_size: batch_size.max(1),
             workers: workers.max(1),
+            memory_budget: None,
+            memory_monitor: None,
+            cancel: None,
+            deadline: None,
+            admission: None,
         }
     }
 
-    /// Process files in batches with controlled concurrency
-    pub async fn process_batches<F, Fut>(
+    /// Worker count for `workers = "auto"`, given how many of the files go to
+    /// formatters that spawn an external process and how many are formatted
+    /// in-process.
+    ///
+    /// Spawned formatters spend most of their time waiting on the child, so
+    /// when they make up the majority twice the CPU count keeps the cores busy;
+    /// otherwise formatting is CPU-bound and one worker per CPU is enough.
+    pub fn auto_workers(cpus: usize, external: usize, native: usize) -> usize {
+        let cpus = cpus.max(1);
+        if external > native {
+            cpus * 2
+        } else {
+            cpus
+        }
+    }
+
+    /// Skip files that have not started yet once `cancel` is triggered
+    pub fn with_cancel_token(mut self, cancel: CancelToken) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    /// Skip files that have not started by `deadline`; files already being
+    /// processed run to completion
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Bound the total size of files processed concurrently
+    pub fn with_memory_budget(mut self, budget: MemoryBudget) -> Self {
+        self.memory_budget = Some(budget);
+        self
+    }
+
+    /// Hold back new files while the process uses more memory than `monitor` allows
+    pub fn with_memory_monitor(mut self, monitor: MemoryMonitor) -> Self {
+        self.memory_monitor = Some(monitor);
+        self
+    }
+
+    /// Wait on `admission` before taking a worker slot, so a file blocked on
+    /// another limit never keeps a worker idle
+    pub fn with_admission(mut self, admission: Admission) -> Self {
+        self.admission = Some(admission);
+        self
+    }
+
+    /// Process files with controlled concurrency, yielding each result
+    /// through the returned channel as soon as it completes
+    pub fn process_streaming<F, Fut>(
         &self,
         files: Vec<PathBuf>,
         process_fn: F,
-    ) -> Vec<FormatResult>
+    ) -> mpsc::Receiver<FormatResult>
     where
         F: Fn(PathBuf) -> Fut + Send + Sync + 'static,
         Fut: std::future::Future<Output = FormatResult> + Send + 'static,
     {
+        // Sized to hold every result so workers never wait on a slow consumer
+        let (sender, receiver) = mpsc::channel(files.len().max(1));
         let semaphore = Arc::new(Semaphore::new(self.workers));
         let process_fn = Arc::new(process_fn);
-        let mut handles = Vec::new();
 
         for file in files {
-            let sem_clone = semaphore.clone();
-            let process_fn = Arc::clone(&process_fn);
-
-            let handle = tokio::spawn(async move {
-                let _permit = match sem_clone.acquire().await {
-                    Ok(permit) => permit,
-                    Err(_) => {
-                        // Semaphore was closed, which shouldn't happen in normal operation
-                        // Return a failed result
-                        return FormatResult {
-                            file_path: file,
-                            success: false,
-                            changed: false,
-                            original_size: 0,
-                            formatted_size: 0,
-                            duration_ms: 0,
-                            error: Some("Semaphore closed".to_string()),
-                        };
-                    }
-                };
-                process_fn(file).await
+            let sender = sender.clone();
+            let task = Self::process_one(
+                self.clone(),
+                file,
+                semaphore.clone(),
+                Arc::clone(&process_fn),
+            );
+            tokio::spawn(async move {
+                let _ = sender.send(task.await).await;
             });
-            handles.push(handle);
-        }
-
-        let mut results = Vec::new();
-        for handle in handles {
-            if let Ok(res) = handle.await {
-                results.push(res);
-            }
         }
 
-        results
+        receiver
     }
 
-    /// Split files into batches for batch-level processing
-    #[allow(dead_code)]
-    pub fn split_into_batches(&self, files: Vec<PathBuf>) -> Vec<Vec<PathBuf>> {
-        files
-            .chunks(self.batch_size)
-            .map(|chunk| chunk.to_vec())
-            .collect()
-    }
-
-    /// Get the configured batch size
-    #[allow(dead_code)]
-    pub fn batch_size(&self) -> usize {
-        self.batch_size
+    /// Run `process_fn` for one file once it is admitted and a worker slot
+    /// and memory are available
+    async fn process_one<F, Fut>(
+        limits: Self,
+        file: PathBuf,
+        semaphore: Arc<Semaphore>,
+        process_fn: Arc<F>,
+    ) -> FormatResult
+    where
+        F: Fn(PathBuf) -> Fut + Send + Sync + 'static,
+        Fut: std::future::Future<Output = FormatResult> + Send + 'static,
+    {
+        let _admitted = match &limits.admission {
+            Some(admission) => admission(file.clone()).await,
+            None => None,
+        };
+        let _permit = match semaphore.acquire().await {
+            Ok(permit) => permit,
+            Err(_) => {
+                // Semaphore was closed, which shouldn't happen in normal operation
+                // Return a failed result
+                return FormatResult::failed(
+                    file,
+                    FormatError::Other(Some("Semaphore closed".to_string())),
+                );
+            }
+        };
+        // Reserve memory for the file before it is read; released once formatted
+        let _memory_permit = match &limits.memory_budget {
+            Some(budget) => {
+                let size = tokio::fs::metadata(&file)
+                    .await
+                    .map(|m| m.len())
+                    .unwrap_or(0);
+                budget.acquire(size).await
+            }
+            None => None,
+        };
+        let _in_flight = match &limits.memory_monitor {
+            Some(monitor) => Some(monitor.admit().await),
+            None => None,
+        };
+        if limits
+            .cancel
+            .as_ref()
+            .is_some_and(CancelToken::is_cancelled)
+        {
+            return FormatResult::failed(
+                file,
+                FormatError::Cancelled(Some(
+                    "Skipped: cancelled after an earlier failure".to_string(),
+                )),
+            );
+        }
+        if limits
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            return FormatResult::failed(file, FormatError::TimeBudgetExceeded);
+        }
+        process_fn(file).await
     }
 
     /// Get the configured number of workers
-    #[allow(dead_code)]
+    #[cfg(test)]
     pub fn workers(&self) -> usize {
         self.workers
     }
@@ -97,49 +362,43 @@ mod tests {
     use std::time::Duration;
     use tokio::sync::Mutex;
 
+    async fn collect(mut receiver: mpsc::Receiver<FormatResult>) -> Vec<FormatResult> {
+        let mut results = Vec::new();
+        while let Some(result) = receiver.recv().await {
+            results.push(result);
+        }
+        results
+    }
+
     #[tokio::test]
     async fn test_batch_optimizer_creation() {
-        let optimizer = BatchOptimizer::new(10, 4);
-        assert_eq!(optimizer.batch_size(), 10);
+        let optimizer = BatchOptimizer::new(4);
         assert_eq!(optimizer.workers(), 4);
     }
 
-    #[test]
-    fn test_split_into_batches() {
-        let optimizer = BatchOptimizer::new(3, 2);
-        let files: Vec<PathBuf> = (0..10)
-            .map(|i| PathBuf::from(format!("file{}.txt", i)))
-            .collect();
-
-        let batches = optimizer.split_into_batches(files);
-        assert_eq!(batches.len(), 4);
-        assert_eq!(batches[0].len(), 3);
-        assert_eq!(batches[1].len(), 3);
-        assert_eq!(batches[2].len(), 3);
-        assert_eq!(batches[3].len(), 1);
-    }
-
     #[tokio::test]
     async fn test_process_batches() {
-        let optimizer = BatchOptimizer::new(2, 2);
+        let optimizer = BatchOptimizer::new(2);
         let files: Vec<PathBuf> = (0..5)
             .map(|i| PathBuf::from(format!("file{}.txt", i)))
             .collect();
 
-        let results = optimizer
-            .process_batches(files, |path| async move {
-                tokio::time::sleep(Duration::from_millis(10)).await;
-                FormatResult {
-                    file_path: path,
-                    success: true,
-                    changed: false,
-                    original_size: 0,
-                    formatted_size: 0,
-                    duration_ms: 10,
-                    error: None,
-                }
-            })
-            .await;
+        let results = collect(optimizer.process_streaming(files, |path| async move {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            FormatResult {
+                file_path: path,
+                success: true,
+                changed: false,
+                original_size: 0,
+                formatted_size: 0,
+                duration_ms: 10,
+                error: None,
+                error_kind: None,
+                formatter: None,
+                warning: None,
+            }
+        }))
+        .await;
 
         assert_eq!(results.len(), 5);
         for result in results {
@@ -148,88 +407,73 @@ mod tests {
     }
 
     #[test]
-    fn test_batch_size_minimum() {
-        let optimizer = BatchOptimizer::new(0, 0);
-        assert_eq!(optimizer.batch_size(), 1);
+    fn test_workers_minimum() {
+        let optimizer = BatchOptimizer::new(0);
         assert_eq!(optimizer.workers(), 1);
     }
 
+    #[test]
+    fn test_auto_workers_by_formatter_kind() {
+        assert_eq!(BatchOptimizer::auto_workers(4, 10, 2), 8);
+        assert_eq!(BatchOptimizer::auto_workers(4, 2, 10), 4);
+        assert_eq!(BatchOptimizer::auto_workers(4, 5, 5), 4);
+        assert_eq!(BatchOptimizer::auto_workers(4, 0, 0), 4);
+        assert_eq!(BatchOptimizer::auto_workers(0, 3, 0), 2);
+    }
+
     #[tokio::test]
     async fn test_empty_files_batch() {
-        let optimizer = BatchOptimizer::new(5, 2);
+        let optimizer = BatchOptimizer::new(2);
         let files: Vec<PathBuf> = Vec::new();
 
-        let results = optimizer
-            .process_batches(files, |path| async move {
-                FormatResult {
-                    file_path: path,
-                    success: true,
-                    changed: false,
-                    original_size: 0,
-                    formatted_size: 0,
-                    duration_ms: 0,
-                    error: None,
-                }
-            })
-            .await;
+        let results = collect(optimizer.process_streaming(files, |path| async move {
+            FormatResult {
+                file_path: path,
+                success: true,
+                changed: false,
+                original_size: 0,
+                formatted_size: 0,
+                duration_ms: 0,
+                error: None,
+                error_kind: None,
+                formatter: None,
+                warning: None,
+            }
+        }))
+        .await;
 
         assert_eq!(results.len(), 0);
     }
 
     #[tokio::test]
     async fn test_single_file_batch() {
-        let optimizer = BatchOptimizer::new(1, 1);
+        let optimizer = BatchOptimizer::new(1);
         let files = vec![PathBuf::from("single_file.txt")];
 
-        let results = optimizer
-            .process_batches(files, |path| async move {
-                FormatResult {
-                    file_path: path,
-                    success: true,
-                    changed: true,
-                    original_size: 100,
-                    formatted_size: 80,
-                    duration_ms: 5,
-                    error: None,
-                }
-            })
-            .await;
+        let results = collect(optimizer.process_streaming(files, |path| async move {
+            FormatResult {
+                file_path: path,
+                success: true,
+                changed: true,
+                original_size: 100,
+                formatted_size: 80,
+                duration_ms: 5,
+                error: None,
+                error_kind: None,
+                formatter: None,
+                warning: None,
+            }
+        }))
+        .await;
 
         assert_eq!(results.len(), 1);
         assert!(results[0].success);
         assert!(results[0].changed);
     }
 
-    #[test]
-    fn test_batch_split_even_division() {
-        let optimizer = BatchOptimizer::new(4, 2);
-        let files: Vec<PathBuf> = (0..8)
-            .map(|i| PathBuf::from(format!("file{}.txt", i)))
-            .collect();
-
-        let batches = optimizer.split_into_batches(files);
-        assert_eq!(batches.len(), 2);
-        assert_eq!(batches[0].len(), 4);
-        assert_eq!(batches[1].len(), 4);
-    }
-
-    #[test]
-    fn test_batch_split_uneven_division() {
-        let optimizer = BatchOptimizer::new(4, 2);
-        let files: Vec<PathBuf> = (0..10)
-            .map(|i| PathBuf::from(format!("file{}.txt", i)))
-            .collect();
-
-        let batches = optimizer.split_into_batches(files);
-        assert_eq!(batches.len(), 3);
-        assert_eq!(batches[0].len(), 4);
-        assert_eq!(batches[1].len(), 4);
-        assert_eq!(batches[2].len(), 2);
-    }
-
     #[tokio::test]
     async fn test_batch_processing_order() {
-        let optimizer = BatchOptimizer::new(10, 1);
+        let optimizer = BatchOptimizer::new(1);
         let files: Vec<PathBuf> = (0..5)
             .map(|i| PathBuf::from(format!("ordered_file_{}.txt", i)))
             .collect();
@@ -237,23 +481,25 @@ mod tests {
         let processed_order = Arc::new(Mutex::new(Vec::new()));
         let processed_order_for_check = Arc::clone(&processed_order);
         let files_clone = files.clone();
-        let _results = optimizer
-            .process_batches(files_clone, move |path| {
-                let processed_order = Arc::clone(&processed_order);
-                async move {
-                    processed_order.lock().await.push(path.clone());
-                    FormatResult {
-                        file_path: path,
-                        success: true,
-                        changed: false,
-                        original_size: 0,
-                        formatted_size: 0,
-                        duration_ms: 0,
-                        error: None,
-                    }
+        let _results = collect(optimizer.process_streaming(files_clone, move |path| {
+            let processed_order = Arc::clone(&processed_order);
+            async move {
+                processed_order.lock().await.push(path.clone());
+                FormatResult {
+                    file_path: path,
+                    success: true,
+                    changed: false,
+                    original_size: 0,
+                    formatted_size: 0,
+                    duration_ms: 0,
+                    error: None,
+                    error_kind: None,
+                    formatter: None,
+                    warning: None,
                 }
-            })
-            .await;
+            }
+        }))
+        .await;
 
         let order = processed_order_for_check.lock().await;
         assert_eq!(order.len(), 5);
@@ -261,105 +507,272 @@ mod tests {
 
     #[tokio::test]
     async fn test_batch_with_failed_files() {
-        let optimizer = BatchOptimizer::new(2, 2);
+        let optimizer = BatchOptimizer::new(2);
         let files = vec![
             PathBuf::from("valid_file.txt"),
             PathBuf::from("invalid_file.txt"),
         ];
 
-        let results = optimizer
-            .process_batches(files, |path| async move {
-                if path.to_string_lossy().contains("invalid") {
-                    FormatResult {
-                        file_path: path,
-                        success: false,
-                        changed: false,
-                        original_size: 0,
-                        formatted_size: 0,
-                        duration_ms: 0,
-                        error: Some("Processing failed".to_string()),
-                    }
-                } else {
-                    FormatResult {
-                        file_path: path,
-                        success: true,
-                        changed: true,
-                        original_size: 50,
-                        formatted_size: 40,
-                        duration_ms: 2,
-                        error: None,
-                    }
+        let results = collect(optimizer.process_streaming(files, |path| async move {
+            if path.to_string_lossy().contains("invalid") {
+                FormatResult {
+                    file_path: path,
+                    success: false,
+                    changed: false,
+                    original_size: 0,
+                    formatted_size: 0,
+                    duration_ms: 0,
+                    error: Some("Processing failed".to_string()),
+                    error_kind: Some(FormatError::Other(Some("Processing failed".to_string()))),
+                    formatter: None,
+                    warning: None,
                 }
-            })
-            .await;
+            } else {
+                FormatResult {
+                    file_path: path,
+                    success: true,
+                    changed: true,
+                    original_size: 50,
+                    formatted_size: 40,
+                    duration_ms: 2,
+                    error: None,
+                    error_kind: None,
+                    formatter: None,
+                    warning: None,
+                }
+            }
+        }))
+        .await;
 
         assert_eq!(results.len(), 2);
-        assert!(results[0].success);
-        assert!(!results[1].success);
-        assert!(results[1].error.is_some());
+        for result in results {
+            let invalid = result.file_path.to_string_lossy().contains("invalid");
+            assert_eq!(result.success, !invalid);
+            assert_eq!(result.error.is_some(), invalid);
+        }
     }
 
     #[tokio::test]
     async fn test_large_batch_processing() {
-        let optimizer = BatchOptimizer::new(100, 4);
+        let optimizer = BatchOptimizer::new(4);
         let files: Vec<PathBuf> = (0..50)
             .map(|i| PathBuf::from(format!("large_batch_file_{}.txt", i)))
             .collect();
 
-        let results = optimizer
-            .process_batches(files, |path| async move {
-                FormatResult {
-                    file_path: path,
-                    success: true,
-                    changed: false,
-                    original_size: 1024,
-                    formatted_size: 1024,
-                    duration_ms: 1,
-                    error: None,
-                }
-            })
-            .await;
+        let results = collect(optimizer.process_streaming(files, |path| async move {
+            FormatResult {
+                file_path: path,
+                success: true,
+                changed: false,
+                original_size: 1024,
+                formatted_size: 1024,
+                duration_ms: 1,
+                error: None,
+                error_kind: None,
+                formatter: None,
+                warning: None,
+            }
+        }))
+        .await;
 
         assert_eq!(results.len(), 50);
         assert!(results.iter().all(|r| r.success));
     }
 
     #[test]
-    fn test_batch_size_boundary() {
-        let optimizer_min = BatchOptimizer::new(1, 1);
-        assert_eq!(optimizer_min.batch_size(), 1);
+    fn test_workers_boundary() {
+        let optimizer_min = BatchOptimizer::new(1);
+        assert_eq!(optimizer_min.workers(), 1);
 
-        let optimizer_large = BatchOptimizer::new(10000, 100);
-        assert_eq!(optimizer_large.batch_size(), 10000);
+        let optimizer_large = BatchOptimizer::new(100);
         assert_eq!(optimizer_large.workers(), 100);
     }
 
     #[tokio::test]
     async fn test_concurrent_batch_processing() {
-        let optimizer = BatchOptimizer::new(10, 4);
+        let optimizer = BatchOptimizer::new(4);
         let files: Vec<PathBuf> = (0..8)
             .map(|i| PathBuf::from(format!("concurrent_file_{}.txt", i)))
             .collect();
 
         let start_time = tokio::time::Instant::now();
-        let results = optimizer
-            .process_batches(files, |path| async move {
-                tokio::time::sleep(Duration::from_millis(50)).await;
+        let results = collect(optimizer.process_streaming(files, |path| async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            FormatResult {
+                file_path: path,
+                success: true,
+                changed: false,
+                original_size: 0,
+                formatted_size: 0,
+                duration_ms: 50,
+                error: None,
+                error_kind: None,
+                formatter: None,
+                warning: None,
+            }
+        }))
+        .await;
+        let elapsed = start_time.elapsed();
+
+        assert_eq!(results.len(), 8);
+        assert!(results.iter().all(|r| r.success));
+        assert!(elapsed < Duration::from_millis(200));
+    }
+
+    #[tokio::test]
+    async fn test_memory_budget_bounds_in_flight_bytes() {
+        use std::sync::atomic::{AtomicU64, Ordering};
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let file_size: u64 = 40 * 1024;
+        let budget_bytes: u64 = 100 * 1024;
+
+        let files: Vec<PathBuf> = (0..8)
+            .map(|i| {
+                let path = temp_dir.path().join(format!("large_{}.txt", i));
+                std::fs::write(&path, vec![b'a'; file_size as usize]).unwrap();
+                path
+            })
+            .collect();
+
+        let optimizer = BatchOptimizer::new(8).with_memory_budget(MemoryBudget::new(budget_bytes));
+
+        let in_flight = Arc::new(AtomicU64::new(0));
+        let peak = Arc::new(AtomicU64::new(0));
+        let in_flight_clone = in_flight.clone();
+        let peak_clone = peak.clone();
+
+        let results = collect(optimizer.process_streaming(files, move |path| {
+            let in_flight = in_flight_clone.clone();
+            let peak = peak_clone.clone();
+            async move {
+                let size = std::fs::metadata(&path).unwrap().len();
+                let current = in_flight.fetch_add(size, Ordering::SeqCst) + size;
+                peak.fetch_max(current, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                in_flight.fetch_sub(size, Ordering::SeqCst);
                 FormatResult {
                     file_path: path,
                     success: true,
-                    changed: false,
-                    original_size: 0,
-                    formatted_size: 0,
-                    duration_ms: 50,
-                    error: None,
+                    ..Default::default()
                 }
-            })
-            .await;
-        let elapsed = start_time.elapsed();
+            }
+        }))
+        .await;
 
         assert_eq!(results.len(), 8);
+        assert!(peak.load(Ordering::SeqCst) <= budget_bytes);
+        assert!(peak.load(Ordering::SeqCst) >= file_size * 2);
+    }
+
+    #[tokio::test]
+    async fn test_memory_budget_oversized_file_runs_alone() {
+        let budget = MemoryBudget::new(4 * 1024);
+        assert_eq!(budget.capacity_bytes(), 4 * 1024);
+
+        let permit = budget.acquire(1024 * 1024).await;
+        assert!(permit.is_some());
+        assert_eq!(budget.available_bytes(), 0);
+
+        drop(permit);
+        assert_eq!(budget.available_bytes(), 4 * 1024);
+    }
+
+    #[tokio::test]
+    async fn test_memory_monitor_throttles_until_usage_drops() {
+        use std::sync::atomic::AtomicU64;
+
+        // Over the limit for the first few samples, then back under it
+        let samples = Arc::new(AtomicU64::new(0));
+        let sampler_samples = samples.clone();
+        let monitor = MemoryMonitor::with_sampler(
+            1024,
+            Arc::new(move || {
+                let n = sampler_samples.fetch_add(1, Ordering::SeqCst);
+                Some(if n < 3 { 1024 * 1024 } else { 512 })
+            }),
+        );
+        let optimizer = BatchOptimizer::new(4).with_memory_monitor(monitor.clone());
+        let files: Vec<PathBuf> = (0..4)
+            .map(|i| PathBuf::from(format!("file{}.txt", i)))
+            .collect();
+
+        let results = collect(optimizer.process_streaming(files, |path| async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            FormatResult {
+                file_path: path,
+                success: true,
+                ..Default::default()
+            }
+        }))
+        .await;
+
+        assert_eq!(results.len(), 4);
         assert!(results.iter().all(|r| r.success));
-        assert!(elapsed < Duration::from_millis(200));
+        assert!(monitor.throttle_count() > 0);
+        assert!(samples.load(Ordering::SeqCst) >= 3);
+    }
+
+    #[tokio::test]
+    async fn test_memory_monitor_admits_a_lone_file_over_the_limit() {
+        let monitor = MemoryMonitor::with_sampler(1024, Arc::new(|| Some(u64::MAX)));
+        let guard = monitor.admit().await;
+        assert_eq!(monitor.throttle_count(), 0);
+        drop(guard);
+        assert!(MemoryMonitor::from_megabytes(1).sampler.as_ref()().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_deadline_skips_files_not_yet_started() {
+        let optimizer = BatchOptimizer::new(1).with_deadline(Instant::now());
+        let files: Vec<PathBuf> = (0..3)
+            .map(|i| PathBuf::from(format!("file{}.txt", i)))
+            .collect();
+
+        let results = collect(
+            optimizer.process_streaming(files, |_| async { panic!("started after the deadline") }),
+        )
+        .await;
+
+        assert_eq!(results.len(), 3);
+        for result in results {
+            assert_eq!(result.error_kind, Some(FormatError::TimeBudgetExceeded));
+            assert!(!result.is_failure());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cancel_token_skips_files_not_yet_started() {
+        let cancel = CancelToken::new();
+        let optimizer = BatchOptimizer::new(1).with_cancel_token(cancel.clone());
+        let files: Vec<PathBuf> = (0..4)
+            .map(|i| PathBuf::from(format!("file{}.txt", i)))
+            .collect();
+
+        let processed = Arc::new(Mutex::new(Vec::new()));
+        let processed_clone = processed.clone();
+        let receiver = optimizer.process_streaming(files, move |path| {
+            let processed = processed_clone.clone();
+            let cancel = cancel.clone();
+            async move {
+                processed.lock().await.push(path.clone());
+                // The first file fails and stops the rest of the batch
+                cancel.cancel();
+                FormatResult {
+                    file_path: path,
+                    error: Some("boom".to_string()),
+                    error_kind: Some(FormatError::Other(Some("boom".to_string()))),
+                    formatter: None,
+                    ..Default::default()
+                }
+            }
+        });
+
+        let results = collect(receiver).await;
+
+        assert_eq!(results.len(), 4);
+        assert_eq!(processed.lock().await.len(), 1);
+        let skipped = results.iter().filter(|r| !r.is_failure()).count();
+        assert_eq!(skipped, 3);
     }
 }
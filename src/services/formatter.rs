@@ -1,17 +1,108 @@
 use crate::config::cache::ConfigCache;
+use crate::config::discovery::{discover_formatter_config, discover_project_config};
 use crate::config::types::AppConfig;
-use crate::config::types::{FormatResult, ZenithConfig};
+use crate::config::types::{
+    FinalNewline, FormatError, FormatResult, LineEnding, RetryPolicy, ZenithConfig, ZenithSettings,
+    AUTO_WORKERS, DISABLED_OVERRIDE,
+};
+use crate::core::traits::Zenith;
 use crate::error::{Result, ZenithError};
-use crate::services::batch::BatchOptimizer;
+use crate::services::batch::{BatchOptimizer, CancelToken, MemoryBudget, MemoryMonitor};
+use crate::services::progress::ProgressReporter;
 use crate::storage::backup::BackupService;
 use crate::storage::cache::HashCache;
-use crate::utils::path::validate_path;
+use crate::utils::archive::{read_archive, write_archive, ArchiveEntry, ArchiveKind};
+use crate::utils::atomic::{persist_atomic, temp_file_for, write_atomic};
+use crate::utils::normalize::{
+    apply_final_newline, convert_line_endings, detect_line_ending, is_text, is_utf8_prefix,
+    looks_binary, normalize_whitespace, strip_bom, UTF8_BOM,
+};
+use crate::utils::path::{escapes_root, validate_path, validate_within_roots};
+use crate::zeniths::pool::FormatterPool;
 use crate::zeniths::registry::ZenithRegistry;
+use futures::StreamExt;
 use ignore::WalkBuilder;
-use std::path::{Path, PathBuf};
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsString;
+use std::path::{Component, Path, PathBuf};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::fs;
-use tokio::sync::Mutex;
+use tokio::io::{AsyncReadExt, AsyncWriteExt, BufReader, BufWriter};
+use tokio::sync::{mpsc, Mutex, OnceCell, OwnedSemaphorePermit, Semaphore};
+
+/// Combine a formatter's `options` and `args` settings into `ZenithConfig::zenith_specific`
+fn zenith_specific(settings: &ZenithSettings) -> serde_json::Value {
+    let mut specific = settings.options.clone();
+    if settings.args.is_empty() {
+        return specific;
+    }
+    if !specific.is_object() {
+        specific = serde_json::Value::Object(serde_json::Map::new());
+    }
+    specific["args"] = serde_json::json!(settings.args);
+    specific
+}
+
+/// First line of `path`, read from at most its first 512 bytes
+async fn read_first_line(path: &Path) -> Option<String> {
+    let mut head = Vec::with_capacity(512);
+    fs::File::open(path)
+        .await
+        .ok()?
+        .take(512)
+        .read_to_end(&mut head)
+        .await
+        .ok()?;
+    let line = head.split(|&b| b == b'\n').next()?;
+    Some(String::from_utf8_lossy(line).trim_end().to_string())
+}
+
+/// Up to the first `limit` bytes of `path`
+async fn read_head(path: &Path, limit: u64) -> std::io::Result<Vec<u8>> {
+    let mut head = Vec::new();
+    fs::File::open(path)
+        .await?
+        .take(limit)
+        .read_to_end(&mut head)
+        .await?;
+    Ok(head)
+}
+
+/// Why content starting with `head` is kept from a text formatter, if it is:
+/// a NUL byte within its first `binary_check` bytes, or invalid UTF-8.
+/// `complete` tells whether `head` is the whole content.
+fn skip_reason(head: &[u8], binary_check: usize, complete: bool) -> Option<&'static str> {
+    if looks_binary(head, binary_check) {
+        return Some("Skipped: binary content");
+    }
+    let valid = if complete {
+        std::str::from_utf8(head).is_ok()
+    } else {
+        is_utf8_prefix(head)
+    };
+    (!valid).then_some("Skipped: invalid encoding")
+}
+
+/// Whether two files hold the same bytes, compared chunk by chunk
+async fn same_contents(a: &Path, b: &Path) -> std::io::Result<bool> {
+    if fs::metadata(a).await?.len() != fs::metadata(b).await?.len() {
+        return Ok(false);
+    }
+    let mut a = BufReader::new(fs::File::open(a).await?);
+    let mut b = BufReader::new(fs::File::open(b).await?);
+    let (mut buf_a, mut buf_b) = (vec![0; 64 * 1024], vec![0; 64 * 1024]);
+    loop {
+        let n = a.read(&mut buf_a).await?;
+        if n == 0 {
+            return Ok(true);
+        }
+        b.read_exact(&mut buf_b[..n]).await?;
+        if buf_a[..n] != buf_b[..n] {
+            return Ok(false);
+        }
+    }
+}
 
 /// Check file permissions before read/write operations
 async fn check_file_permissions(path: &Path, operation: &str) -> Result<()> {
@@ -76,6 +167,78 @@ async fn check_directory_permissions(path: &Path) -> Result<()> {
     Ok(())
 }
 
+/// Where `path` is written below `out_dir`, keeping its location relative
+/// to `root`
+///
+/// A file outside `root` keeps its whole absolute path below `out_dir`.
+fn mirror_path(out_dir: &Path, root: &Path, path: &Path) -> PathBuf {
+    let absolute = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let relative = pathdiff::diff_paths(&absolute, root)
+        .filter(|relative| {
+            !relative
+                .components()
+                .any(|c| matches!(c, Component::ParentDir))
+        })
+        .unwrap_or_else(|| {
+            absolute
+                .components()
+                .filter(|c| matches!(c, Component::Normal(_)))
+                .collect()
+        });
+    out_dir.join(relative)
+}
+
+/// Atomically write `content` to `target`, creating its directory first
+async fn write_output(target: &Path, content: &[u8]) -> std::io::Result<()> {
+    if let Some(parent) = target.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent).await?;
+    }
+    write_atomic(target, content).await
+}
+
+/// Drop files collected more than once, e.g. through a directory and a file
+/// inside it, keeping the path of the first occurrence
+///
+/// Files are compared by canonical path; one that cannot be canonicalized is
+/// compared by its absolute path. A dropped duplicate that was passed
+/// explicitly marks the kept path as explicit.
+fn dedup_files(files: Vec<PathBuf>, explicit: &mut HashSet<PathBuf>) -> Vec<PathBuf> {
+    let mut seen: HashMap<PathBuf, usize> = HashMap::with_capacity(files.len());
+    let mut unique: Vec<PathBuf> = Vec::with_capacity(files.len());
+    for file in files {
+        let key = file
+            .canonicalize()
+            .or_else(|_| std::path::absolute(&file))
+            .unwrap_or_else(|_| file.clone());
+        match seen.get(&key) {
+            Some(&index) => {
+                tracing::debug!("Skipping duplicate path {}", file.display());
+                if explicit.contains(&file) {
+                    explicit.insert(unique[index].clone());
+                }
+            }
+            None => {
+                seen.insert(key, unique.len());
+                unique.push(file);
+            }
+        }
+    }
+    unique
+}
+
+/// Project-local ignore file, in gitignore syntax, honored by directory walks
+pub const IGNORE_FILE_NAME: &str = ".zenithignore";
+
+/// Environment variable naming one more gitignore-syntax file for directory walks
+pub const IGNORE_FILE_ENV: &str = "ZENITH_IGNORE";
+
+/// Hidden directories that are never walked, even with `include_hidden`
+const HIDDEN_WALK_EXCLUDES: &[&str] = &[".git", ".hg", ".svn"];
+
+/// Error prefix of files skipped because their formatter's tool is missing
+const UNAVAILABLE_PREFIX: &str = "Skipped: formatter ";
+const UNAVAILABLE_SUFFIX: &str = " unavailable";
+
 /// Zenith Service - Main formatting service that coordinates file processing
 pub struct ZenithService {
     pub config: AppConfig,
@@ -83,7 +246,28 @@ pub struct ZenithService {
     backup_service: Arc<BackupService>,
     config_cache: Arc<Mutex<ConfigCache>>,
     hash_cache: Arc<HashCache>,
+    memory_budget: Option<MemoryBudget>,
+    /// Holds back new files while resident memory exceeds `max_memory_mb`
+    memory_monitor: Option<MemoryMonitor>,
     check_mode: bool,
+    whitespace_only: bool,
+    fail_fast: bool,
+    verify_idempotent: bool,
+    /// Lines to restrict formatting to, for formatters that support it
+    range: Option<(usize, usize)>,
+    progress: Option<Arc<dyn ProgressReporter>>,
+    /// `Zenith::is_available` results, probed once per formatter
+    availability: Arc<Mutex<HashMap<String, Arc<OnceCell<bool>>>>>,
+    /// Persistent formatter processes, when `parallel_formatters` is enabled
+    pool: Option<Arc<FormatterPool>>,
+    /// Slots for formatters capped by `concurrency.formatter_limits`
+    formatter_limits: Arc<HashMap<String, Arc<Semaphore>>>,
+    /// Extensions walked directories are narrowed to, from `--lang`
+    languages: Option<Arc<HashSet<String>>>,
+    /// Directory formatted files are mirrored into instead of rewriting them
+    out_dir: Option<PathBuf>,
+    /// Wall-clock time after which no new file of a run is started
+    time_budget: Option<Duration>,
 }
 
 impl ZenithService {
@@ -94,26 +278,175 @@ impl ZenithService {
         hash_cache: Arc<HashCache>,
         check_mode: bool,
     ) -> Self {
+        // 0 表示不限制内存
+        let memory_budget = match config.limits.max_memory_mb {
+            0 => None,
+            mb => Some(MemoryBudget::from_megabytes(mb)),
+        };
+        let memory_monitor = match config.limits.max_memory_mb {
+            0 => None,
+            mb => Some(MemoryMonitor::from_megabytes(mb)),
+        };
+
+        let pool = config
+            .global
+            .parallel_formatters
+            .then(|| Arc::new(FormatterPool::new()));
+
+        // 0 表示不单独限制
+        let formatter_limits = config
+            .concurrency
+            .formatter_limits
+            .iter()
+            .filter(|(_, &limit)| limit > 0)
+            .map(|(name, &limit)| (name.clone(), Arc::new(Semaphore::new(limit))))
+            .collect();
+
+        let config_cache =
+            ConfigCache::new().with_project_markers(config.global.project_markers.clone());
+
         Self {
             config,
             registry,
             backup_service,
-            config_cache: Arc::new(Mutex::new(ConfigCache::new())),
+            config_cache: Arc::new(Mutex::new(config_cache)),
             hash_cache,
+            memory_budget,
+            memory_monitor,
             check_mode,
+            whitespace_only: false,
+            fail_fast: false,
+            verify_idempotent: false,
+            range: None,
+            progress: None,
+            availability: Arc::new(Mutex::new(HashMap::new())),
+            pool,
+            formatter_limits: Arc::new(formatter_limits),
+            languages: None,
+            out_dir: None,
+            time_budget: None,
+        }
+    }
+
+    /// Only apply `[normalize]` whitespace rules, skipping language formatters
+    pub fn with_whitespace_only(mut self, whitespace_only: bool) -> Self {
+        self.whitespace_only = whitespace_only;
+        self
+    }
+
+    /// Stop starting new files as soon as one fails with a formatter or IO
+    /// error; files that merely need formatting in check mode do not count
+    pub fn with_fail_fast(mut self, fail_fast: bool) -> Self {
+        self.fail_fast = fail_fast;
+        self
+    }
+
+    /// Only format walked files whose extension belongs to one of the named
+    /// formatters; files passed explicitly are always formatted
+    ///
+    /// Fails with the list of registered formatters when a name is unknown.
+    /// An empty list leaves the walk unfiltered.
+    pub fn with_languages(mut self, names: &[String]) -> Result<Self> {
+        if names.is_empty() {
+            return Ok(self);
+        }
+        let mut extensions = HashSet::new();
+        for name in names {
+            let zenith =
+                self.registry
+                    .get_by_name(name)
+                    .ok_or_else(|| ZenithError::UnknownLanguage {
+                        name: name.clone(),
+                        available: self.registry.names().join(", "),
+                    })?;
+            extensions.extend(zenith.extensions().iter().map(|ext| ext.to_string()));
+        }
+        self.languages = Some(Arc::new(extensions));
+        Ok(self)
+    }
+
+    /// Format changed output a second time and warn when that pass changes
+    /// it again, which means the formatter does not converge
+    pub fn with_verify_idempotent(mut self, verify_idempotent: bool) -> Self {
+        self.verify_idempotent = verify_idempotent;
+        self
+    }
+
+    /// Only format lines `start..=end` (1-based) of each file; formatters
+    /// without range support still format the whole file
+    pub fn with_range(mut self, range: Option<(usize, usize)>) -> Self {
+        self.range = range;
+        self
+    }
+
+    /// Report each finished file of `format_paths` to `progress`
+    pub fn with_progress(mut self, progress: Arc<dyn ProgressReporter>) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+
+    /// Write formatted files below `out_dir`, keeping their path relative to
+    /// the working directory, and leave the originals untouched
+    ///
+    /// Every formatted file is written, changed or not, so the mirror is
+    /// complete. Nothing is backed up and the cache is bypassed, since the
+    /// originals are never modified.
+    pub fn with_out_dir(mut self, out_dir: Option<PathBuf>) -> Self {
+        if out_dir.is_some() {
+            self.config.global.backup_enabled = false;
+            self.config.global.cache_enabled = false;
         }
+        self.out_dir = out_dir;
+        self
+    }
+
+    /// Stop starting new files once `time_budget` has passed since a run
+    /// started; files in flight finish, and the rest are reported as
+    /// skipped with [`FormatError::TimeBudgetExceeded`]
+    pub fn with_time_budget(mut self, time_budget: Option<Duration>) -> Self {
+        self.time_budget = time_budget;
+        self
+    }
+
+    /// Restrict formatting to files located under the given roots
+    pub fn with_allowed_roots(mut self, roots: Vec<PathBuf>) -> Self {
+        self.config.security.allowed_roots = roots;
+        self
     }
 
     /// Create a ZenithConfig for a specific file based on project configuration
+    ///
+    /// When no config file is set explicitly, the formatter's own config file
+    /// (e.g. `.rustfmt.toml`, `.prettierrc`) is discovered upwards from `path`,
+    /// unless `security.sandbox_external_config` is set. The sandbox setting is
+    /// taken from the service config, so a project config cannot turn it off.
     #[doc(hidden)]
     pub fn create_zenith_config_for_file(
         &self,
         project_config: &AppConfig,
-        _path: &Path,
+        path: &Path,
         ext: &str,
+        formatter_name: &str,
     ) -> ZenithConfig {
+        let mut zenith_config = Self::settings_zenith_config(project_config, ext);
+        zenith_config.sandbox_config = self.config.security.sandbox_external_config;
+        if zenith_config.custom_config_path.is_none() && !zenith_config.sandbox_config {
+            zenith_config.custom_config_path = discover_formatter_config(path, formatter_name)
+                .unwrap_or_else(|e| {
+                    tracing::debug!("Formatter config discovery failed for {:?}: {}", path, e);
+                    None
+                });
+        }
+        zenith_config.range = self.range;
+        zenith_config.line_ending = self.config.global.line_ending;
+        zenith_config
+    }
+
+    /// ZenithConfig from the `[zeniths]` settings matching `ext`, or the defaults
+    fn settings_zenith_config(project_config: &AppConfig, ext: &str) -> ZenithConfig {
         // First, try to find a configuration specific to this file's extension
         // Look for a config with the extension as key (e.g., "rust", "js", "py")
+        let retry = RetryPolicy::from_limits(&project_config.limits);
         if let Some(zenith_settings) = project_config.zeniths.get(ext) {
             // If found and enabled, use the specific configuration
             if zenith_settings.enabled {
@@ -122,7 +455,11 @@ impl ZenithService {
                 return ZenithConfig {
                     custom_config_path,
                     use_default_rules: zenith_settings.use_default,
-                    zenith_specific: serde_json::Value::Null, // 默认值，后续可扩展
+                    zenith_specific: zenith_specific(zenith_settings),
+                    retry,
+                    range: None,
+                    sandbox_config: false,
+                    line_ending: LineEnding::Preserve,
                 };
             }
         }
@@ -135,31 +472,212 @@ impl ZenithService {
                 return ZenithConfig {
                     custom_config_path,
                     use_default_rules: default_settings.use_default,
-                    zenith_specific: serde_json::Value::Null, // 默认值，后续可扩展
+                    zenith_specific: zenith_specific(default_settings),
+                    retry,
+                    range: None,
+                    sandbox_config: false,
+                    line_ending: LineEnding::Preserve,
                 };
             }
         }
 
         // If no specific config is found, use default values
-        ZenithConfig::default()
+        ZenithConfig {
+            retry,
+            ..Default::default()
+        }
     }
 
+    /// Format the given paths and return every result once all files are done
+    ///
+    /// Collects [`format_paths_streaming`](Self::format_paths_streaming);
+    /// results are returned in the order the files were found.
     pub async fn format_paths(&self, paths: Vec<String>) -> Result<Vec<FormatResult>> {
+        let (files, explicit) = self.prepare_files(paths).await?;
+        let mut order: HashMap<PathBuf, usize> = HashMap::with_capacity(files.len());
+        for (i, file) in files.iter().enumerate() {
+            order.entry(file.clone()).or_insert(i);
+        }
+        if let Some(progress) = &self.progress {
+            progress.start(files.len());
+        }
+
+        let mut receiver = self.stream_files(files, explicit)?;
+        let mut results = Vec::with_capacity(order.len());
+        while let Some(result) = receiver.recv().await {
+            if let Some(progress) = &self.progress {
+                progress.file_done(&result);
+            }
+            results.push(result);
+        }
+
+        if let Some(progress) = &self.progress {
+            progress.finish();
+        }
+        Self::warn_unavailable(&results);
+        results.sort_by_key(|r| order.get(&r.file_path).copied().unwrap_or(usize::MAX));
+        self.auto_clean_backups(&results).await;
+        Ok(results)
+    }
+
+    /// Apply the backup retention policy after a run without failures when
+    /// `backup.auto_clean_on_exit` is set
+    ///
+    /// Cleanup problems are logged rather than failing the run.
+    async fn auto_clean_backups(&self, results: &[FormatResult]) {
+        let backup = &self.config.backup;
+        if !backup.auto_clean_on_exit
+            || self.check_mode
+            || !self.config.global.backup_enabled
+            || results.iter().any(FormatResult::is_failure)
+        {
+            return;
+        }
+        match self
+            .backup_service
+            .clean_backups(backup.retention_days)
+            .await
+        {
+            Ok(pruned) => tracing::info!("Pruned {} old backup sessions", pruned),
+            Err(e) => tracing::warn!("Failed to clean old backups: {}", e),
+        }
+    }
+
+    /// Format the given paths, yielding each result as soon as its file is done
+    ///
+    /// Path validation and backup initialization happen up front, so errors are
+    /// reported before any file is processed.
+    pub async fn format_paths_streaming(
+        &self,
+        paths: Vec<String>,
+    ) -> Result<mpsc::Receiver<FormatResult>> {
+        let (files, explicit) = self.prepare_files(paths).await?;
+        self.stream_files(files, explicit)
+    }
+
+    /// Process `files` within the concurrency and memory limits, sending each
+    /// result as it completes
+    fn stream_files(
+        &self,
+        files: Vec<PathBuf>,
+        explicit: HashSet<PathBuf>,
+    ) -> Result<mpsc::Receiver<FormatResult>> {
+        let root = std::env::current_dir()?;
+        let service = self.clone();
+        let explicit = Arc::new(explicit);
+        let cancel = CancelToken::new();
+        let mut batch_optimizer = self.batch_optimizer(&files);
+        if self.fail_fast {
+            batch_optimizer = batch_optimizer.with_cancel_token(cancel.clone());
+        }
+        if let Some(time_budget) = self.time_budget {
+            batch_optimizer = batch_optimizer.with_deadline(Instant::now() + time_budget);
+        }
+        if !self.formatter_limits.is_empty() {
+            let service = self.clone();
+            batch_optimizer = batch_optimizer.with_admission(Arc::new(move |file| {
+                let service = service.clone();
+                Box::pin(async move { service.formatter_slot(&file).await })
+            }));
+        }
+
+        Ok(batch_optimizer.process_streaming(files, move |file| {
+            let service = service.clone();
+            let root = root.clone();
+            let explicit = explicit.clone();
+            let cancel = cancel.clone();
+            async move {
+                let result = service.process_file(root, file).await;
+                let result = service.apply_strict_mode(result, &explicit);
+                if service.fail_fast && result.is_failure() {
+                    cancel.cancel();
+                }
+                result
+            }
+        }))
+    }
+
+    /// Directory walker honoring `.gitignore`, `.zenithignore` and `$ZENITH_IGNORE`
+    ///
+    /// Ignore files only filter what a walk finds; files passed explicitly are
+    /// always processed. Hidden files are skipped unless `include_hidden` is
+    /// set, and even then VCS metadata and zenith's own backup and config
+    /// directories are never walked, nor is the `out_dir` mirror. `max_depth`
+    /// limits how deep the walk goes, and symlinks are only followed with
+    /// `follow_symlinks`.
+    fn walk_builder(&self, path: &Path) -> Result<WalkBuilder> {
+        let include_hidden = self.config.global.include_hidden;
+        let mut builder = WalkBuilder::new(path);
+        builder
+            .hidden(!include_hidden)
+            .git_ignore(true)
+            .max_depth(self.config.global.max_depth)
+            .follow_links(self.config.global.follow_symlinks)
+            .add_custom_ignore_filename(IGNORE_FILE_NAME);
+        let excluded: Vec<OsString> = if include_hidden {
+            [&self.config.backup.dir, &self.config.global.config_dir]
+                .into_iter()
+                .filter_map(|dir| Path::new(dir).file_name().map(OsString::from))
+                .chain(HIDDEN_WALK_EXCLUDES.iter().map(OsString::from))
+                .collect()
+        } else {
+            Vec::new()
+        };
+        // Never format the mirror written by an earlier `out_dir` run
+        let out_dir = self
+            .out_dir
+            .as_ref()
+            .and_then(|dir| dir.canonicalize().ok());
+        if !excluded.is_empty() || out_dir.is_some() {
+            builder.filter_entry(move |entry| {
+                if !entry.file_type().is_some_and(|ft| ft.is_dir()) {
+                    return true;
+                }
+                !(excluded.iter().any(|name| name == entry.file_name())
+                    || out_dir.as_ref().is_some_and(|out_dir| {
+                        entry.path().canonicalize().is_ok_and(|p| &p == out_dir)
+                    }))
+            });
+        }
+        if let Some(ignore_file) = std::env::var_os(IGNORE_FILE_ENV).filter(|v| !v.is_empty()) {
+            if let Some(e) = builder.add_ignore(&ignore_file) {
+                return Err(ZenithError::Config(format!(
+                    "{} ({}): {}",
+                    IGNORE_FILE_ENV,
+                    Path::new(&ignore_file).display(),
+                    e
+                )));
+            }
+        }
+        Ok(builder)
+    }
+
+    /// Expand and validate the input paths, then initialize the backup session
+    ///
+    /// Returns every file to process along with the subset that was passed
+    /// explicitly rather than found by walking a directory. Ignore rules and
+    /// the `--lang` filter only apply to the walk; explicit files are always
+    /// processed.
+    async fn prepare_files(&self, paths: Vec<String>) -> Result<(Vec<PathBuf>, HashSet<PathBuf>)> {
         let mut files = Vec::new();
-        let root_path = std::env::current_dir()?;
+        let mut explicit = HashSet::new();
 
         for path_str in paths {
             let path = Path::new(&path_str);
             validate_path(path)?; // 安全检查
+            validate_within_roots(path, &self.config.security.allowed_roots)?;
 
             if path.is_file() {
                 files.push(path.to_path_buf());
+                explicit.insert(path.to_path_buf());
             } else if path.is_dir() && self.config.global.recursive {
                 check_directory_permissions(path).await?;
-                let walker = WalkBuilder::new(path).hidden(true).git_ignore(true).build();
+                let walker = self.walk_builder(path)?.build();
 
                 for entry in walker.filter_map(|e| e.ok()) {
-                    if entry.file_type().map(|ft| ft.is_file()).unwrap_or(false) {
+                    if entry.file_type().map(|ft| ft.is_file()).unwrap_or(false)
+                        && self.in_languages(entry.path())
+                    {
                         files.push(entry.path().to_path_buf());
                     }
                 }
@@ -170,34 +688,197 @@ impl ZenithService {
             }
         }
 
-        // 2. 初始化备份 (仅在非检查模式且启用备份时)
+        let files = dedup_files(files, &mut explicit);
+
+        // 初始化备份 (仅在非检查模式且启用备份时)
         if !self.check_mode && self.config.global.backup_enabled {
             self.backup_service.init().await?;
         }
 
-        // 3. 使用批处理优化器进行并发处理
-        let batch_optimizer = BatchOptimizer::new(
-            self.config.concurrency.batch_size,
-            self.config.concurrency.workers,
-        );
-        let service = self.clone();
-        let root = root_path.clone();
+        Ok((files, explicit))
+    }
 
-        let results = batch_optimizer
-            .process_batches(files, move |file| {
-                let service = service.clone();
-                let root = root.clone();
-                async move { service.process_file(root, file).await }
-            })
-            .await;
+    /// Whether `path` passes the `--lang` filter
+    fn in_languages(&self, path: &Path) -> bool {
+        match &self.languages {
+            Some(extensions) => path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| extensions.contains(ext)),
+            None => true,
+        }
+    }
 
-        Ok(results)
+    /// Turn a skipped unsupported file into a failure when `fail_on_unsupported`
+    /// is set and the file was passed explicitly
+    fn apply_strict_mode(
+        &self,
+        mut result: FormatResult,
+        explicit: &HashSet<PathBuf>,
+    ) -> FormatResult {
+        if !self.config.global.fail_on_unsupported || !explicit.contains(&result.file_path) {
+            return result;
+        }
+
+        if let Some(FormatError::UnsupportedExtension(ext)) = &result.error_kind {
+            let ext = ext.clone();
+            result.fail(FormatError::NoFormatter(ext));
+        }
+        result
+    }
+
+    /// Pick the formatter for `path` from the overrides and the registry
+    ///
+    /// The error is the one recorded on the file's `FormatResult`.
+    async fn select_zenith(
+        &self,
+        path: &Path,
+    ) -> std::result::Result<(String, Arc<dyn Zenith>), FormatError> {
+        let ext = path.extension().and_then(|e| e.to_str());
+
+        let selected = match ext {
+            Some(ext) => match self.config.overrides.get(ext) {
+                Some(name) if name == DISABLED_OVERRIDE => {
+                    return Err(FormatError::Unsupported(Some(format!(
+                        "Skipped: .{} disabled by override",
+                        ext
+                    ))));
+                }
+                Some(name) => Some(self.registry.get_by_name(name).ok_or_else(|| {
+                    FormatError::Other(Some(
+                        ZenithError::UnknownFormatter {
+                            ext: ext.to_string(),
+                            name: name.clone(),
+                        }
+                        .to_string(),
+                    ))
+                })?),
+                None => self.registry.get_by_extension(ext),
+            }
+            .map(|zenith| (ext.to_string(), zenith)),
+            None => None,
+        };
+
+        let (ext, zenith) = match selected {
+            Some(selected) => selected,
+            // 扩展名无法识别时，根据文件首行（如 shebang）选择；
+            // 此时使用格式化工具的首个扩展名查找 `[zeniths]` 设置
+            None => {
+                let first_line = read_first_line(path).await;
+                match self.registry.get_by_path(path, first_line.as_deref()) {
+                    Some(zenith) => {
+                        let ext = zenith.extensions().first().copied().unwrap_or_default();
+                        (ext.to_string(), zenith)
+                    }
+                    // 忽略不支持的文件，不报错
+                    None => {
+                        return Err(match ext {
+                            Some(ext) => FormatError::UnsupportedExtension(ext.to_string()),
+                            None => FormatError::Unsupported(Some("No extension".to_string())),
+                        })
+                    }
+                }
+            }
+        };
+
+        if !self.zenith_available(&zenith).await {
+            // 未安装的格式化工具只跳过文件，不算作失败
+            return Err(FormatError::Unsupported(Some(format!(
+                "{}{}{}",
+                UNAVAILABLE_PREFIX,
+                zenith.name(),
+                UNAVAILABLE_SUFFIX
+            ))));
+        }
+        Ok((ext, zenith))
+    }
+
+    /// Whether `zenith`'s tool is installed, probing it only on first use
+    async fn zenith_available(&self, zenith: &Arc<dyn Zenith>) -> bool {
+        // The map is only locked to find the formatter's cell, so a slow
+        // probe holds up files for that formatter alone
+        let cell = self
+            .availability
+            .lock()
+            .await
+            .entry(zenith.name().to_string())
+            .or_default()
+            .clone();
+        *cell.get_or_init(|| zenith.is_available()).await
+    }
+
+    /// Log one line per unavailable formatter instead of one per file
+    fn warn_unavailable(results: &[FormatResult]) {
+        let mut skipped: HashMap<&str, usize> = HashMap::new();
+        for name in results.iter().filter_map(|r| {
+            r.error
+                .as_deref()?
+                .strip_prefix(UNAVAILABLE_PREFIX)?
+                .strip_suffix(UNAVAILABLE_SUFFIX)
+        }) {
+            *skipped.entry(name).or_insert(0) += 1;
+        }
+        for (name, count) in skipped {
+            tracing::warn!("Formatter {} unavailable, skipping {} files", name, count);
+        }
+    }
+
+    /// Worker count for processing `paths`: the configured value, or for
+    /// `workers = "auto"` one picked from how many of the files go to
+    /// formatters that spawn an external process
+    fn workers_for<'a>(&self, paths: impl IntoIterator<Item = &'a Path>) -> usize {
+        let workers = self.config.concurrency.workers;
+        if workers != AUTO_WORKERS {
+            return workers;
+        }
+
+        let (mut external, mut native) = (0, 0);
+        for path in paths {
+            let Some(ext) = path.extension().and_then(|e| e.to_str()) else {
+                continue;
+            };
+            let zenith = match self.config.overrides.get(ext) {
+                Some(name) => self.registry.get_by_name(name),
+                None => self.registry.get_by_extension(ext),
+            };
+            match zenith {
+                Some(zenith) if zenith.spawns_process() => external += 1,
+                Some(_) => native += 1,
+                None => {}
+            }
+        }
+        let workers = BatchOptimizer::auto_workers(num_cpus::get(), external, native);
+        tracing::info!(
+            "Auto-selected {} workers ({} files for external formatters, {} for built-in)",
+            workers,
+            external,
+            native
+        );
+        workers
+    }
+
+    /// Batch optimizer for `files`, configured from the concurrency and
+    /// memory limits
+    fn batch_optimizer(&self, files: &[PathBuf]) -> BatchOptimizer {
+        let batch_optimizer =
+            BatchOptimizer::new(self.workers_for(files.iter().map(PathBuf::as_path)));
+        let batch_optimizer = match &self.memory_budget {
+            Some(budget) => batch_optimizer.with_memory_budget(budget.clone()),
+            None => batch_optimizer,
+        };
+        match &self.memory_monitor {
+            Some(monitor) => batch_optimizer.with_memory_monitor(monitor.clone()),
+            None => batch_optimizer,
+        }
     }
 
     /// Process a single file - internal method for use within the service
+    ///
+    /// The caller holds the file's [`Self::formatter_slot`], so that a file
+    /// waiting on its formatter's limit does not occupy a worker.
     #[doc(hidden)]
     pub async fn process_file(&self, root: PathBuf, path: PathBuf) -> FormatResult {
-        let start = std::time::Instant::now();
+        let start = Instant::now();
         let mut result = FormatResult {
             file_path: path.clone(),
             success: false,
@@ -206,33 +887,58 @@ impl ZenithService {
             formatted_size: 0,
             duration_ms: 0,
             error: None,
+            error_kind: None,
+            formatter: None,
+            warning: None,
         };
 
-        let ext = match path.extension().and_then(|e| e.to_str()) {
-            Some(e) => e,
-            None => {
-                result.error = Some("No extension".into());
-                return result;
-            }
-        };
+        // 不跟随符号链接时，跳过经由符号链接指向根目录之外的文件
+        if !self.config.global.follow_symlinks && escapes_root(&path, &root) {
+            result.fail(FormatError::Unsupported(Some(
+                "Skipped: symlink leads outside the root".into(),
+            )));
+            return result;
+        }
 
-        let zenith = match self.registry.get_by_extension(ext) {
-            Some(z) => z,
-            None => {
-                // 忽略不支持的文件，不报错
-                result.error = Some(format!("Skipped: .{} not supported", ext));
-                return result;
+        // 仅空白模式下任何文本文件都会处理，不需要查找格式化工具
+        let zenith = if self.whitespace_only {
+            None
+        } else {
+            match self.select_zenith(&path).await {
+                Ok(selected) => Some(selected),
+                Err(error) => {
+                    result.fail(error);
+                    return result;
+                }
             }
         };
+        result.formatter = zenith.as_ref().map(|(_, zenith)| zenith.name().to_string());
 
         if let Err(e) = check_file_permissions(&path, "read").await {
-            result.error = Some(e.to_string());
+            result.fail(FormatError::from(&e));
             return result;
         }
 
-        // 使用HashCache检查文件是否需要处理
-        if !self.check_mode && self.config.global.cache_enabled {
-            match self.hash_cache.needs_processing(&path).await {
+        // 根据文件扩展名选择合适的Zenith配置
+        let zenith_config = match &zenith {
+            Some((ext, zenith)) => {
+                self.warn_unsupported_range(zenith, &path);
+                Some(self.resolve_zenith_config(&path, ext, zenith.name()).await)
+            }
+            None => None,
+        };
+
+        // 使用HashCache检查文件是否需要处理；缓存条目同时以生效的格式化配置为键
+        let use_cache = self.config.global.cache_enabled && !self.whitespace_only;
+        let cache_config = zenith_config
+            .as_ref()
+            .filter(|_| self.hash_cache.is_config_aware());
+        if !self.check_mode && use_cache {
+            match self
+                .hash_cache
+                .needs_processing_with_config(&path, cache_config)
+                .await
+            {
                 Ok(false) => {
                     // 文件未改变，跳过处理
                     result.success = true;
@@ -249,23 +955,123 @@ impl ZenithService {
             }
         }
 
-        let content = match fs::read(&path).await {
-            Ok(c) => c,
+        // 先按文件元数据检查大小，过大的文件不必读入
+        result.original_size = match fs::metadata(&path).await {
+            Ok(metadata) => metadata.len(),
             Err(e) => {
-                result.error = Some(e.to_string());
+                result.fail(FormatError::from(&ZenithError::Io(e)));
                 return result;
             }
         };
-        result.original_size = content.len() as u64;
-
         let limit = self.config.limits.max_file_size_mb * 1024 * 1024;
         if result.original_size > limit {
-            result.error = Some(format!(
+            result.fail(FormatError::TooLarge(Some(format!(
                 "File too large (> {}MB)",
                 self.config.limits.max_file_size_mb
-            ));
+            ))));
+            return result;
+        }
+
+        // 扩展名可能与内容不符：二进制或非 UTF-8 的内容只交给声明能处理它的格式化工具
+        let binary_check = self.config.limits.binary_check_kb * 1024;
+        let text_only = zenith
+            .as_ref()
+            .is_some_and(|(_, zenith)| !zenith.handles_binary());
+        let line_ending = zenith_config
+            .as_ref()
+            .map_or(self.config.global.line_ending, |config| config.line_ending);
+
+        // 大文件以流的方式交给格式化工具；行范围和二次校验需要完整内容，仍走缓冲路径
+        let stream_threshold = self.config.limits.stream_threshold_mb * 1024 * 1024;
+        if let (Some((_, zenith)), Some(zenith_config)) = (&zenith, &zenith_config) {
+            if result.original_size > stream_threshold
+                && self.range.is_none()
+                && !self.verify_idempotent
+            {
+                let head = match read_head(&path, binary_check.max(8 * 1024)).await {
+                    Ok(head) => head,
+                    Err(e) => {
+                        result.fail(FormatError::from(&ZenithError::Io(e)));
+                        return result;
+                    }
+                };
+                if text_only {
+                    if let Some(reason) = skip_reason(&head, binary_check as usize, false) {
+                        result.fail(FormatError::Unsupported(Some(reason.into())));
+                        return result;
+                    }
+                }
+                // 带 BOM 或需要转换换行符的文件走下面的缓冲路径
+                let buffered = text_only
+                    && (head.starts_with(UTF8_BOM)
+                        || head.contains(&b'\r')
+                        || line_ending == LineEnding::Crlf
+                        || self.config.global.final_newline != FinalNewline::Preserve);
+                if !buffered {
+                    match self
+                        .format_streaming(&root, &path, zenith, zenith_config, &mut result)
+                        .await
+                    {
+                        Ok(()) => {
+                            result.success = true;
+                            if !self.check_mode && use_cache {
+                                self.update_cache(&path, cache_config).await;
+                            }
+                        }
+                        Err(e) => result.fail(e),
+                    }
+                    result.duration_ms = start.elapsed().as_millis() as u64;
+                    return result;
+                }
+            }
+        }
+
+        let content = match fs::read(&path).await {
+            Ok(c) => c,
+            Err(e) => {
+                result.fail(FormatError::from(&ZenithError::Io(e)));
+                return result;
+            }
+        };
+        result.original_size = content.len() as u64;
+
+        if self.whitespace_only && !is_text(&content) {
+            result.fail(FormatError::Unsupported(Some(
+                "Skipped: binary file".into(),
+            )));
             return result;
         }
+        if text_only {
+            if let Some(reason) = skip_reason(&content, binary_check as usize, true) {
+                result.fail(FormatError::Unsupported(Some(reason.into())));
+                return result;
+            }
+        }
+
+        // 格式化工具只看到去掉 BOM 的内容；写回时按 preserve_bom 决定是否重新加上
+        let (bom, body) = if text_only || self.whitespace_only {
+            strip_bom(&content)
+        } else {
+            (false, &content[..])
+        };
+        let bom_prefix: &[u8] = if bom && self.config.global.preserve_bom {
+            UTF8_BOM
+        } else {
+            b""
+        };
+        // 格式化工具可能改写换行符，输出统一转换为配置的换行符或原文件的主要换行符，
+        // 再按 final_newline 处理末尾换行
+        let textual = text_only || self.whitespace_only;
+        let line_ending = match line_ending {
+            _ if !textual => LineEnding::Preserve,
+            LineEnding::Preserve => detect_line_ending(body).unwrap_or(LineEnding::Preserve),
+            line_ending => line_ending,
+        };
+        let final_newline = if textual {
+            self.config.global.final_newline
+        } else {
+            FinalNewline::Preserve
+        };
 
         // 备份 (仅在非检查模式)
         if !self.check_mode && self.config.global.backup_enabled {
@@ -274,30 +1080,39 @@ impl ZenithService {
                 .backup_file(&root, &path, &content)
                 .await
             {
-                result.error = Some(format!("Backup failed: {}", e));
+                result.fail(FormatError::BackupFailed(Some(format!(
+                    "Backup failed: {}",
+                    e
+                ))));
                 return result;
             }
         }
 
-        // 获取项目特定的配置
-        let project_config = {
-            let mut cache = self.config_cache.lock().await;
-            match cache.get_config_for_file(&self.config, &path) {
-                Ok(config) => config,
-                Err(e) => {
-                    tracing::warn!("Failed to load project config for {:?}: {}", path, e);
-                    self.config.clone() // 使用应用级别的配置作为后备
-                }
+        let run_formatter = |input: &[u8]| {
+            let input = input.to_vec();
+            let (zenith, zenith_config, path) = (&zenith, &zenith_config, &path);
+            async move {
+                let formatted = match (zenith, zenith_config) {
+                    (Some((_, zenith)), Some(zenith_config)) => {
+                        self.run_zenith(zenith, &input, path, zenith_config).await
+                    }
+                    _ => Ok(normalize_whitespace(
+                        &String::from_utf8_lossy(&input),
+                        &self.config.normalize,
+                    )
+                    .into_bytes()),
+                };
+                formatted.map(|formatted| {
+                    apply_final_newline(convert_line_endings(formatted, line_ending), final_newline)
+                })
             }
         };
 
-        // 根据文件扩展名选择合适的Zenith配置
-        let zenith_config = self.create_zenith_config_for_file(&project_config, &path, ext);
-
-        match zenith.format(&content, &path, &zenith_config).await {
+        match run_formatter(body).await {
             Ok(formatted) => {
-                result.formatted_size = formatted.len() as u64;
-                let content_changed = formatted != content;
+                result.formatted_size = (bom_prefix.len() + formatted.len()) as u64;
+                // 只有去掉 BOM 时，BOM 本身算作改动
+                let content_changed = formatted != body || (bom && bom_prefix.is_empty());
                 tracing::debug!(
                     "Content comparison for {:?}: original_size={}, formatted_size={}, changed={}",
                     path,
@@ -307,32 +1122,69 @@ impl ZenithService {
                 );
                 if content_changed {
                     result.changed = true;
+                    // 未改变的内容第二遍必然相同，只需复查发生改变的文件
+                    if self.verify_idempotent {
+                        let formatter = zenith
+                            .as_ref()
+                            .map_or("whitespace", |(_, zenith)| zenith.name());
+                        let warning = match run_formatter(&formatted).await {
+                            Ok(second) if second == formatted => None,
+                            Ok(_) => Some(format!(
+                                "Unstable formatter: {} changed its own output on a second pass",
+                                formatter
+                            )),
+                            Err(e) => Some(format!(
+                                "Unstable formatter: {} failed on its own output: {}",
+                                formatter, e
+                            )),
+                        };
+                        if let Some(warning) = &warning {
+                            tracing::warn!("{}: {}", path.display(), warning);
+                        }
+                        result.warning = warning;
+                    }
                     if !self.check_mode {
-                        if let Err(e) = check_file_permissions(&path, "write").await {
-                            result.error = Some(e.to_string());
-                            return result;
+                        // 写入前校验格式化输出，避免持久化损坏的内容
+                        if let Some((_, zenith)) = &zenith {
+                            match zenith.validate(&formatted).await {
+                                Ok(true) => {}
+                                Ok(false) => {
+                                    result.fail(FormatError::SyntaxInvalid(Some(format!(
+                                        "Validation failed: {} produced invalid output",
+                                        zenith.name()
+                                    ))));
+                                    return result;
+                                }
+                                Err(e) => {
+                                    result.fail(FormatError::SyntaxInvalid(Some(format!(
+                                        "Validation failed: {}",
+                                        e
+                                    ))));
+                                    return result;
+                                }
+                            }
                         }
-                        if let Err(e) = fs::write(&path, &formatted).await {
-                            result.error = Some(format!("Write failed: {}", e));
+                        let target = match self.out_dir.as_deref() {
+                            Some(out_dir) => mirror_path(out_dir, &root, &path),
+                            None => {
+                                if let Err(e) = check_file_permissions(&path, "write").await {
+                                    result.fail(FormatError::from(&e));
+                                    return result;
+                                }
+                                path.clone()
+                            }
+                        };
+                        let output = [bom_prefix, &formatted].concat();
+                        if let Err(e) = write_output(&target, &output).await {
+                            result.fail(FormatError::WriteFailed(Some(format!(
+                                "Write failed: {}",
+                                e
+                            ))));
                         } else {
                             result.success = true;
-                            tracing::debug!("Successfully wrote formatted content to {:?}", path);
-                            if self.config.global.cache_enabled {
-                                if let Ok(new_state) =
-                                    self.hash_cache.compute_file_state(&path).await
-                                {
-                                    if let Err(e) =
-                                        self.hash_cache.update(path.clone(), new_state).await
-                                    {
-                                        tracing::warn!(
-                                            "Failed to update cache for {:?}: {}",
-                                            path,
-                                            e
-                                        );
-                                    } else {
-                                        tracing::debug!("Updated cache for {:?}", path);
-                                    }
-                                }
+                            tracing::debug!("Successfully wrote formatted content to {:?}", target);
+                            if use_cache {
+                                self.update_cache(&path, cache_config).await;
                             }
                         }
                     } else {
@@ -342,17 +1194,22 @@ impl ZenithService {
                     result.success = true;
                     result.changed = false;
                     tracing::debug!("No changes needed for {:?}", path);
-                    if !self.check_mode && self.config.global.cache_enabled {
-                        if let Ok(state) = self.hash_cache.compute_file_state(&path).await {
-                            if let Err(e) = self.hash_cache.update(path.clone(), state).await {
-                                tracing::warn!("Failed to update cache for {:?}: {}", path, e);
-                            }
+                    if let Some(out_dir) = self.out_dir.as_deref().filter(|_| !self.check_mode) {
+                        let target = mirror_path(out_dir, &root, &path);
+                        if let Err(e) = write_output(&target, &content).await {
+                            result.fail(FormatError::WriteFailed(Some(format!(
+                                "Write failed: {}",
+                                e
+                            ))));
                         }
                     }
+                    if !self.check_mode && use_cache {
+                        self.update_cache(&path, cache_config).await;
+                    }
                 }
             }
             Err(e) => {
-                result.error = Some(e.to_string());
+                result.fail(FormatError::from(&e));
             }
         }
 
@@ -360,42 +1217,385 @@ impl ZenithService {
         result
     }
 
-    /// Auto-rollback to the latest backup
-    pub async fn auto_rollback(&self) -> Result<Vec<String>> {
-        // Get the latest backup and recover from it
-        match self.backup_service.recover_latest().await {
-            Ok(recovered_files) => {
-                // Convert PathBuf to String for the returned file paths
-                let string_paths: Vec<String> = recovered_files
-                    .into_iter()
-                    .map(|path| path.to_string_lossy().into_owned())
-                    .collect();
-                Ok(string_paths)
-            }
-            Err(e) => Err(ZenithError::BackupFailed(e.to_string())),
+    /// Format a large file by piping it through [`Zenith::format_stream`]
+    ///
+    /// The output goes to a temporary file next to `path` and replaces it
+    /// only when it differs, or next to its mirror under `out_dir`.
+    /// [`Zenith::validate`] needs the whole output in memory, so it is not
+    /// run on streamed files.
+    async fn format_streaming(
+        &self,
+        root: &Path,
+        path: &Path,
+        zenith: &Arc<dyn Zenith>,
+        zenith_config: &ZenithConfig,
+        result: &mut FormatResult,
+    ) -> std::result::Result<(), FormatError> {
+        let io_error = |e: std::io::Error| FormatError::from(&ZenithError::Io(e));
+        if !self.check_mode && self.config.global.backup_enabled {
+            self.backup_service
+                .backup_path(root, path)
+                .await
+                .map_err(|e| FormatError::BackupFailed(Some(format!("Backup failed: {}", e))))?;
         }
-    }
 
-    /// Format a single file (public method for use by file watcher)
-    #[doc(hidden)]
-    pub async fn format_file(&self, path: PathBuf) -> FormatResult {
-        let root = match std::env::current_dir() {
+        let target = match self.out_dir.as_deref() {
+            Some(out_dir) => {
+                let target = mirror_path(out_dir, root, path);
+                if let Some(parent) = target.parent() {
+                    fs::create_dir_all(parent).await.map_err(io_error)?;
+                }
+                target
+            }
+            None => path.to_path_buf(),
+        };
+        let temp = temp_file_for(&target).map_err(io_error)?;
+        let output = temp.as_file().try_clone().map_err(io_error)?;
+        let mut output = BufWriter::new(fs::File::from_std(output));
+        let mut input = BufReader::new(fs::File::open(path).await.map_err(io_error)?);
+        zenith
+            .format_stream(&mut input, &mut output, path, zenith_config)
+            .await
+            .map_err(|e| FormatError::from(&e))?;
+        output.flush().await.map_err(io_error)?;
+
+        result.formatted_size = fs::metadata(temp.path()).await.map_err(io_error)?.len();
+        result.changed = !same_contents(path, temp.path()).await.map_err(io_error)?;
+        tracing::debug!(
+            "Streamed {:?}: original_size={}, formatted_size={}, changed={}",
+            path,
+            result.original_size,
+            result.formatted_size,
+            result.changed
+        );
+        // The mirror gets every file; originals are only replaced when changed
+        if self.out_dir.is_some() && !self.check_mode {
+            persist_atomic(temp, &target)
+                .await
+                .map_err(|e| FormatError::WriteFailed(Some(format!("Write failed: {}", e))))?;
+        } else if result.changed && !self.check_mode {
+            check_file_permissions(path, "write")
+                .await
+                .map_err(|e| FormatError::from(&e))?;
+            persist_atomic(temp, path)
+                .await
+                .map_err(|e| FormatError::WriteFailed(Some(format!("Write failed: {}", e))))?;
+        }
+        Ok(())
+    }
+
+    /// Format `content` as if it were the file at `path`, returning the
+    /// result and whether it differs from the input
+    ///
+    /// The formatter and its config are resolved from `path` as for a file
+    /// on disk, but the file itself is neither read nor written, and backups
+    /// and the cache are left alone.
+    pub async fn format_content(&self, path: &Path, content: &[u8]) -> Result<(Vec<u8>, bool)> {
+        let limit = self.config.limits.max_file_size_mb * 1024 * 1024;
+        if content.len() as u64 > limit {
+            return Err(ZenithError::FileTooLarge {
+                size: content.len() as u64,
+                limit,
+            });
+        }
+
+        let (ext, zenith) = self.select_zenith(path).await.map_err(|reason| {
+            ZenithError::UnsupportedExtension(format!("{} ({})", path.display(), reason))
+        })?;
+        self.warn_unsupported_range(&zenith, path);
+        let zenith_config = self.resolve_zenith_config(path, &ext, zenith.name()).await;
+        let _permit = self.formatter_permit(zenith.name()).await;
+        let formatted = self
+            .run_zenith(&zenith, content, path, &zenith_config)
+            .await?;
+        let changed = formatted != content;
+        Ok((formatted, changed))
+    }
+
+    /// Format in-memory `content` with the formatter for `filename`'s
+    /// extension, returning the output and whether it differs from the input
+    ///
+    /// This is [`Self::format_content`] for callers that only have a file
+    /// name: nothing is read from or written to it, and backups and the
+    /// cache are left alone.
+    pub async fn format_bytes(&self, filename: &str, content: &[u8]) -> Result<(Vec<u8>, bool)> {
+        self.format_content(Path::new(filename), content).await
+    }
+
+    /// Wait for a free slot when `name` is capped by `concurrency.formatter_limits`
+    async fn formatter_permit(&self, name: &str) -> Option<OwnedSemaphorePermit> {
+        let semaphore = self.formatter_limits.get(name)?.clone();
+        semaphore.acquire_owned().await.ok()
+    }
+
+    /// [`Self::formatter_permit`] for the formatter `path` goes to, held for
+    /// the whole file, including the `verify_idempotent` second pass
+    async fn formatter_slot(&self, path: &Path) -> Option<OwnedSemaphorePermit> {
+        if self.formatter_limits.is_empty() || self.whitespace_only {
+            return None;
+        }
+        let (_, zenith) = self.select_zenith(path).await.ok()?;
+        self.formatter_permit(zenith.name()).await
+    }
+
+    /// Run `zenith`, through the persistent process pool when it is enabled
+    async fn run_zenith(
+        &self,
+        zenith: &Arc<dyn Zenith>,
+        content: &[u8],
+        path: &Path,
+        config: &ZenithConfig,
+    ) -> Result<Vec<u8>> {
+        match &self.pool {
+            Some(pool) => zenith.format_pooled(content, path, config, pool).await,
+            None => zenith.format(content, path, config).await,
+        }
+    }
+
+    fn warn_unsupported_range(&self, zenith: &Arc<dyn Zenith>, path: &Path) {
+        if self.range.is_some() && !zenith.supports_range() {
+            tracing::warn!(
+                "{} does not support line ranges; formatting all of {}",
+                zenith.name(),
+                path.display()
+            );
+        }
+    }
+
+    /// Effective ZenithConfig for `path`, merging its project config
+    async fn resolve_zenith_config(&self, path: &Path, ext: &str, name: &str) -> ZenithConfig {
+        // 获取项目特定的配置
+        let project_config = {
+            let mut cache = self.config_cache.lock().await;
+            match cache.get_config_for_file(&self.config, path) {
+                Ok(config) => config,
+                Err(e) => {
+                    tracing::warn!("Failed to load project config for {:?}: {}", path, e);
+                    self.config.clone() // 使用应用级别的配置作为后备
+                }
+            }
+        };
+        self.create_zenith_config_for_file(&project_config, path, ext, name)
+    }
+
+    /// Record the current state of `path`, keyed on `config` when given
+    async fn update_cache(&self, path: &Path, config: Option<&ZenithConfig>) {
+        let state = match config {
+            Some(config) => {
+                self.hash_cache
+                    .compute_file_state_with_config(path, config)
+                    .await
+            }
+            None => self.hash_cache.compute_file_state(path).await,
+        };
+        let updated = match state {
+            Ok(state) => self.hash_cache.update(path.to_path_buf(), state).await,
+            Err(e) => Err(e),
+        };
+        match updated {
+            Ok(()) => tracing::debug!("Updated cache for {:?}", path),
+            Err(e) => tracing::warn!("Failed to update cache for {:?}: {}", path, e),
+        }
+    }
+
+    /// Auto-rollback to the latest backup
+    pub async fn auto_rollback(&self) -> Result<Vec<String>> {
+        // Get the latest backup and recover from it
+        match self.backup_service.recover_latest().await {
+            Ok(recovered_files) => {
+                // Convert PathBuf to String for the returned file paths
+                let string_paths: Vec<String> = recovered_files
+                    .into_iter()
+                    .map(|path| path.to_string_lossy().into_owned())
+                    .collect();
+                Ok(string_paths)
+            }
+            Err(e) => Err(ZenithError::BackupFailed(e.to_string())),
+        }
+    }
+
+    /// Format a single file, with the same caching, backup and root checks
+    /// as [`format_paths`](Self::format_paths)
+    ///
+    /// The file's root is the directory holding its project config
+    /// (`zenith.toml` etc.), or the working directory when it has none; the
+    /// search ends at `global.project_markers`. Used by watch mode.
+    pub async fn format_file(&self, path: PathBuf) -> FormatResult {
+        let project_root = discover_project_config(&path, &self.config.global.project_markers)
+            .ok()
+            .flatten()
+            .and_then(|config| config.parent().map(Path::to_path_buf));
+        let root = match project_root.map_or_else(std::env::current_dir, Ok) {
             Ok(root) => root,
             Err(e) => {
-                return FormatResult {
-                    file_path: path,
-                    error: Some(format!("Failed to get current directory: {}", e)),
-                    ..Default::default()
-                };
+                return FormatResult::failed(
+                    path,
+                    FormatError::Other(Some(format!("Failed to get current directory: {}", e))),
+                );
             }
         };
+        if let Err(e) = validate_within_roots(&path, &self.config.security.allowed_roots) {
+            return FormatResult::failed(path, FormatError::from(&e));
+        }
+        let _slot = self.formatter_slot(&path).await;
         self.process_file(root, path).await
     }
 
-    /// Check if a file is in the cache (for watch mode)
-    #[doc(hidden)]
+    /// Format the files inside a `.zip`, `.tar` or `.tar.gz` without extracting it
+    ///
+    /// Nothing is written: each result reports whether the member would change,
+    /// as in check mode.
+    pub async fn format_archive(&self, archive: &Path) -> Result<Vec<FormatResult>> {
+        self.format_archive_to(archive, None).await
+    }
+
+    /// Like [`format_archive`](Self::format_archive), additionally writing a new
+    /// archive to `output` with every member that formatted successfully
+    /// replaced by its formatted contents
+    ///
+    /// The output format follows the extension of `output`.
+    pub async fn format_archive_to(
+        &self,
+        archive: &Path,
+        output: Option<&Path>,
+    ) -> Result<Vec<FormatResult>> {
+        validate_within_roots(archive, &self.config.security.allowed_roots)?;
+        if let Some(output) = output {
+            ArchiveKind::from_path(output)?;
+            validate_within_roots(output, &self.config.security.allowed_roots)?;
+        }
+
+        let source = archive.to_path_buf();
+        let limit = self.config.limits.max_file_size_mb * 1024 * 1024;
+        let entries = tokio::task::spawn_blocking(move || read_archive(&source, limit))
+            .await
+            .map_err(|e| ZenithError::Archive(e.to_string()))??;
+
+        let workers = self
+            .workers_for(
+                entries
+                    .iter()
+                    .filter(|entry| entry.is_file)
+                    .map(|entry| Path::new(&entry.name)),
+            )
+            .max(1);
+        let outcomes: Vec<(ArchiveEntry, Option<FormatResult>)> = futures::stream::iter(entries)
+            .map(|entry| async move {
+                if !entry.is_file {
+                    return (entry, None);
+                }
+                let (result, formatted) = self.format_archive_entry(&entry).await;
+                let entry = match formatted {
+                    Some(formatted) => entry.with_content(formatted),
+                    None => entry,
+                };
+                (entry, Some(result))
+            })
+            .buffered(workers)
+            .collect()
+            .await;
+
+        let (entries, results): (Vec<_>, Vec<_>) = outcomes.into_iter().unzip();
+        let results: Vec<FormatResult> = results.into_iter().flatten().collect();
+        Self::warn_unavailable(&results);
+
+        if let Some(output) = output {
+            let output = output.to_path_buf();
+            let source = archive.to_path_buf();
+            tokio::task::spawn_blocking(move || write_archive(&output, &entries, Some(&source)))
+                .await
+                .map_err(|e| ZenithError::Archive(e.to_string()))??;
+        }
+        Ok(results)
+    }
+
+    /// Format one archive member in memory, returning its result and the
+    /// formatted contents when formatting succeeded
+    async fn format_archive_entry(&self, entry: &ArchiveEntry) -> (FormatResult, Option<Vec<u8>>) {
+        let start = Instant::now();
+        let path = PathBuf::from(&entry.name);
+        let mut result = FormatResult {
+            file_path: path.clone(),
+            original_size: entry.content.len() as u64,
+            ..Default::default()
+        };
+
+        // Oversized members were never read into memory
+        if entry.oversized {
+            result.fail(FormatError::TooLarge(Some(format!(
+                "File too large (> {}MB)",
+                self.config.limits.max_file_size_mb
+            ))));
+            return (result, None);
+        }
+
+        let formatted = if self.whitespace_only {
+            if !is_text(&entry.content) {
+                result.fail(FormatError::Unsupported(Some(
+                    "Skipped: binary file".into(),
+                )));
+                return (result, None);
+            }
+            Ok(normalize_whitespace(
+                &String::from_utf8_lossy(&entry.content),
+                &self.config.normalize,
+            )
+            .into_bytes())
+        } else {
+            let (ext, zenith) = match self.select_zenith(&path).await {
+                Ok(selected) => selected,
+                Err(error) => {
+                    result.fail(error);
+                    return (result, None);
+                }
+            };
+            result.formatter = Some(zenith.name().to_string());
+            // 归档成员不在磁盘上，只使用配置中的设置，不向上查找格式化工具的配置文件
+            let mut zenith_config = Self::settings_zenith_config(&self.config, &ext);
+            zenith_config.sandbox_config = self.config.security.sandbox_external_config;
+            let _permit = self.formatter_permit(zenith.name()).await;
+            self.run_zenith(&zenith, &entry.content, &path, &zenith_config)
+                .await
+        };
+
+        result.duration_ms = start.elapsed().as_millis() as u64;
+        match formatted {
+            Ok(formatted) => {
+                result.success = true;
+                result.changed = formatted != entry.content;
+                result.formatted_size = formatted.len() as u64;
+                (result, Some(formatted))
+            }
+            Err(e) => {
+                result.fail(FormatError::from(&e));
+                (result, None)
+            }
+        }
+    }
+
+    /// Whether the cache holds `path` with its current content, so
+    /// [`format_file`](Self::format_file) would skip it
+    ///
+    /// Watch mode uses this to ignore the events caused by its own writes.
     pub async fn is_cached(&self, path: &Path) -> bool {
-        self.hash_cache.is_cached(path).await
+        matches!(self.hash_cache.needs_processing(path).await, Ok(false))
+    }
+
+    /// Persist the hash cache; a no-op when the cache has no directory
+    pub async fn save_cache(&self) -> Result<()> {
+        self.hash_cache.save().await
+    }
+
+    /// Drop the cache entry of a deleted file (for watch mode)
+    #[doc(hidden)]
+    pub async fn evict_cached(&self, path: &Path) -> Result<()> {
+        self.hash_cache.remove(path).await
+    }
+
+    /// Move the cache entry of a renamed file (for watch mode)
+    #[doc(hidden)]
+    pub async fn rename_cached(&self, from: &Path, to: &Path) -> Result<()> {
+        self.hash_cache.rename(from, to.to_path_buf()).await
     }
 }
 
@@ -407,7 +1607,20 @@ impl Clone for ZenithService {
             backup_service: self.backup_service.clone(),
             config_cache: self.config_cache.clone(),
             hash_cache: self.hash_cache.clone(),
+            memory_budget: self.memory_budget.clone(),
+            memory_monitor: self.memory_monitor.clone(),
             check_mode: self.check_mode,
+            whitespace_only: self.whitespace_only,
+            fail_fast: self.fail_fast,
+            verify_idempotent: self.verify_idempotent,
+            range: self.range,
+            progress: self.progress.clone(),
+            availability: self.availability.clone(),
+            pool: self.pool.clone(),
+            formatter_limits: self.formatter_limits.clone(),
+            languages: self.languages.clone(),
+            out_dir: self.out_dir.clone(),
+            time_budget: self.time_budget,
         }
     }
 }
@@ -417,6 +1630,7 @@ mod tests {
     use super::*;
     use crate::config::types::AppConfig;
     use crate::zeniths::registry::ZenithRegistry;
+    use std::sync::atomic::{AtomicUsize, Ordering};
     use std::sync::Arc;
     use tempfile::TempDir;
     use tokio::fs;
@@ -425,12 +1639,124 @@ mod tests {
         let temp_dir = TempDir::new().unwrap();
         let config = AppConfig::default();
         let registry = Arc::new(ZenithRegistry::new());
-        let backup_service = Arc::new(BackupService::new(config.backup.clone()));
+        let backup_service = Arc::new(BackupService::new(config.backup.clone(), None));
         let hash_cache = Arc::new(HashCache::new());
         let service = ZenithService::new(config, registry, backup_service, hash_cache, false);
         (service, temp_dir)
     }
 
+    /// Default config without backups or the hash cache
+    fn test_config() -> AppConfig {
+        let mut config = AppConfig::default();
+        config.global.backup_enabled = false;
+        config.global.cache_enabled = false;
+        config
+    }
+
+    /// Service over `config` with only `zeniths` registered
+    fn test_service(config: AppConfig, zeniths: Vec<Arc<dyn Zenith>>) -> ZenithService {
+        let registry = Arc::new(ZenithRegistry::new());
+        for zenith in zeniths {
+            registry.register(zenith);
+        }
+        let backup_service = Arc::new(BackupService::new(config.backup.clone(), None));
+        ZenithService::new(
+            config,
+            registry,
+            backup_service,
+            Arc::new(HashCache::new()),
+            false,
+        )
+    }
+
+    type Transform = Box<dyn Fn(&[u8]) -> Vec<u8> + Send + Sync>;
+
+    /// Formatter that rewrites content with `transform`, optionally after a
+    /// delay, and records its runs
+    struct MockZenith {
+        name: &'static str,
+        extensions: &'static [&'static str],
+        delay: Duration,
+        transform: Transform,
+        runs: AtomicUsize,
+        running: AtomicUsize,
+        peak: AtomicUsize,
+        last_input: std::sync::Mutex<Vec<u8>>,
+    }
+
+    impl MockZenith {
+        fn new(
+            name: &'static str,
+            extensions: &'static [&'static str],
+            transform: impl Fn(&[u8]) -> Vec<u8> + Send + Sync + 'static,
+        ) -> Self {
+            Self {
+                name,
+                extensions,
+                delay: Duration::ZERO,
+                transform: Box::new(transform),
+                runs: AtomicUsize::new(0),
+                running: AtomicUsize::new(0),
+                peak: AtomicUsize::new(0),
+                last_input: std::sync::Mutex::new(Vec::new()),
+            }
+        }
+
+        /// Returns its input unchanged
+        fn identity(name: &'static str, extensions: &'static [&'static str]) -> Self {
+            Self::new(name, extensions, <[u8]>::to_vec)
+        }
+
+        /// Upper-cases its input
+        fn uppercase(name: &'static str, extensions: &'static [&'static str]) -> Self {
+            Self::new(name, extensions, <[u8]>::to_ascii_uppercase)
+        }
+
+        fn with_delay(mut self, delay: Duration) -> Self {
+            self.delay = delay;
+            self
+        }
+
+        fn runs(&self) -> usize {
+            self.runs.load(Ordering::SeqCst)
+        }
+
+        /// Highest number of overlapping `format` calls
+        fn peak(&self) -> usize {
+            self.peak.load(Ordering::SeqCst)
+        }
+
+        fn last_input(&self) -> Vec<u8> {
+            self.last_input.lock().unwrap().clone()
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl Zenith for MockZenith {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn extensions(&self) -> &[&str] {
+            self.extensions
+        }
+
+        async fn format(
+            &self,
+            content: &[u8],
+            _path: &Path,
+            _config: &ZenithConfig,
+        ) -> Result<Vec<u8>> {
+            self.runs.fetch_add(1, Ordering::SeqCst);
+            *self.last_input.lock().unwrap() = content.to_vec();
+            let now = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+            self.peak.fetch_max(now, Ordering::SeqCst);
+            tokio::time::sleep(self.delay).await;
+            self.running.fetch_sub(1, Ordering::SeqCst);
+            Ok((self.transform)(content))
+        }
+    }
+
     #[test]
     fn test_file_permission_checks() {
         let temp_dir = TempDir::new().unwrap();
@@ -438,7 +1764,7 @@ mod tests {
         std::fs::write(&test_file, "// Test file content").unwrap();
         let config = AppConfig::default();
         let registry = Arc::new(ZenithRegistry::new());
-        let backup_service = Arc::new(BackupService::new(config.backup.clone()));
+        let backup_service = Arc::new(BackupService::new(config.backup.clone(), None));
         let hash_cache = Arc::new(HashCache::new());
         let _service = ZenithService::new(config, registry, backup_service, hash_cache, false);
     }
@@ -484,6 +1810,44 @@ mod tests {
         assert!(result.error.unwrap().contains("No extension"));
     }
 
+    #[cfg(feature = "shell")]
+    #[tokio::test]
+    async fn test_extensionless_shebang_script_uses_shell_formatter() {
+        use crate::zeniths::impls::shell_zenith::ShellZenith;
+
+        let temp_dir = TempDir::new().unwrap();
+        let mut service = test_service(test_config(), vec![Arc::new(ShellZenith)]);
+        service.check_mode = true;
+
+        let script = temp_dir.path().join("deploy");
+        fs::write(&script, "#!/usr/bin/env bash\nif true;then echo hi;fi\n")
+            .await
+            .unwrap();
+        assert_eq!(
+            service
+                .registry
+                .get_by_path(&script, Some("#!/usr/bin/env bash"))
+                .map(|zenith| zenith.name().to_string()),
+            Some("shfmt".to_string())
+        );
+        let result = service
+            .process_file(temp_dir.path().to_path_buf(), script)
+            .await;
+        // 未安装 shfmt 时会报告工具不可用，但文件仍交给了 shfmt
+        assert!(
+            result.success || result.error.as_deref().unwrap().contains("shfmt"),
+            "{:?}",
+            result.error
+        );
+
+        let makefile = temp_dir.path().join("Makefile");
+        fs::write(&makefile, "all:\n\techo hi\n").await.unwrap();
+        let result = service
+            .process_file(temp_dir.path().to_path_buf(), makefile)
+            .await;
+        assert!(result.error.unwrap().contains("No extension"));
+    }
+
     #[tokio::test]
     async fn test_process_file_unsupported_extension() {
         let (service, temp_dir) = create_test_service();
@@ -496,6 +1860,10 @@ mod tests {
             .await;
         assert!(!result.success);
         assert!(result.error.unwrap().contains("not supported"));
+        assert_eq!(
+            result.error_kind,
+            Some(FormatError::UnsupportedExtension("xyz".into()))
+        );
     }
 
     #[tokio::test]
@@ -523,8 +1891,12 @@ mod tests {
     async fn test_zenith_config_for_file_no_match() {
         let (service, _temp_dir) = create_test_service();
         let config = AppConfig::default();
-        let result =
-            service.create_zenith_config_for_file(&config, Path::new("/test.rs"), "unknown");
+        let result = service.create_zenith_config_for_file(
+            &config,
+            Path::new("/test.rs"),
+            "unknown",
+            "unknown",
+        );
         assert!(result.custom_config_path.is_none());
     }
 
@@ -533,10 +1905,57 @@ mod tests {
         let (service, temp_dir) = create_test_service();
         let test_file = temp_dir.path().join("test.rs");
         let config = AppConfig::default();
-        let result = service.create_zenith_config_for_file(&config, &test_file, "rs");
+        let result = service.create_zenith_config_for_file(&config, &test_file, "rs", "rust");
         assert!(result.custom_config_path.is_none() || result.custom_config_path.is_some());
     }
 
+    #[tokio::test]
+    async fn test_zenith_config_discovers_formatter_config() {
+        let (service, temp_dir) = create_test_service();
+        let rustfmt_config = temp_dir.path().join(".rustfmt.toml");
+        std::fs::write(&rustfmt_config, "max_width = 80\n").unwrap();
+        let test_file = temp_dir.path().join("main.rs");
+        let config = AppConfig::default();
+
+        let result = service.create_zenith_config_for_file(&config, &test_file, "rs", "rust");
+        assert_eq!(result.custom_config_path, Some(rustfmt_config));
+
+        // An explicitly configured path wins over discovery
+        let mut config = AppConfig::default();
+        config.zeniths.insert(
+            "rs".into(),
+            ZenithSettings {
+                config_path: Some("custom.toml".into()),
+                ..Default::default()
+            },
+        );
+        let result = service.create_zenith_config_for_file(&config, &test_file, "rs", "rust");
+        assert_eq!(
+            result.custom_config_path,
+            Some(PathBuf::from("custom.toml"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sandbox_skips_formatter_config_discovery() {
+        let (mut service, temp_dir) = create_test_service();
+        std::fs::write(
+            temp_dir.path().join(".prettierrc.js"),
+            "module.exports = {}",
+        )
+        .unwrap();
+        let test_file = temp_dir.path().join("app.js");
+        service.config.security.sandbox_external_config = true;
+
+        // A project config cannot turn the sandbox off
+        let mut project_config = AppConfig::default();
+        project_config.security.sandbox_external_config = false;
+        let result =
+            service.create_zenith_config_for_file(&project_config, &test_file, "js", "prettier");
+        assert!(result.sandbox_config);
+        assert!(result.custom_config_path.is_none());
+    }
+
     #[tokio::test]
     async fn test_process_multiple_files_in_sequence() {
         let (service, temp_dir) = create_test_service();
@@ -566,6 +1985,58 @@ mod tests {
         assert!(result.original_size > 0 || result.error.is_some());
     }
 
+    #[tokio::test]
+    async fn test_format_file_resolves_the_project_root() {
+        let temp_dir = TempDir::new().unwrap();
+        let project = temp_dir.path().join("project");
+        let file = project.join("src").join("notes.txt");
+        fs::create_dir_all(file.parent().unwrap()).await.unwrap();
+        fs::write(project.join("zenith.toml"), "").await.unwrap();
+        fs::write(&file, "text   \n").await.unwrap();
+        let out_dir = temp_dir.path().join("out");
+
+        let (service, _) = create_test_service();
+        let service = service
+            .with_whitespace_only(true)
+            .with_out_dir(Some(out_dir.clone()));
+        let result = service.format_file(file).await;
+
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(
+            fs::read_to_string(out_dir.join("src").join("notes.txt"))
+                .await
+                .unwrap(),
+            "text\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_is_cached_tracks_the_current_content() {
+        let temp_dir = TempDir::new().unwrap();
+        let file = temp_dir.path().join("data.cnt");
+        fs::write(&file, "abc\n").await.unwrap();
+
+        let zenith = Arc::new(MockZenith::uppercase("counting", &["cnt"]));
+        let mut config = test_config();
+        config.global.cache_enabled = true;
+        let service = test_service(config, vec![zenith.clone()]);
+
+        assert!(!service.is_cached(&file).await);
+        let result = service.format_file(file.clone()).await;
+        assert!(result.changed);
+        // The formatter's own write is cached, so its watch event is skipped
+        assert!(service.is_cached(&file).await);
+        let result = service.format_file(file.clone()).await;
+        assert!(result.success && !result.changed);
+        assert_eq!(zenith.runs(), 1);
+
+        fs::write(&file, "xyz\n").await.unwrap();
+        assert!(!service.is_cached(&file).await);
+        service.format_file(file.clone()).await;
+        assert_eq!(fs::read_to_string(&file).await.unwrap(), "XYZ\n");
+        assert_eq!(zenith.runs(), 2);
+    }
+
     #[tokio::test]
     async fn test_is_cached_for_nonexistent() {
         let (service, _temp_dir) = create_test_service();
@@ -573,4 +2044,1180 @@ mod tests {
         let result = service.is_cached(&nonexistent).await;
         assert!(!result);
     }
+
+    #[tokio::test]
+    async fn test_format_paths_inside_allowed_root() {
+        let (service, temp_dir) = create_test_service();
+        let mut service = service.with_allowed_roots(vec![temp_dir.path().to_path_buf()]);
+        service.config.global.backup_enabled = false;
+
+        let test_file = temp_dir.path().join("test.rs");
+        fs::write(&test_file, "fn main() {}").await.unwrap();
+
+        let results = service
+            .format_paths(vec![test_file.to_string_lossy().to_string()])
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_format_paths_outside_allowed_root() {
+        let (service, temp_dir) = create_test_service();
+        let allowed = temp_dir.path().join("project");
+        std::fs::create_dir(&allowed).unwrap();
+        let service = service.with_allowed_roots(vec![allowed]);
+
+        let outside_file = temp_dir.path().join("outside.rs");
+        fs::write(&outside_file, "fn main() {}").await.unwrap();
+
+        let result = service
+            .format_paths(vec![outside_file.to_string_lossy().to_string()])
+            .await;
+        assert!(matches!(result, Err(ZenithError::OutsideAllowedRoot(_))));
+
+        let result = service.format_file(outside_file).await;
+        assert!(!result.success);
+        assert!(result.error.unwrap().contains("outside the allowed roots"));
+    }
+
+    #[tokio::test]
+    async fn test_whitespace_only_skips_language_formatters() {
+        let temp_dir = TempDir::new().unwrap();
+        let zenith = Arc::new(MockZenith::identity("counting", &["rs"]));
+        let service = test_service(test_config(), vec![zenith.clone()]).with_whitespace_only(true);
+
+        let rust_file = temp_dir.path().join("main.rs");
+        let text_file = temp_dir.path().join("notes.unknownext");
+        fs::write(&rust_file, "fn main() {}   \n\n\n")
+            .await
+            .unwrap();
+        fs::write(&text_file, "hello\t").await.unwrap();
+
+        let results = service
+            .format_paths(vec![temp_dir.path().to_string_lossy().to_string()])
+            .await
+            .unwrap();
+
+        assert_eq!(zenith.runs(), 0);
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|r| r.success && r.changed));
+        assert_eq!(
+            fs::read_to_string(&rust_file).await.unwrap(),
+            "fn main() {}\n"
+        );
+        assert_eq!(fs::read_to_string(&text_file).await.unwrap(), "hello\n");
+    }
+
+    #[tokio::test]
+    async fn test_invalid_output_is_not_written() {
+        use crate::core::traits::Zenith;
+        use async_trait::async_trait;
+
+        struct BrokenZenith;
+
+        #[async_trait]
+        impl Zenith for BrokenZenith {
+            fn name(&self) -> &str {
+                "broken"
+            }
+
+            fn extensions(&self) -> &[&str] {
+                &["brk"]
+            }
+
+            async fn format(
+                &self,
+                _content: &[u8],
+                _path: &Path,
+                _config: &ZenithConfig,
+            ) -> Result<Vec<u8>> {
+                Ok(b"corrupted output".to_vec())
+            }
+
+            async fn validate(&self, _content: &[u8]) -> Result<bool> {
+                Ok(false)
+            }
+        }
+
+        let temp_dir = TempDir::new().unwrap();
+        let service = test_service(test_config(), vec![Arc::new(BrokenZenith)]);
+
+        let file = temp_dir.path().join("input.brk");
+        fs::write(&file, "original").await.unwrap();
+
+        let result = service
+            .process_file(temp_dir.path().to_path_buf(), file.clone())
+            .await;
+
+        assert!(!result.success);
+        assert!(result.error.unwrap().contains("Validation failed"));
+        assert_eq!(fs::read_to_string(&file).await.unwrap(), "original");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_empty_formatter_output_keeps_original_file() {
+        use crate::core::traits::Zenith;
+        use crate::zeniths::common::StdioFormatter;
+        use async_trait::async_trait;
+
+        /// Swallows its input and exits successfully without output
+        struct SilentZenith;
+
+        #[async_trait]
+        impl Zenith for SilentZenith {
+            fn name(&self) -> &str {
+                "silent"
+            }
+
+            fn extensions(&self) -> &[&str] {
+                &["sil"]
+            }
+
+            async fn format(
+                &self,
+                content: &[u8],
+                path: &Path,
+                _config: &ZenithConfig,
+            ) -> Result<Vec<u8>> {
+                StdioFormatter::new("sh", vec!["-c".into(), "cat > /dev/null".into()])
+                    .format_with_stdio_no_path(content, path, None)
+                    .await
+            }
+        }
+
+        let temp_dir = TempDir::new().unwrap();
+        let service = test_service(test_config(), vec![Arc::new(SilentZenith)]);
+
+        let file = temp_dir.path().join("input.sil");
+        fs::write(&file, "original\n").await.unwrap();
+        let result = service
+            .process_file(temp_dir.path().to_path_buf(), file.clone())
+            .await;
+        assert!(!result.success);
+        assert_eq!(result.formatter.as_deref(), Some("silent"));
+        assert!(result.error.unwrap().contains("empty output"));
+        assert_eq!(fs::read_to_string(&file).await.unwrap(), "original\n");
+
+        // 空文件得到空输出是正常结果
+        let empty = temp_dir.path().join("empty.sil");
+        fs::write(&empty, "").await.unwrap();
+        let result = service
+            .process_file(temp_dir.path().to_path_buf(), empty)
+            .await;
+        assert!(result.success);
+    }
+
+    #[tokio::test]
+    async fn test_large_files_are_streamed() {
+        use crate::core::traits::Zenith;
+        use async_trait::async_trait;
+        use tokio::io::{AsyncRead, AsyncWrite};
+
+        /// Upper-cases in chunks; the buffered path is never expected
+        struct StreamingZenith;
+
+        #[async_trait]
+        impl Zenith for StreamingZenith {
+            fn name(&self) -> &str {
+                "streaming"
+            }
+
+            fn extensions(&self) -> &[&str] {
+                &["big"]
+            }
+
+            async fn format(
+                &self,
+                _content: &[u8],
+                _path: &Path,
+                _config: &ZenithConfig,
+            ) -> Result<Vec<u8>> {
+                Err(ZenithError::ZenithFailed {
+                    name: "streaming".into(),
+                    reason: "buffered".into(),
+                })
+            }
+
+            async fn format_stream(
+                &self,
+                input: &mut (dyn AsyncRead + Unpin + Send),
+                output: &mut (dyn AsyncWrite + Unpin + Send),
+                _path: &Path,
+                _config: &ZenithConfig,
+            ) -> Result<()> {
+                let mut buf = [0; 4096];
+                loop {
+                    let n = input.read(&mut buf).await?;
+                    if n == 0 {
+                        return Ok(());
+                    }
+                    output.write_all(&buf[..n].to_ascii_uppercase()).await?;
+                }
+            }
+        }
+
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = AppConfig::default();
+        config.limits.stream_threshold_mb = 0;
+        config.backup.dir = temp_dir.path().join("backups").to_string_lossy().into();
+        let service = |check| {
+            let mut service = test_service(config.clone(), vec![Arc::new(StreamingZenith)]);
+            service.check_mode = check;
+            service
+        };
+
+        let root = temp_dir.path().join("src");
+        fs::create_dir(&root).await.unwrap();
+        let file = root.join("data.big");
+        let original = "abc\n".repeat(50_000);
+        fs::write(&file, &original).await.unwrap();
+
+        let result = service(true).process_file(root.clone(), file.clone()).await;
+        assert!(result.success, "{:?}", result.error);
+        assert!(result.changed);
+        assert_eq!(fs::read_to_string(&file).await.unwrap(), original);
+
+        let service = service(false);
+        let result = service.process_file(root.clone(), file.clone()).await;
+        assert!(result.success, "{:?}", result.error);
+        assert!(result.changed);
+        assert_eq!(result.formatted_size, original.len() as u64);
+        assert_eq!(
+            fs::read_to_string(&file).await.unwrap(),
+            original.to_uppercase()
+        );
+        let backup = temp_dir
+            .path()
+            .join("backups")
+            .join(service.backup_service.get_session_id())
+            .join("data.big");
+        assert_eq!(fs::read_to_string(&backup).await.unwrap(), original);
+
+        // 临时文件不会残留在源目录中
+        let mut entries = fs::read_dir(&root).await.unwrap();
+        let mut names = Vec::new();
+        while let Some(entry) = entries.next_entry().await.unwrap() {
+            names.push(entry.file_name());
+        }
+        assert_eq!(names, vec!["data.big"]);
+    }
+
+    #[tokio::test]
+    async fn test_formatter_limit_caps_concurrent_runs() {
+        let delay = Duration::from_millis(20);
+        let capped = Arc::new(MockZenith::identity("capped", &["cap"]).with_delay(delay));
+        let wide = Arc::new(MockZenith::identity("wide", &["wide"]).with_delay(delay));
+
+        let temp_dir = TempDir::new().unwrap();
+        for i in 0..6 {
+            fs::write(temp_dir.path().join(format!("{}.cap", i)), "x")
+                .await
+                .unwrap();
+            fs::write(temp_dir.path().join(format!("{}.wide", i)), "x")
+                .await
+                .unwrap();
+        }
+
+        let mut config = test_config();
+        config.concurrency.workers = 4;
+        config
+            .concurrency
+            .formatter_limits
+            .insert("capped".into(), 1);
+        let service = test_service(config, vec![capped.clone(), wide.clone()]);
+
+        let results = service
+            .format_paths(vec![temp_dir.path().to_string_lossy().to_string()])
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 12);
+        assert!(results.iter().all(|r| r.success));
+        assert_eq!(capped.peak(), 1);
+        assert!(wide.peak() > 1);
+    }
+
+    #[tokio::test]
+    async fn test_files_waiting_on_a_formatter_limit_leave_workers_free() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut paths = Vec::new();
+        for name in ["a.cap", "b.cap", "c.quick"] {
+            let path = temp_dir.path().join(name);
+            fs::write(&path, "x").await.unwrap();
+            paths.push(path.to_string_lossy().to_string());
+        }
+
+        let mut config = test_config();
+        config.concurrency.workers = 2;
+        config
+            .concurrency
+            .formatter_limits
+            .insert("capped".into(), 1);
+        let service = test_service(
+            config,
+            vec![
+                Arc::new(
+                    MockZenith::identity("capped", &["cap"]).with_delay(Duration::from_millis(300)),
+                ),
+                Arc::new(MockZenith::identity("quick", &["quick"])),
+            ],
+        );
+
+        // b.cap waits for the capped slot without a worker, so c.quick runs
+        // alongside a.cap instead of after it
+        let mut receiver = service.format_paths_streaming(paths).await.unwrap();
+        let first = receiver.recv().await.unwrap();
+        assert!(
+            first.file_path.ends_with("c.quick"),
+            "{:?}",
+            first.file_path
+        );
+    }
+
+    #[tokio::test]
+    async fn test_auto_clean_on_exit_prunes_old_sessions() {
+        let temp_dir = TempDir::new().unwrap();
+        let backup_dir = temp_dir.path().join("backups");
+        let old_session = backup_dir.join("backup_20200101_000000");
+        fs::create_dir_all(&old_session).await.unwrap();
+        let src = temp_dir.path().join("src");
+        fs::create_dir(&src).await.unwrap();
+        fs::write(src.join("notes.txt"), "hello\n").await.unwrap();
+
+        let run = |auto_clean: bool| {
+            let mut config = test_config();
+            config.global.backup_enabled = true;
+            config.backup.dir = backup_dir.to_string_lossy().into_owned();
+            config.backup.auto_clean_on_exit = auto_clean;
+            let service = test_service(config, Vec::new());
+            let paths = vec![src.to_string_lossy().into_owned()];
+            async move { service.format_paths(paths).await.unwrap() }
+        };
+
+        run(false).await;
+        assert!(old_session.exists());
+        run(true).await;
+        assert!(!old_session.exists());
+    }
+
+    #[tokio::test]
+    async fn test_verify_idempotent_flags_unstable_formatter() {
+        let temp_dir = TempDir::new().unwrap();
+        let service = |verify| {
+            // Appends a marker on every pass, so it never converges
+            let growing = MockZenith::new("growing", &["grow"], |content| [content, b"!"].concat());
+            test_service(test_config(), vec![Arc::new(growing)]).with_verify_idempotent(verify)
+        };
+
+        let file = temp_dir.path().join("input.grow");
+        fs::write(&file, "x").await.unwrap();
+        let result = service(false)
+            .process_file(temp_dir.path().to_path_buf(), file.clone())
+            .await;
+        assert!(result.success);
+        assert!(result.warning.is_none());
+
+        let result = service(true)
+            .process_file(temp_dir.path().to_path_buf(), file.clone())
+            .await;
+        assert!(result.success);
+        assert!(result.warning.unwrap().contains("growing"));
+        // 第一遍的结果照常写入
+        assert_eq!(fs::read_to_string(&file).await.unwrap(), "x!!");
+    }
+
+    #[tokio::test]
+    async fn test_extension_overrides_select_formatter() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = test_config();
+        config.overrides.insert("ts".into(), "custom".into());
+        config
+            .overrides
+            .insert("md".into(), DISABLED_OVERRIDE.into());
+        config.overrides.insert("rs".into(), "missing".into());
+        let service = test_service(
+            config,
+            vec![
+                Arc::new(MockZenith::new("custom", &["ts", "md"], |_| {
+                    b"custom".to_vec()
+                })),
+                Arc::new(MockZenith::new("default", &["ts", "md"], |_| {
+                    b"default".to_vec()
+                })),
+            ],
+        );
+        assert_eq!(
+            service.registry.get_by_extension("ts").unwrap().name(),
+            "default"
+        );
+
+        let ts_file = temp_dir.path().join("app.ts");
+        fs::write(&ts_file, "let x = 1;").await.unwrap();
+        let result = service
+            .process_file(temp_dir.path().to_path_buf(), ts_file.clone())
+            .await;
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(fs::read_to_string(&ts_file).await.unwrap(), "custom");
+
+        let md_file = temp_dir.path().join("README.md");
+        fs::write(&md_file, "# Title").await.unwrap();
+        let result = service
+            .process_file(temp_dir.path().to_path_buf(), md_file.clone())
+            .await;
+        assert_eq!(
+            result.error.as_deref(),
+            Some("Skipped: .md disabled by override")
+        );
+        assert_eq!(fs::read_to_string(&md_file).await.unwrap(), "# Title");
+
+        let rs_file = temp_dir.path().join("main.rs");
+        fs::write(&rs_file, "fn main() {}").await.unwrap();
+        let result = service
+            .process_file(temp_dir.path().to_path_buf(), rs_file)
+            .await;
+        assert_eq!(
+            result.error.as_deref(),
+            Some("Override for .rs refers to unknown formatter 'missing'")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fail_on_unsupported_only_for_explicit_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let explicit_file = temp_dir.path().join("explicit.xyz");
+        let nested_dir = temp_dir.path().join("docs");
+        std::fs::create_dir(&nested_dir).unwrap();
+        fs::write(&explicit_file, "data").await.unwrap();
+        fs::write(nested_dir.join("README.xyz"), "data")
+            .await
+            .unwrap();
+
+        let mut config = test_config();
+        config.global.fail_on_unsupported = true;
+        let service = test_service(config, Vec::new());
+
+        let results = service
+            .format_paths(vec![
+                explicit_file.to_string_lossy().to_string(),
+                nested_dir.to_string_lossy().to_string(),
+            ])
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 2);
+
+        let explicit_result = results
+            .iter()
+            .find(|r| r.file_path == explicit_file)
+            .unwrap();
+        assert!(explicit_result
+            .error
+            .as_ref()
+            .unwrap()
+            .starts_with("Unsupported file type"));
+        assert!(explicit_result.is_failure());
+
+        let walked_result = results
+            .iter()
+            .find(|r| r.file_path != explicit_file)
+            .unwrap();
+        assert!(walked_result.error.as_ref().unwrap().starts_with("Skipped"));
+        assert!(!walked_result.is_failure());
+
+        // Strict failures still show up in --report-unsupported
+        let report = crate::config::types::UnsupportedReport::from_results(&results);
+        assert_eq!(report.extensions["xyz"], 2);
+    }
+
+    #[tokio::test]
+    async fn test_unavailable_formatter_skips_files_after_one_probe() {
+        use crate::zeniths::common::tool_installed;
+        use async_trait::async_trait;
+
+        struct MissingToolZenith {
+            probes: AtomicUsize,
+        }
+
+        #[async_trait]
+        impl Zenith for MissingToolZenith {
+            fn name(&self) -> &str {
+                "missing-tool"
+            }
+
+            fn extensions(&self) -> &[&str] {
+                &["mt"]
+            }
+
+            async fn is_available(&self) -> bool {
+                self.probes.fetch_add(1, Ordering::SeqCst);
+                tool_installed("zenith-test-command-that-does-not-exist").await
+            }
+
+            async fn format(
+                &self,
+                _content: &[u8],
+                _path: &Path,
+                _config: &ZenithConfig,
+            ) -> Result<Vec<u8>> {
+                panic!("format must not run when the tool is unavailable");
+            }
+        }
+
+        let temp_dir = TempDir::new().unwrap();
+        for name in ["a.mt", "b.mt", "c.mt"] {
+            fs::write(temp_dir.path().join(name), "data").await.unwrap();
+        }
+
+        let zenith = Arc::new(MissingToolZenith {
+            probes: AtomicUsize::new(0),
+        });
+        let mut config = test_config();
+        config.concurrency.workers = 2;
+        let service = test_service(config, vec![zenith.clone()]);
+
+        let results = service
+            .format_paths(vec![temp_dir.path().to_string_lossy().to_string()])
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 3);
+        for result in &results {
+            assert_eq!(
+                result.error.as_deref(),
+                Some("Skipped: formatter missing-tool unavailable")
+            );
+            assert!(!result.is_failure());
+        }
+        assert_eq!(zenith.probes.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_slow_availability_probe_does_not_block_other_formatters() {
+        use async_trait::async_trait;
+        use tokio::sync::Notify;
+
+        struct ProbeZenith {
+            name: &'static str,
+            gate: Option<Arc<Notify>>,
+        }
+
+        #[async_trait]
+        impl Zenith for ProbeZenith {
+            fn name(&self) -> &str {
+                self.name
+            }
+
+            fn extensions(&self) -> &[&str] {
+                &[]
+            }
+
+            async fn is_available(&self) -> bool {
+                if let Some(gate) = &self.gate {
+                    gate.notified().await;
+                }
+                true
+            }
+
+            async fn format(
+                &self,
+                content: &[u8],
+                _path: &Path,
+                _config: &ZenithConfig,
+            ) -> Result<Vec<u8>> {
+                Ok(content.to_vec())
+            }
+        }
+
+        let (service, _temp_dir) = create_test_service();
+        let gate = Arc::new(Notify::new());
+        let slow: Arc<dyn Zenith> = Arc::new(ProbeZenith {
+            name: "slow",
+            gate: Some(gate.clone()),
+        });
+        let fast: Arc<dyn Zenith> = Arc::new(ProbeZenith {
+            name: "fast",
+            gate: None,
+        });
+
+        let slow_probe = service.zenith_available(&slow);
+        tokio::pin!(slow_probe);
+        tokio::select! {
+            biased;
+            _ = &mut slow_probe => panic!("the slow probe finished before it was released"),
+            _ = tokio::task::yield_now() => {}
+        }
+
+        let fast_available =
+            tokio::time::timeout(Duration::from_secs(5), service.zenith_available(&fast)).await;
+        assert_eq!(fast_available, Ok(true));
+
+        gate.notify_one();
+        assert!(slow_probe.await);
+    }
+
+    #[tokio::test]
+    async fn test_formatter_config_change_invalidates_cache() {
+        let temp_dir = TempDir::new().unwrap();
+        let rustfmt_toml = temp_dir.path().join(".rustfmt.toml");
+        fs::write(&rustfmt_toml, "max_width = 100\n").await.unwrap();
+        let file = temp_dir.path().join("main.rs");
+        fs::write(&file, "fn main() {}\n").await.unwrap();
+
+        let zenith = Arc::new(MockZenith::identity("rust", &["rs"]));
+        let mut config = test_config();
+        config.global.cache_enabled = true;
+        let service = test_service(config, vec![zenith.clone()]);
+
+        service.format_file(file.clone()).await;
+        service.format_file(file.clone()).await;
+        assert_eq!(zenith.runs(), 1);
+
+        fs::write(&rustfmt_toml, "max_width = 80\n").await.unwrap();
+        service.format_file(file.clone()).await;
+        assert_eq!(zenith.runs(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_zenithignore_skips_walked_files_only() {
+        let temp_dir = TempDir::new().unwrap();
+        let kept = temp_dir.path().join("src").join("kept.xyz");
+        let ignored = temp_dir.path().join("generated").join("ignored.xyz");
+        for file in [&kept, &ignored] {
+            fs::create_dir_all(file.parent().unwrap()).await.unwrap();
+            fs::write(file, "data").await.unwrap();
+        }
+        fs::write(temp_dir.path().join(IGNORE_FILE_NAME), "generated/\n")
+            .await
+            .unwrap();
+
+        let (service, _backup_dir) = create_test_service();
+        let walked = service
+            .format_paths(vec![temp_dir.path().to_string_lossy().to_string()])
+            .await
+            .unwrap();
+        let walked: Vec<_> = walked.into_iter().map(|r| r.file_path).collect();
+        assert_eq!(walked, vec![kept]);
+
+        let explicit = service
+            .format_paths(vec![ignored.to_string_lossy().to_string()])
+            .await
+            .unwrap();
+        assert_eq!(explicit.len(), 1);
+        assert_eq!(explicit[0].file_path, ignored);
+    }
+
+    #[tokio::test]
+    async fn test_gitignored_file_is_formatted_when_passed_explicitly() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::create_dir(temp_dir.path().join(".git")).await.unwrap();
+        fs::write(temp_dir.path().join(".gitignore"), "generated.xyz\n")
+            .await
+            .unwrap();
+        let generated = temp_dir.path().join("generated.xyz");
+        fs::write(&generated, "data").await.unwrap();
+
+        let service = test_service(
+            test_config(),
+            vec![Arc::new(MockZenith::uppercase("uppercase", &["xyz"]))],
+        );
+
+        assert!(walked(&service, temp_dir.path()).await.is_empty());
+        assert_eq!(fs::read_to_string(&generated).await.unwrap(), "data");
+
+        let results = service
+            .format_paths(vec![generated.to_string_lossy().to_string()])
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert!(results[0].success && results[0].changed);
+        assert_eq!(fs::read_to_string(&generated).await.unwrap(), "DATA");
+    }
+
+    #[tokio::test]
+    async fn test_overlapping_paths_are_processed_once() {
+        let (service, temp_dir) = create_test_service();
+        let dir = temp_dir.path().join("src");
+        fs::create_dir(&dir).await.unwrap();
+        let a = dir.join("a.txt");
+        let b = dir.join("b.txt");
+        fs::write(&a, "a").await.unwrap();
+        fs::write(&b, "b").await.unwrap();
+
+        let paths = [
+            a.clone(),
+            dir.clone(),
+            dir.join(".").join("a.txt"),
+            b.clone(),
+        ];
+        let results = service
+            .format_paths(
+                paths
+                    .iter()
+                    .map(|p| p.to_string_lossy().into_owned())
+                    .collect(),
+            )
+            .await
+            .unwrap();
+        let files: Vec<_> = results.iter().map(|r| r.file_path.clone()).collect();
+        assert_eq!(files, [a, b]);
+    }
+
+    #[test]
+    fn test_dedup_files_keeps_uncanonicalizable_paths() {
+        let missing = PathBuf::from("/nonexistent/dir/../file.rs");
+        let mut explicit = HashSet::from([PathBuf::from("other.rs")]);
+        let files = dedup_files(
+            vec![missing.clone(), missing.clone(), PathBuf::from("other.rs")],
+            &mut explicit,
+        );
+        assert_eq!(files, [missing, PathBuf::from("other.rs")]);
+    }
+
+    #[tokio::test]
+    async fn test_format_bytes_selects_formatter_by_extension() {
+        let (service, temp_dir) = create_test_service();
+        service
+            .registry
+            .register(Arc::new(MockZenith::uppercase("uppercase", &["xyz"])));
+        let filename = temp_dir.path().join("notes.xyz");
+        let filename = filename.to_str().unwrap();
+
+        let (formatted, changed) = service.format_bytes(filename, b"data").await.unwrap();
+        assert_eq!(formatted, b"DATA");
+        assert!(changed);
+        let (formatted, changed) = service.format_bytes(filename, b"DATA").await.unwrap();
+        assert_eq!(formatted, b"DATA");
+        assert!(!changed);
+        assert!(!Path::new(filename).exists());
+
+        let err = service
+            .format_bytes("notes.unknown", b"data")
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ZenithError::UnsupportedExtension(_)));
+    }
+
+    #[tokio::test]
+    async fn test_archive_members_honor_the_config_sandbox() {
+        use crate::core::traits::Zenith;
+        use async_trait::async_trait;
+        use std::sync::atomic::AtomicBool;
+
+        struct SandboxProbe(Arc<AtomicBool>);
+
+        #[async_trait]
+        impl Zenith for SandboxProbe {
+            fn name(&self) -> &str {
+                "probe"
+            }
+
+            fn extensions(&self) -> &[&str] {
+                &["xyz"]
+            }
+
+            async fn format(
+                &self,
+                content: &[u8],
+                _path: &Path,
+                config: &ZenithConfig,
+            ) -> Result<Vec<u8>> {
+                self.0.store(config.sandbox_config, Ordering::SeqCst);
+                Ok(content.to_vec())
+            }
+        }
+
+        let (mut service, temp_dir) = create_test_service();
+        service.config.security.sandbox_external_config = true;
+        let sandboxed = Arc::new(AtomicBool::new(false));
+        service
+            .registry
+            .register(Arc::new(SandboxProbe(sandboxed.clone())));
+
+        let archive = temp_dir.path().join("src.tar");
+        let member = temp_dir.path().join("a.xyz");
+        std::fs::write(&member, "data").unwrap();
+        let mut builder = tar::Builder::new(std::fs::File::create(&archive).unwrap());
+        builder.append_path_with_name(&member, "a.xyz").unwrap();
+        builder.finish().unwrap();
+
+        let results = service.format_archive(&archive).await.unwrap();
+        assert!(results[0].success);
+        assert!(sandboxed.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn test_binary_content_is_skipped_before_formatting() {
+        let zenith = Arc::new(MockZenith::uppercase("uppercase", &["json"]));
+        let temp_dir = TempDir::new().unwrap();
+        let binary: &[u8] = b"\x89png\0\0\x01data";
+        let file = temp_dir.path().join("image.json");
+        fs::write(&file, binary).await.unwrap();
+
+        for stream_threshold_mb in [4, 0] {
+            let mut config = test_config();
+            config.limits.stream_threshold_mb = stream_threshold_mb;
+            let service = test_service(config, vec![zenith.clone()]);
+
+            let result = service
+                .process_file(temp_dir.path().to_path_buf(), file.clone())
+                .await;
+            assert!(!result.success);
+            assert_eq!(result.error.as_deref(), Some("Skipped: binary content"));
+            assert_eq!(fs::read(&file).await.unwrap(), binary);
+        }
+        assert_eq!(zenith.runs(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_bom_round_trips_and_invalid_utf8_is_skipped() {
+        let zenith = Arc::new(MockZenith::uppercase("uppercase", &["json"]));
+        let service = |preserve_bom: bool| {
+            let mut config = test_config();
+            config.global.preserve_bom = preserve_bom;
+            test_service(config, vec![zenith.clone()])
+        };
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().to_path_buf();
+
+        let with_bom = temp_dir.path().join("bom.json");
+        fs::write(&with_bom, b"\xEF\xBB\xBF{\"a\": 1}")
+            .await
+            .unwrap();
+        let result = service(true)
+            .process_file(root.clone(), with_bom.clone())
+            .await;
+        assert!(result.success && result.changed);
+        assert!(!zenith.last_input().starts_with(UTF8_BOM));
+        assert_eq!(
+            fs::read(&with_bom).await.unwrap(),
+            b"\xEF\xBB\xBF{\"A\": 1}"
+        );
+        // Unchanged apart from the BOM, which is only a change when dropped
+        let result = service(true)
+            .process_file(root.clone(), with_bom.clone())
+            .await;
+        assert!(result.success && !result.changed);
+        let result = service(false)
+            .process_file(root.clone(), with_bom.clone())
+            .await;
+        assert!(result.success && result.changed);
+        assert_eq!(fs::read(&with_bom).await.unwrap(), b"{\"A\": 1}");
+
+        let latin1: &[u8] = b"{\"caf\xE9\": 1}";
+        let file = temp_dir.path().join("latin1.json");
+        fs::write(&file, latin1).await.unwrap();
+        let result = service(true).process_file(root, file.clone()).await;
+        assert!(!result.success);
+        assert_eq!(result.error.as_deref(), Some("Skipped: invalid encoding"));
+        assert_eq!(fs::read(&file).await.unwrap(), latin1);
+    }
+
+    #[tokio::test]
+    async fn test_line_endings_are_preserved_or_converted() {
+        let service = |line_ending: LineEnding| {
+            // Uppercases and, like many formatters, always writes LF
+            let lf = MockZenith::new("lf", &["json"], |content| {
+                let text = String::from_utf8_lossy(content).replace("\r\n", "\n");
+                text.to_ascii_uppercase().into_bytes()
+            });
+            let mut config = test_config();
+            config.global.line_ending = line_ending;
+            test_service(config, vec![Arc::new(lf)])
+        };
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().to_path_buf();
+        let file = temp_dir.path().join("windows.json");
+
+        fs::write(&file, "{\r\n\"a\": 1\r\n}\r\n").await.unwrap();
+        let result = service(LineEnding::Preserve)
+            .process_file(root.clone(), file.clone())
+            .await;
+        assert!(result.success && result.changed);
+        assert_eq!(
+            fs::read_to_string(&file).await.unwrap(),
+            "{\r\n\"A\": 1\r\n}\r\n"
+        );
+
+        let result = service(LineEnding::Lf)
+            .process_file(root.clone(), file.clone())
+            .await;
+        assert!(result.success && result.changed);
+        assert_eq!(fs::read_to_string(&file).await.unwrap(), "{\n\"A\": 1\n}\n");
+
+        let result = service(LineEnding::Crlf)
+            .process_file(root, file.clone())
+            .await;
+        assert!(result.success && result.changed);
+        assert_eq!(
+            fs::read_to_string(&file).await.unwrap(),
+            "{\r\n\"A\": 1\r\n}\r\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_final_newline_modes() {
+        let service = |final_newline: FinalNewline| {
+            let mut config = test_config();
+            config.global.final_newline = final_newline;
+            // Leave the final newline to `final_newline` alone
+            config.normalize.insert_final_newline = false;
+            test_service(config, Vec::new()).with_whitespace_only(true)
+        };
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().to_path_buf();
+        let file = temp_dir.path().join("notes.txt");
+        let empty = temp_dir.path().join("empty.txt");
+        fs::write(&empty, "").await.unwrap();
+
+        for (mode, input, expected) in [
+            (FinalNewline::Ensure, "a", "a\n"),
+            (FinalNewline::Ensure, "a\r\nb", "a\r\nb\r\n"),
+            (FinalNewline::Remove, "a\n\n", "a"),
+            (FinalNewline::Preserve, "a", "a"),
+            (FinalNewline::Preserve, "a\n\n", "a\n\n"),
+        ] {
+            fs::write(&file, input).await.unwrap();
+            let service = service(mode);
+            let result = service.process_file(root.clone(), file.clone()).await;
+            assert!(result.success);
+            assert_eq!(result.changed, input != expected);
+            assert_eq!(fs::read_to_string(&file).await.unwrap(), expected);
+
+            let result = service.process_file(root.clone(), empty.clone()).await;
+            assert!(result.success && !result.changed);
+            assert_eq!(fs::read_to_string(&empty).await.unwrap(), "");
+        }
+    }
+
+    #[test]
+    fn test_mirror_path_keeps_relative_structure() {
+        let root = Path::new("/work/project");
+        let out_dir = Path::new("/tmp/out");
+        assert_eq!(
+            mirror_path(out_dir, root, Path::new("/work/project/src/main.rs")),
+            Path::new("/tmp/out/src/main.rs")
+        );
+        assert_eq!(
+            mirror_path(out_dir, root, Path::new("/elsewhere/lib.rs")),
+            Path::new("/tmp/out/elsewhere/lib.rs")
+        );
+    }
+
+    async fn walked(service: &ZenithService, dir: &Path) -> Vec<PathBuf> {
+        let results = service
+            .format_paths(vec![dir.to_string_lossy().to_string()])
+            .await
+            .unwrap();
+        let mut files: Vec<_> = results.into_iter().map(|r| r.file_path).collect();
+        files.sort();
+        files
+    }
+
+    #[tokio::test]
+    async fn test_include_hidden_walks_dotfiles() {
+        let temp_dir = TempDir::new().unwrap();
+        let visible = temp_dir.path().join("visible.rs");
+        let hidden = temp_dir.path().join(".foo.rs");
+        let git_file = temp_dir.path().join(".git").join("hook.rs");
+        fs::create_dir_all(git_file.parent().unwrap())
+            .await
+            .unwrap();
+        for file in [&visible, &hidden, &git_file] {
+            fs::write(file, "fn main() {}\n").await.unwrap();
+        }
+
+        let (mut service, _backup_dir) = create_test_service();
+        assert_eq!(
+            walked(&service, temp_dir.path()).await,
+            vec![visible.clone()]
+        );
+
+        service.config.global.include_hidden = true;
+        assert_eq!(
+            walked(&service, temp_dir.path()).await,
+            vec![hidden, visible]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_max_depth_limits_walk() {
+        let temp_dir = TempDir::new().unwrap();
+        let top = temp_dir.path().join("top.rs");
+        let mid = temp_dir.path().join("a").join("mid.rs");
+        let deep = temp_dir.path().join("a").join("b").join("deep.rs");
+        fs::create_dir_all(deep.parent().unwrap()).await.unwrap();
+        for file in [&top, &mid, &deep] {
+            fs::write(file, "fn main() {}\n").await.unwrap();
+        }
+
+        let (mut service, _backup_dir) = create_test_service();
+        assert_eq!(walked(&service, temp_dir.path()).await.len(), 3);
+
+        service.config.global.max_depth = Some(2);
+        assert_eq!(
+            walked(&service, temp_dir.path()).await,
+            vec![mid.clone(), top.clone()]
+        );
+
+        service.config.global.max_depth = Some(1);
+        assert_eq!(walked(&service, temp_dir.path()).await, vec![top]);
+
+        // 显式指定的文件不受深度限制
+        let explicit = service
+            .format_paths(vec![deep.to_string_lossy().to_string()])
+            .await
+            .unwrap();
+        assert_eq!(explicit[0].file_path, deep);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_symlinks_outside_root_are_skipped_unless_followed() {
+        let temp_dir = TempDir::new().unwrap();
+        let tree = temp_dir.path().join("tree");
+        let outside = temp_dir.path().join("outside");
+        fs::create_dir_all(&tree).await.unwrap();
+        fs::create_dir_all(&outside).await.unwrap();
+        let real = tree.join("real.rs");
+        let secret = outside.join("secret.rs");
+        let link = tree.join("link.rs");
+        fs::write(&real, "fn main() {}\n").await.unwrap();
+        fs::write(&secret, "fn secret() {}\n").await.unwrap();
+        std::os::unix::fs::symlink(&secret, &link).unwrap();
+
+        let (mut service, _backup_dir) = create_test_service();
+        assert_eq!(walked(&service, &tree).await, vec![real.clone()]);
+        let result = service.process_file(tree.clone(), link.clone()).await;
+        assert!(result.error.unwrap().starts_with("Skipped: symlink"));
+
+        service.config.global.follow_symlinks = true;
+        assert_eq!(walked(&service, &tree).await, vec![link.clone(), real]);
+        let result = service.process_file(tree.clone(), link).await;
+        assert!(!result.error.unwrap().contains("symlink"));
+    }
+
+    #[tokio::test]
+    async fn test_format_paths_reports_progress() {
+        use crate::services::progress::ProgressReporter;
+
+        #[derive(Default)]
+        struct Counter {
+            total: AtomicUsize,
+            done: AtomicUsize,
+            finished: AtomicUsize,
+        }
+
+        impl ProgressReporter for Counter {
+            fn start(&self, total: usize) {
+                self.total.store(total, Ordering::SeqCst);
+            }
+
+            fn file_done(&self, _result: &FormatResult) {
+                self.done.fetch_add(1, Ordering::SeqCst);
+            }
+
+            fn finish(&self) {
+                self.finished.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let temp_dir = TempDir::new().unwrap();
+        for name in ["a.xyz", "b.xyz", "c.xyz"] {
+            fs::write(temp_dir.path().join(name), "data").await.unwrap();
+        }
+
+        let counter = Arc::new(Counter::default());
+        let service = test_service(test_config(), Vec::new()).with_progress(counter.clone());
+
+        let results = service
+            .format_paths(vec![temp_dir.path().to_string_lossy().to_string()])
+            .await
+            .unwrap();
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(counter.total.load(Ordering::SeqCst), 3);
+        assert_eq!(counter.done.load(Ordering::SeqCst), 3);
+        assert_eq!(counter.finished.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_streaming_results_arrive_before_batch_finishes() {
+        let temp_dir = TempDir::new().unwrap();
+        let slow = temp_dir.path().join("a.slow");
+        let fast = temp_dir.path().join("b.fast");
+        fs::write(&slow, "data").await.unwrap();
+        fs::write(&fast, "data").await.unwrap();
+
+        let mut config = test_config();
+        config.concurrency.workers = 2;
+        let service = test_service(
+            config,
+            vec![
+                Arc::new(
+                    MockZenith::identity("slow", &["slow"]).with_delay(Duration::from_secs(2)),
+                ),
+                Arc::new(MockZenith::identity("fast", &["fast"])),
+            ],
+        );
+
+        let start = Instant::now();
+        let mut receiver = service
+            .format_paths_streaming(vec![
+                slow.to_string_lossy().to_string(),
+                fast.to_string_lossy().to_string(),
+            ])
+            .await
+            .unwrap();
+
+        let first = receiver.recv().await.unwrap();
+        assert_eq!(first.file_path, fast);
+        assert!(start.elapsed() < Duration::from_secs(1));
+
+        let second = receiver.recv().await.unwrap();
+        assert_eq!(second.file_path, slow);
+        assert!(receiver.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_time_budget_skips_files_once_elapsed() {
+        let temp_dir = TempDir::new().unwrap();
+        let files: Vec<PathBuf> = (0..6)
+            .map(|i| temp_dir.path().join(format!("file{}.slow", i)))
+            .collect();
+        for file in &files {
+            fs::write(file, "data").await.unwrap();
+        }
+
+        let mut config = test_config();
+        config.concurrency.workers = 1;
+        let slow = MockZenith::uppercase("slow", &["slow"]).with_delay(Duration::from_millis(200));
+        let service = test_service(config, vec![Arc::new(slow)])
+            .with_time_budget(Some(Duration::from_millis(300)));
+
+        let results = service
+            .format_paths(
+                files
+                    .iter()
+                    .map(|f| f.to_string_lossy().into_owned())
+                    .collect(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(results.len(), files.len());
+        let formatted: Vec<_> = results.iter().filter(|r| r.changed).collect();
+        let skipped: Vec<_> = results
+            .iter()
+            .filter(|r| r.error_kind == Some(FormatError::TimeBudgetExceeded))
+            .collect();
+        assert!(!formatted.is_empty());
+        assert!(!skipped.is_empty());
+        assert_eq!(formatted.len() + skipped.len(), files.len());
+        assert!(results.iter().all(|r| !r.is_failure()));
+        for result in &skipped {
+            assert_eq!(fs::read_to_string(&result.file_path).await.unwrap(), "data");
+        }
+        for result in &formatted {
+            assert_eq!(fs::read_to_string(&result.file_path).await.unwrap(), "DATA");
+        }
+    }
 }
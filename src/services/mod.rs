@@ -5,4 +5,5 @@
 
 pub mod batch;
 pub mod formatter;
+pub mod progress;
 pub mod watch;
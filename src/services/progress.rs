@@ -0,0 +1,60 @@
+// Copyright (c) 2025 Kirky.X
+//
+// Licensed under the MIT License
+// See LICENSE file in the project root for full license information.
+
+//! Progress reporting for batch formatting runs.
+
+use crate::config::types::FormatResult;
+use indicatif::{ProgressBar, ProgressStyle};
+
+/// Receives updates while `ZenithService::format_paths` works through a batch
+pub trait ProgressReporter: Send + Sync {
+    /// Called once the files to process are known
+    fn start(&self, total: usize);
+
+    /// Called as soon as a file is done, in completion order
+    fn file_done(&self, result: &FormatResult);
+
+    /// Called after the last file
+    fn finish(&self);
+}
+
+/// Progress bar drawn on stderr
+pub struct ProgressBarReporter {
+    bar: ProgressBar,
+}
+
+impl ProgressBarReporter {
+    pub fn new() -> Self {
+        let style = ProgressStyle::with_template(
+            "{spinner} [{elapsed_precise}] [{bar:40}] {pos}/{len} {wide_msg}",
+        )
+        .unwrap_or_else(|_| ProgressStyle::default_bar())
+        .progress_chars("=> ");
+        Self {
+            bar: ProgressBar::new(0).with_style(style),
+        }
+    }
+}
+
+impl Default for ProgressBarReporter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProgressReporter for ProgressBarReporter {
+    fn start(&self, total: usize) {
+        self.bar.set_length(total as u64);
+    }
+
+    fn file_done(&self, result: &FormatResult) {
+        self.bar.set_message(result.file_path.display().to_string());
+        self.bar.inc(1);
+    }
+
+    fn finish(&self) {
+        self.bar.finish_and_clear();
+    }
+}
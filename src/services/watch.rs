@@ -7,14 +7,18 @@
 //! Uses the `notify` crate to monitor file system changes.
 
 use crate::config::types::FormatResult;
+use crate::plugins::loader::PluginLoader;
 use crate::services::formatter::ZenithService;
+use crate::zeniths::registry::ZenithRegistry;
 use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::HashMap;
 use std::future::Future;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::mpsc;
 use tokio::task::JoinHandle;
+use tokio::time::Instant;
 
 /// Events from the file watcher
 #[derive(Debug, Clone, PartialEq)]
@@ -25,6 +29,8 @@ pub enum WatchEvent {
     Modified(PathBuf),
     /// File was deleted
     Deleted(PathBuf),
+    /// File was renamed from the first path to the second
+    Renamed(PathBuf, PathBuf),
 }
 
 /// Configuration for the file watcher
@@ -51,6 +57,7 @@ impl Default for WatchConfig {
 /// File watcher service that monitors file changes and triggers formatting
 pub struct FileWatcher {
     config: WatchConfig,
+    service: Arc<ZenithService>,
     watcher: Option<RecommendedWatcher>,
     event_receiver: mpsc::Receiver<WatchEvent>,
     _watcher_task: JoinHandle<()>,
@@ -58,28 +65,18 @@ pub struct FileWatcher {
 
 impl FileWatcher {
     /// Create a new file watcher with the given configuration
-    pub fn new(config: WatchConfig, _service: Arc<ZenithService>) -> Result<Self, notify::Error> {
+    pub fn new(config: WatchConfig, service: Arc<ZenithService>) -> Result<Self, notify::Error> {
         let (event_sender, event_receiver) = mpsc::channel(100);
 
-        // Create a debounced watcher
+        // The notify callback runs on its own thread, so events are sent with
+        // `blocking_send` rather than through the tokio runtime.
         let mut watcher = RecommendedWatcher::new(
             move |result: notify::Result<notify::Event>| {
                 if let Ok(event) = result {
-                    let event_type = match event.kind {
-                        notify::EventKind::Create(_) => WatchEvent::Created,
-                        notify::EventKind::Modify(_) => WatchEvent::Modified,
-                        notify::EventKind::Remove(_) => WatchEvent::Deleted,
-                        _ => WatchEvent::Modified,
-                    };
-
-                    for event_path in event.paths {
-                        let sender = event_sender.clone();
-                        tokio::task::spawn_blocking(move || {
-                            let event = event_type(event_path);
-                            if let Err(e) = sender.blocking_send(event) {
-                                tracing::warn!("Failed to send watch event: {}", e);
-                            }
-                        });
+                    for event in translate_event(event) {
+                        if let Err(e) = event_sender.blocking_send(event) {
+                            tracing::warn!("Failed to send watch event: {}", e);
+                        }
                     }
                 }
             },
@@ -106,29 +103,97 @@ impl FileWatcher {
 
         Ok(Self {
             config,
+            service,
             watcher: Some(watcher),
             event_receiver,
             _watcher_task: watcher_task,
         })
     }
 
-    /// Start watching files and processing events
-    pub async fn start<F, Fut>(&mut self, mut process_fn: F)
+    /// Reload `loader`'s plugins into `registry` whenever a file directly in
+    /// its plugins directory is created, changed or removed.
+    ///
+    /// Those events are debounced like file changes and never reach the
+    /// processing callback; every other event passes through untouched.
+    /// Does nothing when the loader has no existing plugins directory.
+    pub fn with_plugin_reload(
+        mut self,
+        mut loader: PluginLoader,
+        registry: Arc<ZenithRegistry>,
+    ) -> notify::Result<Self> {
+        let Some(plugins_dir) = loader.plugins_dir().and_then(|dir| dir.canonicalize().ok()) else {
+            return Ok(self);
+        };
+        if let Some(ref mut watcher) = self.watcher {
+            watcher.watch(&plugins_dir, RecursiveMode::NonRecursive)?;
+        }
+
+        let debounce = self.config.debounce_duration;
+        let (sender, receiver) = mpsc::channel(100);
+        let mut events = std::mem::replace(&mut self.event_receiver, receiver);
+        self._watcher_task = tokio::spawn(async move {
+            let mut reload_at: Option<Instant> = None;
+            loop {
+                tokio::select! {
+                    event = events.recv() => match event {
+                        Some(event) if is_plugin_event(&event, &plugins_dir) => {
+                            reload_at = Some(Instant::now() + debounce);
+                        }
+                        Some(event) => {
+                            if sender.send(event).await.is_err() {
+                                break;
+                            }
+                        }
+                        None => break,
+                    },
+                    _ = tokio::time::sleep_until(reload_at.unwrap_or_else(Instant::now)),
+                        if reload_at.is_some() =>
+                    {
+                        reload_at = None;
+                        if let Err(e) = loader.reload(&registry).await {
+                            tracing::warn!("Failed to reload plugins: {}", e);
+                        }
+                    }
+                }
+            }
+        });
+        Ok(self)
+    }
+
+    /// Start watching files and processing events.
+    ///
+    /// Changes are debounced per path: `process_fn` is only invoked once
+    /// `debounce_duration` has elapsed without a newer event for that path.
+    pub async fn start<F, Fut>(&mut self, process_fn: F)
     where
         F: FnMut(PathBuf) -> Fut + Send + 'static,
         Fut: Future<Output = FormatResult> + Send + 'static,
     {
-        while let Some(event) = self.event_receiver.recv().await {
-            match event {
-                WatchEvent::Modified(path) | WatchEvent::Created(path) => {
-                    tracing::info!("File changed: {:?}", path);
-                    let _ = process_fn(path).await;
-                }
-                WatchEvent::Deleted(path) => {
-                    tracing::info!("File deleted: {:?}", path);
-                    // Handle deletion if needed
-                }
-            }
+        self.start_until(std::future::pending(), process_fn).await;
+    }
+
+    /// Like [`start`](Self::start), but stop once `shutdown` resolves.
+    ///
+    /// A file that is being formatted when the signal arrives is finished
+    /// first; changes still waiting out the debounce are dropped. The hash
+    /// cache is flushed to disk before returning.
+    pub async fn start_until<S, F, Fut>(&mut self, shutdown: S, process_fn: F)
+    where
+        S: Future<Output = ()>,
+        F: FnMut(PathBuf) -> Fut + Send + 'static,
+        Fut: Future<Output = FormatResult> + Send + 'static,
+    {
+        debounce_events(
+            &mut self.event_receiver,
+            self.config.debounce_duration,
+            Some(self.service.clone()),
+            shutdown,
+            process_fn,
+        )
+        .await;
+
+        if let Err(e) = self.service.save_cache().await {
+            tracing::warn!("Failed to save hash cache: {}", e);
         }
     }
 
@@ -163,6 +228,125 @@ impl FileWatcher {
     }
 }
 
+/// Whether `event` concerns a file directly inside `plugins_dir`, which must
+/// be canonical. Parents are compared so that removed files still match.
+fn is_plugin_event(event: &WatchEvent, plugins_dir: &Path) -> bool {
+    let in_dir = |path: &PathBuf| {
+        path.parent()
+            .and_then(|parent| parent.canonicalize().ok())
+            .is_some_and(|parent| parent == plugins_dir)
+    };
+    match event {
+        WatchEvent::Created(path) | WatchEvent::Modified(path) | WatchEvent::Deleted(path) => {
+            in_dir(path)
+        }
+        WatchEvent::Renamed(from, to) => in_dir(from) || in_dir(to),
+    }
+}
+
+/// Map a raw notify event to watch events.
+fn translate_event(event: notify::Event) -> Vec<WatchEvent> {
+    use notify::event::{ModifyKind, RenameMode};
+    use notify::EventKind;
+
+    match event.kind {
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) if event.paths.len() == 2 => {
+            let mut paths = event.paths.into_iter();
+            match (paths.next(), paths.next()) {
+                (Some(from), Some(to)) => vec![WatchEvent::Renamed(from, to)],
+                _ => Vec::new(),
+            }
+        }
+        EventKind::Modify(ModifyKind::Name(RenameMode::From)) | EventKind::Remove(_) => {
+            event.paths.into_iter().map(WatchEvent::Deleted).collect()
+        }
+        EventKind::Modify(ModifyKind::Name(RenameMode::To)) | EventKind::Create(_) => {
+            event.paths.into_iter().map(WatchEvent::Created).collect()
+        }
+        _ => event.paths.into_iter().map(WatchEvent::Modified).collect(),
+    }
+}
+
+/// Drain `receiver`, coalescing rapid events for the same path.
+///
+/// Each path is tracked with the instant of its most recent event and handed
+/// to `process_fn` once it has been quiet for `debounce`. Paths still pending
+/// when the channel closes are processed immediately, while those pending
+/// when `shutdown` resolves are dropped. Deletions and renames are applied to
+/// the service's hash cache when a service is given.
+async fn debounce_events<S, F, Fut>(
+    receiver: &mut mpsc::Receiver<WatchEvent>,
+    debounce: Duration,
+    service: Option<Arc<ZenithService>>,
+    shutdown: S,
+    mut process_fn: F,
+) where
+    S: Future<Output = ()>,
+    F: FnMut(PathBuf) -> Fut,
+    Fut: Future<Output = FormatResult>,
+{
+    let mut pending: HashMap<PathBuf, Instant> = HashMap::new();
+    tokio::pin!(shutdown);
+
+    loop {
+        let next_deadline = pending.values().min().map(|seen| *seen + debounce);
+
+        tokio::select! {
+            event = receiver.recv() => match event {
+                Some(WatchEvent::Modified(path)) | Some(WatchEvent::Created(path)) => {
+                    pending.insert(path, Instant::now());
+                }
+                Some(WatchEvent::Deleted(path)) => {
+                    tracing::info!("File deleted: {:?}", path);
+                    pending.remove(&path);
+                    if let Some(service) = &service {
+                        if let Err(e) = service.evict_cached(&path).await {
+                            tracing::warn!("Failed to evict cache entry for {:?}: {}", path, e);
+                        }
+                    }
+                }
+                Some(WatchEvent::Renamed(from, to)) => {
+                    tracing::info!("File renamed: {:?} -> {:?}", from, to);
+                    pending.remove(&from);
+                    if let Some(service) = &service {
+                        if let Err(e) = service.rename_cached(&from, &to).await {
+                            tracing::warn!("Failed to move cache entry for {:?}: {}", from, e);
+                        }
+                    }
+                    // Editors often save by renaming a temp file over the target
+                    pending.insert(to, Instant::now());
+                }
+                None => break,
+            },
+            _ = &mut shutdown => {
+                tracing::info!("Stopping watcher, {} pending change(s) dropped", pending.len());
+                return;
+            }
+            _ = tokio::time::sleep_until(next_deadline.unwrap_or_else(Instant::now)),
+                if next_deadline.is_some() =>
+            {
+                let now = Instant::now();
+                let ready: Vec<PathBuf> = pending
+                    .iter()
+                    .filter(|(_, seen)| now.duration_since(**seen) >= debounce)
+                    .map(|(path, _)| path.clone())
+                    .collect();
+
+                for path in ready {
+                    pending.remove(&path);
+                    tracing::info!("File changed: {:?}", path);
+                    let _ = process_fn(path).await;
+                }
+            }
+        }
+    }
+
+    for path in pending.into_keys() {
+        tracing::info!("File changed: {:?}", path);
+        let _ = process_fn(path).await;
+    }
+}
+
 #[allow(dead_code)]
 /// Builder for creating FileWatcher with fluent API
 pub struct FileWatcherBuilder {
@@ -334,4 +518,203 @@ mod tests {
         assert!(!builder.config.recursive);
         assert_eq!(builder.config.debounce_duration, Duration::from_millis(50));
     }
+
+    #[tokio::test]
+    async fn test_debounce_coalesces_rapid_events() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let (sender, mut receiver) = mpsc::channel(10);
+        let path = PathBuf::from("rapid.rs");
+
+        sender
+            .send(WatchEvent::Created(path.clone()))
+            .await
+            .unwrap();
+        sender
+            .send(WatchEvent::Modified(path.clone()))
+            .await
+            .unwrap();
+        sender
+            .send(WatchEvent::Modified(path.clone()))
+            .await
+            .unwrap();
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let calls_clone = calls.clone();
+        let handle = tokio::spawn(async move {
+            debounce_events(
+                &mut receiver,
+                Duration::from_millis(50),
+                None,
+                std::future::pending(),
+                move |path| {
+                    calls_clone.fetch_add(1, Ordering::SeqCst);
+                    async move {
+                        FormatResult {
+                            file_path: path,
+                            success: true,
+                            ..Default::default()
+                        }
+                    }
+                },
+            )
+            .await;
+        });
+
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        drop(sender);
+        handle.await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_is_plugin_event() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let plugins_dir = temp_dir.path().join("plugins");
+        std::fs::create_dir(&plugins_dir).unwrap();
+        let canonical = plugins_dir.canonicalize().unwrap();
+
+        let removed = WatchEvent::Deleted(plugins_dir.join("gone.toml"));
+        assert!(is_plugin_event(&removed, &canonical));
+        let moved_in = WatchEvent::Renamed(
+            temp_dir.path().join("draft.json"),
+            plugins_dir.join("new.json"),
+        );
+        assert!(is_plugin_event(&moved_in, &canonical));
+        let elsewhere = WatchEvent::Modified(temp_dir.path().join("main.rs"));
+        assert!(!is_plugin_event(&elsewhere, &canonical));
+    }
+
+    fn create_test_service() -> (Arc<ZenithService>, Arc<crate::storage::cache::HashCache>) {
+        service_with_cache(crate::storage::cache::HashCache::new())
+    }
+
+    fn service_with_cache(
+        hash_cache: crate::storage::cache::HashCache,
+    ) -> (Arc<ZenithService>, Arc<crate::storage::cache::HashCache>) {
+        use crate::config::types::AppConfig;
+        use crate::storage::backup::BackupService;
+        use crate::zeniths::registry::ZenithRegistry;
+
+        let config = AppConfig::default();
+        let backup_service = Arc::new(BackupService::new(config.backup.clone(), None));
+        let hash_cache = Arc::new(hash_cache);
+        let service = ZenithService::new(
+            config,
+            Arc::new(ZenithRegistry::new()),
+            backup_service,
+            hash_cache.clone(),
+            false,
+        );
+        (Arc::new(service), hash_cache)
+    }
+
+    #[tokio::test]
+    async fn test_delete_and_rename_update_cache() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let deleted = temp_dir.path().join("deleted.rs");
+        let renamed_from = temp_dir.path().join("old.rs");
+        let renamed_to = temp_dir.path().join("new.rs");
+        std::fs::write(&deleted, "fn a() {}").unwrap();
+        std::fs::write(&renamed_from, "fn b() {}").unwrap();
+
+        let (service, hash_cache) = create_test_service();
+        for path in [&deleted, &renamed_from] {
+            let state = hash_cache.compute_file_state(path).await.unwrap();
+            hash_cache.update(path.clone(), state).await.unwrap();
+        }
+
+        let (sender, mut receiver) = mpsc::channel(10);
+        sender
+            .send(WatchEvent::Deleted(deleted.clone()))
+            .await
+            .unwrap();
+        sender
+            .send(WatchEvent::Renamed(
+                renamed_from.clone(),
+                renamed_to.clone(),
+            ))
+            .await
+            .unwrap();
+        drop(sender);
+
+        let processed = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let processed_clone = processed.clone();
+        debounce_events(
+            &mut receiver,
+            Duration::from_millis(10),
+            Some(service),
+            std::future::pending(),
+            move |path| {
+                processed_clone.lock().unwrap().push(path.clone());
+                async move {
+                    FormatResult {
+                        file_path: path,
+                        ..Default::default()
+                    }
+                }
+            },
+        )
+        .await;
+
+        assert!(!hash_cache.is_cached(&deleted).await);
+        assert!(!hash_cache.is_cached(&renamed_from).await);
+        assert!(hash_cache.is_cached(&renamed_to).await);
+        assert_eq!(*processed.lock().unwrap(), vec![renamed_to]);
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_stops_watcher_and_flushes_cache() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let cache_dir = temp_dir.path().join("cache");
+        let file = temp_dir.path().join("main.rs");
+        std::fs::write(&file, "fn main() {}").unwrap();
+
+        let (service, hash_cache) = service_with_cache(
+            crate::storage::cache::HashCache::with_cache_dir(cache_dir.clone()),
+        );
+        let state = hash_cache.compute_file_state(&file).await.unwrap();
+        hash_cache.update(file.clone(), state).await.unwrap();
+
+        let config = WatchConfig {
+            paths: vec![temp_dir.path().to_path_buf()],
+            ..Default::default()
+        };
+        let mut watcher = FileWatcher::new(config, service).unwrap();
+        tokio::time::timeout(
+            Duration::from_secs(5),
+            watcher.start_until(async {}, |path| async move {
+                FormatResult {
+                    file_path: path,
+                    ..Default::default()
+                }
+            }),
+        )
+        .await
+        .expect("watcher should stop on shutdown");
+
+        let saved = std::fs::read_to_string(cache_dir.join("file_cache.json")).unwrap();
+        assert!(saved.contains("main.rs"));
+    }
+
+    #[test]
+    fn test_translate_rename_event() {
+        use notify::event::{ModifyKind, RenameMode};
+
+        let event = notify::Event::new(notify::EventKind::Modify(ModifyKind::Name(
+            RenameMode::Both,
+        )))
+        .add_path(PathBuf::from("old.rs"))
+        .add_path(PathBuf::from("new.rs"));
+
+        assert_eq!(
+            translate_event(event),
+            vec![WatchEvent::Renamed(
+                PathBuf::from("old.rs"),
+                PathBuf::from("new.rs")
+            )]
+        );
+    }
 }
@@ -5,20 +5,66 @@
 
 use crate::config::types::BackupConfig;
 use crate::error::{Result, ZenithError};
-use chrono::Utc;
+use chrono::{NaiveDateTime, Utc};
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
-use std::time::SystemTime;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime};
 use tokio::fs;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+/// 去重模式下存放文件内容的目录，位于备份目录下
+const OBJECTS_DIR: &str = "objects";
+
+/// 新写入或刚被复用的对象在此时长内不会被清理：对象先于会话中的哈希文件写入，
+/// 其他进程的备份可能还没来得及引用它
+const OBJECT_GRACE_PERIOD: Duration = Duration::from_secs(3600);
 
 pub struct BackupService {
     config: BackupConfig,
     session_id: String,
+    object_grace: Duration,
 }
 
 impl BackupService {
-    pub fn new(config: BackupConfig) -> Self {
-        let session_id = format!("backup_{}", Utc::now().format("%Y%m%d_%H%M%S"));
-        Self { config, session_id }
+    /// 创建备份服务，会话 ID 为 `backup_YYYYMMDD_HHMMSS`
+    ///
+    /// 指定 `label` 时追加为会话 ID 的后缀（如 `backup_20250101_120000_pre-refactor`），
+    /// 便于在 `list-backups` 中找到。字母、数字、`-`、`_`、`.` 以外的字符替换为 `-`。
+    pub fn new(config: BackupConfig, label: Option<&str>) -> Self {
+        let mut session_id = format!("backup_{}", Utc::now().format("%Y%m%d_%H%M%S"));
+        if let Some(label) = label.map(Self::sanitize_label).filter(|l| !l.is_empty()) {
+            session_id.push('_');
+            session_id.push_str(&label);
+        }
+        Self {
+            config,
+            session_id,
+            object_grace: OBJECT_GRACE_PERIOD,
+        }
+    }
+
+    fn sanitize_label(label: &str) -> String {
+        label
+            .trim()
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                    c
+                } else {
+                    '-'
+                }
+            })
+            .collect()
+    }
+
+    /// 会话 ID 中时间戳之后的标签，未设置标签时返回 `None`
+    pub fn session_label(session_id: &str) -> Option<&str> {
+        session_id
+            .strip_prefix("backup_")?
+            .get(15..)?
+            .strip_prefix('_')
+            .filter(|label| !label.is_empty())
     }
 
     pub fn get_session_id(&self) -> &str {
@@ -34,12 +80,96 @@ impl BackupService {
     }
 
     /// 备份单个文件，保持相对路径结构并保存哈希校验和
+    ///
+    /// 开启 `dedup` 时内容写入 `objects/<blake3>`（已存在则跳过），
+    /// 会话中只保留哈希文件作为引用。
     pub async fn backup_file(
         &self,
         root_path: &Path,
         file_path: &Path,
         content: &[u8],
     ) -> Result<()> {
+        let (target_path, hash_path) = self.backup_paths(root_path, file_path).await?;
+
+        let hash = blake3::hash(content);
+        if self.config.dedup {
+            self.write_object(hash.to_hex().as_str(), content).await?;
+        } else {
+            // 检查目标文件写入权限
+            self.check_file_permissions(&target_path, "write").await?;
+
+            // 写入内容
+            fs::write(&target_path, content)
+                .await
+                .map_err(|e| ZenithError::BackupFailed(e.to_string()))?;
+        }
+
+        // 检查哈希文件写入权限
+        self.check_file_permissions(&hash_path, "write").await?;
+
+        // 写入哈希
+        fs::write(&hash_path, hash.to_hex().as_str())
+            .await
+            .map_err(|e| ZenithError::BackupFailed(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// 以流的方式备份磁盘上的 `file_path`，边复制边计算哈希，不把整个文件读入内存
+    ///
+    /// 备份布局与 [`Self::backup_file`] 相同。
+    pub async fn backup_path(&self, root_path: &Path, file_path: &Path) -> Result<()> {
+        let (target_path, hash_path) = self.backup_paths(root_path, file_path).await?;
+        let staging_dir = if self.config.dedup {
+            fs::create_dir_all(self.objects_dir()).await?;
+            self.objects_dir()
+        } else {
+            self.check_file_permissions(&target_path, "write").await?;
+            target_path
+                .parent()
+                .map(Path::to_path_buf)
+                .unwrap_or_default()
+        };
+
+        let backup_error = |e: std::io::Error| ZenithError::BackupFailed(e.to_string());
+        let temp = tempfile::NamedTempFile::new_in(&staging_dir).map_err(backup_error)?;
+        let mut output = fs::File::from_std(temp.as_file().try_clone().map_err(backup_error)?);
+        let mut input = fs::File::open(file_path).await?;
+        let mut hasher = blake3::Hasher::new();
+        let mut buf = vec![0; 64 * 1024];
+        loop {
+            let n = input.read(&mut buf).await?;
+            if n == 0 {
+                break;
+            }
+            hasher.update(&buf[..n]);
+            output.write_all(&buf[..n]).await.map_err(backup_error)?;
+        }
+        output.flush().await.map_err(backup_error)?;
+
+        let hash = hasher.finalize().to_hex();
+        let destination = if self.config.dedup {
+            self.objects_dir().join(hash.as_str())
+        } else {
+            target_path
+        };
+        // 去重模式下相同内容的对象已存在时丢弃临时文件
+        if self.config.dedup && destination.exists() {
+            Self::touch_object(&destination);
+        } else {
+            temp.persist(&destination)
+                .map_err(|e| ZenithError::BackupFailed(e.error.to_string()))?;
+        }
+
+        self.check_file_permissions(&hash_path, "write").await?;
+        fs::write(&hash_path, hash.as_str())
+            .await
+            .map_err(|e| ZenithError::BackupFailed(e.to_string()))?;
+        Ok(())
+    }
+
+    /// 备份中保存 `file_path` 内容和哈希的位置，按相对 `root_path` 的路径保持目录结构
+    async fn backup_paths(&self, root_path: &Path, file_path: &Path) -> Result<(PathBuf, PathBuf)> {
         let backup_root = Path::new(&self.config.dir).join(&self.session_id);
 
         // 计算相对路径以保持目录结构
@@ -52,28 +182,100 @@ impl BackupService {
         if let Some(parent) = target_path.parent() {
             fs::create_dir_all(parent).await?;
         }
+        Ok((target_path, hash_path))
+    }
 
-        // 检查目标文件写入权限
-        self.check_file_permissions(&target_path, "write").await?;
+    fn objects_dir(&self) -> PathBuf {
+        Path::new(&self.config.dir).join(OBJECTS_DIR)
+    }
 
-        // 写入内容
-        fs::write(&target_path, content)
+    /// 将内容写入对象存储；同一哈希的对象已存在时不再重复写入
+    async fn write_object(&self, hash: &str, content: &[u8]) -> Result<()> {
+        let object_path = self.objects_dir().join(hash);
+        if object_path.exists() {
+            Self::touch_object(&object_path);
+            return Ok(());
+        }
+        fs::create_dir_all(self.objects_dir()).await?;
+        // 先写临时文件再重命名，并发写入同一对象时不会读到半个文件
+        static NEXT_TEMP: AtomicU64 = AtomicU64::new(0);
+        let temp_path = object_path.with_extension(format!(
+            "tmp{}-{}",
+            std::process::id(),
+            NEXT_TEMP.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::write(&temp_path, content)
             .await
             .map_err(|e| ZenithError::BackupFailed(e.to_string()))?;
-
-        // 检查哈希文件写入权限
-        self.check_file_permissions(&hash_path, "write").await?;
-
-        // 写入哈希
-        let hash = blake3::hash(content);
-        fs::write(&hash_path, hash.to_hex().as_str())
+        fs::rename(&temp_path, &object_path)
             .await
             .map_err(|e| ZenithError::BackupFailed(e.to_string()))?;
-
         Ok(())
     }
 
-    /// 列出所有备份
+    /// 更新被复用对象的修改时间，使其在引用写入前处于清理的宽限期内
+    fn touch_object(path: &Path) {
+        let touched = std::fs::File::options()
+            .append(true)
+            .open(path)
+            .and_then(|file| file.set_modified(SystemTime::now()));
+        if let Err(e) = touched {
+            tracing::debug!("Failed to touch backup object {:?}: {}", path, e);
+        }
+    }
+
+    /// 对象文件名为 64 位小写十六进制的 blake3 哈希
+    fn is_object_name(name: &str) -> bool {
+        name.len() == 64 && name.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+    }
+
+    /// 删除不再被任何会话引用的对象，返回删除数
+    ///
+    /// 只处理以哈希命名的对象，正在写入的临时文件不受影响；修改时间在宽限期内的对象
+    /// 可能属于尚未写完的备份，同样保留。
+    async fn remove_unreferenced_objects(&self) -> Result<usize> {
+        let objects_dir = self.objects_dir();
+        if !objects_dir.exists() {
+            return Ok(0);
+        }
+
+        let mut referenced = HashSet::new();
+        for (name, _, _) in self.list_backups().await? {
+            let backup_path = Path::new(&self.config.dir).join(name);
+            for rel_path in Self::session_files(&backup_path).await? {
+                let hash_path = backup_path.join(format!("{}.blake3", rel_path.display()));
+                if let Ok(hash) = fs::read_to_string(&hash_path).await {
+                    referenced.insert(hash.trim().to_string());
+                }
+            }
+        }
+
+        let now = SystemTime::now();
+        let mut removed = 0;
+        let mut entries = fs::read_dir(&objects_dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if !Self::is_object_name(&name) || referenced.contains(&name) {
+                continue;
+            }
+            let expired = entry
+                .metadata()
+                .await
+                .and_then(|metadata| metadata.modified())
+                .ok()
+                .and_then(|modified| now.duration_since(modified).ok())
+                .is_some_and(|age| age >= self.object_grace);
+            if expired {
+                fs::remove_file(entry.path()).await?;
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+
+    /// 列出所有备份，按创建时间从新到旧排序
+    ///
+    /// 创建时间优先取自会话 ID 中的时间戳（UTC），无法解析时使用目录创建时间。
     pub async fn list_backups(&self) -> Result<Vec<(String, SystemTime, u64)>> {
         let mut backups = Vec::new();
         let dir = Path::new(&self.config.dir);
@@ -88,9 +290,12 @@ impl BackupService {
             if metadata.is_dir() {
                 let name = entry.file_name().to_string_lossy().into_owned();
                 if name.starts_with("backup_") {
-                    let created = match metadata.created() {
-                        Ok(time) => time,
-                        Err(_) => SystemTime::now(), // fallback if creation time cannot be determined
+                    let created = match Self::session_time(&name) {
+                        Some(time) => time,
+                        None => match metadata.created() {
+                            Ok(time) => time,
+                            Err(_) => SystemTime::now(), // fallback if creation time cannot be determined
+                        },
                     };
                     let size = fs_extra::dir::get_size(entry.path()).unwrap_or(0);
                     backups.push((name, created, size));
@@ -99,7 +304,7 @@ impl BackupService {
         }
 
         // 按时间倒序排序
-        backups.sort_by(|a, b| b.1.cmp(&a.1));
+        backups.sort_by_key(|b| std::cmp::Reverse(b.1));
         Ok(backups)
     }
 
@@ -110,68 +315,155 @@ impl BackupService {
             return Err(ZenithError::BackupNotFound(backup_id.into()));
         }
 
-        let target_root = match target_dir {
-            Some(path) => path,
-            None => match std::env::current_dir() {
-                Ok(path) => path,
-                Err(_) => {
-                    return Err(ZenithError::BackupFailed(
-                        "Cannot determine current directory".to_string(),
-                    ))
-                }
-            },
-        };
+        let target_root = Self::target_root(target_dir)?;
         let mut restored_count = 0;
+        for rel_path in Self::session_files(&backup_path).await? {
+            self.restore_file(&backup_path, &rel_path, &target_root)
+                .await?;
+            restored_count += 1;
+        }
+
+        Ok(restored_count)
+    }
+
+    /// 批量恢复时间窗口 `[since, until]` 内所有备份会话中的文件
+    ///
+    /// 会话按创建时间从早到晚应用，同一路径以最晚的会话为准（last-writer-wins），
+    /// 每个文件只写入一次。会话时间与 [`Self::list_backups`] 相同。返回恢复的文件数。
+    pub async fn recover_range(
+        &self,
+        since: SystemTime,
+        until: SystemTime,
+        target: Option<PathBuf>,
+    ) -> Result<usize> {
+        let mut sessions: Vec<(String, SystemTime)> = self
+            .list_backups()
+            .await?
+            .into_iter()
+            .map(|(name, created, _)| (name, created))
+            .filter(|(_, time)| *time >= since && *time <= until)
+            .collect();
+        if sessions.is_empty() {
+            return Err(ZenithError::BackupNotFound(
+                "No backups in the requested time range".into(),
+            ));
+        }
+        sessions.sort_by(|a, b| a.1.cmp(&b.1).then_with(|| a.0.cmp(&b.0)));
+
+        // 后面的会话覆盖前面的会话
+        let mut newest: HashMap<PathBuf, PathBuf> = HashMap::new();
+        for (name, _) in &sessions {
+            let backup_path = Path::new(&self.config.dir).join(name);
+            for rel_path in Self::session_files(&backup_path).await? {
+                newest.insert(rel_path, backup_path.clone());
+            }
+        }
+
+        let target_root = Self::target_root(target)?;
+        for (rel_path, backup_path) in &newest {
+            self.restore_file(backup_path, rel_path, &target_root)
+                .await?;
+        }
+
+        Ok(newest.len())
+    }
+
+    /// 从 `backup_YYYYMMDD_HHMMSS[_标签]` 形式的会话 ID 中解析创建时间
+    fn session_time(session_id: &str) -> Option<SystemTime> {
+        let stamp = session_id.strip_prefix("backup_")?.get(..15)?;
+        let time = NaiveDateTime::parse_from_str(stamp, "%Y%m%d_%H%M%S").ok()?;
+        Some(time.and_utc().into())
+    }
+
+    fn target_root(target_dir: Option<PathBuf>) -> Result<PathBuf> {
+        match target_dir {
+            Some(path) => Ok(path),
+            None => std::env::current_dir().map_err(|_| {
+                ZenithError::BackupFailed("Cannot determine current directory".to_string())
+            }),
+        }
+    }
 
-        // 遍历备份目录并恢复
-        let mut stack = vec![backup_path.clone()];
+    /// 列出备份会话中的文件（相对于会话根目录），跳过哈希文件
+    ///
+    /// 去重模式下会话中只有哈希文件，此时以去掉 `.blake3` 后的路径作为文件。
+    async fn session_files(backup_path: &Path) -> Result<Vec<PathBuf>> {
+        let mut files = Vec::new();
+        let mut stack = vec![backup_path.to_path_buf()];
         while let Some(curr) = stack.pop() {
             let mut entries = fs::read_dir(&curr).await?;
             while let Some(entry) = entries.next_entry().await? {
                 let path = entry.path();
                 if path.is_dir() {
                     stack.push(path);
-                } else if path.extension().map(|e| e == "blake3").unwrap_or(false) {
-                    // 跳过哈希文件
-                    continue;
                 } else {
+                    let file_path = if path.extension().is_some_and(|e| e == "blake3") {
+                        // 内容与哈希并存时跳过哈希文件，只有哈希时为去重引用
+                        let content_path = path.with_extension("");
+                        if content_path.exists() {
+                            continue;
+                        }
+                        content_path
+                    } else {
+                        path
+                    };
                     // 计算相对于备份根目录的路径
-                    let rel_path = path
-                        .strip_prefix(&backup_path)
+                    let rel_path = file_path
+                        .strip_prefix(backup_path)
                         .map_err(|_| ZenithError::RecoverFailed("Invalid path structure".into()))?;
+                    files.push(rel_path.to_path_buf());
+                }
+            }
+        }
+        Ok(files)
+    }
 
-                    // 验证哈希（如果存在）
-                    let hash_path = backup_path.join(format!("{}.blake3", rel_path.display()));
-                    if hash_path.exists() {
-                        let content = fs::read(&path).await?;
-                        let actual_hash = blake3::hash(&content).to_hex().to_string();
-                        let expected_hash = fs::read_to_string(&hash_path).await?;
-
-                        if actual_hash != expected_hash.trim() {
-                            return Err(ZenithError::RecoverFailed(format!(
-                                "Hash mismatch for file: {}",
-                                rel_path.display()
-                            )));
-                        }
-                    }
-
-                    let restore_target = target_root.join(rel_path);
-
-                    if let Some(parent) = restore_target.parent() {
-                        fs::create_dir_all(parent).await?;
-                    }
-
-                    // 检查恢复目标文件的写入权限
-                    self.check_file_permissions(&restore_target, "write")
-                        .await?;
-
-                    fs::copy(&path, &restore_target).await?;
-                    restored_count += 1;
+    /// 校验哈希后将备份中的单个文件恢复到目标目录
+    async fn restore_file(
+        &self,
+        backup_path: &Path,
+        rel_path: &Path,
+        target_root: &Path,
+    ) -> Result<()> {
+        let mut path = backup_path.join(rel_path);
+
+        // 验证哈希（如果存在）
+        let hash_path = backup_path.join(format!("{}.blake3", rel_path.display()));
+        if hash_path.exists() {
+            let expected_hash = fs::read_to_string(&hash_path).await?;
+            if !path.exists() {
+                // 去重引用：内容在对象存储中
+                path = self.objects_dir().join(expected_hash.trim());
+                if !path.exists() {
+                    return Err(ZenithError::RecoverFailed(format!(
+                        "Missing backup object for file: {}",
+                        rel_path.display()
+                    )));
                 }
             }
+            let content = fs::read(&path).await?;
+            let actual_hash = blake3::hash(&content).to_hex().to_string();
+
+            if actual_hash != expected_hash.trim() {
+                return Err(ZenithError::RecoverFailed(format!(
+                    "Hash mismatch for file: {}",
+                    rel_path.display()
+                )));
+            }
         }
 
-        Ok(restored_count)
+        let restore_target = target_root.join(rel_path);
+
+        if let Some(parent) = restore_target.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+
+        // 检查恢复目标文件的写入权限
+        self.check_file_permissions(&restore_target, "write")
+            .await?;
+
+        fs::copy(&path, &restore_target).await?;
+        Ok(())
     }
 
     /// 检查文件权限
@@ -222,6 +514,9 @@ impl BackupService {
     }
 
     /// 清理过期备份
+    ///
+    /// 先删除超过 `retention_days` 天的会话；若配置了 `max_sessions`，
+    /// 再按 [`Self::prune_sessions`] 只保留最新的会话。返回删除的会话总数。
     pub async fn clean_backups(&self, retention_days: u32) -> Result<usize> {
         let backups = self.list_backups().await?;
         let now = SystemTime::now();
@@ -240,6 +535,28 @@ impl BackupService {
             }
         }
 
+        if let Some(max_sessions) = self.config.max_sessions {
+            deleted_count += self.prune_sessions(max_sessions).await?;
+        }
+        if deleted_count > 0 {
+            self.remove_unreferenced_objects().await?;
+        }
+
+        Ok(deleted_count)
+    }
+
+    /// 只保留最新的 `max_sessions` 个备份会话，删除更早的会话，返回删除数
+    pub async fn prune_sessions(&self, max_sessions: usize) -> Result<usize> {
+        let backups = self.list_backups().await?;
+        let mut deleted_count = 0;
+        for (name, _, _) in backups.into_iter().skip(max_sessions) {
+            let path = Path::new(&self.config.dir).join(name);
+            fs::remove_dir_all(path).await?;
+            deleted_count += 1;
+        }
+        if deleted_count > 0 {
+            self.remove_unreferenced_objects().await?;
+        }
         Ok(deleted_count)
     }
 
@@ -258,41 +575,15 @@ impl BackupService {
         let current_dir = std::env::current_dir()?;
 
         // Use the recover method to restore the backup
-        let _restored_count = self.recover(backup_id, Some(current_dir)).await?;
-
-        // Since the recover method returns the count, we'll return an empty vector
-        // To return the actual paths, we need to track them during recovery
-        // For now, let's implement a method that returns the paths that were recovered
+        let _restored_count = self.recover(backup_id, Some(current_dir.clone())).await?;
 
-        // Get all files in the backup to return their paths
+        // Report the paths that were restored
         let backup_path = Path::new(&self.config.dir).join(backup_id);
-        let mut recovered_files = Vec::new();
-
-        if backup_path.exists() {
-            // Walk through the backup directory to get the files that would be restored
-            let mut stack = vec![backup_path.clone()];
-            while let Some(curr) = stack.pop() {
-                let mut entries = fs::read_dir(&curr).await?;
-                while let Some(entry) = entries.next_entry().await? {
-                    let path = entry.path();
-                    if path.is_dir() {
-                        stack.push(path);
-                    } else if path.extension().map(|e| e == "blake3").unwrap_or(false) {
-                        // Skip hash files
-                        continue;
-                    } else {
-                        // Calculate the relative path from backup root
-                        let rel_path = path.strip_prefix(&backup_path).map_err(|_| {
-                            ZenithError::RecoverFailed("Invalid path structure".into())
-                        })?;
-
-                        // The restored path would be current_dir + rel_path
-                        let restored_path = std::env::current_dir()?.join(rel_path);
-                        recovered_files.push(restored_path);
-                    }
-                }
-            }
-        }
+        let recovered_files = Self::session_files(&backup_path)
+            .await?
+            .into_iter()
+            .map(|rel_path| current_dir.join(rel_path))
+            .collect();
 
         Ok(recovered_files)
     }
@@ -316,10 +607,13 @@ mod tests {
             let config = BackupConfig {
                 dir: backup_dir.to_string_lossy().to_string(),
                 retention_days: 7,
+                max_sessions: None,
+                dedup: false,
+                auto_clean_on_exit: false,
             };
 
             // Create backup service
-            let service = BackupService::new(config);
+            let service = BackupService::new(config, None);
 
             // Initialize backup service
             service.init().await.unwrap();
@@ -357,10 +651,13 @@ mod tests {
             let config = BackupConfig {
                 dir: backup_dir.to_string_lossy().to_string(),
                 retention_days: 7,
+                max_sessions: None,
+                dedup: false,
+                auto_clean_on_exit: false,
             };
 
             // Create backup service
-            let service = BackupService::new(config);
+            let service = BackupService::new(config, None);
 
             // Create a test file
             let test_file = temp_dir.path().join("test.txt");
@@ -371,4 +668,261 @@ mod tests {
             assert!(result.is_ok());
         }
     }
+
+    #[tokio::test]
+    async fn test_recover_range_latest_session_wins() {
+        let temp_dir = TempDir::new().unwrap();
+        let backup_dir = temp_dir.path().join("backups");
+        let root = temp_dir.path().join("project");
+        let config = BackupConfig {
+            dir: backup_dir.to_string_lossy().to_string(),
+            retention_days: 7,
+            max_sessions: None,
+            dedup: false,
+            auto_clean_on_exit: false,
+        };
+
+        // 三个会话，a.txt 在每个会话中都有备份
+        let sessions: [(&str, &[(&str, &str)]); 3] = [
+            (
+                "backup_20250101_100000",
+                &[("a.txt", "a1"), ("sub/b.txt", "b1")],
+            ),
+            ("backup_20250101_110000", &[("a.txt", "a2")]),
+            (
+                "backup_20250101_120000",
+                &[("a.txt", "a3"), ("c.txt", "c3")],
+            ),
+        ];
+        for (session_id, files) in sessions {
+            let mut service = BackupService::new(config.clone(), None);
+            service.session_id = session_id.to_string();
+            service.init().await.unwrap();
+            for (name, content) in files {
+                service
+                    .backup_file(&root, &root.join(name), content.as_bytes())
+                    .await
+                    .unwrap();
+            }
+        }
+
+        let at = |stamp: &str| BackupService::session_time(&format!("backup_{}", stamp)).unwrap();
+        let target = temp_dir.path().join("restored");
+        let service = BackupService::new(config, None);
+        let restored = service
+            .recover_range(
+                at("20250101_090000"),
+                at("20250101_113000"),
+                Some(target.clone()),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(restored, 2);
+        assert_eq!(std::fs::read_to_string(target.join("a.txt")).unwrap(), "a2");
+        assert_eq!(
+            std::fs::read_to_string(target.join("sub/b.txt")).unwrap(),
+            "b1"
+        );
+        assert!(!target.join("c.txt").exists());
+
+        let empty = service
+            .recover_range(at("20240101_000000"), at("20240102_000000"), Some(target))
+            .await;
+        assert!(matches!(empty, Err(ZenithError::BackupNotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_clean_backups_keeps_newest_sessions() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = BackupConfig {
+            dir: temp_dir.path().to_string_lossy().to_string(),
+            retention_days: 36500,
+            max_sessions: Some(2),
+            dedup: false,
+            auto_clean_on_exit: false,
+        };
+        let sessions = [
+            "backup_20250101_100000",
+            "backup_20250103_100000",
+            "backup_20250102_100000",
+            "backup_20250104_100000",
+        ];
+        for session_id in sessions {
+            std::fs::create_dir(temp_dir.path().join(session_id)).unwrap();
+        }
+
+        let service = BackupService::new(config, None);
+        assert_eq!(service.clean_backups(36500).await.unwrap(), 2);
+
+        let remaining: Vec<String> = service
+            .list_backups()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|(name, _, _)| name)
+            .collect();
+        assert_eq!(
+            remaining,
+            vec!["backup_20250104_100000", "backup_20250103_100000"]
+        );
+        assert_eq!(service.prune_sessions(5).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_labeled_session_id() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = BackupConfig {
+            dir: temp_dir.path().to_string_lossy().to_string(),
+            retention_days: 7,
+            max_sessions: None,
+            dedup: false,
+            auto_clean_on_exit: false,
+        };
+
+        let service = BackupService::new(config.clone(), Some("pre refactor/1"));
+        let session_id = service.get_session_id();
+        assert!(session_id.ends_with("_pre-refactor-1"));
+        assert_eq!(
+            BackupService::session_label(session_id),
+            Some("pre-refactor-1")
+        );
+        assert!(BackupService::session_time(session_id).is_some());
+
+        service.init().await.unwrap();
+        let backups = service.list_backups().await.unwrap();
+        assert_eq!(backups[0].0, session_id);
+
+        let unlabeled = BackupService::new(config, Some("  "));
+        assert_eq!(
+            unlabeled.get_session_id().len(),
+            "backup_20250101_120000".len()
+        );
+        assert_eq!(
+            BackupService::session_label(unlabeled.get_session_id()),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_dedup_sessions_share_objects() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().join("project");
+        let file = root.join("src/main.rs");
+        let config = BackupConfig {
+            dir: temp_dir
+                .path()
+                .join("backups")
+                .to_string_lossy()
+                .to_string(),
+            retention_days: 36500,
+            max_sessions: None,
+            dedup: true,
+            auto_clean_on_exit: false,
+        };
+        let objects = temp_dir.path().join("backups").join(OBJECTS_DIR);
+        let object_count = || std::fs::read_dir(&objects).unwrap().count();
+
+        let mut sessions = Vec::new();
+        for (session_id, content) in [
+            ("backup_20250101_100000", "fn main() {}\n"),
+            ("backup_20250102_100000", "fn main() {}\n"),
+            ("backup_20250103_100000", "fn main() { }\n"),
+        ] {
+            let mut service = BackupService::new(config.clone(), None);
+            service.session_id = session_id.to_string();
+            service
+                .backup_file(&root, &file, content.as_bytes())
+                .await
+                .unwrap();
+            sessions.push(service);
+        }
+        // 前两个会话内容相同，共享同一个对象
+        assert_eq!(object_count(), 2);
+        let session_dir = temp_dir.path().join("backups/backup_20250101_100000/src");
+        assert!(!session_dir.join("main.rs").exists());
+        assert!(session_dir.join("main.rs.blake3").exists());
+
+        let target = temp_dir.path().join("restored");
+        let service = &sessions[0];
+        assert_eq!(
+            service
+                .recover("backup_20250101_100000", Some(target.clone()))
+                .await
+                .unwrap(),
+            1
+        );
+        assert_eq!(
+            std::fs::read_to_string(target.join("src/main.rs")).unwrap(),
+            "fn main() {}\n"
+        );
+
+        // 删除会话后仍被引用的对象保留，无引用的对象被清理
+        let mut service = BackupService::new(config.clone(), None);
+        service.object_grace = Duration::ZERO;
+        assert_eq!(service.prune_sessions(2).await.unwrap(), 1);
+        assert_eq!(object_count(), 2);
+        assert_eq!(service.prune_sessions(1).await.unwrap(), 1);
+        assert_eq!(object_count(), 1);
+
+        // 对象被篡改时拒绝恢复
+        for entry in std::fs::read_dir(&objects).unwrap() {
+            std::fs::write(entry.unwrap().path(), "tampered").unwrap();
+        }
+        assert!(matches!(
+            service
+                .recover("backup_20250103_100000", Some(target))
+                .await,
+            Err(ZenithError::RecoverFailed(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_cleanup_keeps_temp_files_and_fresh_objects() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().join("project");
+        let config = BackupConfig {
+            dir: temp_dir
+                .path()
+                .join("backups")
+                .to_string_lossy()
+                .to_string(),
+            retention_days: 36500,
+            max_sessions: None,
+            dedup: true,
+            auto_clean_on_exit: false,
+        };
+        let objects = temp_dir.path().join("backups").join(OBJECTS_DIR);
+
+        let mut old = BackupService::new(config.clone(), None);
+        old.session_id = "backup_20250101_100000".to_string();
+        old.backup_file(&root, &root.join("a.rs"), b"fn a() {}\n")
+            .await
+            .unwrap();
+        // 另一个进程正在写入的对象，以及尚未被引用的新对象
+        let stray = objects.join(format!("{}.tmp4242-0", "0".repeat(64)));
+        std::fs::write(&stray, "partial").unwrap();
+        let unreferenced = objects.join(blake3::hash(b"pending").to_hex().as_str());
+        std::fs::write(&unreferenced, "pending").unwrap();
+
+        let mut service = BackupService::new(config.clone(), None);
+        service.session_id = "backup_20250102_100000".to_string();
+        service
+            .backup_file(&root, &root.join("b.rs"), b"fn b() {}\n")
+            .await
+            .unwrap();
+
+        // 宽限期内的对象都保留
+        assert_eq!(service.prune_sessions(1).await.unwrap(), 1);
+        assert!(stray.exists());
+        assert!(unreferenced.exists());
+        assert_eq!(std::fs::read_dir(&objects).unwrap().count(), 4);
+
+        // 过了宽限期只清理无引用的对象，临时文件不受影响
+        service.object_grace = Duration::ZERO;
+        service.remove_unreferenced_objects().await.unwrap();
+        assert!(stray.exists());
+        assert!(!unreferenced.exists());
+        assert_eq!(std::fs::read_dir(&objects).unwrap().count(), 2);
+    }
 }
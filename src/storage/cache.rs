@@ -15,6 +15,24 @@ use tokio::fs::{self, File};
 use tokio::io::{AsyncWriteExt, BufWriter};
 use tokio::sync::RwLock;
 
+/// Name of the persisted cache file inside the cache directory
+pub const CACHE_FILE_NAME: &str = "file_cache.json";
+
+/// Hash of the effective formatter config for a file
+///
+/// Covers the serialized `ZenithConfig` and the contents of its config file,
+/// so editing e.g. `.rustfmt.toml` invalidates the files it applies to.
+pub fn config_hash(config: &ZenithConfig) -> Hash {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(serde_json::to_string(config).unwrap_or_default().as_bytes());
+    if let Some(path) = &config.custom_config_path {
+        if let Ok(contents) = std::fs::read(path) {
+            hasher.update(&contents);
+        }
+    }
+    hasher.finalize()
+}
+
 /// Represents the state of a file including content hash and metadata
 #[derive(Debug, Clone)]
 pub struct FileState {
@@ -39,8 +57,7 @@ impl FileState {
     }
 
     pub fn with_config(hash: Hash, modified: SystemTime, size: u64, config: &ZenithConfig) -> Self {
-        let config_str = serde_json::to_string(config).unwrap_or_default();
-        let config_hash = blake3::hash(config_str.as_bytes());
+        let config_hash = config_hash(config);
         Self {
             hash,
             modified,
@@ -150,7 +167,7 @@ impl HashCache {
             cache: Arc::new(RwLock::new(HashMap::new())),
             cache_dir: None,
             max_entry_age: Duration::from_secs(24 * 60 * 60), // 24 hours default
-            config_aware: false,
+            config_aware: true,
         }
     }
 
@@ -161,16 +178,21 @@ impl HashCache {
             cache: Arc::new(RwLock::new(HashMap::new())),
             cache_dir: Some(cache_dir),
             max_entry_age: Duration::from_secs(24 * 60 * 60),
-            config_aware: false,
+            config_aware: true,
         }
     }
 
-    /// Enable config-aware caching
+    /// Enable or disable config-aware caching (enabled by default)
     pub fn with_config_aware(mut self, enabled: bool) -> Self {
         self.config_aware = enabled;
         self
     }
 
+    /// Whether cache entries are keyed on the effective formatter config too
+    pub fn is_config_aware(&self) -> bool {
+        self.config_aware
+    }
+
     /// Set maximum entry age for cache validation
     pub fn with_max_entry_age(mut self, age: Duration) -> Self {
         self.max_entry_age = age;
@@ -181,6 +203,11 @@ impl HashCache {
         self.cache_dir.as_deref()
     }
 
+    /// Path of the persisted cache file, when a cache directory is set
+    pub fn cache_file(&self) -> Option<PathBuf> {
+        self.cache_dir.as_ref().map(|dir| dir.join(CACHE_FILE_NAME))
+    }
+
     /// Serialize a path to a string for storage
     fn serialize_path(path: &Path) -> String {
         path.to_string_lossy().into_owned()
@@ -199,7 +226,7 @@ impl HashCache {
             return Ok(());
         };
 
-        let cache_file = cache_dir.join("file_cache.json");
+        let cache_file = cache_dir.join(CACHE_FILE_NAME);
         let cache = self.cache.read().await;
 
         let entries: Vec<(String, SerializedFileState)> = cache
@@ -236,7 +263,7 @@ impl HashCache {
             return Ok(());
         };
 
-        let cache_file = cache_dir.join("file_cache.json");
+        let cache_file = cache_dir.join(CACHE_FILE_NAME);
         if !cache_file.exists() {
             return Ok(());
         }
@@ -294,7 +321,7 @@ impl HashCache {
             };
 
             if let Ok(json) = serde_json::to_string_pretty(&serialized) {
-                let cache_file = cache_dir.join("file_cache.json");
+                let cache_file = cache_dir.join(CACHE_FILE_NAME);
                 if let Ok(file) = File::create(&cache_file).await {
                     let mut writer = BufWriter::new(file);
                     if writer.write_all(json.as_bytes()).await.is_ok() {
@@ -370,13 +397,8 @@ impl HashCache {
 
                 let hash_changed = cached_state.hash != current_state.hash;
 
-                let config_changed = if let Some(config) = config {
-                    let current_config_hash =
-                        blake3::hash(serde_json::to_string(config).unwrap_or_default().as_bytes());
-                    cached_state.config_hash != Some(current_config_hash)
-                } else {
-                    false
-                };
+                let config_changed =
+                    config.is_some() && cached_state.config_hash != current_state.config_hash;
 
                 tracing::debug!(
                     "Cache comparison for {:?}: hash_changed={}, config_changed={}",
@@ -414,6 +436,15 @@ impl HashCache {
         Ok(())
     }
 
+    /// Move a cache entry to a new path, e.g. after a file was renamed
+    pub async fn rename(&self, from: &Path, to: PathBuf) -> Result<()> {
+        let mut cache = self.cache.write().await;
+        if let Some(state) = cache.remove(from) {
+            cache.insert(to, state);
+        }
+        Ok(())
+    }
+
     /// Clear the entire cache
     pub async fn clear(&self) -> Result<()> {
         let mut cache = self.cache.write().await;
@@ -421,6 +452,20 @@ impl HashCache {
         Ok(())
     }
 
+    /// Clear the cache and delete its persisted file
+    ///
+    /// Returns whether a file was deleted.
+    pub async fn clear_persisted(&self) -> Result<bool> {
+        self.clear().await?;
+        match self.cache_file() {
+            Some(file) if file.exists() => {
+                fs::remove_file(&file).await?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
     /// Get cache statistics
     pub async fn stats(&self) -> CacheStats {
         let cache = self.cache.read().await;
@@ -856,6 +901,29 @@ mod tests {
         assert!(!cache.is_cached(path).await);
     }
 
+    #[tokio::test]
+    async fn test_rename_cache_entry() {
+        let cache = HashCache::new();
+        let temp_file = NamedTempFile::new().unwrap();
+        let path = temp_file.path();
+
+        fs::write(path, b"test content").await.unwrap();
+        let state = cache.compute_file_state(path).await.unwrap();
+        cache
+            .update(path.to_path_buf(), state.clone())
+            .await
+            .unwrap();
+
+        let renamed = path.with_extension("renamed");
+        cache.rename(path, renamed.clone()).await.unwrap();
+
+        assert!(!cache.is_cached(path).await);
+        assert_eq!(
+            cache.get_cached_state(&renamed).await.unwrap().hash,
+            state.hash
+        );
+    }
+
     #[tokio::test]
     async fn test_clear_cache() {
         let cache = HashCache::new();
@@ -0,0 +1,75 @@
+// Copyright (c) 2025 Kirky.X
+//
+// Licensed under the MIT License
+// See LICENSE file in the project root for full license information.
+
+//! CI annotations for check-mode results.
+//!
+//! GitHub Actions turns `::error file=...::message` lines on stdout into
+//! inline annotations on the pull request.
+
+use crate::config::types::FormatResult;
+
+/// Whether Zenith runs inside a GitHub Actions job
+pub fn running_in_github_actions() -> bool {
+    std::env::var("GITHUB_ACTIONS").is_ok_and(|value| value == "true")
+}
+
+/// One `::error` workflow command per file that needs formatting
+pub fn github_annotations(results: &[FormatResult]) -> Vec<String> {
+    results
+        .iter()
+        .filter(|result| result.success && result.changed)
+        .map(|result| {
+            format!(
+                "::error file={}::{}",
+                escape_property(&result.file_path.to_string_lossy()),
+                escape_data("File is not formatted")
+            )
+        })
+        .collect()
+}
+
+/// Escape the message of a workflow command
+fn escape_data(value: &str) -> String {
+    value
+        .replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Escape a property value, which additionally ends at `:` or `,`
+fn escape_property(value: &str) -> String {
+    escape_data(value).replace(':', "%3A").replace(',', "%2C")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn result(path: &str, changed: bool) -> FormatResult {
+        FormatResult {
+            file_path: PathBuf::from(path),
+            success: true,
+            changed,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_github_annotations_cover_changed_files() {
+        let results = [
+            result("src/main.rs", true),
+            result("src/lib.rs", false),
+            result("docs/a,b:c%.md", true),
+        ];
+        assert_eq!(
+            github_annotations(&results),
+            [
+                "::error file=src/main.rs::File is not formatted",
+                "::error file=docs/a%2Cb%3Ac%25.md::File is not formatted",
+            ]
+        );
+    }
+}
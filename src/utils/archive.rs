@@ -0,0 +1,365 @@
+// Copyright (c) 2025 Kirky.X
+//
+// Licensed under the MIT License
+// See LICENSE file in the project root for full license information.
+
+//! In-memory reading and writing of `.zip`, `.tar` and `.tar.gz` archives.
+
+use crate::error::{Result, ZenithError};
+use crate::utils::atomic::{persist, temp_file_for};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Seek, Write};
+use std::path::Path;
+use zip::write::SimpleFileOptions;
+
+/// Mode given to files that carry no permissions of their own
+const DEFAULT_FILE_MODE: u32 = 0o644;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveKind {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+impl ArchiveKind {
+    /// Detect the archive format from the file name
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        if name.ends_with(".zip") {
+            Ok(Self::Zip)
+        } else if name.ends_with(".tar") {
+            Ok(Self::Tar)
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Ok(Self::TarGz)
+        } else {
+            Err(ZenithError::Archive(format!(
+                "unsupported archive format: {}",
+                path.display()
+            )))
+        }
+    }
+}
+
+/// One member of an archive, held in memory unless oversized
+#[derive(Debug, Clone)]
+pub struct ArchiveEntry {
+    /// Path of the member inside the archive
+    pub name: String,
+    pub content: Vec<u8>,
+    /// Directories, links and other special members are copied as-is
+    pub is_file: bool,
+    /// Unix permission bits, when the archive records them
+    pub mode: Option<u32>,
+    /// Larger than the limit given to [`read_archive`]; `content` is left
+    /// empty and the member is streamed from the source archive when writing
+    pub oversized: bool,
+    /// Original tar header, reused when writing a tar so links survive
+    tar_header: Option<tar::Header>,
+}
+
+impl ArchiveEntry {
+    /// Copy of this entry with different contents
+    pub fn with_content(&self, content: Vec<u8>) -> Self {
+        Self {
+            content,
+            ..self.clone()
+        }
+    }
+}
+
+fn archive_error(path: &Path, e: impl std::fmt::Display) -> ZenithError {
+    ZenithError::Archive(format!("{}: {}", path.display(), e))
+}
+
+/// Read every member of `path` into memory, in archive order
+///
+/// At most `max_member_size + 1` bytes of a member are read, whatever size its
+/// header claims; larger members are returned empty and marked `oversized`.
+pub fn read_archive(path: &Path, max_member_size: u64) -> Result<Vec<ArchiveEntry>> {
+    let file = BufReader::new(File::open(path)?);
+    match ArchiveKind::from_path(path)? {
+        ArchiveKind::Zip => read_zip(path, file, max_member_size),
+        ArchiveKind::Tar => read_tar(path, file, max_member_size),
+        ArchiveKind::TarGz => read_tar(path, GzDecoder::new(file), max_member_size),
+    }
+}
+
+/// Contents of `member` and whether it exceeds `limit`, in which case
+/// nothing is kept
+fn read_member(member: impl Read, limit: u64) -> Result<(Vec<u8>, bool)> {
+    let mut content = Vec::new();
+    member
+        .take(limit.saturating_add(1))
+        .read_to_end(&mut content)?;
+    if content.len() as u64 > limit {
+        return Ok((Vec::new(), true));
+    }
+    Ok((content, false))
+}
+
+fn read_zip(path: &Path, reader: BufReader<File>, limit: u64) -> Result<Vec<ArchiveEntry>> {
+    let mut archive = zip::ZipArchive::new(reader).map_err(|e| archive_error(path, e))?;
+    let mut entries = Vec::new();
+    for i in 0..archive.len() {
+        let mut member = archive.by_index(i).map_err(|e| archive_error(path, e))?;
+        let (content, oversized) = read_member(&mut member, limit)?;
+        entries.push(ArchiveEntry {
+            name: member.name().to_string(),
+            content,
+            is_file: member.is_file(),
+            mode: member.unix_mode(),
+            oversized,
+            tar_header: None,
+        });
+    }
+    Ok(entries)
+}
+
+fn read_tar(path: &Path, reader: impl Read, limit: u64) -> Result<Vec<ArchiveEntry>> {
+    let mut archive = tar::Archive::new(reader);
+    let mut entries = Vec::new();
+    for member in archive.entries().map_err(|e| archive_error(path, e))? {
+        let mut member = member.map_err(|e| archive_error(path, e))?;
+        let header = member.header().clone();
+        let name = member
+            .path()
+            .map_err(|e| archive_error(path, e))?
+            .to_string_lossy()
+            .into_owned();
+        let (content, oversized) = read_member(&mut member, limit)?;
+        entries.push(ArchiveEntry {
+            name,
+            content,
+            is_file: header.entry_type().is_file(),
+            mode: header.mode().ok(),
+            oversized,
+            tar_header: Some(header),
+        });
+    }
+    Ok(entries)
+}
+
+/// Write `entries` to a new archive at `path`, in the format its name implies
+///
+/// `entries` must be in the order [`read_archive`] returned them from
+/// `source`, which supplies the contents of `oversized` members. The archive
+/// is written next to `path` and moved over it at the end, so `path` may be
+/// `source` itself.
+pub fn write_archive(path: &Path, entries: &[ArchiveEntry], source: Option<&Path>) -> Result<()> {
+    let kind = ArchiveKind::from_path(path)?;
+    let oversized = entries.iter().any(|entry| entry.oversized);
+    if oversized && source.is_none() {
+        return Err(archive_error(
+            path,
+            "oversized members need a source archive",
+        ));
+    }
+    let source = source.filter(|_| oversized);
+    let temp = temp_file_for(path)?;
+    let file = BufWriter::new(temp.as_file());
+    match kind {
+        ArchiveKind::Zip => write_zip(path, file, entries, source)?,
+        ArchiveKind::Tar => write_tar(file, entries, source)?.flush()?,
+        ArchiveKind::TarGz => {
+            let encoder = write_tar(
+                GzEncoder::new(file, Compression::default()),
+                entries,
+                source,
+            )?;
+            encoder.finish()?.flush()?
+        }
+    }
+    persist(temp, path)?;
+    Ok(())
+}
+
+fn write_zip(
+    path: &Path,
+    writer: impl Write + Seek,
+    entries: &[ArchiveEntry],
+    source: Option<&Path>,
+) -> Result<()> {
+    let mut archive = zip::ZipWriter::new(writer);
+    for (i, entry) in entries.iter().enumerate() {
+        let options = SimpleFileOptions::default()
+            .unix_permissions(entry.mode.unwrap_or(DEFAULT_FILE_MODE) & 0o777);
+        if entry.is_file {
+            archive
+                .start_file(entry.name.as_str(), options)
+                .map_err(|e| archive_error(path, e))?;
+            match source.filter(|_| entry.oversized) {
+                Some(source) => {
+                    std::io::copy(&mut source_member(source, i)?, &mut archive)?;
+                }
+                None => archive.write_all(&entry.content)?,
+            }
+        } else if entry.name.ends_with('/') {
+            archive
+                .add_directory(entry.name.as_str(), options)
+                .map_err(|e| archive_error(path, e))?;
+        }
+    }
+    archive
+        .finish()
+        .map_err(|e| archive_error(path, e))?
+        .flush()?;
+    Ok(())
+}
+
+fn write_tar<W: Write>(writer: W, entries: &[ArchiveEntry], source: Option<&Path>) -> Result<W> {
+    let mut builder = tar::Builder::new(writer);
+    for (i, entry) in entries.iter().enumerate() {
+        let mut header = match &entry.tar_header {
+            Some(header) => header.clone(),
+            None => {
+                let mut header = tar::Header::new_gnu();
+                header.set_entry_type(if entry.is_file {
+                    tar::EntryType::Regular
+                } else {
+                    tar::EntryType::Directory
+                });
+                header.set_mode(entry.mode.unwrap_or(DEFAULT_FILE_MODE) & 0o7777);
+                header
+            }
+        };
+        match source.filter(|_| entry.oversized) {
+            Some(source) => {
+                let member = source_member(source, i)?;
+                header.set_size(member.metadata()?.len());
+                builder.append_data(&mut header, &entry.name, BufReader::new(member))?;
+            }
+            None => {
+                header.set_size(entry.content.len() as u64);
+                builder.append_data(&mut header, &entry.name, entry.content.as_slice())?;
+            }
+        }
+    }
+    Ok(builder.into_inner()?)
+}
+
+/// Stream member `index` of `source` into an anonymous temp file
+///
+/// Oversized members are copied through disk rather than memory, and the
+/// source may be in a different format than the archive being written.
+fn source_member(source: &Path, index: usize) -> Result<File> {
+    let mut spill = tempfile::tempfile()?;
+    let file = BufReader::new(File::open(source)?);
+    match ArchiveKind::from_path(source)? {
+        ArchiveKind::Zip => {
+            let mut archive = zip::ZipArchive::new(file).map_err(|e| archive_error(source, e))?;
+            let mut member = archive
+                .by_index(index)
+                .map_err(|e| archive_error(source, e))?;
+            std::io::copy(&mut member, &mut spill)?;
+        }
+        kind => {
+            let reader: Box<dyn Read> = match kind {
+                ArchiveKind::TarGz => Box::new(GzDecoder::new(file)),
+                _ => Box::new(file),
+            };
+            let mut archive = tar::Archive::new(reader);
+            let mut member = archive
+                .entries()?
+                .nth(index)
+                .transpose()?
+                .ok_or_else(|| archive_error(source, format!("member {index} is missing")))?;
+            std::io::copy(&mut member, &mut spill)?;
+        }
+    }
+    spill.seek(std::io::SeekFrom::Start(0))?;
+    Ok(spill)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn file(name: &str, content: &str) -> ArchiveEntry {
+        ArchiveEntry {
+            name: name.to_string(),
+            content: content.as_bytes().to_vec(),
+            is_file: true,
+            mode: Some(0o755),
+            oversized: false,
+            tar_header: None,
+        }
+    }
+
+    #[test]
+    fn test_archive_kind_from_path() {
+        assert_eq!(
+            ArchiveKind::from_path(Path::new("a.ZIP")).unwrap(),
+            ArchiveKind::Zip
+        );
+        assert_eq!(
+            ArchiveKind::from_path(Path::new("dir/a.tar")).unwrap(),
+            ArchiveKind::Tar
+        );
+        assert_eq!(
+            ArchiveKind::from_path(Path::new("a.tgz")).unwrap(),
+            ArchiveKind::TarGz
+        );
+        assert!(ArchiveKind::from_path(Path::new("a.rar")).is_err());
+    }
+
+    #[test]
+    fn test_archive_roundtrip_across_formats() {
+        let temp_dir = TempDir::new().unwrap();
+        let entries = vec![
+            file("src/main.rs", "fn main() {}\n"),
+            file("run.sh", "ls\n"),
+        ];
+
+        let mut previous = entries.clone();
+        for name in ["a.zip", "b.tar", "c.tar.gz", "d.zip"] {
+            let path = temp_dir.path().join(name);
+            write_archive(&path, &previous, None).unwrap();
+            let read = read_archive(&path, u64::MAX).unwrap();
+            assert_eq!(read.len(), entries.len());
+            for (read, expected) in read.iter().zip(&entries) {
+                assert_eq!(read.name, expected.name);
+                assert_eq!(read.content, expected.content);
+                assert!(read.is_file);
+                assert_eq!(read.mode.map(|m| m & 0o777), Some(0o755));
+            }
+            previous = read;
+        }
+    }
+
+    #[test]
+    fn test_oversized_members_are_streamed_not_read() {
+        let temp_dir = TempDir::new().unwrap();
+        let big = "x".repeat(4096);
+        let entries = vec![file("small.rs", "fn a() {}\n"), file("big.txt", &big)];
+
+        for name in ["a.zip", "b.tar.gz"] {
+            let path = temp_dir.path().join(name);
+            write_archive(&path, &entries, None).unwrap();
+
+            let read = read_archive(&path, 1024).unwrap();
+            assert!(!read[0].oversized);
+            assert_eq!(read[0].content, entries[0].content);
+            assert!(read[1].oversized);
+            assert!(read[1].content.is_empty());
+            assert!(write_archive(&path, &read, None).is_err());
+
+            // Rewritten in place and into the other format
+            let other = temp_dir.path().join(format!("out-{name}.tar"));
+            write_archive(&other, &read, Some(&path)).unwrap();
+            write_archive(&path, &read, Some(&path)).unwrap();
+            for written in [&path, &other] {
+                let reread = read_archive(written, u64::MAX).unwrap();
+                assert_eq!(reread[1].content, big.as_bytes());
+                assert_eq!(reread[0].content, entries[0].content);
+            }
+        }
+    }
+}
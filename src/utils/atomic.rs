@@ -0,0 +1,234 @@
+// Copyright (c) 2025 Kirky.X
+//
+// Licensed under the MIT License
+// See LICENSE file in the project root for full license information.
+
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use tempfile::NamedTempFile;
+
+/// Atomically replace the contents of `path`.
+///
+/// The data is written to a temporary file in the same directory, flushed to
+/// disk and then renamed over the original, so a crash or a full disk never
+/// leaves a truncated file behind. The original file's permissions (and, on
+/// Unix, ownership where allowed) are carried over to the new file. A symlink
+/// at `path` is kept and its target replaced instead, and a file with other
+/// hard links is overwritten in place so the links keep sharing it.
+pub(crate) async fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let path = path.to_path_buf();
+    let contents = contents.to_vec();
+    tokio::task::spawn_blocking(move || write_atomic_with(&path, |file| file.write_all(&contents)))
+        .await
+        .map_err(io::Error::other)?
+}
+
+/// Temporary file next to `path`, to be moved over it by [`persist_atomic`].
+///
+/// Dropping it without persisting removes it again.
+pub(crate) fn temp_file_for(path: &Path) -> io::Result<NamedTempFile> {
+    let path = &resolve_target(path);
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    tempfile::Builder::new()
+        .prefix(&format!(".{}.", file_name))
+        .suffix(".zenith-tmp")
+        .tempfile_in(parent_dir(path))
+}
+
+/// Atomically replace `path` with the already written `temp`, which must
+/// come from [`temp_file_for`].
+pub(crate) async fn persist_atomic(temp: NamedTempFile, path: &Path) -> io::Result<()> {
+    let path = path.to_path_buf();
+    tokio::task::spawn_blocking(move || persist(temp, &path))
+        .await
+        .map_err(io::Error::other)?
+}
+
+/// The file a write to `path` lands in: symlinks are followed, even dangling
+/// ones, so the rename replaces their target rather than the link.
+fn resolve_target(path: &Path) -> PathBuf {
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_symlink() => std::fs::canonicalize(path)
+            .or_else(|_| std::fs::read_link(path).map(|link| parent_dir(path).join(link)))
+            .unwrap_or_else(|_| path.to_path_buf()),
+        _ => path.to_path_buf(),
+    }
+}
+
+fn parent_dir(path: &Path) -> &Path {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    }
+}
+
+fn write_atomic_with<F>(path: &Path, write: F) -> io::Result<()>
+where
+    F: FnOnce(&mut File) -> io::Result<()>,
+{
+    // Dropping the temporary file on any error below removes it again
+    let mut temp = temp_file_for(path)?;
+    write(temp.as_file_mut())?;
+    persist(temp, path)
+}
+
+/// Blocking counterpart of [`persist_atomic`]
+pub(crate) fn persist(temp: NamedTempFile, path: &Path) -> io::Result<()> {
+    let path = &resolve_target(path);
+    temp.as_file().sync_all()?;
+
+    let mut hard_linked = false;
+    if let Ok(metadata) = std::fs::metadata(path) {
+        temp.as_file().set_permissions(metadata.permissions())?;
+        preserve_owner(temp.path(), &metadata);
+        hard_linked = link_count(&metadata) > 1;
+    }
+
+    let copy_over = |temp: &Path| -> io::Result<()> {
+        std::fs::copy(temp, path)?;
+        File::open(path)?.sync_all()
+    };
+    if hard_linked {
+        // A rename would detach `path` from its other links; rewrite it in place
+        copy_over(temp.path())?;
+        return Ok(());
+    }
+    match temp.persist(path) {
+        Ok(_) => {}
+        Err(e) if e.error.kind() == io::ErrorKind::CrossesDevices => {
+            // Rename cannot cross filesystems; copy the flushed data instead
+            copy_over(e.file.path())?;
+        }
+        Err(e) => return Err(e.error),
+    }
+
+    sync_dir(parent_dir(path));
+    Ok(())
+}
+
+#[cfg(unix)]
+fn preserve_owner(temp_path: &Path, original: &std::fs::Metadata) {
+    use std::os::unix::fs::MetadataExt;
+
+    // Changing ownership usually requires privileges; keep the new owner otherwise
+    if let Err(e) = std::os::unix::fs::chown(temp_path, Some(original.uid()), Some(original.gid()))
+    {
+        tracing::debug!("Could not preserve ownership for {:?}: {}", temp_path, e);
+    }
+}
+
+#[cfg(not(unix))]
+fn preserve_owner(_temp_path: &Path, _original: &std::fs::Metadata) {}
+
+#[cfg(unix)]
+fn link_count(metadata: &std::fs::Metadata) -> u64 {
+    std::os::unix::fs::MetadataExt::nlink(metadata)
+}
+
+#[cfg(not(unix))]
+fn link_count(_metadata: &std::fs::Metadata) -> u64 {
+    1
+}
+
+/// Flush the directory entry so the rename itself survives a crash.
+#[cfg(unix)]
+fn sync_dir(dir: &Path) {
+    if let Err(e) = File::open(dir).and_then(|d| d.sync_all()) {
+        tracing::debug!("Failed to sync directory {:?}: {}", dir, e);
+    }
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn entries(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[tokio::test]
+    async fn test_write_atomic_replaces_contents() {
+        let temp_dir = TempDir::new().unwrap();
+        let file = temp_dir.path().join("main.rs");
+        std::fs::write(&file, "old").unwrap();
+
+        write_atomic(&file, b"new contents").await.unwrap();
+
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "new contents");
+        assert_eq!(entries(temp_dir.path()), vec!["main.rs"]);
+    }
+
+    #[test]
+    fn test_failed_write_leaves_original_untouched() {
+        let temp_dir = TempDir::new().unwrap();
+        let file = temp_dir.path().join("main.rs");
+        std::fs::write(&file, "original").unwrap();
+
+        let result = write_atomic_with(&file, |f| {
+            f.write_all(b"partial")?;
+            Err(io::Error::new(io::ErrorKind::StorageFull, "disk full"))
+        });
+
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::StorageFull);
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "original");
+        assert_eq!(entries(temp_dir.path()), vec!["main.rs"]);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_write_atomic_preserves_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = TempDir::new().unwrap();
+        let file = temp_dir.path().join("script.sh");
+        std::fs::write(&file, "echo old").unwrap();
+        std::fs::set_permissions(&file, std::fs::Permissions::from_mode(0o754)).unwrap();
+
+        write_atomic(&file, b"echo new").await.unwrap();
+
+        let mode = std::fs::metadata(&file).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o754);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_write_atomic_keeps_symlinks_and_hard_links() {
+        let temp_dir = TempDir::new().unwrap();
+        let target = temp_dir.path().join("real.rs");
+        let link = temp_dir.path().join("link.rs");
+        let hard = temp_dir.path().join("hard.rs");
+        std::fs::write(&target, "old").unwrap();
+        std::os::unix::fs::symlink("real.rs", &link).unwrap();
+        std::fs::hard_link(&target, &hard).unwrap();
+
+        write_atomic(&link, b"via symlink").await.unwrap();
+
+        assert!(std::fs::symlink_metadata(&link)
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        assert_eq!(std::fs::read_to_string(&target).unwrap(), "via symlink");
+        assert_eq!(std::fs::read_to_string(&hard).unwrap(), "via symlink");
+
+        write_atomic(&hard, b"via hard link").await.unwrap();
+
+        assert_eq!(std::fs::read_to_string(&target).unwrap(), "via hard link");
+        assert_eq!(
+            entries(temp_dir.path()),
+            vec!["hard.rs", "link.rs", "real.rs"]
+        );
+    }
+}
@@ -3,6 +3,7 @@
 // Licensed under the MIT License
 // See LICENSE file in the project root for full license information.
 
+use crate::utils::version;
 use crate::zeniths::registry::ZenithRegistry;
 use colored::*;
 use std::collections::HashMap;
@@ -15,13 +16,38 @@ pub struct ToolStatus {
     pub name: String,
     pub available: bool,
     pub version: Option<String>,
+    /// Minimum version declared by the formatter, if any
+    pub min_version: Option<String>,
     pub category: String,
 }
 
+impl ToolStatus {
+    /// The version number parsed from the `--version` output.
+    pub fn detected_version(&self) -> Option<&str> {
+        self.version.as_deref().and_then(version::extract_version)
+    }
+
+    /// Whether the installed tool is older than the declared minimum.
+    /// Versions that cannot be parsed are not flagged.
+    pub fn below_minimum(&self) -> bool {
+        let (Some(detected), Some(min)) = (self.detected_version(), &self.min_version) else {
+            return false;
+        };
+        match (
+            version::parse_version(detected),
+            version::parse_version(min),
+        ) {
+            (Some(detected), Some(min)) => detected < min,
+            _ => false,
+        }
+    }
+}
+
 pub struct DoctorSummary {
     pub total_tools: usize,
     pub available_tools: usize,
     pub missing_tools: usize,
+    pub outdated_tools: usize,
     pub categories: HashMap<String, CategorySummary>,
 }
 
@@ -32,7 +58,28 @@ pub struct CategorySummary {
 
 impl EnvironmentChecker {
     pub fn check_tool(tool: &str, category: &str) -> ToolStatus {
-        match Command::new(tool).arg("--version").output() {
+        // The python formatter runs ruff or black; report whichever are installed
+        if tool == "python" {
+            let versions: Vec<String> = ["ruff", "black"]
+                .into_iter()
+                .filter_map(|program| Self::check_tool(program, category).version)
+                .collect();
+            return ToolStatus {
+                name: tool.to_string(),
+                available: !versions.is_empty(),
+                version: (!versions.is_empty()).then(|| versions.join(", ")),
+                min_version: None,
+                category: category.to_string(),
+            };
+        }
+
+        // gofmt has no version flag; it ships with the go toolchain
+        let (program, args) = match tool {
+            "gofmt" => ("go", ["version"]),
+            "rust" => ("rustfmt", ["--version"]),
+            _ => (tool, ["--version"]),
+        };
+        match Command::new(program).args(args).output() {
             Ok(output) if output.status.success() => {
                 let version = String::from_utf8_lossy(&output.stdout)
                     .lines()
@@ -42,6 +89,7 @@ impl EnvironmentChecker {
                     name: tool.to_string(),
                     available: true,
                     version,
+                    min_version: None,
                     category: category.to_string(),
                 }
             }
@@ -49,21 +97,30 @@ impl EnvironmentChecker {
                 name: tool.to_string(),
                 available: false,
                 version: None,
+                min_version: None,
                 category: category.to_string(),
             },
         }
     }
 
-    pub fn check_all(registry: Arc<ZenithRegistry>) -> Vec<ToolStatus> {
-        let mut tool_categories: HashMap<String, String> = HashMap::new();
+    /// Availability comes from `Zenith::is_available`, so built-in
+    /// formatters count as available; the version is probed separately.
+    pub async fn check_all(registry: Arc<ZenithRegistry>) -> Vec<ToolStatus> {
+        let mut zeniths = HashMap::new();
         for zenith in registry.list_all() {
-            let category = Self::get_tool_category(zenith.name());
-            tool_categories.insert(zenith.name().to_string(), category);
+            zeniths.insert(zenith.name().to_string(), zenith);
         }
 
         let mut results = Vec::new();
-        for (tool, category) in tool_categories {
-            results.push(Self::check_tool(&tool, &category));
+        for (tool, zenith) in zeniths {
+            let category = Self::get_tool_category(&tool);
+            let mut status = Self::check_tool(&tool, &category);
+            status.available = zenith.is_available().await;
+            if !status.available {
+                status.version = None;
+            }
+            status.min_version = zenith.min_version().map(str::to_string);
+            results.push(status);
         }
         results.sort_by(|a, b| a.name.cmp(&b.name));
         results
@@ -75,10 +132,11 @@ impl EnvironmentChecker {
             "python" => "Python",
             "prettier" => "JavaScript/TypeScript",
             "clang" => "C/C++",
-            "java" => "Java",
-            "shell" => "Shell",
+            "java" | "google-java-format" => "Java",
+            "shell" | "shfmt" => "Shell",
             "toml" => "Configuration",
             "ini" => "Configuration",
+            "gofmt" => "Go",
             _ => "Other",
         }
         .to_string()
@@ -88,12 +146,16 @@ impl EnvironmentChecker {
         let mut categories: HashMap<String, CategorySummary> = HashMap::new();
         let mut total_tools = 0;
         let mut available_tools = 0;
+        let mut outdated_tools = 0;
 
         for result in results {
             total_tools += 1;
             if result.available {
                 available_tools += 1;
             }
+            if result.below_minimum() {
+                outdated_tools += 1;
+            }
 
             let category_summary =
                 categories
@@ -112,6 +174,7 @@ impl EnvironmentChecker {
             total_tools,
             available_tools,
             missing_tools: total_tools - available_tools,
+            outdated_tools,
             categories,
         }
     }
@@ -139,11 +202,16 @@ impl EnvironmentChecker {
             };
 
             print!("  {:<20} {}", res.name.bold(), status);
-            if let Some(v) = &res.version {
-                if verbose {
+            if verbose {
+                if let Some(v) = &res.version {
                     print!(" ({})", v.dimmed());
                 }
             }
+            if res.below_minimum() {
+                if let Some(min) = &res.min_version {
+                    print!(" {}", format!("⚠ requires >= {}", min).yellow());
+                }
+            }
             println!();
         }
 
@@ -161,6 +229,12 @@ impl EnvironmentChecker {
             "  Missing:        {}",
             summary.missing_tools.to_string().red()
         );
+        if summary.outdated_tools > 0 {
+            println!(
+                "  Outdated:       {}",
+                summary.outdated_tools.to_string().yellow()
+            );
+        }
 
         if !summary.categories.is_empty() {
             println!();
@@ -181,3 +255,34 @@ impl EnvironmentChecker {
         summary
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(version: Option<&str>, min_version: Option<&str>) -> ToolStatus {
+        ToolStatus {
+            name: "prettier".to_string(),
+            available: version.is_some(),
+            version: version.map(str::to_string),
+            min_version: min_version.map(str::to_string),
+            category: "JavaScript/TypeScript".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_below_minimum_compares_parsed_versions() {
+        assert!(status(Some("1.19.1"), Some("2.0.0")).below_minimum());
+        assert!(!status(Some("3.3.3"), Some("2.0.0")).below_minimum());
+        assert!(!status(Some("2.0.0"), Some("2.0.0")).below_minimum());
+        assert!(!status(Some("unknown"), Some("2.0.0")).below_minimum());
+        assert!(!status(Some("1.0.0"), None).below_minimum());
+        assert!(!status(None, Some("2.0.0")).below_minimum());
+
+        let summary = EnvironmentChecker::generate_summary(&[
+            status(Some("1.19.1"), Some("2.0.0")),
+            status(Some("3.3.3"), Some("2.0.0")),
+        ]);
+        assert_eq!(summary.outdated_tools, 1);
+    }
+}
@@ -3,7 +3,13 @@
 // Licensed under the MIT License
 // See LICENSE file in the project root for full license information.
 
+pub mod annotations;
+pub(crate) mod archive;
+pub(crate) mod atomic;
 pub(crate) mod directory;
 pub(crate) mod environment;
+pub mod git;
+pub(crate) mod normalize;
 pub mod path;
+pub(crate) mod range;
 pub(crate) mod version;
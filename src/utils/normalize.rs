@@ -0,0 +1,230 @@
+// Copyright (c) 2025 Kirky.X
+//
+// Licensed under the MIT License
+// See LICENSE file in the project root for full license information.
+
+//! Whitespace normalization shared by all file types.
+
+use crate::config::types::{FinalNewline, LineEnding, NormalizeConfig};
+
+/// Whether `content` looks like text that is safe to normalize.
+pub fn is_text(content: &[u8]) -> bool {
+    !content.contains(&0) && std::str::from_utf8(content).is_ok()
+}
+
+/// Whether `content` has a NUL byte within its first `limit` bytes, which
+/// marks it as binary whatever its extension says.
+pub fn looks_binary(content: &[u8], limit: usize) -> bool {
+    content[..content.len().min(limit)].contains(&0)
+}
+
+/// Byte order mark some editors put at the start of UTF-8 files.
+pub const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// `content` without its UTF-8 BOM, and whether it had one.
+pub fn strip_bom(content: &[u8]) -> (bool, &[u8]) {
+    match content.strip_prefix(UTF8_BOM) {
+        Some(body) => (true, body),
+        None => (false, content),
+    }
+}
+
+/// Whether `head`, the start of a file, is valid UTF-8 up to a character
+/// that may be cut off at its end.
+pub fn is_utf8_prefix(head: &[u8]) -> bool {
+    match std::str::from_utf8(head) {
+        Ok(_) => true,
+        Err(e) => e.error_len().is_none(),
+    }
+}
+
+/// The line ending used by most lines of `content`; `None` when it has no
+/// line breaks. Ties go to LF.
+pub fn detect_line_ending(content: &[u8]) -> Option<LineEnding> {
+    let lf = content.iter().filter(|&&b| b == b'\n').count();
+    let crlf = content.windows(2).filter(|w| w == b"\r\n").count();
+    match lf {
+        0 => None,
+        _ if crlf > lf - crlf => Some(LineEnding::Crlf),
+        _ => Some(LineEnding::Lf),
+    }
+}
+
+/// `content` with every line break turned into `target`. `Preserve` leaves it
+/// unchanged.
+pub fn convert_line_endings(content: Vec<u8>, target: LineEnding) -> Vec<u8> {
+    let crlf = match target {
+        LineEnding::Preserve => return content,
+        LineEnding::Lf if !content.contains(&b'\r') => return content,
+        LineEnding::Lf => false,
+        LineEnding::Crlf => true,
+    };
+    let mut output = Vec::with_capacity(content.len() + content.len() / 32);
+    let mut bytes = content.iter().peekable();
+    while let Some(&b) = bytes.next() {
+        match b {
+            b'\r' if bytes.peek() == Some(&&b'\n') => {}
+            b'\n' if crlf => output.extend_from_slice(b"\r\n"),
+            _ => output.push(b),
+        }
+    }
+    output
+}
+
+/// `content` with its final newline added or removed as `mode` asks. An empty
+/// file stays empty, and an added newline matches the file's line endings.
+pub fn apply_final_newline(mut content: Vec<u8>, mode: FinalNewline) -> Vec<u8> {
+    match mode {
+        FinalNewline::Ensure if !content.is_empty() && !content.ends_with(b"\n") => {
+            if detect_line_ending(&content) == Some(LineEnding::Crlf) {
+                content.push(b'\r');
+            }
+            content.push(b'\n');
+        }
+        FinalNewline::Remove => {
+            let len = content.len()
+                - content
+                    .iter()
+                    .rev()
+                    .take_while(|&&b| b == b'\n' || b == b'\r')
+                    .count();
+            content.truncate(len);
+        }
+        _ => {}
+    }
+    content
+}
+
+/// Apply the `[normalize]` rules to `text`.
+///
+/// Trailing spaces and tabs are stripped from every line and the file is made
+/// to end with exactly one newline. Line endings are kept as they are; an
+/// empty file stays empty.
+pub fn normalize_whitespace(text: &str, config: &NormalizeConfig) -> String {
+    if text.is_empty() {
+        return String::new();
+    }
+
+    let mut output = if config.trim_trailing_whitespace {
+        let mut trimmed = String::with_capacity(text.len());
+        for line in text.split_inclusive('\n') {
+            let (body, ending) = match line.strip_suffix("\r\n") {
+                Some(body) => (body, "\r\n"),
+                None => match line.strip_suffix('\n') {
+                    Some(body) => (body, "\n"),
+                    None => (line, ""),
+                },
+            };
+            trimmed.push_str(body.trim_end_matches([' ', '\t']));
+            trimmed.push_str(ending);
+        }
+        trimmed
+    } else {
+        text.to_string()
+    };
+
+    if config.insert_final_newline {
+        let newline = if text.contains("\r\n") { "\r\n" } else { "\n" };
+        let content_len = output.trim_end_matches(['\r', '\n']).len();
+        output.truncate(content_len);
+        output.push_str(newline);
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_bom_and_utf8_prefix() {
+        assert_eq!(strip_bom(b"\xEF\xBB\xBFkey = 1"), (true, &b"key = 1"[..]));
+        assert_eq!(strip_bom(b"key = 1"), (false, &b"key = 1"[..]));
+        // "é" cut after its first byte
+        assert!(is_utf8_prefix(b"caf\xC3"));
+        assert!(!is_utf8_prefix(b"caf\xE9 au lait"));
+    }
+
+    #[test]
+    fn test_detect_and_convert_line_endings() {
+        assert_eq!(detect_line_ending(b"a\r\nb\r\nc\n"), Some(LineEnding::Crlf));
+        assert_eq!(detect_line_ending(b"a\r\nb\n"), Some(LineEnding::Lf));
+        assert_eq!(detect_line_ending(b"a"), None);
+
+        let mixed = b"a\r\nb\nc\r".to_vec();
+        assert_eq!(
+            convert_line_endings(mixed.clone(), LineEnding::Lf),
+            b"a\nb\nc\r"
+        );
+        assert_eq!(
+            convert_line_endings(mixed.clone(), LineEnding::Crlf),
+            b"a\r\nb\r\nc\r"
+        );
+        assert_eq!(
+            convert_line_endings(mixed.clone(), LineEnding::Preserve),
+            mixed
+        );
+    }
+
+    #[test]
+    fn test_apply_final_newline() {
+        let ensure = |s: &str| apply_final_newline(s.into(), FinalNewline::Ensure);
+        assert_eq!(ensure("a"), b"a\n");
+        assert_eq!(ensure("a\n"), b"a\n");
+        assert_eq!(ensure("a\r\nb"), b"a\r\nb\r\n");
+        assert_eq!(ensure(""), b"");
+
+        let remove = |s: &str| apply_final_newline(s.into(), FinalNewline::Remove);
+        assert_eq!(remove("a\r\n\n"), b"a");
+        assert_eq!(remove("a"), b"a");
+        assert_eq!(remove(""), b"");
+
+        assert_eq!(
+            apply_final_newline("a\n\n".into(), FinalNewline::Preserve),
+            b"a\n\n"
+        );
+    }
+
+    #[test]
+    fn test_looks_binary_checks_only_the_head() {
+        assert!(looks_binary(b"{\"a\":\0}", 8));
+        assert!(!looks_binary(b"{\"a\": 1}", 8));
+        assert!(!looks_binary(b"0123456789\0", 8));
+        assert!(!looks_binary(b"\0", 0));
+    }
+
+    #[test]
+    fn test_trims_trailing_whitespace_and_fixes_eof() {
+        let config = NormalizeConfig::default();
+        let input = "fn main() {  \n\tlet x = 1;\t\n}\n\n\n";
+        assert_eq!(
+            normalize_whitespace(input, &config),
+            "fn main() {\n\tlet x = 1;\n}\n"
+        );
+        assert_eq!(normalize_whitespace("a", &config), "a\n");
+        assert_eq!(normalize_whitespace("", &config), "");
+    }
+
+    #[test]
+    fn test_preserves_crlf_line_endings() {
+        let config = NormalizeConfig::default();
+        assert_eq!(normalize_whitespace("a  \r\nb", &config), "a\r\nb\r\n");
+    }
+
+    #[test]
+    fn test_rules_can_be_disabled() {
+        let config = NormalizeConfig {
+            trim_trailing_whitespace: false,
+            insert_final_newline: false,
+        };
+        assert_eq!(normalize_whitespace("a  \nb", &config), "a  \nb");
+    }
+
+    #[test]
+    fn test_is_text() {
+        assert!(is_text(b"plain text"));
+        assert!(!is_text(b"\x00\x01binary"));
+        assert!(!is_text(&[0xff, 0xfe, 0x41]));
+    }
+}
@@ -4,7 +4,7 @@
 // See LICENSE file in the project root for full license information.
 
 use crate::error::{Result, ZenithError};
-use std::path::{Component, Path};
+use std::path::{Component, Path, PathBuf};
 
 pub fn validate_path(path: &Path) -> Result<()> {
     for component in path.components() {
@@ -15,6 +15,50 @@ pub fn validate_path(path: &Path) -> Result<()> {
     Ok(())
 }
 
+/// Ensure `path` resolves inside one of `roots`.
+///
+/// An empty `roots` list disables the check. Both sides are canonicalized so
+/// symlinks and relative segments cannot be used to escape an allowed root.
+pub fn validate_within_roots(path: &Path, roots: &[PathBuf]) -> Result<()> {
+    if roots.is_empty() {
+        return Ok(());
+    }
+
+    let canonical = path.canonicalize().map_err(|_| ZenithError::FileNotFound {
+        path: path.to_path_buf(),
+    })?;
+
+    let inside = roots.iter().any(|root| {
+        let root = root.canonicalize().unwrap_or_else(|_| root.clone());
+        canonical.starts_with(&root)
+    });
+
+    if inside {
+        Ok(())
+    } else {
+        Err(ZenithError::OutsideAllowedRoot(path.to_path_buf()))
+    }
+}
+
+/// Whether `path` lies under `root` as written but resolves outside it,
+/// i.e. a symlink along the way leads out of the tree.
+///
+/// Paths outside `root` to begin with, and paths that cannot be resolved,
+/// are not escapes.
+pub fn escapes_root(path: &Path, root: &Path) -> bool {
+    let (Ok(absolute), Ok(absolute_root)) = (std::path::absolute(path), std::path::absolute(root))
+    else {
+        return false;
+    };
+    if !absolute.starts_with(&absolute_root) {
+        return false;
+    }
+    match (path.canonicalize(), root.canonicalize()) {
+        (Ok(canonical), Ok(canonical_root)) => !canonical.starts_with(canonical_root),
+        _ => false,
+    }
+}
+
 pub fn validate_path_strict(path: &Path) -> Result<()> {
     validate_path(path)?;
 
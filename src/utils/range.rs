@@ -0,0 +1,194 @@
+// Copyright (c) 2025 Kirky.X
+//
+// Licensed under the MIT License
+// See LICENSE file in the project root for full license information.
+
+//! Restricting a whole-file format to a range of lines.
+//!
+//! Tools without native range support still format the whole file; the
+//! result is then diffed against the original line by line and only the
+//! changes that touch the range are kept. Formatters mostly move whitespace,
+//! so a block of changed lines is split further wherever the original and
+//! formatted lines end on the same non-whitespace character.
+//!
+//! Picking changes apart is only safe while they move whitespace. When the
+//! formatter also changed code, for example by reordering imports, and the
+//! kept changes leave the code matching neither the original nor the fully
+//! formatted text, the whole formatted file is returned instead.
+
+#[cfg(feature = "rust")]
+use similar::{DiffOp, TextDiff};
+
+/// Parse a `A:B` line range, 1-based and inclusive
+pub fn parse_line_range(value: &str) -> Result<(usize, usize), String> {
+    let (start, end) = value
+        .split_once(':')
+        .ok_or_else(|| format!("expected START:END, got '{}'", value))?;
+    let parse = |part: &str| {
+        part.trim()
+            .parse::<usize>()
+            .map_err(|_| format!("invalid line number '{}'", part))
+    };
+    let (start, end) = (parse(start)?, parse(end)?);
+    if start == 0 || end < start {
+        return Err(format!(
+            "invalid line range {}:{}; lines start at 1 and END must not be before START",
+            start, end
+        ));
+    }
+    Ok((start, end))
+}
+
+/// Keep only the changes from `original` to `formatted` that touch lines
+/// `start..=end` of `original`
+#[cfg(feature = "rust")]
+pub fn restrict_to_lines(original: &str, formatted: &str, (start, end): (usize, usize)) -> String {
+    // 1-based lines `first..=last` of the original overlap the range
+    let touches = |first: usize, last: usize| first <= end && last >= start;
+
+    let diff = TextDiff::from_lines(original, formatted);
+    let old = diff.old_slices();
+    let new = diff.new_slices();
+    let mut out = String::with_capacity(original.len());
+    for op in diff.ops() {
+        let chunks = match *op {
+            DiffOp::Replace {
+                old_index,
+                old_len,
+                new_index,
+                new_len,
+            } => split_replacement(
+                &old[old_index..old_index + old_len],
+                &new[new_index..new_index + new_len],
+            ),
+            _ => vec![(op.old_range().len(), op.new_range().len())],
+        };
+
+        let (mut old_at, mut new_at) = (op.old_range().start, op.new_range().start);
+        for (old_len, new_len) in chunks {
+            let keep_new = match *op {
+                DiffOp::Equal { .. } => false,
+                // An insertion sits between two original lines and touches both
+                _ if old_len == 0 => touches(old_at, old_at + 1),
+                _ => touches(old_at + 1, old_at + old_len),
+            };
+            let lines = if keep_new {
+                &new[new_at..new_at + new_len]
+            } else {
+                &old[old_at..old_at + old_len]
+            };
+            lines.iter().for_each(|line| out.push_str(line));
+            old_at += old_len;
+            new_at += new_len;
+        }
+    }
+
+    let code = visible(&out);
+    if code == visible(original) || code == visible(formatted) {
+        out
+    } else {
+        formatted.to_string()
+    }
+}
+
+/// The text with all whitespace removed
+#[cfg(feature = "rust")]
+fn visible(text: &str) -> String {
+    text.chars().filter(|c| !c.is_whitespace()).collect()
+}
+
+/// Split a block of replaced lines into `(old_len, new_len)` pieces that end
+/// where both sides have produced the same non-whitespace text
+#[cfg(feature = "rust")]
+fn split_replacement(old: &[&str], new: &[&str]) -> Vec<(usize, usize)> {
+    let mut chunks = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    let (mut chunk_i, mut chunk_j) = (0, 0);
+    let (mut old_text, mut new_text) = (String::new(), String::new());
+    while i < old.len() || j < new.len() {
+        if j == new.len() || (i < old.len() && old_text.len() <= new_text.len()) {
+            old_text.push_str(&visible(old[i]));
+            i += 1;
+        } else {
+            new_text.push_str(&visible(new[j]));
+            j += 1;
+        }
+        if i > chunk_i && j > chunk_j && !old_text.is_empty() && old_text == new_text {
+            chunks.push((i - chunk_i, j - chunk_j));
+            (chunk_i, chunk_j) = (i, j);
+            old_text.clear();
+            new_text.clear();
+        }
+    }
+    if i > chunk_i || j > chunk_j {
+        chunks.push((i - chunk_i, j - chunk_j));
+    }
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_line_range() {
+        assert_eq!(parse_line_range("3:7"), Ok((3, 7)));
+        assert_eq!(parse_line_range("5:5"), Ok((5, 5)));
+        assert!(parse_line_range("0:3").is_err());
+        assert!(parse_line_range("7:3").is_err());
+        assert!(parse_line_range("7").is_err());
+        assert!(parse_line_range("a:b").is_err());
+    }
+
+    #[cfg(feature = "rust")]
+    #[test]
+    fn test_restrict_to_lines_keeps_changes_outside_range() {
+        let original = "a =1\nb =2\nc =3\nd =4\n";
+        let formatted = "a = 1\nb = 2\nc = 3\nd = 4\n";
+        assert_eq!(
+            restrict_to_lines(original, formatted, (2, 3)),
+            "a =1\nb = 2\nc = 3\nd =4\n"
+        );
+        assert_eq!(restrict_to_lines(original, formatted, (1, 4)), formatted);
+        assert_eq!(restrict_to_lines(original, formatted, (9, 9)), original);
+    }
+
+    #[cfg(feature = "rust")]
+    #[test]
+    fn test_restrict_to_lines_splits_adjacent_changes() {
+        let original = "fn a( ){let x=1;}\nfn b( ){let y=2;}\n";
+        let formatted = "fn a() {\n    let x = 1;\n}\nfn b() {\n    let y = 2;\n}\n";
+        assert_eq!(
+            restrict_to_lines(original, formatted, (2, 2)),
+            "fn a( ){let x=1;}\nfn b() {\n    let y = 2;\n}\n"
+        );
+        assert_eq!(
+            restrict_to_lines(original, formatted, (1, 1)),
+            "fn a() {\n    let x = 1;\n}\nfn b( ){let y=2;}\n"
+        );
+    }
+
+    #[cfg(feature = "rust")]
+    #[test]
+    fn test_restrict_to_lines_falls_back_when_code_changes() {
+        let original = "use b;\nuse a;\n";
+        let formatted = "use a;\nuse b;\n";
+        assert_eq!(restrict_to_lines(original, formatted, (2, 2)), formatted);
+    }
+
+    #[cfg(feature = "rust")]
+    #[test]
+    fn test_restrict_to_lines_applies_split_and_joined_lines() {
+        // Line 2 is split into three lines, lines 4-5 are joined
+        let original = "keep\nf(){x}\nkeep\ng(\n)\n";
+        let formatted = "keep\nf() {\n    x\n}\nkeep\ng()\n";
+        assert_eq!(
+            restrict_to_lines(original, formatted, (2, 2)),
+            "keep\nf() {\n    x\n}\nkeep\ng(\n)\n"
+        );
+        assert_eq!(
+            restrict_to_lines(original, formatted, (4, 4)),
+            "keep\nf(){x}\nkeep\ng()\n"
+        );
+    }
+}
@@ -3,7 +3,9 @@
 // Licensed under the MIT License
 // See LICENSE file in the project root for full license information.
 
+#[cfg(any(feature = "rust", feature = "prettier"))]
 use crate::error::{Result, ZenithError};
+#[cfg(any(feature = "rust", feature = "prettier"))]
 use std::process::Command;
 
 pub fn parse_version(version_str: &str) -> Option<Vec<u32>> {
@@ -19,6 +21,20 @@ pub fn parse_version(version_str: &str) -> Option<Vec<u32>> {
     }
 }
 
+/// Extracts the version number from a `--version` line such as
+/// `rustfmt 1.8.0-stable (2025-01-01)` or `go version go1.22.1 linux/amd64`.
+pub fn extract_version(line: &str) -> Option<&str> {
+    line.split_whitespace().find_map(|token| {
+        let token = token.trim_start_matches(|c: char| !c.is_ascii_digit());
+        let end = token
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(token.len());
+        let version = token[..end].trim_end_matches('.');
+        version.contains('.').then_some(version)
+    })
+}
+
+#[cfg(any(feature = "rust", feature = "prettier"))]
 pub fn check_version(tool: &str, version_str: &str, min_version: &str) -> Result<()> {
     let current = parse_version(version_str).ok_or_else(|| ZenithError::ZenithFailed {
         name: tool.to_string(),
@@ -41,6 +57,7 @@ pub fn check_version(tool: &str, version_str: &str, min_version: &str) -> Result
     Ok(())
 }
 
+#[cfg(any(feature = "rust", feature = "prettier"))]
 pub fn get_tool_version(tool: &str) -> Result<String> {
     let output =
         Command::new(tool)
@@ -86,6 +103,21 @@ mod tests {
         assert_eq!(parse_version("invalid"), None);
     }
 
+    #[test]
+    fn test_extract_version() {
+        assert_eq!(
+            extract_version("rustfmt 1.8.0-stable (a1b2c3 2025-01-01)"),
+            Some("1.8.0")
+        );
+        assert_eq!(
+            extract_version("go version go1.22.1 linux/amd64"),
+            Some("1.22.1")
+        );
+        assert_eq!(extract_version("v3.3.3"), Some("3.3.3"));
+        assert_eq!(extract_version("prettier"), None);
+    }
+
+    #[cfg(any(feature = "rust", feature = "prettier"))]
     #[test]
     fn test_check_version_compatible() {
         assert!(check_version("test", "1.5.0", "1.0.0").is_ok());
@@ -93,6 +125,7 @@ mod tests {
         assert!(check_version("test", "1.5.0", "1.5.0").is_ok());
     }
 
+    #[cfg(any(feature = "rust", feature = "prettier"))]
     #[test]
     fn test_check_version_incompatible() {
         let result = check_version("test", "1.0.0", "1.5.0");
@@ -3,15 +3,137 @@
 // Licensed under the MIT License
 // See LICENSE file in the project root for full license information.
 
+use crate::config::types::{RetryPolicy, ZenithConfig};
 use crate::error::{Result, ZenithError};
 use crate::utils::path::sanitize_path_for_log;
+use std::io::ErrorKind;
 use std::path::Path;
 use std::process::Stdio;
 use std::time::Duration;
-use tokio::io::{AsyncWriteExt, BufWriter};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufWriter};
 use tokio::process::Command;
-use tokio::time::timeout;
-use tracing::{debug, error};
+use tokio::time::{sleep, timeout};
+use tracing::{debug, error, warn};
+
+/// Config file extensions that run code when a formatter loads them
+const EXECUTABLE_CONFIG_EXTENSIONS: &[&str] = &["js", "cjs", "mjs", "ts", "cts", "mts"];
+
+/// The config file to pass to a formatter
+///
+/// With `ZenithConfig::sandbox_config` set, config files that would run code
+/// when loaded (e.g. `prettier.config.js`) are refused.
+pub fn trusted_config_path(config: &ZenithConfig) -> Result<Option<&Path>> {
+    let Some(path) = config.custom_config_path.as_deref() else {
+        return Ok(None);
+    };
+    let executable = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| EXECUTABLE_CONFIG_EXTENSIONS.contains(&ext));
+    if config.sandbox_config && executable {
+        return Err(ZenithError::Config(format!(
+            "Refusing to load executable config {} with security.sandbox_external_config",
+            path.display()
+        )));
+    }
+    Ok(Some(path))
+}
+
+/// Arguments passing `config_path` to a tool with `flag`, none without a path
+///
+/// A flag ending in `=` or `:` (e.g. clang-format's `--style=file:`) is
+/// joined with the path into one argument; any other flag is followed by it.
+pub fn config_path_args(flag: &str, config_path: Option<&Path>) -> Vec<String> {
+    let Some(config_path) = config_path else {
+        return Vec::new();
+    };
+    let config_path = config_path.to_string_lossy();
+    if flag.ends_with(['=', ':']) {
+        vec![format!("{}{}", flag, config_path)]
+    } else {
+        vec![flag.to_string(), config_path.into_owned()]
+    }
+}
+
+/// Extra command-line arguments configured for a formatter (`args` in its settings)
+pub fn configured_args(config: &ZenithConfig) -> Vec<String> {
+    config
+        .zenith_specific
+        .get("args")
+        .and_then(|args| args.as_array())
+        .map(|args| {
+            args.iter()
+                .filter_map(|arg| arg.as_str().map(String::from))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Whether `program` can be started, probed with `--version`
+///
+/// Only a failure to spawn counts as missing; tools that reject the flag
+/// (such as `gofmt`) are still installed.
+pub async fn tool_installed(program: &str) -> bool {
+    Command::new(program)
+        .arg("--version")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .await
+        .is_ok()
+}
+
+/// Interpreter named by a `#!` line, without its directory
+///
+/// `#!/usr/bin/env bash` and `#!/usr/bin/env -S bash -e` both give `bash`.
+pub fn shebang_interpreter(first_line: &str) -> Option<&str> {
+    let mut words = first_line.strip_prefix("#!")?.split_whitespace();
+    let program = words.next()?.rsplit('/').next()?;
+    if program != "env" {
+        return Some(program);
+    }
+    words.find(|word| !word.starts_with('-') && !word.contains('='))
+}
+
+/// Stderr fragments that point at the environment rather than the input.
+///
+/// A failure matching one of these is worth retrying; anything else, such as
+/// a syntax error, would fail the same way again.
+const TRANSIENT_STDERR_PATTERNS: &[&str] = &[
+    "resource temporarily unavailable",
+    "eagain",
+    "device or resource busy",
+    "ebusy",
+    "text file busy",
+    "etxtbsy",
+    "too many open files",
+    "emfile",
+    "cannot allocate memory",
+    "connection reset",
+    "econnreset",
+    "broken pipe",
+    "interrupted system call",
+];
+
+/// Whether a failed run's stderr looks like a transient environment problem
+pub fn is_transient_stderr(stderr: &str) -> bool {
+    let stderr = stderr.to_lowercase();
+    TRANSIENT_STDERR_PATTERNS
+        .iter()
+        .any(|pattern| stderr.contains(pattern))
+}
+
+/// Reject empty output for input that had visible content
+///
+/// A tool that crashes quietly, or misreads its arguments, can exit 0 with
+/// nothing on stdout; writing that back would wipe the file.
+pub fn reject_empty_output(tool: &str, input: &[u8], output: Vec<u8>) -> Result<Vec<u8>> {
+    if output.is_empty() && !input.iter().all(u8::is_ascii_whitespace) {
+        return Err(ZenithError::FormatterEmptyOutput { tool: tool.into() });
+    }
+    Ok(output)
+}
 
 #[derive(Debug, Clone)]
 pub struct StdioFormatter {
@@ -19,6 +141,8 @@ pub struct StdioFormatter {
     pub args: Vec<String>,
     /// Optional timeout for command execution (in seconds)
     pub timeout_seconds: Option<u64>,
+    /// How often transient failures are retried, and how long to back off
+    pub retry: RetryPolicy,
 }
 
 impl Default for StdioFormatter {
@@ -27,6 +151,29 @@ impl Default for StdioFormatter {
             tool_name: "",
             args: Vec::new(),
             timeout_seconds: Some(30), // Default 30 second timeout
+            retry: RetryPolicy::default(),
+        }
+    }
+}
+
+/// A failed run, and whether running again might succeed
+struct Failure {
+    error: ZenithError,
+    transient: bool,
+}
+
+impl Failure {
+    fn transient(error: ZenithError) -> Self {
+        Self {
+            error,
+            transient: true,
+        }
+    }
+
+    fn permanent(error: ZenithError) -> Self {
+        Self {
+            error,
+            transient: false,
         }
     }
 }
@@ -38,6 +185,7 @@ impl StdioFormatter {
             tool_name,
             args,
             timeout_seconds: Some(30),
+            retry: RetryPolicy::default(),
         }
     }
 
@@ -53,13 +201,48 @@ impl StdioFormatter {
         self
     }
 
+    /// Retry transient failures according to `retry`
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
     /// Core implementation shared between format_with_stdio and format_with_stdio_no_path
+    ///
+    /// Runs the tool, retrying with exponential backoff while the failure
+    /// looks transient and retries remain.
     async fn execute_command(
         &self,
         content: &[u8],
         path: Option<&Path>,
         extra_args: Option<Vec<String>>,
     ) -> Result<Vec<u8>> {
+        let extra_args = extra_args.unwrap_or_default();
+        let mut attempt = 0;
+        loop {
+            match self.execute_once(content, path, &extra_args).await {
+                Ok(output) => return reject_empty_output(self.tool_name, content, output),
+                Err(failure) if failure.transient && attempt < self.retry.retries => {
+                    let delay = self.retry.delay(attempt);
+                    attempt += 1;
+                    warn!(
+                        "Formatter '{}' failed transiently ({}), retry {}/{} in {:?}",
+                        self.tool_name, failure.error, attempt, self.retry.retries, delay
+                    );
+                    sleep(delay).await;
+                }
+                Err(failure) => return Err(failure.error),
+            }
+        }
+    }
+
+    /// Run the tool once
+    async fn execute_once(
+        &self,
+        content: &[u8],
+        path: Option<&Path>,
+        extra_args: &[String],
+    ) -> std::result::Result<Vec<u8>, Failure> {
         let path_str = path.map(sanitize_path_for_log).unwrap_or_default();
         debug!(
             "Executing formatter '{}' with args: {:?}, extra_args: {:?}, path: {}",
@@ -74,10 +257,8 @@ impl StdioFormatter {
         }
 
         // Add extra arguments
-        if let Some(extra) = extra_args {
-            for arg in extra {
-                cmd.arg(arg);
-            }
+        for arg in extra_args {
+            cmd.arg(arg);
         }
 
         // Add path argument if provided
@@ -87,64 +268,59 @@ impl StdioFormatter {
 
         cmd.stdin(Stdio::piped())
             .stdout(Stdio::piped())
-            .stderr(Stdio::piped());
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
 
         let mut child = cmd.spawn().map_err(|e| {
             error!("Failed to spawn formatter '{}': {}", self.tool_name, e);
-            ZenithError::ToolNotFound {
-                tool: self.tool_name.into(),
+            match e.kind() {
+                // Missing or unusable binaries will not appear on a retry
+                ErrorKind::NotFound | ErrorKind::PermissionDenied => {
+                    Failure::permanent(ZenithError::ToolNotFound {
+                        tool: self.tool_name.into(),
+                    })
+                }
+                _ => Failure::transient(ZenithError::Io(e)),
             }
         })?;
 
-        // Write content to stdin
-        if let Some(mut stdin) = child.stdin.take() {
-            let mut writer = BufWriter::new(&mut stdin);
-            writer.write_all(content).await.map_err(|e| {
-                error!(
-                    "Failed to write to formatter '{}' stdin: {}",
-                    self.tool_name, e
-                );
-                ZenithError::Io(e)
-            })?;
-            writer.flush().await.map_err(|e| {
-                error!(
-                    "Failed to flush formatter '{}' stdin: {}",
-                    self.tool_name, e
-                );
-                ZenithError::Io(e)
-            })?;
-        }
-
-        // Execute command - always wait for output first
-        let output_result = child.wait_with_output().await;
-
-        // Apply timeout if configured
-        let output = match (self.timeout_seconds, output_result) {
-            (Some(timeout_secs), Ok(child_output)) => {
-                let duration = Duration::from_secs(timeout_secs);
-                match timeout(duration, async { Ok::<_, std::io::Error>(child_output) }).await {
-                    Ok(Ok(output)) => output,
-                    Ok(Err(e)) => {
-                        error!("Failed to wait for formatter '{}': {}", self.tool_name, e);
-                        return Err(ZenithError::Io(e));
-                    }
-                    Err(_) => {
-                        return Err(ZenithError::ZenithFailed {
-                            name: self.tool_name.into(),
-                            reason: format!("Command timed out after {} seconds", timeout_secs),
-                        });
-                    }
-                }
-            }
-            (Some(_), Err(e)) => {
-                error!("Failed to wait for formatter '{}': {}", self.tool_name, e);
-                return Err(ZenithError::Io(e));
+        let run = async {
+            // Write content to stdin
+            if let Some(mut stdin) = child.stdin.take() {
+                let mut writer = BufWriter::new(&mut stdin);
+                writer.write_all(content).await.map_err(|e| {
+                    error!(
+                        "Failed to write to formatter '{}' stdin: {}",
+                        self.tool_name, e
+                    );
+                    Failure::transient(ZenithError::Io(e))
+                })?;
+                writer.flush().await.map_err(|e| {
+                    error!(
+                        "Failed to flush formatter '{}' stdin: {}",
+                        self.tool_name, e
+                    );
+                    Failure::transient(ZenithError::Io(e))
+                })?;
             }
-            (None, Ok(output)) => output,
-            (None, Err(e)) => {
+
+            child.wait_with_output().await.map_err(|e| {
                 error!("Failed to wait for formatter '{}': {}", self.tool_name, e);
-                return Err(ZenithError::Io(e));
-            }
+                Failure::transient(ZenithError::Io(e))
+            })
+        };
+
+        // On timeout the child is dropped with the future and killed
+        let output = match self.timeout_seconds {
+            Some(timeout_secs) => timeout(Duration::from_secs(timeout_secs), run)
+                .await
+                .map_err(|_| {
+                    Failure::transient(ZenithError::FormatterTimeout {
+                        tool: self.tool_name.into(),
+                        seconds: timeout_secs,
+                    })
+                })??,
+            None => run.await?,
         };
 
         if output.status.success() {
@@ -162,13 +338,124 @@ impl StdioFormatter {
                 output.status.code(),
                 stderr
             );
-            Err(ZenithError::ZenithFailed {
-                name: self.tool_name.into(),
-                reason: stderr.to_string(),
+            // No exit code means the process was killed by a signal
+            let transient = output.status.code().is_none() || is_transient_stderr(&stderr);
+            Err(Failure {
+                error: ZenithError::ZenithFailed {
+                    name: self.tool_name.into(),
+                    reason: stderr.to_string(),
+                },
+                transient,
             })
         }
     }
 
+    /// Pipe `input` through the tool into `output` without buffering it
+    ///
+    /// The tool runs once: by the time a failure shows, the input has been
+    /// consumed, so transient failures are not retried. `path` is appended
+    /// to the arguments as in [`Self::format_with_stdio`].
+    pub async fn format_stream(
+        &self,
+        input: &mut (dyn AsyncRead + Unpin + Send),
+        output: &mut (dyn AsyncWrite + Unpin + Send),
+        path: Option<&Path>,
+    ) -> Result<()> {
+        debug!(
+            "Streaming through formatter '{}' with args: {:?}, path: {}",
+            self.tool_name,
+            self.args,
+            path.map(sanitize_path_for_log).unwrap_or_default()
+        );
+
+        let mut cmd = Command::new(self.tool_name);
+        cmd.args(&self.args);
+        if let Some(p) = path {
+            cmd.arg(p);
+        }
+        let mut child = cmd
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| match e.kind() {
+                ErrorKind::NotFound | ErrorKind::PermissionDenied => ZenithError::ToolNotFound {
+                    tool: self.tool_name.into(),
+                },
+                _ => ZenithError::Io(e),
+            })?;
+        let (Some(mut stdin), Some(mut stdout), Some(mut stderr)) =
+            (child.stdin.take(), child.stdout.take(), child.stderr.take())
+        else {
+            return Err(ZenithError::ZenithFailed {
+                name: self.tool_name.into(),
+                reason: "stdio not captured".into(),
+            });
+        };
+
+        // Feed stdin while draining stdout and stderr, so neither pipe can
+        // fill up and stall the tool
+        let feed = async move {
+            let mut blank = true;
+            let mut buf = vec![0; 64 * 1024];
+            loop {
+                let n = input.read(&mut buf).await?;
+                if n == 0 {
+                    break;
+                }
+                blank &= buf[..n].iter().all(u8::is_ascii_whitespace);
+                match stdin.write_all(&buf[..n]).await {
+                    // The tool stopped reading; its exit status tells why
+                    Err(e) if e.kind() == ErrorKind::BrokenPipe => break,
+                    result => result?,
+                }
+            }
+            drop(stdin);
+            Ok::<_, std::io::Error>(blank)
+        };
+        let run = async {
+            let mut stderr_buf = Vec::new();
+            let (blank, written, _, status) = tokio::try_join!(
+                feed,
+                tokio::io::copy(&mut stdout, output),
+                stderr.read_to_end(&mut stderr_buf),
+                child.wait(),
+            )?;
+            output.flush().await?;
+            Ok::<_, std::io::Error>((blank, written, stderr_buf, status))
+        };
+        let (blank, written, stderr_buf, status) = match self.timeout_seconds {
+            Some(secs) => timeout(Duration::from_secs(secs), run)
+                .await
+                .map_err(|_| ZenithError::FormatterTimeout {
+                    tool: self.tool_name.into(),
+                    seconds: secs,
+                })??,
+            None => run.await?,
+        };
+
+        if !status.success() {
+            let stderr = String::from_utf8_lossy(&stderr_buf);
+            error!(
+                "Formatter '{}' failed with exit code: {:?}, stderr: {}",
+                self.tool_name,
+                status.code(),
+                stderr
+            );
+            return Err(ZenithError::ZenithFailed {
+                name: self.tool_name.into(),
+                reason: stderr.into_owned(),
+            });
+        }
+        if written == 0 && !blank {
+            return Err(ZenithError::FormatterEmptyOutput {
+                tool: self.tool_name.into(),
+            });
+        }
+        Ok(())
+    }
+
     pub async fn format_with_stdio(
         &self,
         content: &[u8],
@@ -186,4 +473,211 @@ impl StdioFormatter {
     ) -> Result<Vec<u8>> {
         self.execute_command(content, None, extra_args).await
     }
+
+    /// Run the tool with `config_path`, if any, passed with `flag` as built
+    /// by [`config_path_args`]; `path` is appended as in
+    /// [`Self::format_with_stdio`] when given.
+    pub async fn format_with_config_path(
+        &self,
+        content: &[u8],
+        path: Option<&Path>,
+        config_path: Option<&Path>,
+        flag: &str,
+    ) -> Result<Vec<u8>> {
+        self.execute_command(content, path, Some(config_path_args(flag, config_path)))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_shebang_interpreter() {
+        assert_eq!(shebang_interpreter("#!/bin/bash"), Some("bash"));
+        assert_eq!(shebang_interpreter("#! /bin/sh -e"), Some("sh"));
+        assert_eq!(shebang_interpreter("#!/usr/bin/env bash"), Some("bash"));
+        assert_eq!(
+            shebang_interpreter("#!/usr/bin/env -S LANG=C python3 -u"),
+            Some("python3")
+        );
+        assert_eq!(shebang_interpreter("echo hi"), None);
+        assert_eq!(shebang_interpreter("#!"), None);
+    }
+
+    /// Write an executable shell script and return its path as a tool name
+    fn script(dir: &TempDir, body: &str) -> &'static str {
+        let path = dir.path().join("tool.sh");
+        std::fs::write(&path, format!("#!/bin/sh\n{}", body)).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        Box::leak(path.to_string_lossy().into_owned().into_boxed_str())
+    }
+
+    fn retrying(tool_name: &'static str, retries: u32) -> StdioFormatter {
+        StdioFormatter::new(tool_name, Vec::new()).with_retry(RetryPolicy {
+            retries,
+            base_delay_ms: 1,
+        })
+    }
+
+    #[test]
+    fn test_retry_delay_backs_off_exponentially() {
+        let policy = RetryPolicy {
+            retries: 3,
+            base_delay_ms: 100,
+        };
+        assert_eq!(policy.delay(0), Duration::from_millis(100));
+        assert_eq!(policy.delay(2), Duration::from_millis(400));
+        assert_eq!(policy.delay(64), Duration::from_secs(10));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_transient_failure_is_retried() {
+        let dir = TempDir::new().unwrap();
+        let marker = dir.path().join("failed-once");
+        let tool = script(
+            &dir,
+            &format!(
+                "if [ -f '{m}' ]; then cat; else cat > /dev/null; touch '{m}'; \
+                 echo 'Resource temporarily unavailable' >&2; exit 1; fi\n",
+                m = marker.display()
+            ),
+        );
+
+        let result = retrying(tool, 0)
+            .format_with_stdio_no_path(b"x = 1\n", Path::new("a.txt"), None)
+            .await;
+        assert!(result.is_err());
+
+        std::fs::remove_file(&marker).unwrap();
+        let output = retrying(tool, 2)
+            .format_with_stdio_no_path(b"x = 1\n", Path::new("a.txt"), None)
+            .await
+            .unwrap();
+        assert_eq!(output, b"x = 1\n");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_deterministic_failure_is_not_retried() {
+        let dir = TempDir::new().unwrap();
+        let runs = dir.path().join("runs");
+        let tool = script(
+            &dir,
+            &format!(
+                "cat > /dev/null; echo run >> '{}'; echo 'syntax error at line 1' >&2; exit 2\n",
+                runs.display()
+            ),
+        );
+
+        let err = retrying(tool, 3)
+            .format_with_stdio_no_path(b"{", Path::new("a.txt"), None)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("syntax error"));
+        assert_eq!(std::fs::read_to_string(&runs).unwrap().lines().count(), 1);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_hanging_tool_times_out() {
+        let dir = TempDir::new().unwrap();
+        let tool = script(&dir, "sleep 30\n");
+
+        let started = std::time::Instant::now();
+        let err = retrying(tool, 0)
+            .with_timeout(1)
+            .format_with_stdio_no_path(b"x = 1\n", Path::new("a.txt"), None)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            ZenithError::FormatterTimeout { seconds: 1, .. }
+        ));
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_format_stream_pipes_through_tool() {
+        let dir = TempDir::new().unwrap();
+        let tool = script(&dir, "tr a-z A-Z\n");
+        let input = "abc\n".repeat(100_000);
+
+        let mut output = Vec::new();
+        StdioFormatter::new(tool, Vec::new())
+            .format_stream(&mut input.as_bytes(), &mut output, None)
+            .await
+            .unwrap();
+        assert_eq!(output, input.to_uppercase().into_bytes());
+
+        let failing = script(&dir, "echo 'bad input' >&2; exit 1\n");
+        let err = StdioFormatter::new(failing, Vec::new())
+            .format_stream(&mut input.as_bytes(), &mut Vec::new(), None)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("bad input"));
+    }
+
+    #[test]
+    fn test_config_path_args() {
+        let config_path = Some(Path::new("/repo/.clang-format"));
+        assert_eq!(
+            config_path_args("--config-path", config_path),
+            ["--config-path", "/repo/.clang-format"]
+        );
+        assert_eq!(
+            config_path_args("--style=file:", config_path),
+            ["--style=file:/repo/.clang-format"]
+        );
+        assert!(config_path_args("--config", None).is_empty());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_format_with_config_path_passes_the_flag() {
+        let dir = TempDir::new().unwrap();
+        let tool = script(&dir, "cat > /dev/null; echo \"$@\"\n");
+        let formatter = StdioFormatter::new(tool, vec!["--quiet".into()]);
+
+        let output = formatter
+            .format_with_config_path(
+                b"x",
+                Some(Path::new("a.txt")),
+                Some(Path::new("/repo/fmt.toml")),
+                "--config",
+            )
+            .await
+            .unwrap();
+        assert_eq!(output, b"--quiet --config /repo/fmt.toml a.txt\n");
+
+        let output = formatter
+            .format_with_config_path(b"x", None, None, "--config")
+            .await
+            .unwrap();
+        assert_eq!(output, b"--quiet\n");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_empty_output_for_non_empty_input_is_rejected() {
+        let dir = TempDir::new().unwrap();
+        let tool = script(&dir, "cat > /dev/null\n");
+
+        let err = retrying(tool, 0)
+            .format_with_stdio_no_path(b"x = 1\n", Path::new("a.txt"), None)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ZenithError::FormatterEmptyOutput { .. }));
+
+        let output = retrying(tool, 0)
+            .format_with_stdio_no_path(b"\n  \n", Path::new("a.txt"), None)
+            .await
+            .unwrap();
+        assert!(output.is_empty());
+    }
 }
@@ -6,12 +6,52 @@
 use crate::config::types::ZenithConfig;
 use crate::core::traits::Zenith;
 use crate::error::Result;
-use crate::zeniths::common::StdioFormatter;
+use crate::zeniths::common::{config_path_args, configured_args, tool_installed, StdioFormatter};
 use async_trait::async_trait;
 use std::path::Path;
+use tokio::io::{AsyncRead, AsyncWrite};
 
+/// C/C++ formatter backed by clang-format.
+///
+/// Content is piped through stdin with `--assume-filename`, so clang-format
+/// picks the language from the extension. A discovered `.clang-format` is
+/// passed explicitly with `--style=file:<path>`; otherwise clang-format looks
+/// for one next to the file itself. `style` in `ZenithConfig::zenith_specific`
+/// (`LLVM`, `Google`, `Chromium`, `Mozilla`, `WebKit`, `GNU`, ...) sets the
+/// style used when no `.clang-format` exists. Line ranges are passed to
+/// clang-format as `--lines`.
 pub struct ClangZenith;
 
+impl ClangZenith {
+    fn style_args(config: &ZenithConfig) -> Vec<String> {
+        let mut args = config_path_args("--style=file:", config.custom_config_path.as_deref());
+        if let Some(style) = config
+            .zenith_specific
+            .get("style")
+            .and_then(serde_json::Value::as_str)
+        {
+            args.push(format!("--fallback-style={}", style));
+        }
+        args
+    }
+
+    fn formatter(config: &ZenithConfig) -> StdioFormatter {
+        let mut args = Self::style_args(config);
+        if let Some((start, end)) = config.range {
+            args.push(format!("--lines={}:{}", start, end));
+        }
+        args.extend(configured_args(config));
+        args.push("--assume-filename".into());
+
+        StdioFormatter {
+            tool_name: "clang-format",
+            args,
+            timeout_seconds: None,
+            retry: config.retry,
+        }
+    }
+}
+
 #[async_trait]
 impl Zenith for ClangZenith {
     fn name(&self) -> &str {
@@ -22,12 +62,57 @@ impl Zenith for ClangZenith {
         &["c", "cpp", "cc", "h", "hpp"]
     }
 
-    async fn format(&self, content: &[u8], path: &Path, _config: &ZenithConfig) -> Result<Vec<u8>> {
-        let formatter = StdioFormatter {
-            tool_name: "clang-format",
-            args: vec!["--assume-filename".into()],
-            timeout_seconds: None,
+    fn spawns_process(&self) -> bool {
+        true
+    }
+
+    async fn is_available(&self) -> bool {
+        tool_installed("clang-format").await
+    }
+
+    fn supports_range(&self) -> bool {
+        true
+    }
+
+    async fn format(&self, content: &[u8], path: &Path, config: &ZenithConfig) -> Result<Vec<u8>> {
+        Self::formatter(config)
+            .format_with_stdio(content, path, None)
+            .await
+    }
+
+    async fn format_stream(
+        &self,
+        input: &mut (dyn AsyncRead + Unpin + Send),
+        output: &mut (dyn AsyncWrite + Unpin + Send),
+        path: &Path,
+        config: &ZenithConfig,
+    ) -> Result<()> {
+        Self::formatter(config)
+            .format_stream(input, output, Some(path))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_style_args() {
+        assert!(ClangZenith::style_args(&ZenithConfig::default()).is_empty());
+
+        let config = ZenithConfig {
+            custom_config_path: Some(PathBuf::from("/repo/.clang-format")),
+            zenith_specific: serde_json::json!({ "style": "Google" }),
+            ..Default::default()
         };
-        formatter.format_with_stdio(content, path, None).await
+        assert_eq!(
+            ClangZenith::style_args(&config),
+            [
+                "--style=file:/repo/.clang-format",
+                "--fallback-style=Google"
+            ]
+        );
     }
 }
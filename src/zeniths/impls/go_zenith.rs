@@ -0,0 +1,62 @@
+// Copyright (c) 2025 Kirky.X
+//
+// Licensed under the MIT License
+// See LICENSE file in the project root for full license information.
+
+use crate::config::types::ZenithConfig;
+use crate::core::traits::Zenith;
+use crate::error::{Result, ZenithError};
+use crate::zeniths::common::{configured_args, tool_installed, StdioFormatter};
+use async_trait::async_trait;
+use std::path::Path;
+
+/// Go formatter.
+///
+/// Uses `goimports` when it is installed, which formats like `gofmt` and also
+/// fixes the import block, and falls back to plain `gofmt` otherwise.
+pub struct GoZenith;
+
+#[async_trait]
+impl Zenith for GoZenith {
+    fn name(&self) -> &str {
+        "gofmt"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["go"]
+    }
+
+    fn spawns_process(&self) -> bool {
+        true
+    }
+
+    async fn is_available(&self) -> bool {
+        tool_installed("goimports").await || tool_installed("gofmt").await
+    }
+
+    async fn format(&self, content: &[u8], path: &Path, config: &ZenithConfig) -> Result<Vec<u8>> {
+        let args = configured_args(config);
+
+        let goimports = StdioFormatter {
+            tool_name: "goimports",
+            args: args.clone(),
+            timeout_seconds: None,
+            retry: config.retry,
+        };
+        match goimports
+            .format_with_stdio_no_path(content, path, None)
+            .await
+        {
+            Err(ZenithError::ToolNotFound { .. }) => {}
+            result => return result,
+        }
+
+        let gofmt = StdioFormatter {
+            tool_name: "gofmt",
+            args,
+            timeout_seconds: None,
+            retry: config.retry,
+        };
+        gofmt.format_with_stdio_no_path(content, path, None).await
+    }
+}
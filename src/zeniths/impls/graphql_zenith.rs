@@ -0,0 +1,119 @@
+// Copyright (c) 2025 Kirky.X
+//
+// Licensed under the MIT License
+// See LICENSE file in the project root for full license information.
+
+use crate::config::types::ZenithConfig;
+use crate::core::traits::Zenith;
+use crate::error::{Result, ZenithError};
+use async_trait::async_trait;
+use std::path::Path;
+
+/// Native GraphQL formatter for schemas and executable documents.
+///
+/// Documents are parsed with `graphql-parser` and pretty-printed with two-space
+/// indentation; descriptions and directives are kept. The parser drops `#`
+/// comments, so documents containing comments are rejected instead of being
+/// silently stripped.
+pub struct GraphqlZenith;
+
+impl GraphqlZenith {
+    fn format_document(text: &str) -> Result<String> {
+        if has_comments(text) {
+            return Err(ZenithError::ZenithFailed {
+                name: "graphql".into(),
+                reason: "document contains comments, which would be lost when formatting".into(),
+            });
+        }
+
+        let formatted = match graphql_parser::parse_query::<String>(text) {
+            Ok(document) => document.to_string(),
+            Err(query_err) => match graphql_parser::parse_schema::<String>(text) {
+                Ok(document) => document.to_string(),
+                Err(schema_err) => {
+                    return Err(ZenithError::ZenithFailed {
+                        name: "graphql".into(),
+                        reason: format!(
+                            "not a valid executable document ({}) or schema ({})",
+                            query_err.to_string().trim(),
+                            schema_err.to_string().trim()
+                        ),
+                    });
+                }
+            },
+        };
+
+        let mut formatted = formatted.trim_end().to_string();
+        formatted.push('\n');
+        Ok(formatted)
+    }
+}
+
+/// Whether `text` contains a `#` comment outside of string literals.
+fn has_comments(text: &str) -> bool {
+    let mut chars = text.chars().peekable();
+    let mut in_string = false;
+    let mut in_block_string = false;
+
+    while let Some(c) = chars.next() {
+        if in_block_string {
+            if c == '\\' && text_starts_with(&mut chars, "\"\"\"") {
+                continue;
+            }
+            if c == '"' && text_starts_with(&mut chars, "\"\"") {
+                in_block_string = false;
+            }
+        } else if in_string {
+            match c {
+                '\\' => {
+                    chars.next();
+                }
+                '"' | '\n' => in_string = false,
+                _ => {}
+            }
+        } else {
+            match c {
+                '#' => return true,
+                '"' if text_starts_with(&mut chars, "\"\"") => in_block_string = true,
+                '"' => in_string = true,
+                _ => {}
+            }
+        }
+    }
+
+    false
+}
+
+/// Consume `pattern` from `chars` if it comes next.
+fn text_starts_with(chars: &mut std::iter::Peekable<std::str::Chars<'_>>, pattern: &str) -> bool {
+    let upcoming: String = chars.clone().take(pattern.len()).collect();
+    if upcoming == pattern {
+        for _ in 0..pattern.len() {
+            chars.next();
+        }
+        true
+    } else {
+        false
+    }
+}
+
+#[async_trait]
+impl Zenith for GraphqlZenith {
+    fn name(&self) -> &str {
+        "graphql"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["graphql", "gql"]
+    }
+
+    async fn format(
+        &self,
+        content: &[u8],
+        _path: &Path,
+        _config: &ZenithConfig,
+    ) -> Result<Vec<u8>> {
+        let text = String::from_utf8(content.to_vec())?;
+        Ok(Self::format_document(&text)?.into_bytes())
+    }
+}
@@ -9,51 +9,194 @@ use crate::error::Result;
 use async_trait::async_trait;
 use std::path::Path;
 
+/// Native INI formatter.
+///
+/// Whitespace around `=` is trimmed, runs of blank lines are collapsed to one
+/// and every section header is preceded by a single blank line. Comment lines
+/// (`;` or `#`) stay attached to the entry or section that follows them.
+///
+/// Two options can be set through `ZenithConfig::zenith_specific`:
+/// `sort_keys` sorts the entries within each section and `sort_sections` sorts
+/// the sections by name. Both sorts are stable, so duplicate keys and sections
+/// keep their relative order, and entries before the first section stay first.
 pub struct IniZenith;
 
-#[async_trait]
-impl Zenith for IniZenith {
-    fn name(&self) -> &str {
-        "ini"
+#[derive(Debug, Default, Clone, Copy)]
+struct IniOptions {
+    sort_keys: bool,
+    sort_sections: bool,
+}
+
+impl IniOptions {
+    fn from_config(config: &ZenithConfig) -> Self {
+        let flag = |name: &str| {
+            config
+                .zenith_specific
+                .get(name)
+                .and_then(serde_json::Value::as_bool)
+                .unwrap_or(false)
+        };
+        Self {
+            sort_keys: flag("sort_keys"),
+            sort_sections: flag("sort_sections"),
+        }
     }
+}
 
-    fn extensions(&self) -> &[&str] {
-        &["ini", "conf"]
+struct Entry {
+    comments: Vec<String>,
+    blank_before: bool,
+    key: String,
+    line: String,
+}
+
+#[derive(Default)]
+struct Section {
+    comments: Vec<String>,
+    header: Option<String>,
+    entries: Vec<Entry>,
+}
+
+struct IniDocument {
+    /// The first section holds the entries before any header
+    sections: Vec<Section>,
+    /// Comments after the last entry of the file
+    trailing: Vec<String>,
+}
+
+impl Section {
+    fn name(&self) -> &str {
+        self.header
+            .as_deref()
+            .map(|h| h.trim_start_matches('[').trim_end_matches(']').trim())
+            .unwrap_or("")
     }
+}
 
-    async fn format(
-        &self,
-        content: &[u8],
-        _path: &Path,
-        _config: &ZenithConfig,
-    ) -> Result<Vec<u8>> {
-        let text = String::from_utf8_lossy(content);
-        let mut result = String::new();
+impl IniZenith {
+    fn parse(text: &str) -> IniDocument {
+        let mut sections = vec![Section::default()];
+        let mut comments = Vec::new();
+        let mut blank_before = false;
 
         for line in text.lines() {
             let trimmed = line.trim();
+            let current = sections.last_mut().expect("at least the global section");
+
             if trimmed.is_empty() {
-                result.push('\n');
-                continue;
+                blank_before = !current.entries.is_empty() || !comments.is_empty();
+                if !comments.is_empty() {
+                    comments.push(String::new());
+                }
+            } else if trimmed.starts_with(';') || trimmed.starts_with('#') {
+                comments.push(trimmed.to_string());
+            } else if trimmed.starts_with('[') && trimmed.ends_with(']') {
+                sections.push(Section {
+                    comments: take_comments(&mut comments),
+                    header: Some(trimmed.to_string()),
+                    ..Default::default()
+                });
+                blank_before = false;
+            } else {
+                let (key, line) = match trimmed.split_once('=') {
+                    Some((key, value)) => {
+                        let key = key.trim();
+                        let value = value.trim();
+                        let line = if value.is_empty() {
+                            format!("{} =", key)
+                        } else {
+                            format!("{} = {}", key, value)
+                        };
+                        (key.to_string(), line)
+                    }
+                    None => (trimmed.to_string(), trimmed.to_string()),
+                };
+                current.entries.push(Entry {
+                    comments: take_comments(&mut comments),
+                    blank_before,
+                    key,
+                    line,
+                });
+                blank_before = false;
             }
+        }
 
-            if trimmed.starts_with('[') && trimmed.ends_with(']') {
-                // Section
-                result.push_str(trimmed);
-                result.push('\n');
-            } else if trimmed.contains('=') {
-                // Key-value pair
-                let parts: Vec<&str> = trimmed.splitn(2, '=').collect();
-                let key = parts[0].trim();
-                let value = parts[1].trim();
-                result.push_str(&format!("{} = {}\n", key, value));
-            } else {
-                // Comment or other
-                result.push_str(trimmed);
-                result.push('\n');
+        IniDocument {
+            sections,
+            trailing: take_comments(&mut comments),
+        }
+    }
+
+    fn render(document: IniDocument, options: IniOptions) -> String {
+        let IniDocument {
+            mut sections,
+            trailing,
+        } = document;
+        if options.sort_sections {
+            // The global section has no header and must stay in front
+            sections[1..].sort_by(|a, b| a.name().cmp(b.name()));
+        }
+
+        let mut output = String::new();
+        for section in &mut sections {
+            if options.sort_keys {
+                section.entries.sort_by(|a, b| a.key.cmp(&b.key));
+            }
+
+            if let Some(header) = &section.header {
+                if !output.is_empty() {
+                    output.push('\n');
+                }
+                push_lines(&mut output, &section.comments);
+                output.push_str(header);
+                output.push('\n');
+            }
+
+            for (i, entry) in section.entries.iter().enumerate() {
+                // Blank lines separate groups, which sorting does not preserve
+                if entry.blank_before && i > 0 && !options.sort_keys {
+                    output.push('\n');
+                }
+                push_lines(&mut output, &entry.comments);
+                output.push_str(&entry.line);
+                output.push('\n');
             }
         }
+        push_lines(&mut output, &trailing);
+        output
+    }
+}
+
+/// Take the pending comment block, dropping blank lines around it.
+fn take_comments(comments: &mut Vec<String>) -> Vec<String> {
+    let mut block = std::mem::take(comments);
+    while block.last().is_some_and(|line| line.is_empty()) {
+        block.pop();
+    }
+    block.dedup_by(|a, b| a.is_empty() && b.is_empty());
+    block
+}
 
-        Ok(result.into_bytes())
+fn push_lines(output: &mut String, lines: &[String]) {
+    for line in lines {
+        output.push_str(line);
+        output.push('\n');
+    }
+}
+
+#[async_trait]
+impl Zenith for IniZenith {
+    fn name(&self) -> &str {
+        "ini"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["ini", "conf"]
+    }
+
+    async fn format(&self, content: &[u8], _path: &Path, config: &ZenithConfig) -> Result<Vec<u8>> {
+        let text = String::from_utf8_lossy(content);
+        let document = Self::parse(&text);
+        Ok(Self::render(document, IniOptions::from_config(config)).into_bytes())
     }
 }
@@ -6,12 +6,80 @@
 use crate::config::types::ZenithConfig;
 use crate::core::traits::Zenith;
 use crate::error::Result;
-use crate::zeniths::common::StdioFormatter;
+use crate::zeniths::common::{configured_args, tool_installed, StdioFormatter};
 use async_trait::async_trait;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
+/// Environment variable naming a google-java-format jar, run with `java -jar`
+/// when the `google-java-format` launcher is not installed.
+pub const JAR_ENV: &str = "GOOGLE_JAVA_FORMAT_JAR";
+
+/// Java formatter backed by google-java-format.
+///
+/// Content is piped through stdin. The tool is run directly when installed,
+/// otherwise as `java -jar` on the jar named by `jar` in
+/// `ZenithConfig::zenith_specific` or by [`JAR_ENV`].
+///
+/// A discovered `.google-java-format` file lists extra flags, one or more
+/// per line; `key=value` lines such as `style=aosp` are read as options.
+/// `style` in `zenith_specific` (`google` or `aosp`) wins over the file.
 pub struct JavaZenith;
 
+#[derive(Debug, Default, PartialEq, Eq)]
+struct JavaOptions {
+    aosp: bool,
+    args: Vec<String>,
+}
+
+impl JavaOptions {
+    /// Flags and `key=value` options from a discovered config file
+    fn parse_config_file(text: &str) -> Self {
+        let mut options = Self::default();
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            match line.split_once('=') {
+                Some((key, value)) if !key.starts_with('-') => {
+                    if key.trim() == "style" {
+                        options.aosp = value.trim().eq_ignore_ascii_case("aosp");
+                    }
+                }
+                _ => options
+                    .args
+                    .extend(line.split_whitespace().map(String::from)),
+            }
+        }
+        options
+    }
+
+    async fn from_config(config: &ZenithConfig) -> Result<Self> {
+        let mut options = match &config.custom_config_path {
+            Some(path) => Self::parse_config_file(&tokio::fs::read_to_string(path).await?),
+            None => Self::default(),
+        };
+        if let Some(style) = config
+            .zenith_specific
+            .get("style")
+            .and_then(serde_json::Value::as_str)
+        {
+            options.aosp = style.eq_ignore_ascii_case("aosp");
+        }
+        Ok(options)
+    }
+}
+
+impl JavaZenith {
+    fn jar(config: &ZenithConfig) -> Option<PathBuf> {
+        config
+            .zenith_specific
+            .get("jar")
+            .and_then(serde_json::Value::as_str)
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os(JAR_ENV).map(PathBuf::from))
+    }
+}
+
 #[async_trait]
 impl Zenith for JavaZenith {
     fn name(&self) -> &str {
@@ -22,12 +90,65 @@ impl Zenith for JavaZenith {
         &["java"]
     }
 
-    async fn format(&self, content: &[u8], path: &Path, _config: &ZenithConfig) -> Result<Vec<u8>> {
+    fn spawns_process(&self) -> bool {
+        true
+    }
+
+    async fn is_available(&self) -> bool {
+        tool_installed("google-java-format").await
+            || (std::env::var_os(JAR_ENV).is_some() && tool_installed("java").await)
+    }
+
+    async fn format(&self, content: &[u8], path: &Path, config: &ZenithConfig) -> Result<Vec<u8>> {
+        let options = JavaOptions::from_config(config).await?;
+
+        let (tool_name, mut args) = match Self::jar(config) {
+            Some(jar) if !tool_installed("google-java-format").await => (
+                "java",
+                vec!["-jar".to_string(), jar.to_string_lossy().into_owned()],
+            ),
+            _ => ("google-java-format", Vec::new()),
+        };
+        args.extend(options.args);
+        args.extend(configured_args(config));
+        if options.aosp {
+            args.push("--aosp".into());
+        }
+        args.push("--stdin-filename".into());
+        args.push(path.to_string_lossy().into_owned());
+        args.push("-".into());
+
         let formatter = StdioFormatter {
-            tool_name: "google-java-format",
-            args: vec!["--stdin-filename".into()],
+            tool_name,
+            args,
             timeout_seconds: None,
+            retry: config.retry,
         };
-        formatter.format_with_stdio(content, path, None).await
+        formatter
+            .format_with_stdio_no_path(content, path, None)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_config_file() {
+        let options = JavaOptions::parse_config_file(
+            "# project style\nstyle = AOSP\n--skip-sorting-imports\n\n--skip-javadoc-formatting --fix-imports-only\n",
+        );
+        assert_eq!(
+            options,
+            JavaOptions {
+                aosp: true,
+                args: vec![
+                    "--skip-sorting-imports".into(),
+                    "--skip-javadoc-formatting".into(),
+                    "--fix-imports-only".into(),
+                ],
+            }
+        );
     }
 }
@@ -52,6 +52,10 @@ macro_rules! zenith_stdio_impl {
                 Self::EXTENSIONS
             }
 
+            fn spawns_process(&self) -> bool {
+                true
+            }
+
             async fn format(
                 &self,
                 content: &[u8],
@@ -98,6 +102,10 @@ macro_rules! zenith_stdio_impl_custom {
                 Self::EXTENSIONS
             }
 
+            fn spawns_process(&self) -> bool {
+                true
+            }
+
             async fn format(
                 &self,
                 $content: &[u8],
@@ -11,6 +11,7 @@ use async_trait::async_trait;
 use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 pub struct MarkdownZenith;
 
@@ -65,23 +66,79 @@ try_lazy_regex!(SINGLE_LINE_CODE_PATTERN, r"(?s)```(\w+)\s+([^\n]+?)\s*```");
 /// Safely get a regex from a Lazy<Result<Regex, Error>>, converting errors to ZenithError
 macro_rules! get_regex {
     ($name:ident) => {
-        match &$name {
-            lazy_regex => {
-                let result = lazy_regex.as_ref();
-                match result {
-                    Ok(regex) => regex.clone(),
-                    Err(e) => {
-                        return Err(ZenithError::Config(format!(
-                            "Failed to compile regex: {}",
-                            e
-                        )));
-                    }
-                }
+        match $name.as_ref() {
+            Ok(regex) => regex,
+            Err(e) => {
+                return Err(ZenithError::Config(format!(
+                    "Failed to compile regex: {}",
+                    e
+                )));
             }
         }
     };
 }
 
+impl MarkdownZenith {
+    /// Compile every static pattern up front.
+    ///
+    /// The patterns are constants, so a failure here is a bug; calling this at
+    /// registration turns it into a startup error listing every broken pattern
+    /// instead of a failure on each formatted file.
+    pub fn validate_regexes() -> Result<()> {
+        let patterns = [
+            ("INLINE_CODE_PATTERN", &INLINE_CODE_PATTERN),
+            ("TASK_LIST_PATTERN", &TASK_LIST_PATTERN),
+            ("STRIKETHROUGH_PATTERN", &STRIKETHROUGH_PATTERN),
+            ("LINK_PATTERN", &LINK_PATTERN),
+            ("BOLD_PATTERN", &BOLD_PATTERN),
+            ("ITALIC_PATTERN", &ITALIC_PATTERN),
+            ("BOLD_ITALIC_PATTERN", &BOLD_ITALIC_PATTERN),
+            ("HORIZONTAL_RULE_PATTERN", &HORIZONTAL_RULE_PATTERN),
+            ("MULTI_LINE_CODE_PATTERN", &MULTI_LINE_CODE_PATTERN),
+            ("SINGLE_LINE_CODE_PATTERN", &SINGLE_LINE_CODE_PATTERN),
+        ];
+
+        let errors: Vec<String> = patterns
+            .iter()
+            .filter_map(|(name, pattern)| {
+                pattern.as_ref().err().map(|e| format!("{}: {}", name, e))
+            })
+            .collect();
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ZenithError::Config(format!(
+                "Failed to compile markdown regexes: {}",
+                errors.join("; ")
+            )))
+        }
+    }
+}
+
+impl MarkdownZenith {
+    /// prettier formatting the preprocessed document; when sandboxed it
+    /// ignores the config files next to the document
+    fn prettier(config: &ZenithConfig) -> StdioFormatter {
+        let mut args = Vec::new();
+        if config.sandbox_config {
+            args.push("--no-config".into());
+            args.push("--no-editorconfig".into());
+        }
+        args.extend([
+            "--stdin-filepath".into(),
+            "--parser".into(),
+            "markdown".into(),
+        ]);
+        StdioFormatter {
+            tool_name: "prettier",
+            args,
+            timeout_seconds: None,
+            retry: config.retry,
+        }
+    }
+}
+
 #[async_trait]
 impl Zenith for MarkdownZenith {
     fn name(&self) -> &str {
@@ -92,34 +149,46 @@ impl Zenith for MarkdownZenith {
         &["md"]
     }
 
+    fn spawns_process(&self) -> bool {
+        true
+    }
+
     fn priority(&self) -> i32 {
         100
     }
 
-    async fn format(&self, content: &[u8], path: &Path, _config: &ZenithConfig) -> Result<Vec<u8>> {
-        let preprocessed = preprocess_extremely_compressed(content)?;
-        let with_inline_code_formatted = format_inline_code(&preprocessed)?;
-        let with_task_lists = format_task_lists(&with_inline_code_formatted)?;
-        let with_strikethrough = format_strikethrough(&with_task_lists)?;
-        let with_links = format_links_and_images(&with_strikethrough)?;
-        let with_emphasis = format_emphasis(&with_links)?;
-        let with_horizontal_rules = format_horizontal_rules(&with_emphasis)?;
-        let with_rust_formatted = format_rust_code_blocks(&with_horizontal_rules)?;
-        let formatter = StdioFormatter {
-            tool_name: "prettier",
-            args: vec![
-                "--stdin-filepath".into(),
-                "--parser".into(),
-                "markdown".into(),
-            ],
-            timeout_seconds: None,
-        };
-        formatter
+    async fn format(&self, content: &[u8], path: &Path, config: &ZenithConfig) -> Result<Vec<u8>> {
+        // The passes spawn rustfmt and walk the whole document, so keep them
+        // off the async workers that drive the other formatters
+        let content = content.to_vec();
+        let with_rust_formatted = tokio::task::spawn_blocking(move || preprocess(&content))
+            .await
+            .map_err(|e| ZenithError::ZenithFailed {
+                name: "markdown".to_string(),
+                reason: e.to_string(),
+            })??;
+        Self::prettier(config)
             .format_with_stdio_no_path(with_rust_formatted.as_bytes(), path, None)
             .await
     }
 }
 
+/// Run the preprocessing passes that precede prettier.
+///
+/// The passes stay sequential: each one rewrites the output of the previous
+/// one (bold-italic before bold before italic, rust blocks after the emphasis
+/// rewrite), so running them side by side would change the result.
+fn preprocess(content: &[u8]) -> Result<String> {
+    let preprocessed = preprocess_extremely_compressed(content)?;
+    let with_inline_code_formatted = format_inline_code(&preprocessed)?;
+    let with_task_lists = format_task_lists(&with_inline_code_formatted)?;
+    let with_strikethrough = format_strikethrough(&with_task_lists)?;
+    let with_links = format_links_and_images(&with_strikethrough)?;
+    let with_emphasis = format_emphasis(&with_links)?;
+    let with_horizontal_rules = format_horizontal_rules(&with_emphasis)?;
+    format_rust_code_blocks(&with_horizontal_rules)
+}
+
 fn preprocess_extremely_compressed(content: &[u8]) -> Result<String> {
     let text = String::from_utf8_lossy(content);
     let mut result = String::new();
@@ -422,19 +491,22 @@ fn format_inline_code(text: &str) -> Result<String> {
 
     let mut result = text.to_string();
 
-    let replacements: Vec<(String, String)> = regex
+    let candidates: Vec<(String, String)> = regex
         .captures_iter(&result)
         .filter_map(|cap| {
             let full_match = cap.get(0)?.as_str().to_string();
             let code_content = cap.get(1)?.as_str().to_string();
-            let lang = detect_inline_language(&code_content);
-            if lang == "rust" {
-                if let Ok(formatted) = format_with_rustfmt(&code_content) {
-                    let cleaned = clean_inline_code(&formatted);
-                    return Some((full_match, format!("`{}`", cleaned)));
-                }
-            }
-            None
+            (detect_inline_language(&code_content) == "rust").then_some((full_match, code_content))
+        })
+        .collect();
+
+    let codes: Vec<String> = candidates.iter().map(|(_, code)| code.clone()).collect();
+    let replacements: Vec<(String, String)> = candidates
+        .into_iter()
+        .zip(MarkdownZenith::format_rust_snippets(&codes))
+        .filter_map(|((full_match, _), formatted)| {
+            let cleaned = clean_inline_code(&formatted?);
+            Some((full_match, format!("`{}`", cleaned)))
         })
         .collect();
 
@@ -528,23 +600,7 @@ fn format_rust_code_blocks(content: &str) -> Result<String> {
 
     let mut result = content.to_string();
 
-    let replacements: Vec<(String, String, String)> = multi_regex
-        .captures_iter(&result)
-        .filter_map(|cap| {
-            let lang = cap.get(1)?.as_str();
-            if !SUPPORTED_LANGUAGES.contains(&lang) {
-                return None;
-            }
-            let full_match = cap.get(0)?.as_str().to_string();
-            let code_content = cap.get(2)?.as_str().to_string();
-            let formatted = if lang == "rust" {
-                format_with_rustfmt(&code_content).ok()?
-            } else {
-                code_content
-            };
-            Some((full_match, lang.to_string(), formatted))
-        })
-        .collect();
+    let replacements = format_code_blocks(multi_regex, &result, |formatted| formatted);
 
     for (original, lang, formatted) in replacements.iter().rev() {
         let replacement = format!("```{}\n{}\n```", lang, formatted);
@@ -555,8 +611,34 @@ fn format_rust_code_blocks(content: &str) -> Result<String> {
         }
     }
 
-    let single_replacements: Vec<(String, String, String)> = single_regex
-        .captures_iter(&result)
+    let single_replacements = format_code_blocks(single_regex, &result, |formatted| {
+        clean_inline_code(&formatted)
+    });
+
+    for (original, lang, formatted) in single_replacements.iter().rev() {
+        let replacement = format!("```{}\n{}\n```", lang, formatted);
+        if let Some(pos) = result.rfind(original) {
+            let before = &result[..pos];
+            let after = &result[pos + original.len()..];
+            result = format!("{}{}{}", before, replacement, after);
+        }
+    }
+
+    Ok(result)
+}
+
+/// Collect the fenced blocks in a supported language as
+/// `(full match, language, new body)`.
+///
+/// Rust bodies are formatted in one batch; blocks rustfmt rejects are left
+/// out, like before.
+fn format_code_blocks(
+    regex: &regex::Regex,
+    text: &str,
+    finish: impl Fn(String) -> String,
+) -> Vec<(String, String, String)> {
+    let blocks: Vec<(String, String, String)> = regex
+        .captures_iter(text)
         .filter_map(|cap| {
             let lang = cap.get(1)?.as_str();
             if !SUPPORTED_LANGUAGES.contains(&lang) {
@@ -564,26 +646,184 @@ fn format_rust_code_blocks(content: &str) -> Result<String> {
             }
             let full_match = cap.get(0)?.as_str().to_string();
             let code_content = cap.get(2)?.as_str().to_string();
+            Some((full_match, lang.to_string(), code_content))
+        })
+        .collect();
+
+    let rust_codes: Vec<String> = blocks
+        .iter()
+        .filter(|(_, lang, _)| lang == "rust")
+        .map(|(_, _, code)| code.clone())
+        .collect();
+    let mut rust_formatted = MarkdownZenith::format_rust_snippets(&rust_codes).into_iter();
+
+    blocks
+        .into_iter()
+        .filter_map(|(full_match, lang, code_content)| {
             let formatted = if lang == "rust" {
-                format_with_rustfmt(&code_content).ok()?
+                rust_formatted.next().flatten()?
             } else {
                 code_content
             };
-            let cleaned = clean_inline_code(&formatted);
-            Some((full_match, lang.to_string(), cleaned))
+            Some((full_match, lang, finish(formatted)))
         })
-        .collect();
+        .collect()
+}
 
-    for (original, lang, formatted) in single_replacements.iter().rev() {
-        let replacement = format!("```{}\n{}\n```", lang, formatted);
-        if let Some(pos) = result.rfind(original) {
-            let before = &result[..pos];
-            let after = &result[pos + original.len()..];
-            result = format!("{}{}{}", before, replacement, after);
+const SNIPPET_MARKER: &str = "// zenith:snippet:";
+
+impl MarkdownZenith {
+    /// Format Rust snippets with as few rustfmt processes as possible.
+    ///
+    /// All snippets go through a single rustfmt invocation, separated by
+    /// marker comments. If rustfmt rejects the batch (usually because one
+    /// snippet is not valid on its own, such as a bare statement), the snippet
+    /// named in the error is checked on its own and the rest are retried as a
+    /// batch once. When that fails too, or the failure cannot be attributed,
+    /// every snippet gets its own rustfmt run, a few processes at a time.
+    ///
+    /// The result has one entry per snippet, `None` where rustfmt failed.
+    pub fn format_rust_snippets(snippets: &[String]) -> Vec<Option<String>> {
+        let snippets: Vec<&str> = snippets.iter().map(String::as_str).collect();
+        format_snippets(&snippets)
+    }
+}
+
+fn format_snippets(snippets: &[&str]) -> Vec<Option<String>> {
+    if snippets.len() < 2 {
+        return format_each(snippets);
+    }
+    match format_rust_batch(snippets) {
+        Ok(formatted) => formatted.into_iter().map(Some).collect(),
+        Err(BatchFailure::Unavailable) => vec![None; snippets.len()],
+        Err(BatchFailure::Snippet(index)) => {
+            let rest: Vec<&str> = snippets
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| *i != index)
+                .map(|(_, snippet)| *snippet)
+                .collect();
+            let mut formatted = match format_rust_batch(&rest) {
+                Ok(formatted) => formatted.into_iter().map(Some).collect(),
+                Err(BatchFailure::Unavailable) => vec![None; rest.len()],
+                Err(_) => format_each(&rest),
+            };
+            formatted.insert(index, format_with_rustfmt(snippets[index]).ok());
+            formatted
         }
+        Err(BatchFailure::Unknown) => format_each(snippets),
     }
+}
 
-    Ok(result)
+/// Format every snippet with its own rustfmt run, with no more processes at
+/// once than there are CPUs
+fn format_each(snippets: &[&str]) -> Vec<Option<String>> {
+    let workers = std::thread::available_parallelism()
+        .map_or(1, usize::from)
+        .min(snippets.len());
+    let next = AtomicUsize::new(0);
+    let mut formatted = vec![None; snippets.len()];
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done = Vec::new();
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(snippet) = snippets.get(index) else {
+                            break done;
+                        };
+                        done.push((index, format_with_rustfmt(snippet).ok()));
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            for (index, result) in handle.join().unwrap_or_default() {
+                formatted[index] = result;
+            }
+        }
+    });
+    formatted
+}
+
+enum BatchFailure {
+    /// rustfmt could not be started at all
+    Unavailable,
+    /// rustfmt reported an error inside this snippet
+    Snippet(usize),
+    /// The failure could not be attributed to a single snippet
+    Unknown,
+}
+
+/// Format all snippets in one rustfmt run.
+///
+/// Fails if rustfmt rejects the input or the markers did not survive
+/// unchanged (for example when a snippet leaves a block or comment open, or
+/// imports were reordered across snippets).
+fn format_rust_batch(snippets: &[&str]) -> std::result::Result<Vec<String>, BatchFailure> {
+    let mut combined = String::new();
+    let mut start_lines = Vec::with_capacity(snippets.len());
+    for (i, snippet) in snippets.iter().enumerate() {
+        // The blank line keeps `use` groups of neighbouring snippets apart
+        combined.push_str(&format!("\n{}{}\n", SNIPPET_MARKER, i));
+        start_lines.push(combined.matches('\n').count() + 1);
+        combined.push_str(snippet);
+        combined.push('\n');
+    }
+
+    let output = match format_with_rustfmt(&combined) {
+        Ok(output) => output,
+        Err(ZenithError::ZenithFailed { reason, .. }) => {
+            return Err(error_line(&reason)
+                .map(|line| start_lines.partition_point(|&start| start <= line))
+                .filter(|&count| count > 0)
+                .map(|count| BatchFailure::Snippet(count - 1))
+                .unwrap_or(BatchFailure::Unknown));
+        }
+        Err(_) => return Err(BatchFailure::Unavailable),
+    };
+
+    let mut pieces: Vec<String> = Vec::with_capacity(snippets.len());
+    let mut current: Option<String> = None;
+    for line in output.lines() {
+        if line.trim_start().starts_with(SNIPPET_MARKER) {
+            let expected = pieces.len() + usize::from(current.is_some());
+            if line != format!("{}{}", SNIPPET_MARKER, expected) {
+                return Err(BatchFailure::Unknown);
+            }
+            pieces.extend(current.take());
+            current = Some(String::new());
+        } else if let Some(piece) = current.as_mut() {
+            piece.push_str(line);
+            piece.push('\n');
+        } else if !line.trim().is_empty() {
+            return Err(BatchFailure::Unknown);
+        }
+    }
+    pieces.extend(current);
+
+    if pieces.len() != snippets.len() {
+        return Err(BatchFailure::Unknown);
+    }
+    Ok(pieces
+        .into_iter()
+        .map(|piece| {
+            // Match what rustfmt prints for the snippet on its own
+            let piece = piece.trim_matches('\n');
+            if piece.is_empty() {
+                String::new()
+            } else {
+                format!("{}\n", piece)
+            }
+        })
+        .collect())
+}
+
+/// The line of the first `--> <stdin>:LINE:COL` location in rustfmt's errors.
+fn error_line(stderr: &str) -> Option<usize> {
+    let location = &stderr[stderr.find("<stdin>:")? + "<stdin>:".len()..];
+    location.split(':').next()?.trim().parse().ok()
 }
 
 fn format_with_rustfmt(code: &str) -> Result<String> {
@@ -7,6 +7,10 @@ mod macros;
 
 #[cfg(feature = "c")]
 pub mod c_zenith;
+#[cfg(feature = "go")]
+pub mod go_zenith;
+#[cfg(feature = "graphql")]
+pub mod graphql_zenith;
 #[cfg(feature = "ini")]
 pub mod ini_zenith;
 #[cfg(feature = "java")]
@@ -21,5 +25,11 @@ pub mod python_zenith;
 pub mod rust_zenith;
 #[cfg(feature = "shell")]
 pub mod shell_zenith;
+#[cfg(feature = "sql")]
+pub mod sql_zenith;
 #[cfg(feature = "toml")]
 pub mod toml_zenith;
+#[cfg(feature = "vue")]
+pub mod vue_zenith;
+#[cfg(feature = "yaml")]
+pub mod yaml_zenith;
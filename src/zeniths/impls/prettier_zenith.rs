@@ -8,6 +8,10 @@ use crate::core::traits::Zenith;
 use crate::error::{Result, ZenithError};
 use crate::utils::path::sanitize_path_for_log;
 use crate::utils::version;
+use crate::zeniths::common::{
+    config_path_args, configured_args, tool_installed, trusted_config_path,
+};
+use crate::zeniths::pool::{DaemonSpec, FormatterPool};
 use async_trait::async_trait;
 use std::path::Path;
 use std::process::{Command, Stdio};
@@ -17,7 +21,130 @@ pub struct PrettierZenith;
 
 const PRETTIER_MIN_VERSION: &str = "2.0.0";
 
+/// Daemon for [`FormatterPool`] that keeps one node process with prettier
+/// loaded. The minimum version is passed as the last argument; a local
+/// `prettier` module is preferred over the global install, so the daemon is
+/// not used when external config is sandboxed.
+const PRETTIER_DAEMON_JS: &str = r#"
+const path = require("path");
+const min = process.argv[process.argv.length - 1];
+function load() {
+  try {
+    return require("prettier");
+  } catch (e) {
+    const root = require("child_process")
+      .execSync("npm root -g", { stdio: ["ignore", "pipe", "ignore"] })
+      .toString()
+      .trim();
+    return require(path.join(root, "prettier"));
+  }
+}
+function older(version, minimum) {
+  const a = version.split(".").map(Number);
+  const b = minimum.split(".").map(Number);
+  for (let i = 0; i < b.length; i++) {
+    if ((a[i] || 0) !== b[i]) return (a[i] || 0) < b[i];
+  }
+  return false;
+}
+let prettier;
+try {
+  prettier = load();
+  if (older(prettier.version, min)) throw new Error(`prettier ${prettier.version} is older than ${min}`);
+} catch (e) {
+  process.stdout.write(JSON.stringify({ ready: false, error: String(e.message || e) }) + "\n");
+  process.exit(1);
+}
+process.stdout.write(JSON.stringify({ ready: true }) + "\n");
+let buf = Buffer.alloc(0);
+let busy = false;
+async function pump() {
+  if (busy) return;
+  busy = true;
+  for (;;) {
+    const nl = buf.indexOf(10);
+    if (nl < 0) break;
+    const header = JSON.parse(buf.subarray(0, nl).toString());
+    if (buf.length < nl + 1 + header.length) break;
+    const source = buf.subarray(nl + 1, nl + 1 + header.length).toString();
+    buf = buf.subarray(nl + 1 + header.length);
+    let ok = true;
+    let body;
+    try {
+      const options = header.config
+        ? (await prettier.resolveConfig(header.path, { config: header.config })) || {}
+        : {};
+      body = await prettier.format(source, { ...options, parser: header.parser });
+    } catch (e) {
+      ok = false;
+      body = String(e.message || e);
+    }
+    const out = Buffer.from(body);
+    process.stdout.write(JSON.stringify({ ok, length: out.length }) + "\n");
+    process.stdout.write(out);
+  }
+  busy = false;
+}
+process.stdin.on("data", (d) => {
+  buf = Buffer.concat([buf, d]);
+  pump();
+});
+process.stdin.on("end", () => process.exit(0));
+"#;
+
 impl PrettierZenith {
+    fn daemon() -> DaemonSpec {
+        DaemonSpec {
+            name: "prettier",
+            program: "node",
+            args: vec![
+                "-e".into(),
+                PRETTIER_DAEMON_JS.into(),
+                PRETTIER_MIN_VERSION.into(),
+            ],
+        }
+    }
+
+    /// Config arguments for the prettier CLI
+    ///
+    /// When sandboxed, prettier is kept from discovering `.prettierrc*`,
+    /// `package.json` and `.editorconfig` files next to the formatted file;
+    /// an explicitly configured, non-executable config file still applies.
+    fn config_args(config: &ZenithConfig) -> Result<Vec<String>> {
+        let config_path = trusted_config_path(config)?;
+        let mut args = config_path_args("--config", config_path);
+        if config_path.is_none() && config.sandbox_config {
+            args.push("--no-config".into());
+        }
+        if config.sandbox_config {
+            args.push("--no-editorconfig".into());
+        }
+        Ok(args)
+    }
+
+    fn parser(path: &Path) -> &'static str {
+        match path.extension().and_then(|e| e.to_str()).unwrap_or("") {
+            "js" | "jsx" => "babel",
+            "ts" | "tsx" => "typescript",
+            "json" => "json",
+            "yaml" | "yml" => "yaml",
+            "md" => "markdown",
+            "css" => "css",
+            "scss" => "scss",
+            "html" => "html",
+            "vue" => "vue",
+            _ => "babel",
+        }
+    }
+
+    fn with_newline(content: &[u8]) -> Vec<u8> {
+        let mut content_with_newline = content.to_vec();
+        if !content.is_empty() && content[content.len() - 1] != b'\n' {
+            content_with_newline.push(b'\n');
+        }
+        content_with_newline
+    }
+
     fn check_prettier_version() -> Result<()> {
         let version_str = version::get_tool_version("prettier")?;
         version::check_version("prettier", &version_str, PRETTIER_MIN_VERSION)?;
@@ -37,28 +164,24 @@ impl Zenith for PrettierZenith {
         ]
     }
 
-    async fn format(&self, content: &[u8], path: &Path, _config: &ZenithConfig) -> Result<Vec<u8>> {
-        Self::check_prettier_version()?;
+    fn spawns_process(&self) -> bool {
+        true
+    }
 
+    fn min_version(&self) -> Option<&str> {
+        Some(PRETTIER_MIN_VERSION)
+    }
+
+    async fn is_available(&self) -> bool {
+        tool_installed("prettier").await
+    }
+
+    async fn format(&self, content: &[u8], path: &Path, config: &ZenithConfig) -> Result<Vec<u8>> {
         let sanitized_path = sanitize_path_for_log(path);
-        let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
-        let parser = match ext {
-            "js" | "jsx" => "babel",
-            "ts" | "tsx" => "typescript",
-            "json" => "json",
-            "yaml" | "yml" => "yaml",
-            "md" => "markdown",
-            "css" => "css",
-            "scss" => "scss",
-            "html" => "html",
-            "vue" => "vue",
-            _ => "babel",
-        };
+        let parser = Self::parser(path);
+        let content_with_newline = Self::with_newline(content);
 
-        let mut content_with_newline = content.to_vec();
-        if !content.is_empty() && content[content.len() - 1] != b'\n' {
-            content_with_newline.push(b'\n');
-        }
+        Self::check_prettier_version()?;
 
         debug!(
             "Executing formatter 'prettier' with parser: {}, path: {}",
@@ -67,6 +190,8 @@ impl Zenith for PrettierZenith {
 
         let mut cmd = Command::new("prettier");
         cmd.args(["--parser", parser])
+            .args(Self::config_args(config)?)
+            .args(configured_args(config))
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
             .stderr(Stdio::piped());
@@ -110,4 +235,64 @@ impl Zenith for PrettierZenith {
             })
         }
     }
+
+    async fn format_pooled(
+        &self,
+        content: &[u8],
+        path: &Path,
+        config: &ZenithConfig,
+        pool: &FormatterPool,
+    ) -> Result<Vec<u8>> {
+        // Extra command-line arguments have no library equivalent, so only
+        // plain invocations go to the persistent process. The daemon loads
+        // prettier from the project's node_modules, which a sandboxed run
+        // must not execute.
+        if configured_args(config).is_empty() && !config.sandbox_config {
+            // The daemon only resolves config from the file it is given
+            let header = serde_json::json!({
+                "path": path,
+                "parser": Self::parser(path),
+                "config": trusted_config_path(config)?,
+            });
+            if let Some(result) = pool
+                .request(&Self::daemon(), header, &Self::with_newline(content))
+                .await
+            {
+                return result;
+            }
+        }
+        self.format(content, path, config).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_sandbox_ignores_project_config() {
+        let mut config = ZenithConfig::default();
+        assert!(PrettierZenith::config_args(&config).unwrap().is_empty());
+
+        config.sandbox_config = true;
+        assert_eq!(
+            PrettierZenith::config_args(&config).unwrap(),
+            ["--no-config", "--no-editorconfig"]
+        );
+
+        config.custom_config_path = Some(PathBuf::from("/trusted/.prettierrc.json"));
+        assert_eq!(
+            PrettierZenith::config_args(&config).unwrap(),
+            ["--config", "/trusted/.prettierrc.json", "--no-editorconfig"]
+        );
+
+        config.custom_config_path = Some(PathBuf::from("/repo/prettier.config.js"));
+        assert!(PrettierZenith::config_args(&config).is_err());
+        config.sandbox_config = false;
+        assert_eq!(
+            PrettierZenith::config_args(&config).unwrap(),
+            ["--config", "/repo/prettier.config.js"]
+        );
+    }
 }
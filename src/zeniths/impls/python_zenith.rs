@@ -5,13 +5,122 @@
 
 use crate::config::types::ZenithConfig;
 use crate::core::traits::Zenith;
-use crate::error::Result;
-use crate::zeniths::common::StdioFormatter;
+use crate::error::{Result, ZenithError};
+use crate::zeniths::common::{config_path_args, configured_args, tool_installed, StdioFormatter};
 use async_trait::async_trait;
 use std::path::Path;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 
+/// Python formatter backed by `ruff format` or black.
+///
+/// Content is piped through stdin. `tool` in `ZenithConfig::zenith_specific`
+/// (`ruff` or `black`) picks the formatter; without it ruff is used when
+/// installed and black otherwise. With ruff, setting `fix` to `true` runs
+/// `ruff check --fix` before formatting to apply lint autofixes such as
+/// import sorting.
 pub struct PythonZenith;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PythonTool {
+    Ruff,
+    Black,
+}
+
+impl PythonTool {
+    /// The configured tool, or ruff unless only black is installed
+    fn select(
+        configured: Option<&str>,
+        ruff_installed: bool,
+        black_installed: bool,
+    ) -> Result<Self> {
+        match configured {
+            Some(tool) if tool.eq_ignore_ascii_case("ruff") => Ok(Self::Ruff),
+            Some(tool) if tool.eq_ignore_ascii_case("black") => Ok(Self::Black),
+            Some(tool) => Err(ZenithError::Config(format!(
+                "Unknown python tool '{}', expected 'ruff' or 'black'",
+                tool
+            ))),
+            None if !ruff_installed && black_installed => Ok(Self::Black),
+            None => Ok(Self::Ruff),
+        }
+    }
+
+    async fn from_config(config: &ZenithConfig) -> Result<Self> {
+        let configured = config
+            .zenith_specific
+            .get("tool")
+            .and_then(serde_json::Value::as_str);
+        if configured.is_some() {
+            return Self::select(configured, false, false);
+        }
+        Self::select(
+            None,
+            tool_installed("ruff").await,
+            tool_installed("black").await,
+        )
+    }
+}
+
+impl PythonZenith {
+    /// Whether `ruff check --fix` runs before `ruff format`
+    fn fix(config: &ZenithConfig) -> bool {
+        config
+            .zenith_specific
+            .get("fix")
+            .and_then(serde_json::Value::as_bool)
+            .unwrap_or(false)
+    }
+
+    /// `--config` for ruff; ruff only understands its own config files,
+    /// others are left to auto-discovery, which `--isolated` turns off when
+    /// sandboxed
+    fn ruff_config_args(config: &ZenithConfig) -> Vec<String> {
+        match config.custom_config_path.as_deref().filter(|p| {
+            matches!(
+                p.file_name().and_then(|n| n.to_str()),
+                Some("ruff.toml" | ".ruff.toml")
+            )
+        }) {
+            Some(config_path) => config_path_args("--config", Some(config_path)),
+            None if config.sandbox_config => vec!["--isolated".into()],
+            None => Vec::new(),
+        }
+    }
+
+    /// `ruff <subcommand>` reading stdin, with the file path appended by
+    /// `StdioFormatter`
+    fn ruff(subcommand: &[&str], config: &ZenithConfig) -> StdioFormatter {
+        let mut args: Vec<String> = subcommand.iter().map(|arg| arg.to_string()).collect();
+        args.extend(Self::ruff_config_args(config));
+        args.extend(configured_args(config));
+        args.push("--stdin-filename".into());
+
+        StdioFormatter {
+            tool_name: "ruff",
+            args,
+            timeout_seconds: None,
+            retry: config.retry,
+        }
+    }
+
+    /// black reading stdin; the path only names the file for config
+    /// discovery and messages
+    fn black(path: &Path, config: &ZenithConfig) -> StdioFormatter {
+        let mut args = vec!["--quiet".to_string()];
+        args.extend(configured_args(config));
+        args.push("--stdin-filename".into());
+        args.push(path.to_string_lossy().into_owned());
+        args.push("-".into());
+
+        StdioFormatter {
+            tool_name: "black",
+            args,
+            timeout_seconds: None,
+            retry: config.retry,
+        }
+    }
+}
+
 #[async_trait]
 impl Zenith for PythonZenith {
     fn name(&self) -> &str {
@@ -22,12 +131,130 @@ impl Zenith for PythonZenith {
         &["py", "pyi"]
     }
 
-    async fn format(&self, content: &[u8], path: &Path, _config: &ZenithConfig) -> Result<Vec<u8>> {
-        let formatter = StdioFormatter {
-            tool_name: "ruff",
-            args: vec!["format".into(), "--stdin-filename".into()],
-            timeout_seconds: None,
+    fn spawns_process(&self) -> bool {
+        true
+    }
+
+    async fn is_available(&self) -> bool {
+        tool_installed("ruff").await || tool_installed("black").await
+    }
+
+    async fn format(&self, content: &[u8], path: &Path, config: &ZenithConfig) -> Result<Vec<u8>> {
+        match PythonTool::from_config(config).await? {
+            PythonTool::Black => {
+                Self::black(path, config)
+                    .format_with_stdio_no_path(content, path, None)
+                    .await
+            }
+            PythonTool::Ruff if Self::fix(config) => {
+                // --exit-zero: remaining unfixable violations are not a formatting failure
+                let fixed = Self::ruff(&["check", "--fix", "--exit-zero", "--quiet"], config)
+                    .format_with_stdio(content, path, None)
+                    .await?;
+                Self::ruff(&["format"], config)
+                    .format_with_stdio(&fixed, path, None)
+                    .await
+            }
+            PythonTool::Ruff => {
+                Self::ruff(&["format"], config)
+                    .format_with_stdio(content, path, None)
+                    .await
+            }
+        }
+    }
+
+    async fn format_stream(
+        &self,
+        input: &mut (dyn AsyncRead + Unpin + Send),
+        output: &mut (dyn AsyncWrite + Unpin + Send),
+        path: &Path,
+        config: &ZenithConfig,
+    ) -> Result<()> {
+        match PythonTool::from_config(config).await? {
+            PythonTool::Black => {
+                Self::black(path, config)
+                    .format_stream(input, output, None)
+                    .await
+            }
+            // Two passes need the whole file; buffer it like the default
+            PythonTool::Ruff if Self::fix(config) => {
+                let mut content = Vec::new();
+                input.read_to_end(&mut content).await?;
+                let formatted = self.format(&content, path, config).await?;
+                output.write_all(&formatted).await?;
+                Ok(())
+            }
+            PythonTool::Ruff => {
+                Self::ruff(&["format"], config)
+                    .format_stream(input, output, Some(path))
+                    .await
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select_tool() {
+        assert_eq!(
+            PythonTool::select(None, true, true).unwrap(),
+            PythonTool::Ruff
+        );
+        assert_eq!(
+            PythonTool::select(None, false, true).unwrap(),
+            PythonTool::Black
+        );
+        assert_eq!(
+            PythonTool::select(None, false, false).unwrap(),
+            PythonTool::Ruff
+        );
+        assert_eq!(
+            PythonTool::select(Some("black"), true, false).unwrap(),
+            PythonTool::Black
+        );
+        assert_eq!(
+            PythonTool::select(Some("Ruff"), false, true).unwrap(),
+            PythonTool::Ruff
+        );
+        assert!(PythonTool::select(Some("yapf"), true, true).is_err());
+    }
+
+    #[test]
+    fn test_sandboxed_ruff_is_isolated() {
+        let config = ZenithConfig {
+            sandbox_config: true,
+            ..Default::default()
         };
-        formatter.format_with_stdio(content, path, None).await
+        assert_eq!(
+            PythonZenith::ruff(&["format"], &config).args,
+            ["format", "--isolated", "--stdin-filename"]
+        );
+
+        let config = ZenithConfig {
+            custom_config_path: Some("/trusted/ruff.toml".into()),
+            ..config
+        };
+        assert_eq!(
+            PythonZenith::ruff(&["format"], &config).args,
+            [
+                "format",
+                "--config",
+                "/trusted/ruff.toml",
+                "--stdin-filename"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_black_reads_stdin() {
+        let formatter = PythonZenith::black(Path::new("app.py"), &ZenithConfig::default());
+        assert_eq!(formatter.tool_name, "black");
+        assert_eq!(
+            formatter.args,
+            ["--quiet", "--stdin-filename", "app.py", "-"]
+        );
     }
 }
@@ -3,12 +3,13 @@
 // Licensed under the MIT License
 // See LICENSE file in the project root for full license information.
 
-use crate::config::discovery::discover_formatter_config;
+use crate::config::discovery::{discover_formatter_config, discover_rust_edition};
 use crate::config::types::ZenithConfig;
 use crate::core::traits::Zenith;
 use crate::error::Result;
+use crate::utils::range::restrict_to_lines;
 use crate::utils::version;
-use crate::zeniths::common::StdioFormatter;
+use crate::zeniths::common::{configured_args, tool_installed, StdioFormatter};
 use async_trait::async_trait;
 use std::path::Path;
 
@@ -16,12 +17,28 @@ pub struct RustZenith;
 
 const RUSTFMT_MIN_VERSION: &str = "1.0.0";
 
+/// Edition used when neither the config nor a `Cargo.toml` specifies one
+const DEFAULT_EDITION: &str = "2021";
+
 impl RustZenith {
     fn check_rustfmt_version() -> Result<()> {
         let version_str = version::get_tool_version("rustfmt")?;
         version::check_version("rustfmt", &version_str, RUSTFMT_MIN_VERSION)?;
         Ok(())
     }
+
+    /// Resolve the edition: `edition` in the formatter options, then the
+    /// nearest `Cargo.toml`, then [`DEFAULT_EDITION`].
+    fn edition(path: &Path, config: &ZenithConfig) -> Result<String> {
+        if let Some(edition) = config
+            .zenith_specific
+            .get("edition")
+            .and_then(|e| e.as_str())
+        {
+            return Ok(edition.to_string());
+        }
+        Ok(discover_rust_edition(path)?.unwrap_or_else(|| DEFAULT_EDITION.to_string()))
+    }
 }
 
 #[async_trait]
@@ -34,23 +51,65 @@ impl Zenith for RustZenith {
         &["rs"]
     }
 
-    async fn format(&self, content: &[u8], path: &Path, _config: &ZenithConfig) -> Result<Vec<u8>> {
+    fn spawns_process(&self) -> bool {
+        true
+    }
+
+    fn min_version(&self) -> Option<&str> {
+        Some(RUSTFMT_MIN_VERSION)
+    }
+
+    async fn is_available(&self) -> bool {
+        tool_installed("rustfmt").await
+    }
+
+    /// Stable rustfmt has no line ranges, so the whole file is formatted
+    /// and only the changes touching the range are kept.
+    fn supports_range(&self) -> bool {
+        true
+    }
+
+    async fn format(&self, content: &[u8], path: &Path, config: &ZenithConfig) -> Result<Vec<u8>> {
         Self::check_rustfmt_version()?;
 
         let mut extra_args = vec!["--emit".into(), "stdout".into()];
 
-        if let Some(config_path) = discover_formatter_config(path, "rust")? {
-            extra_args.push("--config-path".into());
-            extra_args.push(config_path.to_string_lossy().into());
+        let config_path = match &config.custom_config_path {
+            Some(config_path) => Some(config_path.clone()),
+            None => discover_formatter_config(path, "rust")?,
+        };
+        let user_args = configured_args(config);
+        // An explicit `--edition` in the configured args wins
+        if !user_args.iter().any(|arg| arg.starts_with("--edition")) {
+            extra_args.push("--edition".into());
+            extra_args.push(Self::edition(path, config)?);
         }
+        extra_args.extend(user_args);
 
         let formatter = StdioFormatter {
             tool_name: "rustfmt",
             args: extra_args,
             timeout_seconds: None,
+            retry: config.retry,
+        };
+        let formatted = formatter
+            .format_with_config_path(content, None, config_path.as_deref(), "--config-path")
+            .await?;
+        Ok(match config.range {
+            Some(range) => restrict_to_lines(
+                &String::from_utf8_lossy(content),
+                &String::from_utf8_lossy(&formatted),
+                range,
+            )
+            .into_bytes(),
+            None => formatted,
+        })
+    }
+
+    async fn validate(&self, content: &[u8]) -> Result<bool> {
+        let Ok(source) = std::str::from_utf8(content) else {
+            return Ok(false);
         };
-        formatter
-            .format_with_stdio_no_path(content, path, None)
-            .await
+        Ok(syn::parse_file(source).is_ok())
     }
 }
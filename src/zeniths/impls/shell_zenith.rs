@@ -6,12 +6,63 @@
 use crate::config::types::ZenithConfig;
 use crate::core::traits::Zenith;
 use crate::error::Result;
-use crate::zeniths::common::StdioFormatter;
+use crate::zeniths::common::{
+    configured_args, shebang_interpreter, tool_installed, StdioFormatter,
+};
 use async_trait::async_trait;
 use std::path::Path;
+use tokio::io::{AsyncRead, AsyncWrite};
 
+/// Shell interpreters whose scripts shfmt can parse
+const SHELL_INTERPRETERS: &[&str] = &["sh", "bash", "dash", "ksh", "mksh", "zsh"];
+
+/// Shell formatter backed by shfmt.
+///
+/// Besides `.sh`, `.bash` and `.zsh` files it claims scripts without a known
+/// extension whose shebang names a shell, such as `#!/usr/bin/env bash`.
+///
+/// Content is piped through stdin. Two options can be set through
+/// `ZenithConfig::zenith_specific`: `indent_width` (`shfmt -i`, 0 for tabs)
+/// and `binary_next_line` (`shfmt -bn`), which puts `&&` and `|` at the
+/// start of continued lines. Without them shfmt falls back to any
+/// `.editorconfig` it finds.
 pub struct ShellZenith;
 
+impl ShellZenith {
+    fn formatter(config: &ZenithConfig) -> StdioFormatter {
+        let mut args = Self::option_args(config);
+        args.extend(configured_args(config));
+        args.push("-filename".into());
+
+        StdioFormatter {
+            tool_name: "shfmt",
+            args,
+            timeout_seconds: None,
+            retry: config.retry,
+        }
+    }
+
+    fn option_args(config: &ZenithConfig) -> Vec<String> {
+        let specific = &config.zenith_specific;
+        let mut args = Vec::new();
+        if let Some(width) = specific
+            .get("indent_width")
+            .and_then(serde_json::Value::as_u64)
+        {
+            args.push("-i".to_string());
+            args.push(width.to_string());
+        }
+        if specific
+            .get("binary_next_line")
+            .and_then(serde_json::Value::as_bool)
+            .unwrap_or(false)
+        {
+            args.push("-bn".to_string());
+        }
+        args
+    }
+}
+
 #[async_trait]
 impl Zenith for ShellZenith {
     fn name(&self) -> &str {
@@ -22,12 +73,55 @@ impl Zenith for ShellZenith {
         &["sh", "bash", "zsh"]
     }
 
-    async fn format(&self, content: &[u8], path: &Path, _config: &ZenithConfig) -> Result<Vec<u8>> {
-        let formatter = StdioFormatter {
-            tool_name: "shfmt",
-            args: vec!["-filename".into()],
-            timeout_seconds: None,
+    fn spawns_process(&self) -> bool {
+        true
+    }
+
+    fn supports_path(&self, path: &Path, first_line: Option<&str>) -> bool {
+        let by_extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| self.extensions().contains(&ext));
+        by_extension
+            || first_line
+                .and_then(shebang_interpreter)
+                .is_some_and(|interpreter| SHELL_INTERPRETERS.contains(&interpreter))
+    }
+
+    async fn is_available(&self) -> bool {
+        tool_installed("shfmt").await
+    }
+
+    async fn format(&self, content: &[u8], path: &Path, config: &ZenithConfig) -> Result<Vec<u8>> {
+        Self::formatter(config)
+            .format_with_stdio(content, path, None)
+            .await
+    }
+
+    async fn format_stream(
+        &self,
+        input: &mut (dyn AsyncRead + Unpin + Send),
+        output: &mut (dyn AsyncWrite + Unpin + Send),
+        path: &Path,
+        config: &ZenithConfig,
+    ) -> Result<()> {
+        Self::formatter(config)
+            .format_stream(input, output, Some(path))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_option_args() {
+        assert!(ShellZenith::option_args(&ZenithConfig::default()).is_empty());
+        let config = ZenithConfig {
+            zenith_specific: serde_json::json!({ "indent_width": 4, "binary_next_line": true }),
+            ..Default::default()
         };
-        formatter.format_with_stdio(content, path, None).await
+        assert_eq!(ShellZenith::option_args(&config), ["-i", "4", "-bn"]);
     }
 }
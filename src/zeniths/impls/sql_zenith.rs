@@ -0,0 +1,68 @@
+// Copyright (c) 2025 Kirky.X
+//
+// Licensed under the MIT License
+// See LICENSE file in the project root for full license information.
+
+use crate::config::types::ZenithConfig;
+use crate::core::traits::Zenith;
+use crate::error::{Result, ZenithError};
+use async_trait::async_trait;
+use sqlformat::{FormatOptions, Indent, QueryParams};
+use std::path::Path;
+
+/// Native SQL formatter built on `sqlformat`, so no external tool is needed.
+///
+/// Clauses go on their own lines and statements are separated by a blank
+/// line. Two options can be set through
+/// `ZenithConfig::zenith_specific`: `uppercase` (default `true`) upper-cases
+/// reserved keywords and `indent_width` (default 2) sets the indentation.
+pub struct SqlZenith;
+
+const DEFAULT_INDENT_WIDTH: u8 = 2;
+
+impl SqlZenith {
+    fn options(config: &ZenithConfig) -> FormatOptions {
+        let specific = &config.zenith_specific;
+        let uppercase = specific
+            .get("uppercase")
+            .and_then(serde_json::Value::as_bool)
+            .unwrap_or(true);
+        let indent_width = specific
+            .get("indent_width")
+            .and_then(serde_json::Value::as_u64)
+            .and_then(|width| u8::try_from(width).ok())
+            .unwrap_or(DEFAULT_INDENT_WIDTH);
+        FormatOptions {
+            indent: Indent::Spaces(indent_width),
+            uppercase,
+            lines_between_queries: 2,
+        }
+    }
+}
+
+#[async_trait]
+impl Zenith for SqlZenith {
+    fn name(&self) -> &str {
+        "sql"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["sql"]
+    }
+
+    async fn format(&self, content: &[u8], _path: &Path, config: &ZenithConfig) -> Result<Vec<u8>> {
+        let text = std::str::from_utf8(content).map_err(|e| ZenithError::ZenithFailed {
+            name: self.name().into(),
+            reason: format!("invalid UTF-8: {}", e),
+        })?;
+        if text.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut formatted = sqlformat::format(text, &QueryParams::None, Self::options(config))
+            .trim_end()
+            .to_string();
+        formatted.push('\n');
+        Ok(formatted.into_bytes())
+    }
+}
@@ -5,36 +5,241 @@
 
 use crate::config::types::ZenithConfig;
 use crate::core::traits::Zenith;
-use crate::error::Result;
-use crate::zeniths::common::StdioFormatter;
+use crate::error::{Result, ZenithError};
 use async_trait::async_trait;
 use std::path::Path;
+use toml_edit::{Array, DocumentMut, InlineTable, Item, RawString, Table, Value};
 
+const INDENT: &str = "    ";
+
+/// Native TOML formatter.
+///
+/// Documents are parsed with `toml_edit`, so comments, key order and value
+/// representations are preserved; only the whitespace around them is
+/// normalized:
+///
+/// - `key = value` with single spaces, no indentation of keys or headers
+/// - one blank line before every table header, at most one blank line
+///   between entries
+/// - inline tables as `{ a = 1, b = 2 }`
+/// - arrays on one line unless they were written across lines or contain
+///   comments, in which case each element goes on its own indented line with
+///   a trailing comma
 pub struct TomlZenith;
 
+impl TomlZenith {
+    fn format_document(text: &str) -> Result<String> {
+        let mut doc: DocumentMut =
+            text.parse()
+                .map_err(|e: toml_edit::TomlError| ZenithError::ZenithFailed {
+                    name: "toml".into(),
+                    reason: e.to_string().trim().to_string(),
+                })?;
+
+        normalize_table_body(doc.as_table_mut());
+        let trailing = comment_lines(&format!("{}\n", raw(Some(doc.trailing()))), true);
+        doc.set_trailing(trailing);
+
+        let formatted = doc.to_string();
+        let formatted = formatted.trim_start_matches('\n').trim_end();
+        if formatted.is_empty() {
+            return Ok(String::new());
+        }
+        Ok(format!("{}\n", formatted))
+    }
+}
+
 #[async_trait]
 impl Zenith for TomlZenith {
     fn name(&self) -> &str {
-        "taplo"
+        "toml"
     }
 
     fn extensions(&self) -> &[&str] {
         &["toml"]
     }
 
-    async fn format(&self, content: &[u8], path: &Path, _config: &ZenithConfig) -> Result<Vec<u8>> {
-        let formatter = StdioFormatter {
-            tool_name: "taplo",
-            args: vec![
-                "format".into(),
-                "-".into(),
-                "--stdin-filepath".into(),
-                path.to_string_lossy().into(),
-            ],
-            timeout_seconds: None,
+    async fn format(
+        &self,
+        content: &[u8],
+        _path: &Path,
+        _config: &ZenithConfig,
+    ) -> Result<Vec<u8>> {
+        let text = String::from_utf8(content.to_vec())?;
+        Ok(Self::format_document(&text)?.into_bytes())
+    }
+
+    async fn validate(&self, content: &[u8]) -> Result<bool> {
+        Ok(std::str::from_utf8(content)
+            .map(|text| text.parse::<DocumentMut>().is_ok())
+            .unwrap_or(false))
+    }
+}
+
+fn raw(raw: Option<&RawString>) -> &str {
+    raw.and_then(RawString::as_str).unwrap_or("")
+}
+
+/// Keep the comment lines of a whitespace-and-comments prefix, dropping
+/// indentation and collapsing runs of blank lines into one.
+///
+/// Leading blank lines are only kept when `keep_leading_blank` is set.
+fn comment_lines(prefix: &str, keep_leading_blank: bool) -> String {
+    let mut output = String::new();
+    let mut pending_blank = false;
+    let mut lines: Vec<&str> = prefix.split('\n').collect();
+    // The final segment is the indentation in front of the item itself
+    lines.pop();
+
+    for line in lines {
+        let line = line.trim();
+        if line.is_empty() {
+            pending_blank = true;
+            continue;
+        }
+        if pending_blank && (keep_leading_blank || !output.is_empty()) {
+            output.push('\n');
+        }
+        pending_blank = false;
+        output.push_str(line);
+        output.push('\n');
+    }
+    if pending_blank && (keep_leading_blank || !output.is_empty()) {
+        output.push('\n');
+    }
+    output
+}
+
+/// Normalize the text after a value or header on the same line.
+fn trailing_comment(suffix: &str) -> String {
+    let comment = suffix.trim();
+    if comment.is_empty() {
+        String::new()
+    } else {
+        format!(" {}", comment)
+    }
+}
+
+fn normalize_table_body(table: &mut Table) {
+    let mut first_entry = true;
+    for (mut key, item) in table.iter_mut() {
+        key.dotted_decor_mut().clear();
+        match item {
+            Item::Value(value) => {
+                let prefix = comment_lines(raw(key.leaf_decor().prefix()), !first_entry);
+                key.leaf_decor_mut().set_prefix(prefix);
+                key.leaf_decor_mut().set_suffix(" ");
+                normalize_value(value, 0);
+                let suffix = trailing_comment(raw(value.decor().suffix()));
+                value.decor_mut().set_prefix(" ");
+                value.decor_mut().set_suffix(suffix);
+                first_entry = false;
+            }
+            Item::Table(child) if child.is_dotted() => {
+                key.leaf_decor_mut().set_prefix("");
+                key.leaf_decor_mut().set_suffix("");
+                normalize_table_body(child);
+                first_entry = false;
+            }
+            Item::Table(child) => {
+                key.leaf_decor_mut().set_prefix("");
+                key.leaf_decor_mut().set_suffix("");
+                normalize_table(child);
+            }
+            Item::ArrayOfTables(tables) => {
+                key.leaf_decor_mut().set_prefix("");
+                key.leaf_decor_mut().set_suffix("");
+                for child in tables.iter_mut() {
+                    normalize_table(child);
+                }
+            }
+            Item::None => {}
+        }
+    }
+}
+
+fn normalize_table(table: &mut Table) {
+    let decor = table.decor_mut();
+    let prefix = format!("\n{}", comment_lines(raw(decor.prefix()), false));
+    let suffix = trailing_comment(raw(decor.suffix()));
+    decor.set_prefix(prefix);
+    decor.set_suffix(suffix);
+    normalize_table_body(table);
+}
+
+fn normalize_value(value: &mut Value, depth: usize) {
+    match value {
+        Value::Array(array) => normalize_array(array, depth),
+        Value::InlineTable(table) => normalize_inline_table(table, depth),
+        _ => {}
+    }
+}
+
+fn normalize_inline_table(table: &mut InlineTable, depth: usize) {
+    // Inline tables cannot contain newlines or comments, so the default
+    // decoration (`{ a = 1, b = 2 }`) is always safe to apply
+    table.set_preamble("");
+    for (mut key, value) in table.iter_mut() {
+        key.leaf_decor_mut().clear();
+        key.dotted_decor_mut().clear();
+        normalize_value(value, depth);
+        value.decor_mut().clear();
+    }
+}
+
+fn normalize_array(array: &mut Array, depth: usize) {
+    let multiline = raw(Some(array.trailing())).contains(['\n', '#'])
+        || array.iter().any(|value| {
+            let decor = value.decor();
+            raw(decor.prefix()).contains(['\n', '#']) || raw(decor.suffix()).contains('#')
+        });
+
+    if !multiline {
+        for (i, value) in array.iter_mut().enumerate() {
+            normalize_value(value, depth);
+            value.decor_mut().set_prefix(if i == 0 { "" } else { " " });
+            value.decor_mut().set_suffix("");
+        }
+        array.set_trailing("");
+        array.set_trailing_comma(false);
+        return;
+    }
+
+    let indent = INDENT.repeat(depth + 1);
+    for value in array.iter_mut() {
+        normalize_value(value, depth + 1);
+        let prefix = element_prefix(raw(value.decor().prefix()), &indent);
+        let suffix = match trailing_comment(raw(value.decor().suffix())) {
+            comment if comment.is_empty() => comment,
+            comment => format!("{}\n{}", comment, indent),
         };
-        formatter
-            .format_with_stdio_no_path(content, path, None)
-            .await
+        value.decor_mut().set_prefix(prefix);
+        value.decor_mut().set_suffix(suffix);
+    }
+    let trailing = element_prefix(raw(Some(array.trailing())), &INDENT.repeat(depth));
+    array.set_trailing(trailing);
+    array.set_trailing_comma(!array.is_empty());
+}
+
+/// Whitespace in front of a multi-line array element (or the closing `]`).
+///
+/// A comment on the same line as the previous element stays there; comment
+/// lines in between are re-indented.
+fn element_prefix(prefix: &str, indent: &str) -> String {
+    let (same_line, rest) = match prefix.split_once('\n') {
+        Some((same_line, rest)) => (same_line, format!("\n{}", rest)),
+        None => ("", String::new()),
+    };
+
+    let mut output = trailing_comment(same_line);
+    output.push('\n');
+    for line in comment_lines(&rest, false).lines() {
+        if !line.is_empty() {
+            output.push_str(indent);
+            output.push_str(line);
+        }
+        output.push('\n');
     }
+    output.push_str(indent);
+    output
 }
@@ -0,0 +1,278 @@
+// Copyright (c) 2025 Kirky.X
+//
+// Licensed under the MIT License
+// See LICENSE file in the project root for full license information.
+
+use crate::config::types::ZenithConfig;
+use crate::core::traits::Zenith;
+use crate::error::{Result, ZenithError};
+use crate::zeniths::registry::ZenithRegistry;
+use async_trait::async_trait;
+use std::path::Path;
+use std::sync::Weak;
+
+/// Formatter for Vue single-file components.
+///
+/// Splits the component into its top-level `<template>`, `<script>` and
+/// `<style>` blocks and formats each one with the formatter registered for
+/// its language (`lang` attribute, or html/js/css by default). Opening tags,
+/// custom blocks and the text between blocks are kept verbatim, so attributes
+/// such as `lang="ts"` or `scoped` and the block order are preserved. Blocks
+/// without a registered formatter are left unchanged.
+pub struct VueZenith {
+    registry: Weak<ZenithRegistry>,
+}
+
+impl VueZenith {
+    /// The registry is held weakly because it also owns this formatter.
+    pub fn new(registry: Weak<ZenithRegistry>) -> Self {
+        Self { registry }
+    }
+
+    async fn format_block(
+        &self,
+        block: &SfcBlock<'_>,
+        path: &Path,
+        config: &ZenithConfig,
+    ) -> Result<String> {
+        let unchanged = format!("{}{}{}", block.open, block.content, block.close);
+        if block.content.trim().is_empty() {
+            return Ok(unchanged);
+        }
+
+        let ext = block.language();
+        let formatter = self
+            .registry
+            .upgrade()
+            .and_then(|registry| registry.get_by_extension(ext))
+            .filter(|formatter| formatter.name() != self.name());
+        let Some(formatter) = formatter else {
+            tracing::debug!("No formatter for <{}> block with lang '{}'", block.tag, ext);
+            return Ok(unchanged);
+        };
+
+        let block_path = path.with_extension(ext);
+        let inner = block.content.trim_matches(['\r', '\n']);
+        let formatted = formatter
+            .format(inner.as_bytes(), &block_path, config)
+            .await
+            .map_err(|e| ZenithError::ZenithFailed {
+                name: self.name().into(),
+                reason: format!("<{}> block: {}", block.tag, e),
+            })?;
+        let formatted = String::from_utf8(formatted)?;
+
+        let mut output = String::from(block.open);
+        output.push('\n');
+        for line in formatted.trim_end().lines() {
+            // Template content is conventionally indented inside <template>
+            if block.tag == "template" && !line.is_empty() {
+                output.push_str("  ");
+            }
+            output.push_str(line);
+            output.push('\n');
+        }
+        output.push_str(block.close);
+        Ok(output)
+    }
+}
+
+#[async_trait]
+impl Zenith for VueZenith {
+    fn name(&self) -> &str {
+        "vue"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["vue"]
+    }
+
+    /// Takes precedence over prettier's wholesale `.vue` handling.
+    fn priority(&self) -> i32 {
+        10
+    }
+
+    async fn format(&self, content: &[u8], path: &Path, config: &ZenithConfig) -> Result<Vec<u8>> {
+        let text = std::str::from_utf8(content).map_err(|e| ZenithError::ZenithFailed {
+            name: self.name().into(),
+            reason: format!("invalid UTF-8: {}", e),
+        })?;
+
+        let mut output = String::with_capacity(text.len());
+        for segment in parse_sfc(text)? {
+            match segment {
+                Segment::Text(raw) => output.push_str(raw),
+                Segment::Block(block) => {
+                    output.push_str(&self.format_block(&block, path, config).await?)
+                }
+            }
+        }
+
+        Ok(output.into_bytes())
+    }
+}
+
+/// A top-level SFC block split into its raw parts.
+struct SfcBlock<'a> {
+    tag: &'a str,
+    open: &'a str,
+    content: &'a str,
+    close: &'a str,
+}
+
+impl SfcBlock<'_> {
+    /// Extension of the formatter to use, from `lang` or the block default.
+    fn language(&self) -> &str {
+        attribute(self.open, "lang").unwrap_or(match self.tag {
+            "template" => "html",
+            "script" => "js",
+            _ => "css",
+        })
+    }
+}
+
+enum Segment<'a> {
+    Text(&'a str),
+    Block(SfcBlock<'a>),
+}
+
+const FORMATTED_BLOCKS: &[&str] = &["template", "script", "style"];
+
+/// Split an SFC into formattable blocks and verbatim text.
+fn parse_sfc(text: &str) -> Result<Vec<Segment<'_>>> {
+    let mut segments = Vec::new();
+    let mut text_start = 0;
+    let mut pos = 0;
+
+    while let Some(offset) = text[pos..].find('<') {
+        let tag_start = pos + offset;
+        let rest = &text[tag_start..];
+
+        if rest.starts_with("<!--") {
+            pos = rest
+                .find("-->")
+                .map(|end| tag_start + end + 3)
+                .unwrap_or(text.len());
+            continue;
+        }
+
+        let name_len = rest[1..]
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == '_'))
+            .unwrap_or(rest.len() - 1);
+        if name_len == 0 {
+            pos = tag_start + 1;
+            continue;
+        }
+        let tag = &rest[1..1 + name_len];
+
+        let open_end = tag_start
+            + rest
+                .find('>')
+                .ok_or_else(|| malformed(format!("unterminated <{}> tag", tag)))?
+            + 1;
+        if text[..open_end].ends_with("/>") {
+            pos = open_end;
+            continue;
+        }
+
+        let close_start = find_closing_tag(text, open_end, tag)
+            .ok_or_else(|| malformed(format!("missing </{}>", tag)))?;
+        let close_end = close_start
+            + text[close_start..]
+                .find('>')
+                .ok_or_else(|| malformed(format!("unterminated </{}> tag", tag)))?
+            + 1;
+
+        if FORMATTED_BLOCKS.contains(&tag) {
+            if text_start < tag_start {
+                segments.push(Segment::Text(&text[text_start..tag_start]));
+            }
+            segments.push(Segment::Block(SfcBlock {
+                tag,
+                open: &text[tag_start..open_end],
+                content: &text[open_end..close_start],
+                close: &text[close_start..close_end],
+            }));
+            text_start = close_end;
+        }
+        // Custom blocks are skipped as a whole and kept verbatim
+        pos = close_end;
+    }
+
+    if text_start < text.len() {
+        segments.push(Segment::Text(&text[text_start..]));
+    }
+    Ok(segments)
+}
+
+/// Find the `</tag>` matching an opening tag, accounting for nesting.
+fn find_closing_tag(text: &str, from: usize, tag: &str) -> Option<usize> {
+    let open = format!("<{}", tag);
+    let close = format!("</{}", tag);
+    let mut depth = 1;
+    let mut pos = from;
+
+    loop {
+        let next_close = pos + text[pos..].find(&close)?;
+        // Only <template> can legitimately nest inside itself
+        let next_open = if tag == "template" {
+            text[pos..next_close]
+                .match_indices(&open)
+                .map(|(i, _)| pos + i)
+                .find(|&i| is_tag_boundary(text, i + open.len()))
+        } else {
+            None
+        };
+
+        match next_open {
+            Some(open_at) => {
+                depth += 1;
+                pos = open_at + open.len();
+            }
+            None if is_tag_boundary(text, next_close + close.len()) => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(next_close);
+                }
+                pos = next_close + close.len();
+            }
+            None => pos = next_close + close.len(),
+        }
+    }
+}
+
+fn is_tag_boundary(text: &str, at: usize) -> bool {
+    text[at..]
+        .chars()
+        .next()
+        .is_some_and(|c| c == '>' || c == '/' || c.is_whitespace())
+}
+
+/// Read a quoted attribute value from an opening tag.
+fn attribute<'a>(open_tag: &'a str, name: &str) -> Option<&'a str> {
+    let needle = format!("{}=", name);
+    let mut search = 0;
+    while let Some(found) = open_tag[search..].find(&needle) {
+        let at = search + found;
+        search = at + needle.len();
+        let preceded_by_space = open_tag[..at]
+            .chars()
+            .last()
+            .is_some_and(|c| c.is_whitespace());
+        if !preceded_by_space {
+            continue;
+        }
+        let value = &open_tag[search..];
+        let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+        let end = value[1..].find(quote)?;
+        return Some(&value[1..1 + end]);
+    }
+    None
+}
+
+fn malformed(reason: String) -> ZenithError {
+    ZenithError::ZenithFailed {
+        name: "vue".into(),
+        reason,
+    }
+}
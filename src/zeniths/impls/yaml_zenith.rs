@@ -0,0 +1,368 @@
+// Copyright (c) 2025 Kirky.X
+//
+// Licensed under the MIT License
+// See LICENSE file in the project root for full license information.
+
+use crate::config::types::ZenithConfig;
+use crate::core::traits::Zenith;
+use crate::error::{Result, ZenithError};
+use async_trait::async_trait;
+use std::path::Path;
+use yaml_rust2::parser::{Event, EventReceiver, Parser};
+use yaml_rust2::scanner::TScalarStyle;
+
+/// Native YAML formatter.
+///
+/// Documents are re-emitted in block style with two-space indentation.
+/// Scalars keep their original text and quoting, so `0x1F`, `~` or `yes`
+/// come out exactly as written. Multi-document files keep their `---`
+/// separators.
+///
+/// The parser drops comments and the emitter has no way to reproduce
+/// anchors, aliases, tags, directives or folded scalars, so any file using
+/// them is reported as skipped rather than rewritten with a loss.
+///
+/// Setting `sort_keys` in `ZenithConfig::zenith_specific` sorts the keys of
+/// every mapping. The sort is stable, so duplicate keys keep their order.
+pub struct YamlZenith;
+
+#[derive(Debug, Default, Clone, Copy)]
+struct YamlOptions {
+    sort_keys: bool,
+}
+
+impl YamlOptions {
+    fn from_config(config: &ZenithConfig) -> Self {
+        Self {
+            sort_keys: config
+                .zenith_specific
+                .get("sort_keys")
+                .and_then(serde_json::Value::as_bool)
+                .unwrap_or(false),
+        }
+    }
+}
+
+enum Node {
+    Scalar(String, TScalarStyle),
+    Seq(Vec<Node>),
+    Map(Vec<(Node, Node)>),
+}
+
+enum Frame {
+    Seq(Vec<Node>),
+    Map(Vec<(Node, Node)>, Option<Node>),
+}
+
+/// Builds a node tree from parser events, flagging anything the emitter
+/// cannot reproduce.
+#[derive(Default)]
+struct TreeBuilder {
+    documents: Vec<Node>,
+    stack: Vec<Frame>,
+    unsupported: bool,
+}
+
+impl TreeBuilder {
+    fn push(&mut self, node: Node) {
+        match self.stack.last_mut() {
+            None => self.documents.push(node),
+            Some(Frame::Seq(items)) => items.push(node),
+            Some(Frame::Map(entries, key)) => match key.take() {
+                Some(key) => entries.push((key, node)),
+                None => {
+                    let plain_key = matches!(
+                        &node,
+                        Node::Scalar(text, style) if !text.is_empty() && *style != TScalarStyle::Literal
+                    );
+                    if !plain_key {
+                        self.unsupported = true;
+                    }
+                    *key = Some(node);
+                }
+            },
+        }
+    }
+}
+
+impl EventReceiver for TreeBuilder {
+    fn on_event(&mut self, ev: Event) {
+        match ev {
+            Event::Alias(_) => self.unsupported = true,
+            Event::Scalar(text, style, anchor, tag) => {
+                if anchor != 0 || tag.is_some() || style == TScalarStyle::Folded {
+                    self.unsupported = true;
+                }
+                self.push(Node::Scalar(text, style));
+            }
+            Event::SequenceStart(anchor, tag) => {
+                if anchor != 0 || tag.is_some() {
+                    self.unsupported = true;
+                }
+                self.stack.push(Frame::Seq(Vec::new()));
+            }
+            Event::MappingStart(anchor, tag) => {
+                if anchor != 0 || tag.is_some() {
+                    self.unsupported = true;
+                }
+                self.stack.push(Frame::Map(Vec::new(), None));
+            }
+            Event::SequenceEnd | Event::MappingEnd => {
+                let node = match self.stack.pop() {
+                    Some(Frame::Seq(items)) => Node::Seq(items),
+                    Some(Frame::Map(entries, _)) => Node::Map(entries),
+                    None => return,
+                };
+                self.push(node);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl YamlZenith {
+    /// Comments, directives and explicit document ends are not reported by
+    /// the parser, so they are detected on the source text instead. Quoted
+    /// `#` characters also match, which only costs a skipped rewrite.
+    fn unparsed_syntax(text: &str) -> Option<&'static str> {
+        text.lines().find_map(|line| {
+            let trimmed = line.trim_start();
+            if trimmed.starts_with('%') {
+                return Some("cannot keep directives");
+            }
+            if trimmed.starts_with("...") {
+                return Some("cannot keep explicit document ends");
+            }
+            let comment = trimmed.starts_with('#')
+                || line
+                    .as_bytes()
+                    .windows(2)
+                    .any(|pair| pair[0].is_ascii_whitespace() && pair[1] == b'#');
+            comment.then_some("cannot keep comments")
+        })
+    }
+
+    fn sort(node: &mut Node) {
+        match node {
+            Node::Scalar(..) => {}
+            Node::Seq(items) => items.iter_mut().for_each(Self::sort),
+            Node::Map(entries) => {
+                entries.sort_by(|(a, _), (b, _)| Self::key_text(a).cmp(Self::key_text(b)));
+                entries.iter_mut().for_each(|(_, value)| Self::sort(value));
+            }
+        }
+    }
+
+    fn key_text(node: &Node) -> &str {
+        match node {
+            Node::Scalar(text, _) => text,
+            _ => "",
+        }
+    }
+
+    fn double_quoted(text: &str) -> String {
+        let mut out = String::with_capacity(text.len() + 2);
+        out.push('"');
+        for c in text.chars() {
+            match c {
+                '\\' => out.push_str("\\\\"),
+                '"' => out.push_str("\\\""),
+                '\n' => out.push_str("\\n"),
+                '\t' => out.push_str("\\t"),
+                '\r' => out.push_str("\\r"),
+                c if c.is_control() && (c as u32) < 0x100 => {
+                    out.push_str(&format!("\\x{:02X}", c as u32))
+                }
+                c if c.is_control() => out.push_str(&format!("\\u{:04X}", c as u32)),
+                c => out.push(c),
+            }
+        }
+        out.push('"');
+        out
+    }
+
+    /// Writes a scalar after its `key:` or `-`, including the newline.
+    /// Block scalar lines go two spaces deeper than `indent`.
+    fn write_scalar(text: &str, style: TScalarStyle, indent: usize, out: &mut String) {
+        match style {
+            TScalarStyle::Plain if text.is_empty() => {}
+            TScalarStyle::Plain | TScalarStyle::SingleQuoted if text.contains('\n') => {
+                out.push(' ');
+                out.push_str(&Self::double_quoted(text));
+            }
+            TScalarStyle::Plain => {
+                out.push(' ');
+                out.push_str(text);
+            }
+            TScalarStyle::SingleQuoted => {
+                out.push_str(" '");
+                out.push_str(&text.replace('\'', "''"));
+                out.push('\'');
+            }
+            TScalarStyle::Literal => {
+                // Leading spaces would need an indentation indicator, and a
+                // body of blank lines cannot be expressed with chomping alone
+                let first = text.lines().find(|line| !line.is_empty());
+                if first.is_none_or(|line| line.starts_with(' ')) && !text.is_empty() {
+                    out.push(' ');
+                    out.push_str(&Self::double_quoted(text));
+                } else {
+                    let body = text.strip_suffix('\n');
+                    let (chomp, body) = match body {
+                        None => ("-", text),
+                        Some(rest) if rest.ends_with('\n') => ("+", rest),
+                        Some(rest) => ("", rest),
+                    };
+                    out.push_str(" |");
+                    out.push_str(chomp);
+                    if !body.is_empty() {
+                        for line in body.split('\n') {
+                            out.push('\n');
+                            if !line.is_empty() {
+                                out.push_str(&" ".repeat(indent + 2));
+                                out.push_str(line);
+                            }
+                        }
+                    }
+                }
+            }
+            _ => {
+                out.push(' ');
+                out.push_str(&Self::double_quoted(text));
+            }
+        }
+        out.push('\n');
+    }
+
+    /// Writes the node that follows a `key:` or `-` at `indent`.
+    fn write_child(node: &Node, indent: usize, after_dash: bool, out: &mut String) {
+        match node {
+            Node::Scalar(text, style) => Self::write_scalar(text, *style, indent, out),
+            Node::Seq(items) if items.is_empty() => out.push_str(" []\n"),
+            Node::Map(entries) if entries.is_empty() => out.push_str(" {}\n"),
+            Node::Seq(items) if after_dash => {
+                out.push(' ');
+                Self::write_seq(items, indent + 2, true, out);
+            }
+            Node::Map(entries) if after_dash => {
+                out.push(' ');
+                Self::write_map(entries, indent + 2, true, out);
+            }
+            Node::Seq(items) => {
+                out.push('\n');
+                Self::write_seq(items, indent + 2, false, out);
+            }
+            Node::Map(entries) => {
+                out.push('\n');
+                Self::write_map(entries, indent + 2, false, out);
+            }
+        }
+    }
+
+    fn write_map(entries: &[(Node, Node)], indent: usize, inline_first: bool, out: &mut String) {
+        for (i, (key, value)) in entries.iter().enumerate() {
+            if i > 0 || !inline_first {
+                out.push_str(&" ".repeat(indent));
+            }
+            if let Node::Scalar(text, style) = key {
+                let mut rendered = String::new();
+                Self::write_scalar(text, *style, indent, &mut rendered);
+                out.push_str(rendered.trim());
+            }
+            out.push(':');
+            Self::write_child(value, indent, false, out);
+        }
+    }
+
+    fn write_seq(items: &[Node], indent: usize, inline_first: bool, out: &mut String) {
+        for (i, item) in items.iter().enumerate() {
+            if i > 0 || !inline_first {
+                out.push_str(&" ".repeat(indent));
+            }
+            out.push('-');
+            Self::write_child(item, indent, true, out);
+        }
+    }
+
+    fn render(documents: &[Node], leading_marker: bool) -> String {
+        let mut out = String::new();
+        for (i, document) in documents.iter().enumerate() {
+            if i > 0 || leading_marker {
+                out.push_str("---\n");
+            }
+            match document {
+                Node::Scalar(text, TScalarStyle::Plain) if text.is_empty() => {}
+                Node::Scalar(text, style) => {
+                    let mut rendered = String::new();
+                    Self::write_scalar(text, *style, 0, &mut rendered);
+                    out.push_str(rendered.strip_prefix(' ').unwrap_or(&rendered));
+                }
+                Node::Seq(items) if !items.is_empty() => Self::write_seq(items, 0, false, &mut out),
+                Node::Map(entries) if !entries.is_empty() => {
+                    Self::write_map(entries, 0, false, &mut out)
+                }
+                Node::Seq(_) => out.push_str("[]\n"),
+                Node::Map(_) => out.push_str("{}\n"),
+            }
+        }
+        out
+    }
+
+    fn skipped(reason: &str) -> ZenithError {
+        tracing::warn!("YAML file left unchanged: the formatter {}", reason);
+        ZenithError::ZenithSkipped {
+            name: "yaml".into(),
+            reason: reason.into(),
+        }
+    }
+
+    fn format_text(text: &str, options: YamlOptions) -> Result<String> {
+        if let Some(reason) = Self::unparsed_syntax(text) {
+            return Err(Self::skipped(reason));
+        }
+
+        let mut builder = TreeBuilder::default();
+        Parser::new_from_str(text)
+            .load(&mut builder, true)
+            .map_err(|e| ZenithError::ZenithFailed {
+                name: "yaml".into(),
+                reason: e.to_string(),
+            })?;
+        if builder.unsupported {
+            return Err(Self::skipped("cannot keep anchors, aliases or tags"));
+        }
+        if builder.documents.is_empty() {
+            return Ok(text.to_string());
+        }
+
+        if options.sort_keys {
+            builder.documents.iter_mut().for_each(Self::sort);
+        }
+        let leading_marker = text.trim_start().starts_with("---");
+        Ok(Self::render(&builder.documents, leading_marker))
+    }
+}
+
+#[async_trait]
+impl Zenith for YamlZenith {
+    fn name(&self) -> &str {
+        "yaml"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["yaml", "yml"]
+    }
+
+    /// Takes precedence over prettier's YAML handling.
+    fn priority(&self) -> i32 {
+        10
+    }
+
+    async fn format(&self, content: &[u8], _path: &Path, config: &ZenithConfig) -> Result<Vec<u8>> {
+        let text = std::str::from_utf8(content).map_err(|e| ZenithError::ZenithFailed {
+            name: self.name().into(),
+            reason: format!("invalid UTF-8: {}", e),
+        })?;
+        Self::format_text(text, YamlOptions::from_config(config)).map(String::into_bytes)
+    }
+}
@@ -4,6 +4,7 @@
 // See LICENSE file in the project root for full license information.
 
 pub mod common;
+pub mod pool;
 pub mod registry;
 
 pub(crate) mod impls;
@@ -0,0 +1,360 @@
+// Copyright (c) 2025 Kirky.X
+//
+// Licensed under the MIT License
+// See LICENSE file in the project root for full license information.
+
+//! Long-lived formatter processes that are reused across files.
+//!
+//! Starting `prettier` once per file dominates runtime on large trees. A
+//! formatter that can handle many inputs over one stdin runs as a daemon
+//! instead, and the pool hands idle daemons to whichever file needs one.
+//!
+//! Daemons speak a small length-prefixed protocol over stdin/stdout:
+//!
+//! - on startup the daemon writes `{"ready": true}` (or `{"ready": false,
+//!   "error": "..."}`) on one line;
+//! - each request is one JSON header line carrying `length` plus any
+//!   formatter-specific fields, followed by `length` bytes of content;
+//! - each response is one `{"ok": bool, "length": n}` line followed by `n`
+//!   bytes: the formatted content, or the error message when `ok` is false.
+//!
+//! A daemon that fails to start, or breaks the protocol, marks its formatter
+//! as unsupported; callers then fall back to spawning one process per file.
+//! A daemon that takes too long on one file is killed, and that file alone
+//! falls back.
+
+use crate::error::{Result, ZenithError};
+use crate::zeniths::common::reject_empty_output;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, ChildStdout, Command};
+use tokio::sync::Mutex;
+use tokio::time::timeout;
+use tracing::{debug, warn};
+
+/// How long a daemon may take to report that it is ready
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long a daemon may take to format one file, as for a spawned tool
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How to start the daemon for one formatter
+#[derive(Debug, Clone)]
+pub struct DaemonSpec {
+    /// Formatter name, used to key the pool
+    pub name: &'static str,
+    pub program: &'static str,
+    pub args: Vec<String>,
+}
+
+#[derive(Debug)]
+struct Daemon {
+    // Held so the process is killed when the daemon is dropped
+    _child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+}
+
+#[derive(Deserialize)]
+struct Ready {
+    ready: bool,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Response {
+    ok: bool,
+    length: usize,
+}
+
+/// Pool of idle formatter daemons, keyed by formatter name
+#[derive(Debug)]
+pub struct FormatterPool {
+    idle: Mutex<HashMap<&'static str, Vec<Daemon>>>,
+    unsupported: Mutex<HashSet<&'static str>>,
+    request_timeout: Duration,
+}
+
+impl Default for FormatterPool {
+    fn default() -> Self {
+        Self {
+            idle: Mutex::default(),
+            unsupported: Mutex::default(),
+            request_timeout: REQUEST_TIMEOUT,
+        }
+    }
+}
+
+impl FormatterPool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set how long a daemon may take to format one file
+    pub fn with_request_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = timeout;
+        self
+    }
+
+    /// Send one file to a daemon for `spec`
+    ///
+    /// Returns `None` when the formatter cannot run as a daemon, in which
+    /// case the caller should spawn the tool for this file itself. Errors
+    /// reported by the daemon, such as syntax errors, come back as
+    /// `Some(Err(_))`.
+    pub async fn request(
+        &self,
+        spec: &DaemonSpec,
+        header: serde_json::Value,
+        content: &[u8],
+    ) -> Option<Result<Vec<u8>>> {
+        if self.unsupported.lock().await.contains(spec.name) {
+            return None;
+        }
+
+        let idle = self.idle.lock().await.get_mut(spec.name).and_then(Vec::pop);
+        let mut daemon = match idle {
+            Some(daemon) => daemon,
+            None => match Self::spawn(spec).await {
+                Ok(daemon) => daemon,
+                Err(reason) => {
+                    self.mark_unsupported(spec, &reason).await;
+                    return None;
+                }
+            },
+        };
+
+        let exchange = Self::exchange(&mut daemon, header, content);
+        match timeout(self.request_timeout, exchange).await {
+            Ok(Ok(response)) => {
+                self.idle
+                    .lock()
+                    .await
+                    .entry(spec.name)
+                    .or_default()
+                    .push(daemon);
+                Some(
+                    response
+                        .map_err(|reason| ZenithError::ZenithFailed {
+                            name: spec.name.into(),
+                            reason,
+                        })
+                        .and_then(|output| reject_empty_output(spec.name, content, output)),
+                )
+            }
+            Ok(Err(e)) => {
+                self.mark_unsupported(spec, &e.to_string()).await;
+                None
+            }
+            Err(_) => {
+                // The daemon may still be working on the request; dropping
+                // it kills the process so its reply cannot reach the next file
+                warn!(
+                    "Persistent formatter '{}' timed out after {:?}; spawning it for this file",
+                    spec.name, self.request_timeout
+                );
+                None
+            }
+        }
+    }
+
+    async fn mark_unsupported(&self, spec: &DaemonSpec, reason: &str) {
+        if self.unsupported.lock().await.insert(spec.name) {
+            warn!(
+                "Formatter '{}' cannot run as a persistent process ({}); spawning it per file",
+                spec.name, reason
+            );
+        }
+        self.idle.lock().await.remove(spec.name);
+    }
+
+    async fn spawn(spec: &DaemonSpec) -> std::result::Result<Daemon, String> {
+        debug!("Starting persistent formatter '{}'", spec.name);
+        let mut child = Command::new(spec.program)
+            .args(&spec.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| e.to_string())?;
+        let (Some(stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+            return Err("stdio not captured".into());
+        };
+        let mut daemon = Daemon {
+            _child: child,
+            stdin,
+            stdout: BufReader::new(stdout),
+        };
+
+        let mut line = String::new();
+        match timeout(STARTUP_TIMEOUT, daemon.stdout.read_line(&mut line)).await {
+            Ok(Ok(n)) if n > 0 => {}
+            Ok(Ok(_)) => return Err("exited during startup".into()),
+            Ok(Err(e)) => return Err(e.to_string()),
+            Err(_) => return Err("timed out during startup".into()),
+        }
+        let ready: Ready = serde_json::from_str(&line).map_err(|e| e.to_string())?;
+        if ready.ready {
+            Ok(daemon)
+        } else {
+            Err(ready.error.unwrap_or_else(|| "not ready".into()))
+        }
+    }
+
+    /// One request/response round trip; the outer error means the daemon is
+    /// no longer usable
+    async fn exchange(
+        daemon: &mut Daemon,
+        mut header: serde_json::Value,
+        content: &[u8],
+    ) -> std::io::Result<std::result::Result<Vec<u8>, String>> {
+        header["length"] = content.len().into();
+        let mut request = serde_json::to_vec(&header)?;
+        request.push(b'\n');
+        request.extend_from_slice(content);
+        daemon.stdin.write_all(&request).await?;
+        daemon.stdin.flush().await?;
+
+        let mut line = String::new();
+        if daemon.stdout.read_line(&mut line).await? == 0 {
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        }
+        let response: Response = serde_json::from_str(&line)?;
+        let mut body = vec![0; response.length];
+        daemon.stdout.read_exact(&mut body).await?;
+
+        Ok(if response.ok {
+            Ok(body)
+        } else {
+            Err(String::from_utf8_lossy(&body).into_owned())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A daemon that upper-cases its input and rejects empty files
+    fn upper_daemon(name: &'static str) -> DaemonSpec {
+        DaemonSpec {
+            name,
+            program: "node",
+            args: vec![
+                "-e".into(),
+                r#"
+let buf = Buffer.alloc(0);
+process.stdout.write(JSON.stringify({ ready: true }) + "\n");
+process.stdin.on("data", (d) => {
+  buf = Buffer.concat([buf, d]);
+  for (;;) {
+    const nl = buf.indexOf(10);
+    if (nl < 0) return;
+    const header = JSON.parse(buf.subarray(0, nl).toString());
+    if (buf.length < nl + 1 + header.length) return;
+    const text = buf.subarray(nl + 1, nl + 1 + header.length).toString();
+    buf = buf.subarray(nl + 1 + header.length);
+    const ok = text.length > 0;
+    const out = Buffer.from(ok ? header.prefix + text.toUpperCase() : "empty input");
+    process.stdout.write(JSON.stringify({ ok, length: out.length }) + "\n");
+    process.stdout.write(out);
+  }
+});
+"#
+                .into(),
+            ],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_pool_reuses_daemon_across_requests() {
+        if std::process::Command::new("node")
+            .arg("--version")
+            .output()
+            .is_err()
+        {
+            return;
+        }
+
+        let pool = FormatterPool::new();
+        let spec = upper_daemon("upper");
+        for text in ["abc", "déjà vu\n"] {
+            let output = pool
+                .request(
+                    &spec,
+                    serde_json::json!({ "prefix": "> " }),
+                    text.as_bytes(),
+                )
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(output, format!("> {}", text.to_uppercase()).into_bytes());
+        }
+        assert_eq!(pool.idle.lock().await["upper"].len(), 1);
+
+        let err = pool
+            .request(&spec, serde_json::json!({}), b"")
+            .await
+            .unwrap()
+            .unwrap_err();
+        assert!(err.to_string().contains("empty input"));
+        assert_eq!(pool.idle.lock().await["upper"].len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_pool_falls_back_when_daemon_cannot_start() {
+        let pool = FormatterPool::new();
+        let spec = DaemonSpec {
+            name: "missing",
+            program: "zenith-definitely-missing-daemon",
+            args: Vec::new(),
+        };
+        assert!(pool
+            .request(&spec, serde_json::json!({}), b"x")
+            .await
+            .is_none());
+        assert!(pool.unsupported.lock().await.contains("missing"));
+    }
+
+    #[tokio::test]
+    async fn test_pool_drops_daemon_that_times_out() {
+        if std::process::Command::new("node")
+            .arg("--version")
+            .output()
+            .is_err()
+        {
+            return;
+        }
+
+        let pool = FormatterPool::new().with_request_timeout(Duration::from_millis(500));
+        let spec = DaemonSpec {
+            name: "silent",
+            program: "node",
+            args: vec![
+                "-e".into(),
+                r#"
+process.stdout.write(JSON.stringify({ ready: true }) + "\n");
+process.stdin.resume();
+"#
+                .into(),
+            ],
+        };
+        assert!(pool
+            .request(&spec, serde_json::json!({}), b"x")
+            .await
+            .is_none());
+        assert!(pool
+            .idle
+            .lock()
+            .await
+            .get("silent")
+            .is_none_or(Vec::is_empty));
+        // Only this file falls back; the formatter keeps using daemons
+        assert!(!pool.unsupported.lock().await.contains("silent"));
+    }
+}
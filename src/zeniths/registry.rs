@@ -5,6 +5,8 @@
 
 use crate::core::traits::Zenith;
 use dashmap::DashMap;
+use std::cmp::Reverse;
+use std::path::Path;
 use std::sync::Arc;
 
 pub struct ZenithRegistry {
@@ -29,6 +31,8 @@ impl ZenithRegistry {
     pub fn register(&self, zenith: Arc<dyn Zenith>) {
         let name = zenith.name().to_string();
         let priority = zenith.priority();
+        // Re-registering a name replaces it, including its old extensions
+        self.unregister(&name);
         for ext in zenith.extensions() {
             self.extension_map
                 .entry(ext.to_string())
@@ -49,10 +53,44 @@ impl ZenithRegistry {
             .and_then(|name| self.zeniths.get(&name).map(|z| z.clone()))
     }
 
+    /// Highest-priority formatter whose `supports_path` claims `path`
+    pub fn get_by_path(&self, path: &Path, first_line: Option<&str>) -> Option<Arc<dyn Zenith>> {
+        self.zeniths
+            .iter()
+            .filter(|entry| entry.value().supports_path(path, first_line))
+            .max_by_key(|entry| (entry.value().priority(), Reverse(entry.key().clone())))
+            .map(|entry| entry.value().clone())
+    }
+
+    pub fn get_by_name(&self, name: &str) -> Option<Arc<dyn Zenith>> {
+        self.zeniths.get(name).map(|z| z.clone())
+    }
+
+    /// Remove a formatter and its extension mappings.
+    ///
+    /// Returns `false` if no formatter with that name was registered.
+    pub fn unregister(&self, name: &str) -> bool {
+        if self.zeniths.remove(name).is_none() {
+            return false;
+        }
+        for mut entries in self.extension_map.iter_mut() {
+            entries.retain(|(_, n, _)| n != name);
+        }
+        self.extension_map.retain(|_, entries| !entries.is_empty());
+        true
+    }
+
     pub fn list_all(&self) -> Vec<Arc<dyn Zenith>> {
         self.zeniths
             .iter()
             .map(|item| item.value().clone())
             .collect()
     }
+
+    /// Names of all registered formatters, sorted
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.zeniths.iter().map(|item| item.key().clone()).collect();
+        names.sort();
+        names
+    }
 }
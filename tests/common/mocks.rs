@@ -82,3 +82,50 @@ impl Zenith for MockFormatter {
         Ok(true)
     }
 }
+
+/// Formatter that strips surrounding whitespace from every line and ends the
+/// output with a single newline, so its effect is easy to assert on.
+#[cfg(feature = "full")]
+pub struct LineTrimFormatter {
+    name: String,
+    extensions: Vec<&'static str>,
+}
+
+#[cfg(feature = "full")]
+impl LineTrimFormatter {
+    pub fn new(name: &str, extensions: &[&'static str]) -> Self {
+        Self {
+            name: name.to_string(),
+            extensions: extensions.to_vec(),
+        }
+    }
+}
+
+#[cfg(feature = "full")]
+#[async_trait::async_trait]
+impl Zenith for LineTrimFormatter {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &self.extensions
+    }
+
+    async fn format(
+        &self,
+        content: &[u8],
+        _path: &std::path::Path,
+        _config: &ZenithConfig,
+    ) -> Result<Vec<u8>, ZenithError> {
+        let text = String::from_utf8_lossy(content);
+        let mut output: String = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n");
+        output.push('\n');
+        Ok(output.into_bytes())
+    }
+}
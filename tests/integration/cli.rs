@@ -197,8 +197,7 @@ fn test_zenith_format_with_config() {
 [global]
 backup_enabled = false
 
-[formatters.rust]
-command = "rustfmt"
+[zeniths.rs]
 args = ["--edition", "2021"]
 "#;
     create_test_file(temp_dir.path(), "zenith.toml", config);
@@ -271,6 +270,14 @@ fn test_zenith_clean_backups() {
     assert_command_success(clean_cmd.assert());
 }
 
+/// CLI command: clean-backups rejects a zero session limit
+#[test]
+fn test_zenith_clean_backups_rejects_zero_max_sessions() {
+    let mut cmd = Command::new(cargo::cargo_bin!("zenith"));
+    cmd.arg("clean-backups").arg("--max-sessions").arg("0");
+    cmd.assert().failure();
+}
+
 /// CLI command: List backups test
 #[test]
 fn test_zenith_list_backups() {
@@ -459,3 +466,578 @@ fn test_zenith_format_dry_run() {
     let content_after = fs::read_to_string(&test_file).unwrap();
     assert_ne!(original_content, content_after);
 }
+
+/// CLI command: lock file generation and drift check test
+#[test]
+fn test_zenith_lock_and_locked_format() {
+    let temp_dir = create_temp_dir();
+    create_test_file(temp_dir.path(), "test.rs", "fn main() {}\n");
+
+    let mut lock_cmd = Command::new(cargo::cargo_bin!("zenith"));
+    lock_cmd.current_dir(temp_dir.path()).arg("lock");
+    assert_command_success(lock_cmd.assert());
+    assert!(temp_dir.path().join("zenith.lock").exists());
+
+    // Pin a tool that is not installed, so the environment drifts from the lock
+    fs::write(
+        temp_dir.path().join("zenith.lock"),
+        "version = 1\n\n[tools]\nzenith-missing-tool = \"1.0.0\"\n",
+    )
+    .unwrap();
+
+    let mut locked_cmd = Command::new(cargo::cargo_bin!("zenith"));
+    locked_cmd
+        .current_dir(temp_dir.path())
+        .arg("format")
+        .arg("test.rs")
+        .arg("--no-backup")
+        .arg("--locked");
+    locked_cmd.assert().failure();
+}
+
+/// Configured formatter args are passed to the spawned tool
+#[cfg(unix)]
+#[test]
+fn test_zenith_format_passes_configured_args() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp_dir = create_temp_dir();
+    let bin_dir = temp_dir.path().join("bin");
+    fs::create_dir(&bin_dir).unwrap();
+    // Stand-in for shfmt that replaces the file with its own arguments
+    let fake_shfmt = bin_dir.join("shfmt");
+    fs::write(&fake_shfmt, "#!/bin/sh\ncat > /dev/null\necho \"$@\"\n").unwrap();
+    fs::set_permissions(&fake_shfmt, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let config = r#"
+[global]
+backup_enabled = false
+cache_enabled = false
+
+[zeniths.sh]
+args = ["-i", "2", "-ci"]
+"#;
+    create_test_file(temp_dir.path(), "zenith.toml", config);
+    create_test_file(temp_dir.path(), "script.sh", "echo hi\n");
+
+    let path = format!(
+        "{}:{}",
+        bin_dir.display(),
+        std::env::var("PATH").unwrap_or_default()
+    );
+    let mut cmd = Command::new(cargo::cargo_bin!("zenith"));
+    cmd.env("PATH", path)
+        .arg("--config")
+        .arg(temp_dir.path().join("zenith.toml"))
+        .arg("format")
+        .arg(temp_dir.path().join("script.sh"));
+    assert_command_success(cmd.assert());
+
+    let output = fs::read_to_string(temp_dir.path().join("script.sh")).unwrap();
+    assert!(
+        output.starts_with("-i 2 -ci -filename "),
+        "unexpected arguments: {}",
+        output
+    );
+}
+
+/// `--quiet` hides the summary but keeps the check-mode failure
+#[test]
+fn test_zenith_format_quiet_check_still_fails() {
+    let temp_dir = create_temp_dir();
+    let input_file = create_test_file(temp_dir.path(), "settings.ini", "key=value\n");
+
+    let output = Command::new(cargo::cargo_bin!("zenith"))
+        .arg("format")
+        .arg(&input_file)
+        .arg("--check")
+        .arg("--quiet")
+        .arg("--no-color")
+        .current_dir(temp_dir.path())
+        .output()
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.contains("执行摘要"));
+    assert!(stdout.contains("检查失败"));
+    assert!(!stdout.contains('\u{1b}'));
+}
+
+/// `--annotate github` emits a workflow command per unformatted file
+#[test]
+fn test_zenith_format_check_github_annotations() {
+    let temp_dir = create_temp_dir();
+    create_test_file(temp_dir.path(), "settings.ini", "key=value\n");
+    create_test_file(temp_dir.path(), "clean.ini", "key = value\n");
+
+    let check = |args: &[&str], github_actions: &str| {
+        Command::new(cargo::cargo_bin!("zenith"))
+            .args(["format", "settings.ini", "clean.ini", "--check", "--quiet"])
+            .args(args)
+            .env("GITHUB_ACTIONS", github_actions)
+            .current_dir(temp_dir.path())
+            .output()
+            .unwrap()
+    };
+
+    let annotation = "::error file=settings.ini::File is not formatted";
+    let output = check(&["--annotate", "github"], "false");
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains(annotation), "{}", stdout);
+    assert!(!stdout.contains("clean.ini"));
+
+    // Enabled automatically inside GitHub Actions
+    assert!(String::from_utf8_lossy(&check(&[], "true").stdout).contains(annotation));
+    assert!(!String::from_utf8_lossy(&check(&[], "false").stdout).contains("::error"));
+}
+
+/// `--out-dir` mirrors formatted files and leaves the originals alone
+#[test]
+fn test_zenith_format_out_dir() {
+    let temp_dir = create_temp_dir();
+    let conf = temp_dir.path().join("conf");
+    fs::create_dir(&conf).unwrap();
+    let original = create_test_file(&conf, "settings.ini", "key=value\n");
+
+    let output = Command::new(cargo::cargo_bin!("zenith"))
+        .args(["format", "conf", "--recursive", "--out-dir", "formatted"])
+        .current_dir(temp_dir.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    assert_eq!(fs::read_to_string(&original).unwrap(), "key=value\n");
+    let mirrored = temp_dir.path().join("formatted/conf/settings.ini");
+    assert_eq!(fs::read_to_string(mirrored).unwrap(), "key = value\n");
+    assert!(!temp_dir.path().join(".zenith_backup").exists());
+
+    // Walking the parent directory skips the mirror itself
+    let output = Command::new(cargo::cargo_bin!("zenith"))
+        .args(["format", ".", "--recursive", "--out-dir", "formatted"])
+        .current_dir(temp_dir.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert!(!temp_dir.path().join("formatted/formatted").exists());
+}
+
+/// `--time-budget` accepts durations with units and rejects malformed ones
+#[test]
+fn test_zenith_format_time_budget() {
+    let temp_dir = create_temp_dir();
+    let file = create_test_file(temp_dir.path(), "settings.ini", "key=value\n");
+
+    let output = Command::new(cargo::cargo_bin!("zenith"))
+        .args([
+            "format",
+            "settings.ini",
+            "--no-backup",
+            "--time-budget",
+            "5m",
+        ])
+        .current_dir(temp_dir.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(fs::read_to_string(&file).unwrap(), "key = value\n");
+
+    for invalid in ["5x", "0s", "soon"] {
+        let output = Command::new(cargo::cargo_bin!("zenith"))
+            .args(["format", "settings.ini", "--time-budget", invalid])
+            .current_dir(temp_dir.path())
+            .output()
+            .unwrap();
+        assert!(!output.status.success(), "{}: {:?}", invalid, output);
+    }
+}
+
+/// `--fail-fast` exits with code 2 once a formatter fails
+#[test]
+fn test_zenith_format_fail_fast_exit_code() {
+    let temp_dir = create_temp_dir();
+    let broken = create_test_file(temp_dir.path(), "broken.toml", "key = [1, 2\n");
+
+    let output = Command::new(cargo::cargo_bin!("zenith"))
+        .arg("format")
+        .arg(&broken)
+        .arg("--no-backup")
+        .arg("--fail-fast")
+        .current_dir(temp_dir.path())
+        .output()
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("--fail-fast"));
+}
+
+/// `archive` reports members needing formatting and can write a fixed copy
+#[test]
+fn test_zenith_archive_check_and_output() {
+    use std::io::{Read, Write};
+
+    let temp_dir = create_temp_dir();
+    let archive = temp_dir.path().join("artifact.zip");
+    let mut writer = zip::ZipWriter::new(std::fs::File::create(&archive).unwrap());
+    let options = zip::write::SimpleFileOptions::default();
+    writer.start_file("conf/settings.ini", options).unwrap();
+    writer.write_all(b"key=value\n").unwrap();
+    writer.start_file("notes.xyz", options).unwrap();
+    writer.write_all(b"left alone").unwrap();
+    writer.finish().unwrap();
+
+    let check = Command::new(cargo::cargo_bin!("zenith"))
+        .arg("archive")
+        .arg(&archive)
+        .arg("--no-color")
+        .current_dir(temp_dir.path())
+        .output()
+        .unwrap();
+    assert_eq!(check.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&check.stdout).contains("conf/settings.ini"));
+
+    let fixed = temp_dir.path().join("fixed.tar");
+    let write = Command::new(cargo::cargo_bin!("zenith"))
+        .arg("archive")
+        .arg(&archive)
+        .arg("--output")
+        .arg(&fixed)
+        .current_dir(temp_dir.path())
+        .output()
+        .unwrap();
+    assert!(write.status.success());
+
+    let mut members = std::collections::HashMap::new();
+    let mut tar = tar::Archive::new(std::fs::File::open(&fixed).unwrap());
+    for entry in tar.entries().unwrap() {
+        let mut entry = entry.unwrap();
+        let name = entry.path().unwrap().to_string_lossy().into_owned();
+        let mut content = String::new();
+        entry.read_to_string(&mut content).unwrap();
+        members.insert(name, content);
+    }
+    assert_eq!(members["conf/settings.ini"], "key = value\n");
+    assert_eq!(members["notes.xyz"], "left alone");
+}
+
+/// `config-schema` prints only the schema, and `config-show` hides API keys
+#[test]
+fn test_zenith_config_schema_and_show() {
+    let temp_dir = create_temp_dir();
+    create_test_file(
+        temp_dir.path(),
+        "zenith.toml",
+        "[mcp]\napi_key = \"secret\"\n\n[backup]\nretention_days = 3\n",
+    );
+
+    let schema = Command::new(cargo::cargo_bin!("zenith"))
+        .arg("config-schema")
+        .current_dir(temp_dir.path())
+        .output()
+        .unwrap();
+    assert!(schema.status.success());
+    let schema: serde_json::Value = serde_json::from_slice(&schema.stdout).unwrap();
+    assert!(schema["properties"]["global"].is_object());
+
+    let show = Command::new(cargo::cargo_bin!("zenith"))
+        .arg("config-show")
+        .current_dir(temp_dir.path())
+        .output()
+        .unwrap();
+    assert!(show.status.success());
+    let effective: serde_json::Value = serde_json::from_slice(&show.stdout).unwrap();
+    assert_eq!(effective["backup"]["retention_days"], 3);
+    assert_eq!(effective["mcp"]["api_key"], "<redacted>");
+}
+
+/// `init` writes a loadable `zenith.toml` and only overwrites it with `--force`
+#[test]
+fn test_zenith_init() {
+    let temp_dir = create_temp_dir();
+    let init = |args: &[&str]| {
+        Command::new(cargo::cargo_bin!("zenith"))
+            .arg("init")
+            .args(args)
+            .current_dir(temp_dir.path())
+            .output()
+            .unwrap()
+    };
+
+    let output = init(&["--config-dir"]);
+    assert!(output.status.success());
+    assert!(temp_dir.path().join(".zenith").join("plugins").is_dir());
+    let config_file = temp_dir.path().join("zenith.toml");
+    let text = fs::read_to_string(&config_file).unwrap();
+    assert!(text.contains("[global]"));
+    toml::from_str::<toml::Value>(&text).unwrap();
+
+    fs::write(&config_file, "# edited\n").unwrap();
+    assert!(!init(&[]).status.success());
+    assert_eq!(fs::read_to_string(&config_file).unwrap(), "# edited\n");
+
+    assert!(init(&["--force"]).status.success());
+    assert_eq!(fs::read_to_string(&config_file).unwrap(), text);
+
+    let show = Command::new(cargo::cargo_bin!("zenith"))
+        .arg("config-show")
+        .current_dir(temp_dir.path())
+        .output()
+        .unwrap();
+    assert!(show.status.success());
+}
+
+/// `--profile` and `$ZENITH_PROFILE` layer `[profiles.<name>]` over the file,
+/// below environment variables
+#[test]
+fn test_zenith_config_profiles() {
+    let temp_dir = create_temp_dir();
+    create_test_file(
+        temp_dir.path(),
+        "zenith.toml",
+        "[backup]\nretention_days = 3\n\n[profiles.ci.backup]\nretention_days = 30\ndedup = false\n",
+    );
+    let show = |args: &[&str], envs: &[(&str, &str)]| {
+        Command::new(cargo::cargo_bin!("zenith"))
+            .args(args)
+            .arg("config-show")
+            .envs(envs.iter().copied())
+            .current_dir(temp_dir.path())
+            .output()
+            .unwrap()
+    };
+
+    let output = show(&["--profile", "ci"], &[]);
+    assert!(output.status.success());
+    let effective: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(effective["backup"]["retention_days"], 30);
+    assert_eq!(effective["profile"], "ci");
+
+    let output = show(
+        &[],
+        &[
+            ("ZENITH_PROFILE", "ci"),
+            ("ZENITH__BACKUP__DEDUP", "true"),
+            ("ZENITH__CONCURRENCY__WORKERS", "3"),
+        ],
+    );
+    assert!(output.status.success());
+    let effective: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(effective["backup"]["retention_days"], 30);
+    assert_eq!(effective["backup"]["dedup"], true);
+    assert_eq!(effective["concurrency"]["workers"], 3);
+
+    let output = show(&["--profile", "strict"], &[]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Unknown profile 'strict'"));
+}
+
+/// `$ZENITH_IGNORE` names an extra ignore file for directory walks
+#[test]
+fn test_zenith_ignore_env_skips_matching_files() {
+    let temp_dir = create_temp_dir();
+    let project = temp_dir.path().join("project");
+    fs::create_dir(&project).unwrap();
+    let formatted = create_test_file(&project, "a.ini", "key=value\n");
+    let ignored = create_test_file(&project, "b.ini", "key=value\n");
+    let ignore_file = create_test_file(temp_dir.path(), "format.ignore", "b.ini\n");
+
+    let output = Command::new(cargo::cargo_bin!("zenith"))
+        .arg("format")
+        .arg(&project)
+        .arg("--no-backup")
+        .env("ZENITH_IGNORE", &ignore_file)
+        .current_dir(temp_dir.path())
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(fs::read_to_string(formatted).unwrap(), "key = value\n");
+    assert_eq!(fs::read_to_string(ignored).unwrap(), "key=value\n");
+}
+
+/// `cache-stats` reports the persisted cache and `cache-clear` deletes it
+#[tokio::test]
+async fn test_zenith_cache_stats_and_clear() {
+    let temp_dir = create_temp_dir();
+    create_test_file(
+        temp_dir.path(),
+        "zenith.toml",
+        "[global]\nconfig_dir = \"state\"\n",
+    );
+    let file = create_test_file(temp_dir.path(), "a.ini", "key=value\n");
+    let cache = zenith::internal::HashCache::with_cache_dir(temp_dir.path().join("state"));
+    let state = cache.compute_file_state(&file).await.unwrap();
+    cache.update(file, state).await.unwrap();
+    cache.save().await.unwrap();
+
+    let stats = |json: bool| {
+        let mut cmd = Command::new(cargo::cargo_bin!("zenith"));
+        cmd.arg("cache-stats").current_dir(temp_dir.path());
+        if json {
+            cmd.arg("--json");
+        }
+        cmd.output().unwrap()
+    };
+
+    let output = stats(true);
+    assert!(output.status.success());
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["entries"], 1);
+    assert_eq!(report["valid_entries"], 1);
+    assert_eq!(report["exists"], true);
+    assert!(String::from_utf8_lossy(&stats(false).stdout).contains("条目总数: 1"));
+
+    Command::new(cargo::cargo_bin!("zenith"))
+        .arg("cache-clear")
+        .current_dir(temp_dir.path())
+        .assert()
+        .success();
+    assert!(!temp_dir.path().join("state/file_cache.json").exists());
+
+    let report: serde_json::Value = serde_json::from_slice(&stats(true).stdout).unwrap();
+    assert_eq!(report["entries"], 0);
+    assert_eq!(report["exists"], false);
+}
+
+/// `--log-format json --log-file` writes one JSON object per line to the file
+#[test]
+fn test_zenith_json_log_file() {
+    let temp_dir = create_temp_dir();
+    let log_file = temp_dir.path().join("zenith.log");
+
+    let output = Command::new(cargo::cargo_bin!("zenith"))
+        .args(["--log-format", "json", "--log-file"])
+        .arg(&log_file)
+        .arg("list-backups")
+        .current_dir(temp_dir.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(output.stderr.is_empty());
+
+    let logs = fs::read_to_string(&log_file).unwrap();
+    assert!(!logs.is_empty());
+    for line in logs.lines() {
+        let entry: serde_json::Value = serde_json::from_str(line).unwrap();
+        assert!(entry["level"].is_string());
+        assert!(entry["fields"]["message"].is_string());
+    }
+}
+
+/// `--stage` adds formatted files to the git index and refuses `--check`
+#[test]
+fn test_zenith_format_stage() {
+    let temp_dir = create_temp_dir();
+    let git = |args: &[&str]| {
+        Command::new("git")
+            .args(args)
+            .current_dir(temp_dir.path())
+            .output()
+            .unwrap()
+    };
+    let init = git(&["init", "-q"]);
+    assert!(
+        init.status.success(),
+        "git init failed: {}",
+        String::from_utf8_lossy(&init.stderr)
+    );
+    let messy = create_test_file(temp_dir.path(), "settings.ini", "key=value\n");
+    let tidy = create_test_file(temp_dir.path(), "tidy.ini", "key = value\n");
+
+    let output = Command::new(cargo::cargo_bin!("zenith"))
+        .args(["format", "--stage", "--check"])
+        .arg(&messy)
+        .current_dir(temp_dir.path())
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+
+    Command::new(cargo::cargo_bin!("zenith"))
+        .args(["format", "--stage", "--no-backup"])
+        .arg(&messy)
+        .arg(&tidy)
+        .current_dir(temp_dir.path())
+        .assert()
+        .success();
+
+    let staged = git(&["diff", "--cached", "--name-only"]);
+    assert_eq!(
+        String::from_utf8_lossy(&staged.stdout).trim(),
+        "settings.ini"
+    );
+}
+
+/// Outside a git repository `--stage` only warns
+#[test]
+fn test_zenith_format_stage_outside_repo() {
+    let temp_dir = create_temp_dir();
+    let messy = create_test_file(temp_dir.path(), "settings.ini", "key=value\n");
+
+    let output = Command::new(cargo::cargo_bin!("zenith"))
+        .args(["format", "--stage", "--no-backup", "--no-color"])
+        .arg(&messy)
+        .env("GIT_CEILING_DIRECTORIES", temp_dir.path().parent().unwrap())
+        .current_dir(temp_dir.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("不在 git 仓库中"));
+    assert_eq!(fs::read_to_string(&messy).unwrap(), "key = value\n");
+}
+
+/// `--lang` narrows a directory walk to one formatter's extensions
+#[test]
+fn test_zenith_format_lang_filter() {
+    let temp_dir = create_temp_dir();
+    let ini = create_test_file(temp_dir.path(), "settings.ini", "key=value\n");
+    let toml = create_test_file(temp_dir.path(), "config.toml", "key=1\n");
+
+    Command::new(cargo::cargo_bin!("zenith"))
+        .args(["format", "-r", "--no-backup", "--lang", "ini"])
+        .arg(temp_dir.path())
+        .current_dir(temp_dir.path())
+        .assert()
+        .success();
+    assert_eq!(fs::read_to_string(&ini).unwrap(), "key = value\n");
+    assert_eq!(fs::read_to_string(&toml).unwrap(), "key=1\n");
+
+    let output = Command::new(cargo::cargo_bin!("zenith"))
+        .args(["format", "--no-backup", "--lang", "cobol"])
+        .arg(temp_dir.path())
+        .current_dir(temp_dir.path())
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("cobol") && stderr.contains("ini"),
+        "{}",
+        stderr
+    );
+}
+
+/// `--by-formatter` groups the summary by the formatter that handled each file
+#[test]
+fn test_zenith_format_by_formatter() {
+    let temp_dir = create_temp_dir();
+    create_test_file(temp_dir.path(), "a.ini", "key=value\n");
+    create_test_file(temp_dir.path(), "b.ini", "key = value\n");
+    create_test_file(temp_dir.path(), "notes.xyz", "anything\n");
+
+    let output = Command::new(cargo::cargo_bin!("zenith"))
+        .args(["format", "-r", "--no-backup", "--by-formatter"])
+        .arg(temp_dir.path())
+        .current_dir(temp_dir.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let ini: Vec<&str> = stdout
+        .lines()
+        .map(str::split_whitespace)
+        .map(Iterator::collect::<Vec<_>>)
+        .find(|tokens| tokens.first() == Some(&"ini"))
+        .unwrap_or_else(|| panic!("no ini row in:\n{}", stdout));
+    assert_eq!(ini, ["ini", "文件:", "2", "已修改:", "1", "失败:", "0"]);
+}
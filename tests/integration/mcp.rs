@@ -3,9 +3,10 @@
 // Licensed under the MIT License
 // See LICENSE file in the project root for full license information.
 
+use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Arc;
-use zenith::config::types::{AppConfig, McpConfig, McpUser};
+use zenith::config::types::{AppConfig, FormatError, McpConfig, McpUser, UnsupportedReport};
 use zenith::internal::{HashCache, McpServer};
 use zenith::zeniths::registry::ZenithRegistry;
 use zenith::{
@@ -116,6 +117,7 @@ async fn test_format_params_defaults() {
     assert!(!params.recursive);
     assert!(!params.backup);
     assert!(params.workers.is_none());
+    assert!(!params.check);
 }
 
 #[tokio::test]
@@ -175,18 +177,24 @@ async fn test_format_response_data_serialization() {
         failed_files: 2,
         backup_id: Some("backup-123".to_string()),
         duration_ms: 1500,
+        unsupported: UnsupportedReport::default(),
+        per_formatter_ms: HashMap::from([("rust".to_string(), 900), ("yaml".to_string(), 40)]),
         results: vec![
             FileFormatResult {
                 path: PathBuf::from("/tmp/test1.rs"),
                 success: true,
                 changed: true,
+                formatter: Some("rust".to_string()),
                 error: None,
+                error_kind: None,
             },
             FileFormatResult {
                 path: PathBuf::from("/tmp/test2.rs"),
                 success: false,
                 changed: false,
+                formatter: Some("rust".to_string()),
                 error: Some("Syntax error".to_string()),
+                error_kind: Some(FormatError::SyntaxInvalid(Some("Syntax error".to_string()))),
             },
         ],
     };
@@ -199,6 +207,16 @@ async fn test_format_response_data_serialization() {
     assert_eq!(json["backup_id"], "backup-123");
     assert_eq!(json["duration_ms"], 1500);
     assert_eq!(json["results"].as_array().unwrap().len(), 2);
+    assert_eq!(json["results"][0]["formatter"], "rust");
+    assert_eq!(
+        json["per_formatter_ms"],
+        serde_json::json!({ "rust": 900, "yaml": 40 })
+    );
+    assert!(json["results"][0].get("error_kind").is_none());
+    assert_eq!(
+        json["results"][1]["error_kind"],
+        serde_json::json!({ "kind": "syntax_invalid", "message": "Syntax error" })
+    );
 }
 
 #[tokio::test]
@@ -220,7 +238,9 @@ async fn test_file_format_result() {
         path: PathBuf::from("/tmp/test.rs"),
         success: true,
         changed: true,
+        formatter: None,
         error: None,
+        error_kind: None,
     };
 
     assert_eq!(result.path, PathBuf::from("/tmp/test.rs"));
@@ -235,7 +255,9 @@ async fn test_file_format_result_with_error() {
         path: PathBuf::from("/tmp/test.rs"),
         success: false,
         changed: false,
+        formatter: None,
         error: Some("Format failed".to_string()),
+        error_kind: None,
     };
 
     assert!(!result.success);
@@ -256,12 +278,15 @@ async fn test_mcp_config() {
             McpUser {
                 api_key: "test-key-1".to_string(),
                 role: "admin".to_string(),
+                allowed_paths: vec![],
             },
             McpUser {
                 api_key: "test-key-2".to_string(),
                 role: "user".to_string(),
+                allowed_paths: vec![],
             },
         ],
+        request_logging: false,
     };
 
     assert!(config.enabled);
@@ -280,6 +305,42 @@ async fn test_mcp_server_creation() {
     let _server = McpServer::new(config, registry, hash_cache);
 }
 
+#[tokio::test]
+async fn test_mcp_server_flushes_cache_on_shutdown() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let cache_dir = temp_dir.path().join("cache");
+    let file = temp_dir.path().join("main.rs");
+    std::fs::write(&file, "fn main() {}").unwrap();
+
+    let hash_cache = Arc::new(HashCache::with_cache_dir(cache_dir.clone()));
+    let state = hash_cache.compute_file_state(&file).await.unwrap();
+    hash_cache.update(file, state).await.unwrap();
+
+    let server = McpServer::new(
+        AppConfig::default(),
+        Arc::new(ZenithRegistry::new()),
+        hash_cache,
+    );
+    let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel::<()>();
+    let handle = tokio::spawn(async move {
+        server
+            .run_until("127.0.0.1:0".parse().unwrap(), async {
+                let _ = shutdown_rx.await;
+            })
+            .await
+    });
+
+    shutdown_tx.send(()).unwrap();
+    tokio::time::timeout(std::time::Duration::from_secs(5), handle)
+        .await
+        .expect("server should stop after the shutdown signal")
+        .unwrap()
+        .unwrap();
+
+    let saved = std::fs::read_to_string(cache_dir.join("file_cache.json")).unwrap();
+    assert!(saved.contains("main.rs"));
+}
+
 #[tokio::test]
 async fn test_jsonrpc_request_without_id() {
     let request = JsonRpcRequest {
@@ -370,16 +431,19 @@ async fn test_mcp_user_roles() {
     let admin = McpUser {
         api_key: "admin-key".to_string(),
         role: "admin".to_string(),
+        allowed_paths: vec![],
     };
 
     let user = McpUser {
         api_key: "user-key".to_string(),
         role: "user".to_string(),
+        allowed_paths: vec![],
     };
 
     let readonly = McpUser {
         api_key: "readonly-key".to_string(),
         role: "readonly".to_string(),
+        allowed_paths: vec![],
     };
 
     assert_eq!(admin.role, "admin");
@@ -395,6 +459,8 @@ async fn test_format_response_data_without_backup() {
         failed_files: 0,
         backup_id: None,
         duration_ms: 1000,
+        unsupported: UnsupportedReport::default(),
+        per_formatter_ms: HashMap::new(),
         results: vec![],
     };
 
@@ -428,3 +494,756 @@ async fn test_jsonrpc_version_validation() {
         assert_eq!(request.jsonrpc, version);
     }
 }
+
+#[cfg(feature = "ini")]
+#[tokio::test]
+async fn test_format_stream_sse_events() {
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use tower::ServiceExt;
+    use zenith::internal::IniZenith;
+
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let file1 = temp_dir.path().join("a.ini");
+    let file2 = temp_dir.path().join("b.ini");
+    std::fs::write(&file1, "[core]\nkey=value\n").unwrap();
+    std::fs::write(&file2, "[core]\nname = zenith\n").unwrap();
+
+    let mut config = AppConfig::default();
+    config.global.cache_enabled = false;
+    config.mcp.auth_enabled = true;
+    config.mcp.users = vec![McpUser {
+        api_key: "stream-key".to_string(),
+        role: "user".to_string(),
+        allowed_paths: vec![],
+    }];
+    let registry = Arc::new(ZenithRegistry::new());
+    registry.register(Arc::new(IniZenith));
+    let server = McpServer::new(config, registry, Arc::new(HashCache::new()));
+
+    let body = serde_json::json!({ "paths": [file1, file2], "backup": false });
+    let request = Request::builder()
+        .method("POST")
+        .uri("/format/stream")
+        .header("content-type", "application/json")
+        .header("authorization", "Bearer stream-key")
+        .body(Body::from(body.to_string()))
+        .unwrap();
+
+    let response = server.router().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()["content-type"].to_str().unwrap(),
+        "text/event-stream"
+    );
+
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let text = String::from_utf8(bytes.to_vec()).unwrap();
+
+    assert_eq!(text.matches("event: result").count(), 2);
+    assert_eq!(text.matches("event: summary").count(), 1);
+
+    let summary_line = text
+        .split("event: summary")
+        .nth(1)
+        .and_then(|rest| rest.lines().find(|line| line.starts_with("data:")))
+        .unwrap();
+    let summary: serde_json::Value =
+        serde_json::from_str(summary_line.trim_start_matches("data:").trim()).unwrap();
+    assert_eq!(summary["total_files"], 2);
+    assert_eq!(summary["formatted_files"], 2);
+    assert_eq!(
+        std::fs::read_to_string(&file1).unwrap(),
+        "[core]\nkey = value\n"
+    );
+}
+
+#[cfg(feature = "ini")]
+#[tokio::test]
+async fn test_format_stream_reports_request_errors_as_an_event() {
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use tower::ServiceExt;
+    use zenith::internal::IniZenith;
+
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let jail = temp_dir.path().join("jail");
+    std::fs::create_dir(&jail).unwrap();
+    let outside = temp_dir.path().join("outside.ini");
+    std::fs::write(&outside, "[core]\nkey=value\n").unwrap();
+
+    let mut config = AppConfig::default();
+    config.global.cache_enabled = false;
+    config.mcp.auth_enabled = true;
+    config.mcp.users = vec![McpUser {
+        api_key: "stream-key".to_string(),
+        role: "user".to_string(),
+        allowed_paths: vec![jail],
+    }];
+    let registry = Arc::new(ZenithRegistry::new());
+    registry.register(Arc::new(IniZenith));
+    let server = McpServer::new(config, registry, Arc::new(HashCache::new()));
+
+    let body = serde_json::json!({ "paths": [outside], "backup": false });
+    let request = Request::builder()
+        .method("POST")
+        .uri("/format/stream")
+        .header("content-type", "application/json")
+        .header("authorization", "Bearer stream-key")
+        .body(Body::from(body.to_string()))
+        .unwrap();
+
+    let response = server.router().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let text = String::from_utf8(bytes.to_vec()).unwrap();
+
+    assert_eq!(text.matches("event: error").count(), 1);
+    assert!(!text.contains("event: result"));
+    assert!(!text.contains("event: summary"));
+    let error_line = text.lines().find(|line| line.starts_with("data:")).unwrap();
+    let error: serde_json::Value =
+        serde_json::from_str(error_line.trim_start_matches("data:").trim()).unwrap();
+    assert_eq!(error["code"], 1006);
+    assert_eq!(
+        std::fs::read_to_string(&outside).unwrap(),
+        "[core]\nkey=value\n"
+    );
+}
+
+#[cfg(feature = "ini")]
+#[tokio::test]
+async fn test_format_check_reports_changes_without_writing() {
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use tower::ServiceExt;
+    use zenith::internal::IniZenith;
+
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let messy = temp_dir.path().join("a.ini");
+    let clean = temp_dir.path().join("b.ini");
+    std::fs::write(&messy, "[core]\nkey=value\n").unwrap();
+    std::fs::write(&clean, "[core]\nname = zenith\n").unwrap();
+
+    let mut config = AppConfig::default();
+    config.global.cache_enabled = false;
+    config.backup.dir = temp_dir
+        .path()
+        .join("backups")
+        .to_string_lossy()
+        .into_owned();
+    config.mcp.auth_enabled = true;
+    config.mcp.api_key = Some("check-key".to_string());
+    let registry = Arc::new(ZenithRegistry::new());
+    registry.register(Arc::new(IniZenith));
+    let server = McpServer::new(config, registry, Arc::new(HashCache::new()));
+
+    let body = serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "format",
+        "params": { "paths": [messy, clean], "backup": true, "check": true }
+    });
+    let request = Request::builder()
+        .method("POST")
+        .uri("/")
+        .header("content-type", "application/json")
+        .header("authorization", "Bearer check-key")
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    let response = server.router().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+
+    let result = &body["result"];
+    assert!(result["backup_id"].is_null());
+    assert_eq!(result["total_files"], 2);
+    assert!(result["per_formatter_ms"]["ini"].is_u64());
+    assert_eq!(result["results"][0]["formatter"], "ini");
+    let changed: Vec<bool> = result["results"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|r| r["changed"].as_bool().unwrap())
+        .collect();
+    assert_eq!(changed, vec![true, false]);
+    assert_eq!(
+        std::fs::read_to_string(&messy).unwrap(),
+        "[core]\nkey=value\n"
+    );
+    assert!(!temp_dir.path().join("backups").exists());
+}
+
+#[cfg(feature = "ini")]
+#[tokio::test]
+async fn test_notification_runs_without_a_response() {
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use tower::ServiceExt;
+    use zenith::internal::IniZenith;
+
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let file = temp_dir.path().join("a.ini");
+    std::fs::write(&file, "[core]\nkey=value\n").unwrap();
+
+    let mut config = AppConfig::default();
+    config.global.cache_enabled = false;
+    config.mcp.auth_enabled = true;
+    config.mcp.api_key = Some("notify-key".to_string());
+    let registry = Arc::new(ZenithRegistry::new());
+    registry.register(Arc::new(IniZenith));
+    let server = McpServer::new(config, registry, Arc::new(HashCache::new()));
+
+    let post = |body: serde_json::Value| {
+        Request::builder()
+            .method("POST")
+            .uri("/")
+            .header("content-type", "application/json")
+            .header("authorization", "Bearer notify-key")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    };
+
+    let notification = serde_json::json!({
+        "jsonrpc": "2.0",
+        "method": "format",
+        "params": { "paths": [file], "backup": false }
+    });
+    let response = server.router().oneshot(post(notification)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    assert!(bytes.is_empty());
+    assert_eq!(
+        std::fs::read_to_string(&file).unwrap(),
+        "[core]\nkey = value\n"
+    );
+
+    // Failing notifications stay silent too
+    let unknown = serde_json::json!({ "jsonrpc": "2.0", "method": "no_such_method" });
+    let response = server.router().oneshot(post(unknown)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+}
+
+#[cfg(feature = "ini")]
+#[tokio::test]
+async fn test_format_paths_are_confined_to_allowed_paths() {
+    use axum::body::Body;
+    use axum::http::Request;
+    use tower::ServiceExt;
+    use zenith::internal::IniZenith;
+
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let jail = temp_dir.path().join("tenant");
+    let outside = temp_dir.path().join("other");
+    std::fs::create_dir_all(&jail).unwrap();
+    std::fs::create_dir_all(&outside).unwrap();
+    let inside_file = jail.join("a.ini");
+    let outside_file = outside.join("b.ini");
+    std::fs::write(&inside_file, "key=value\n").unwrap();
+    std::fs::write(&outside_file, "key=value\n").unwrap();
+
+    let mut config = AppConfig::default();
+    config.global.cache_enabled = false;
+    config.mcp.auth_enabled = true;
+    config.mcp.users = vec![McpUser {
+        api_key: "tenant-key".to_string(),
+        role: "user".to_string(),
+        allowed_paths: vec![jail.clone()],
+    }];
+    let registry = Arc::new(ZenithRegistry::new());
+    registry.register(Arc::new(IniZenith));
+    let server = McpServer::new(config, registry, Arc::new(HashCache::new()));
+
+    let format = |path: std::path::PathBuf| {
+        let body = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "format",
+            "params": { "paths": [path], "backup": false }
+        });
+        Request::builder()
+            .method("POST")
+            .uri("/")
+            .header("content-type", "application/json")
+            .header("authorization", "Bearer tenant-key")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    };
+    let call = |request: Request<Body>| async {
+        let response = server.router().oneshot(request).await.unwrap();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice::<serde_json::Value>(&bytes).unwrap()
+    };
+
+    let body = call(format(inside_file.clone())).await;
+    assert!(body["error"].is_null(), "{}", body);
+    assert_eq!(body["result"]["formatted_files"], 1);
+    assert_eq!(
+        std::fs::read_to_string(&inside_file).unwrap(),
+        "key = value\n"
+    );
+
+    for path in [outside_file.clone(), jail.join("../other/b.ini")] {
+        let body = call(format(path)).await;
+        assert_eq!(body["error"]["code"], 1006, "{}", body);
+        assert!(body["result"].is_null());
+    }
+    assert_eq!(
+        std::fs::read_to_string(&outside_file).unwrap(),
+        "key=value\n"
+    );
+}
+
+#[cfg(feature = "ini")]
+#[tokio::test]
+async fn test_recover_and_format_content_are_confined_to_allowed_paths() {
+    use axum::body::Body;
+    use axum::http::Request;
+    use tower::ServiceExt;
+    use zenith::internal::IniZenith;
+
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let jail = temp_dir.path().join("tenant");
+    let outside = temp_dir.path().join("other");
+    std::fs::create_dir_all(&jail).unwrap();
+    std::fs::create_dir_all(&outside).unwrap();
+    let inside_file = jail.join("a.ini");
+    let outside_file = outside.join("b.ini");
+    std::fs::write(&inside_file, "key=value\n").unwrap();
+    std::fs::write(&outside_file, "key=value\n").unwrap();
+
+    let mut config = AppConfig::default();
+    config.global.cache_enabled = false;
+    config.backup.dir = temp_dir
+        .path()
+        .join("backups")
+        .to_string_lossy()
+        .into_owned();
+    config.mcp.auth_enabled = true;
+    config.mcp.users = vec![
+        McpUser {
+            api_key: "tenant-key".to_string(),
+            role: "user".to_string(),
+            allowed_paths: vec![jail.clone()],
+        },
+        McpUser {
+            api_key: "open-key".to_string(),
+            role: "user".to_string(),
+            allowed_paths: vec![],
+        },
+    ];
+    let registry = Arc::new(ZenithRegistry::new());
+    registry.register(Arc::new(IniZenith));
+    let server = McpServer::new(config, registry, Arc::new(HashCache::new()));
+
+    let call = |key: &str, method: &str, params: serde_json::Value| {
+        let body = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method,
+            "params": params
+        });
+        let request = Request::builder()
+            .method("POST")
+            .uri("/")
+            .header("content-type", "application/json")
+            .header("authorization", format!("Bearer {}", key))
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        let router = server.router();
+        async move {
+            let response = router.oneshot(request).await.unwrap();
+            let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            serde_json::from_slice::<serde_json::Value>(&bytes).unwrap()
+        }
+    };
+
+    let content =
+        |path: &std::path::Path| serde_json::json!({ "path": path, "content": "key=value\n" });
+    let body = call("tenant-key", "format_content", content(&inside_file)).await;
+    assert_eq!(body["result"]["content"], "key = value\n", "{}", body);
+    let body = call("tenant-key", "format_content", content(&outside_file)).await;
+    assert_eq!(body["error"]["code"], 1006, "{}", body);
+
+    // Past the permission check, the unknown session is reported as missing
+    let recover = |target: &std::path::Path| serde_json::json!({ "backup_id": "backup_missing", "target": target });
+    let body = call("tenant-key", "recover", recover(&jail)).await;
+    assert_eq!(body["error"]["code"], 1004, "{}", body);
+    for target in [outside.clone(), jail.join("../other")] {
+        let body = call("tenant-key", "recover", recover(&target)).await;
+        assert_eq!(body["error"]["code"], 1006, "{}", body);
+    }
+    let body = call(
+        "tenant-key",
+        "recover",
+        serde_json::json!({ "backup_id": "backup_missing" }),
+    )
+    .await;
+    assert_eq!(body["error"]["code"], 1006, "{}", body);
+
+    // Without allowed roots any target passes the permission check
+    let body = call("open-key", "recover", recover(&outside)).await;
+    assert_eq!(body["error"]["code"], 1004, "{}", body);
+}
+
+#[tokio::test]
+async fn test_recover_with_auth_disabled() {
+    use axum::body::Body;
+    use axum::http::Request;
+    use tower::ServiceExt;
+    use zenith::internal::BackupService;
+
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let source = temp_dir.path().join("source");
+    let target = temp_dir.path().join("restored");
+    std::fs::create_dir_all(&source).unwrap();
+
+    let mut config = AppConfig::default();
+    config.mcp.auth_enabled = false;
+    config.backup.dir = temp_dir
+        .path()
+        .join("backups")
+        .to_string_lossy()
+        .into_owned();
+    let backup_service = BackupService::new(config.backup.clone(), None);
+    backup_service.init().await.unwrap();
+    backup_service
+        .backup_file(&source, &source.join("a.ini"), b"key=value\n")
+        .await
+        .unwrap();
+
+    let server = McpServer::new(
+        config,
+        Arc::new(ZenithRegistry::new()),
+        Arc::new(HashCache::new()),
+    );
+    let body = serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "recover",
+        "params": {
+            "backup_id": backup_service.get_session_id(),
+            "target": target
+        }
+    });
+    let request = Request::builder()
+        .method("POST")
+        .uri("/")
+        .header("content-type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    let response = server.router().oneshot(request).await.unwrap();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+
+    assert_eq!(body["result"]["restored_files"], 1, "{}", body);
+    assert_eq!(
+        std::fs::read_to_string(target.join("a.ini")).unwrap(),
+        "key=value\n"
+    );
+}
+
+#[tokio::test]
+async fn test_health_endpoint_needs_no_auth() {
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use tower::ServiceExt;
+
+    let mut config = AppConfig::default();
+    config.mcp.auth_enabled = true;
+    config.mcp.api_key = Some("secret".to_string());
+    config.mcp.request_logging = true;
+    let server = McpServer::new(
+        config,
+        Arc::new(ZenithRegistry::new()),
+        Arc::new(HashCache::new()),
+    );
+
+    let health = Request::builder()
+        .method("GET")
+        .uri("/health")
+        .body(Body::empty())
+        .unwrap();
+    let response = server.router().oneshot(health).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(body, serde_json::json!({ "status": "ok" }));
+
+    // JSON-RPC calls still require credentials
+    let rpc = Request::builder()
+        .method("POST")
+        .uri("/")
+        .header("content-type", "application/json")
+        .body(Body::from(
+            r#"{"jsonrpc":"2.0","id":1,"method":"format","params":{"paths":[]}}"#,
+        ))
+        .unwrap();
+    let response = server.router().oneshot(rpc).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_top_level_api_key_authenticates_as_admin() {
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use tower::ServiceExt;
+
+    let mut config = AppConfig::default();
+    config.mcp.auth_enabled = true;
+    config.mcp.api_key = Some("master-key".to_string());
+    let server = McpServer::new(
+        config,
+        Arc::new(ZenithRegistry::new()),
+        Arc::new(HashCache::new()),
+    );
+
+    let rpc = |token: &str| {
+        Request::builder()
+            .method("POST")
+            .uri("/")
+            .header("content-type", "application/json")
+            .header("authorization", format!("Bearer {}", token))
+            .body(Body::from(
+                r#"{"jsonrpc":"2.0","id":1,"method":"unknown_method"}"#,
+            ))
+            .unwrap()
+    };
+
+    let response = server.router().oneshot(rpc("master-key")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    // Only admins get past the permission check to method dispatch
+    assert_eq!(body["error"]["code"], -32601);
+
+    let response = server.router().oneshot(rpc("wrong-key")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_cors_preflight_follows_allowed_origins() {
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use tower::ServiceExt;
+
+    let preflight = |origin: &str| {
+        Request::builder()
+            .method("OPTIONS")
+            .uri("/")
+            .header("origin", origin)
+            .header("access-control-request-method", "POST")
+            .header(
+                "access-control-request-headers",
+                "authorization,content-type",
+            )
+            .body(Body::empty())
+            .unwrap()
+    };
+    let server = |origins: &[&str]| {
+        let mut config = AppConfig::default();
+        config.mcp.api_key = Some("secret".to_string());
+        config.mcp.allowed_origins = origins.iter().map(|o| o.to_string()).collect();
+        McpServer::new(
+            config,
+            Arc::new(ZenithRegistry::new()),
+            Arc::new(HashCache::new()),
+        )
+    };
+
+    let restricted = server(&["https://app.example.com"]);
+    let response = restricted
+        .router()
+        .oneshot(preflight("https://app.example.com"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let headers = response.headers();
+    assert_eq!(
+        headers["access-control-allow-origin"],
+        "https://app.example.com"
+    );
+    let methods = headers["access-control-allow-methods"].to_str().unwrap();
+    assert!(methods.contains("POST"));
+    let allowed = headers["access-control-allow-headers"]
+        .to_str()
+        .unwrap()
+        .to_lowercase();
+    assert!(allowed.contains("authorization") && allowed.contains("content-type"));
+
+    let response = restricted
+        .router()
+        .oneshot(preflight("https://evil.example.com"))
+        .await
+        .unwrap();
+    assert!(!response
+        .headers()
+        .contains_key("access-control-allow-origin"));
+
+    let response = server(&["*"])
+        .router()
+        .oneshot(preflight("https://anywhere.example.com"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["access-control-allow-origin"], "*");
+}
+
+#[cfg(feature = "rust")]
+#[tokio::test]
+async fn test_format_content_with_line_range() {
+    use axum::body::Body;
+    use axum::http::Request;
+    use tower::ServiceExt;
+    use zenith::core::traits::Zenith;
+    use zenith::internal::RustZenith;
+
+    if !RustZenith.is_available().await {
+        return;
+    }
+
+    let registry = Arc::new(ZenithRegistry::new());
+    registry.register(Arc::new(RustZenith));
+    let mut config = AppConfig::default();
+    config.mcp.api_key = Some("secret".to_string());
+    let server = McpServer::new(config, registry, Arc::new(HashCache::new()));
+
+    let call = |params: serde_json::Value| {
+        let body = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "format_content",
+            "params": params,
+        });
+        Request::builder()
+            .method("POST")
+            .uri("/")
+            .header("content-type", "application/json")
+            .header("authorization", "Bearer secret")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    };
+    let source = "fn a( ){let x=1;}\nfn b( ){let y=2;}\n";
+
+    let response = server
+        .router()
+        .oneshot(call(serde_json::json!({
+            "path": "src/lib.rs",
+            "content": source,
+            "range": [1, 1],
+        })))
+        .await
+        .unwrap();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(
+        body["result"]["content"],
+        "fn a() {\n    let x = 1;\n}\nfn b( ){let y=2;}\n"
+    );
+    assert_eq!(body["result"]["changed"], true);
+
+    let response = server
+        .router()
+        .oneshot(call(serde_json::json!({
+            "path": "src/lib.rs",
+            "content": source,
+            "range": [3, 1],
+        })))
+        .await
+        .unwrap();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(body["error"]["code"], -32602);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_reload_plugins_is_admin_only_and_registers_new_configs() {
+    use axum::body::Body;
+    use axum::http::Request;
+    use tower::ServiceExt;
+    use zenith::internal::PluginLoader;
+
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let mut loader = PluginLoader::new();
+    loader.load_plugins_from_dir(temp_dir.path()).await.unwrap();
+
+    let mut config = AppConfig::default();
+    config.mcp.users = vec![
+        McpUser {
+            api_key: "admin-key".to_string(),
+            role: "admin".to_string(),
+            allowed_paths: vec![],
+        },
+        McpUser {
+            api_key: "user-key".to_string(),
+            role: "user".to_string(),
+            allowed_paths: vec![],
+        },
+    ];
+    let registry = Arc::new(ZenithRegistry::new());
+    let server = McpServer::new(config, registry.clone(), Arc::new(HashCache::new()))
+        .with_plugin_loader(loader);
+
+    std::fs::write(
+        temp_dir.path().join("ruby.json"),
+        r#"{"name": "ruby-cat", "command": "cat", "args": [], "extensions": ["rb"], "enabled": true}"#,
+    )
+    .unwrap();
+
+    let reload = |key: &str| {
+        Request::builder()
+            .method("POST")
+            .uri("/")
+            .header("content-type", "application/json")
+            .header("authorization", format!("Bearer {}", key))
+            .body(Body::from(
+                r#"{"jsonrpc": "2.0", "id": 1, "method": "reload_plugins"}"#,
+            ))
+            .unwrap()
+    };
+    let call = |key: &'static str| {
+        let router = server.router();
+        async move {
+            let response = router.oneshot(reload(key)).await.unwrap();
+            let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            serde_json::from_slice::<serde_json::Value>(&bytes).unwrap()
+        }
+    };
+
+    let denied = call("user-key").await;
+    assert_eq!(denied["error"]["code"], 1006);
+    assert!(registry.get_by_name("ruby-cat").is_none());
+
+    let reloaded = call("admin-key").await;
+    assert_eq!(
+        reloaded["result"]["plugins"],
+        serde_json::json!(["ruby-cat"])
+    );
+    assert_eq!(registry.get_by_extension("rb").unwrap().name(), "ruby-cat");
+}
@@ -164,9 +164,12 @@ async fn test_backup_cross_platform() {
     let config = BackupConfig {
         dir: backup_dir.to_string_lossy().to_string(),
         retention_days: 7,
+        max_sessions: None,
+        dedup: false,
+        auto_clean_on_exit: false,
     };
 
-    let service = BackupService::new(config);
+    let service = BackupService::new(config, None);
     service.init().await.unwrap();
 
     // Create a test file to backup
@@ -213,7 +216,10 @@ fn test_memory_monitoring_cross_platform() {
 
     // Create services
     let registry = Arc::new(ZenithRegistry::new());
-    let backup_service = Arc::new(zenith::internal::BackupService::new(config.backup.clone()));
+    let backup_service = Arc::new(zenith::internal::BackupService::new(
+        config.backup.clone(),
+        None,
+    ));
     let hash_cache = Arc::new(zenith::internal::HashCache::new());
     let _service = ZenithService::new(config, registry, backup_service, hash_cache, false);
 
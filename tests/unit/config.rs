@@ -48,7 +48,7 @@ use_default = true
     // 创建服务实例
     let app_config = AppConfig::default();
     let registry = ZenithRegistry::new();
-    let backup_service = BackupService::new(app_config.backup.clone());
+    let backup_service = BackupService::new(app_config.backup.clone(), None);
     let hash_cache = HashCache::new();
     let service = ZenithService::new(
         app_config,
@@ -78,6 +78,7 @@ async fn test_extension_specific_config() {
         enabled: true,
         config_path: Some(".rustfmt.toml".to_string()),
         use_default: false,
+        ..Default::default()
     };
 
     app_config.zeniths.insert("rs".to_string(), rust_settings);
@@ -89,7 +90,7 @@ async fn test_extension_specific_config() {
 
     // 创建服务实例
     let registry = ZenithRegistry::new();
-    let backup_service = BackupService::new(app_config.backup.clone());
+    let backup_service = BackupService::new(app_config.backup.clone(), None);
     let hash_cache = HashCache::new();
     let service = ZenithService::new(
         app_config,
@@ -101,7 +102,8 @@ async fn test_extension_specific_config() {
 
     // 获取针对该文件的Zenith配置
     let ext = "rs";
-    let zenith_config = service.create_zenith_config_for_file(&service.config, &test_file, ext);
+    let zenith_config =
+        service.create_zenith_config_for_file(&service.config, &test_file, ext, "rust");
 
     // 验证配置被正确应用
     assert_eq!(
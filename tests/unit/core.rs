@@ -12,7 +12,7 @@ use tempfile::TempDir;
 use walkdir::WalkDir;
 use zenith::core::traits::Zenith;
 use zenith::error::ZenithError;
-use zenith::utils::path::{is_hidden, validate_path};
+use zenith::utils::path::{escapes_root, is_hidden, validate_path};
 use zenith::zeniths::registry::ZenithRegistry;
 
 #[test]
@@ -45,6 +45,25 @@ fn test_validate_path_relative_safe() {
     assert!(validate_path(path).is_ok());
 }
 
+#[cfg(unix)]
+#[test]
+fn test_escapes_root_through_symlink() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path().join("root");
+    let outside = temp_dir.path().join("outside.rs");
+    fs::create_dir(&root).unwrap();
+    fs::write(root.join("inner.rs"), "").unwrap();
+    fs::write(&outside, "").unwrap();
+    std::os::unix::fs::symlink(&outside, root.join("out.rs")).unwrap();
+    std::os::unix::fs::symlink(root.join("inner.rs"), root.join("in.rs")).unwrap();
+
+    assert!(escapes_root(&root.join("out.rs"), &root));
+    assert!(!escapes_root(&root.join("in.rs"), &root));
+    assert!(!escapes_root(&root.join("inner.rs"), &root));
+    // Paths outside the root to begin with are not escapes
+    assert!(!escapes_root(&outside, &root));
+}
+
 #[test]
 fn test_is_hidden_dot_files() {
     let temp_dir = TempDir::new().unwrap();
@@ -116,6 +135,46 @@ fn test_registry_multiple_extensions() {
     assert!(registry.get_by_extension("rust").is_some());
 }
 
+#[test]
+fn test_registry_get_by_name() {
+    let registry = ZenithRegistry::new();
+    registry.register(std::sync::Arc::new(MockZenith::new("rust", &["rs"])));
+
+    assert_eq!(registry.get_by_name("rust").unwrap().name(), "rust");
+    assert!(registry.get_by_name("rs").is_none());
+}
+
+#[test]
+fn test_registry_unregister() {
+    let registry = ZenithRegistry::new();
+    registry.register(std::sync::Arc::new(MockZenith::new("rust", &["rs"])));
+    registry.register(std::sync::Arc::new(MockZenith::new(
+        "python",
+        &["py", "pyi"],
+    )));
+
+    assert_eq!(registry.get_by_name("python").unwrap().name(), "python");
+    assert!(registry.unregister("python"));
+    assert!(!registry.unregister("python"));
+
+    assert!(registry.get_by_name("python").is_none());
+    assert!(registry.get_by_extension("py").is_none());
+    assert!(registry.get_by_extension("pyi").is_none());
+    assert_eq!(registry.get_by_extension("rs").unwrap().name(), "rust");
+    assert_eq!(registry.list_all().len(), 1);
+}
+
+#[test]
+fn test_registry_reregister_replaces_extensions() {
+    let registry = ZenithRegistry::new();
+    registry.register(std::sync::Arc::new(MockZenith::new("web", &["js", "css"])));
+    registry.register(std::sync::Arc::new(MockZenith::new("web", &["js"])));
+
+    assert_eq!(registry.get_by_extension("js").unwrap().name(), "web");
+    assert!(registry.get_by_extension("css").is_none());
+    assert_eq!(registry.list_all().len(), 1);
+}
+
 #[test]
 fn test_registry_get_nonexistent() {
     let registry = ZenithRegistry::new();
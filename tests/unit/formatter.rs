@@ -7,10 +7,17 @@
 //! Tests for Zenith trait implementations and specific language formatters
 
 use crate::common::create_temp_dir;
+use crate::common::mocks::LineTrimFormatter;
 use std::path::PathBuf;
-use zenith::config::types::ZenithConfig;
+use std::sync::Arc;
+use zenith::config::types::{FormatError, ZenithConfig};
 use zenith::core::traits::Zenith;
-use zenith::internal::{PrettierZenith, PythonZenith, RustZenith};
+use zenith::error::ZenithError;
+use zenith::internal::{
+    ClangZenith, GoZenith, GraphqlZenith, IniZenith, JavaZenith, MarkdownZenith, PrettierZenith,
+    PythonZenith, RustZenith, ShellZenith, SqlZenith, TomlZenith, VueZenith, YamlZenith,
+    ZenithRegistry,
+};
 
 #[test]
 fn test_rust_zenith_name() {
@@ -270,3 +277,581 @@ fn test_zenith_trait_send_sync() {
     assert_send_sync::<PythonZenith>();
     assert_send_sync::<PrettierZenith>();
 }
+
+#[tokio::test]
+async fn test_graphql_zenith_schema_round_trip() {
+    let formatter = GraphqlZenith;
+    let path = PathBuf::from("schema.graphql");
+    let config = ZenithConfig::default();
+    let content = br#""""A user""" type User @key(fields: "id") { id: ID!  name(upper: Boolean = false): String @deprecated(reason: "use fullName") }"#;
+
+    let formatted = formatter.format(content, &path, &config).await.unwrap();
+    let formatted = String::from_utf8(formatted).unwrap();
+    assert_eq!(
+        formatted,
+        "\"A user\"\ntype User @key(fields: \"id\") {\n  id: ID!\n  name(upper: Boolean = false): String @deprecated(reason: \"use fullName\")\n}\n"
+    );
+
+    let reformatted = formatter
+        .format(formatted.as_bytes(), &path, &config)
+        .await
+        .unwrap();
+    assert_eq!(String::from_utf8(reformatted).unwrap(), formatted);
+}
+
+#[tokio::test]
+async fn test_graphql_zenith_query_round_trip() {
+    let formatter = GraphqlZenith;
+    let path = PathBuf::from("query.gql");
+    let config = ZenithConfig::default();
+    let content = b"query Q($id: ID!) { user(id: $id) { ...F name @include(if: true) } } fragment F on User { id }";
+
+    let formatted = formatter.format(content, &path, &config).await.unwrap();
+    let formatted = String::from_utf8(formatted).unwrap();
+    assert!(formatted.starts_with("query Q($id: ID!) {\n  user(id: $id) {\n    ...F\n"));
+    assert!(formatted.contains("name @include(if: true)"));
+
+    let reformatted = formatter
+        .format(formatted.as_bytes(), &path, &config)
+        .await
+        .unwrap();
+    assert_eq!(String::from_utf8(reformatted).unwrap(), formatted);
+}
+
+#[tokio::test]
+async fn test_graphql_zenith_rejects_invalid_and_commented_documents() {
+    let formatter = GraphqlZenith;
+    let path = PathBuf::from("broken.graphql");
+    let config = ZenithConfig::default();
+
+    assert!(formatter
+        .format(b"type User { id: ", &path, &config)
+        .await
+        .is_err());
+    assert!(formatter
+        .format(b"# comment\ntype User { id: ID }", &path, &config)
+        .await
+        .is_err());
+    assert!(formatter
+        .format(
+            b"type User { id: ID @tag(name: \"#not-a-comment\") }",
+            &path,
+            &config
+        )
+        .await
+        .is_ok());
+}
+
+#[tokio::test]
+async fn test_toml_zenith_formats_fixture() {
+    let formatter = TomlZenith;
+    let path = PathBuf::from("Cargo.toml");
+    let config = ZenithConfig::default();
+    let messy = include_str!("../fixtures/toml/messy.toml");
+    let golden = include_str!("../fixtures/toml/formatted.toml");
+
+    let formatted = formatter
+        .format(messy.as_bytes(), &path, &config)
+        .await
+        .unwrap();
+    assert_eq!(String::from_utf8(formatted).unwrap(), golden);
+
+    let reformatted = formatter
+        .format(golden.as_bytes(), &path, &config)
+        .await
+        .unwrap();
+    assert_eq!(String::from_utf8(reformatted).unwrap(), golden);
+}
+
+#[tokio::test]
+async fn test_yaml_zenith_formats_fixture() {
+    let formatter = YamlZenith;
+    let path = PathBuf::from("config.yaml");
+    let config = ZenithConfig::default();
+    let messy = include_str!("../fixtures/yaml/messy.yaml");
+    let golden = include_str!("../fixtures/yaml/formatted.yaml");
+
+    let formatted = formatter
+        .format(messy.as_bytes(), &path, &config)
+        .await
+        .unwrap();
+    assert_eq!(String::from_utf8(formatted).unwrap(), golden);
+
+    let reformatted = formatter
+        .format(golden.as_bytes(), &path, &config)
+        .await
+        .unwrap();
+    assert_eq!(String::from_utf8(reformatted).unwrap(), golden);
+}
+
+#[tokio::test]
+async fn test_yaml_zenith_documents_and_options() {
+    let formatter = YamlZenith;
+    let path = PathBuf::from("multi.yml");
+
+    let multi = formatter
+        .format(
+            b"a:   1\n---\nb:\n    - x\n",
+            &path,
+            &ZenithConfig::default(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(String::from_utf8(multi).unwrap(), "a: 1\n---\nb:\n  - x\n");
+
+    let config = ZenithConfig {
+        zenith_specific: serde_json::json!({ "sort_keys": true }),
+        ..Default::default()
+    };
+    let sorted = formatter
+        .format(b"b: 2\na:\n  d: 4\n  c: 3\n", &path, &config)
+        .await
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(sorted).unwrap(),
+        "a:\n  c: 3\n  d: 4\nb: 2\n"
+    );
+
+    // Anchors, aliases and comments cannot be re-emitted, so the file is
+    // skipped instead of reported as formatted
+    let untouched: [&[u8]; 2] = [
+        b"base: &base {x: 1}\nchild:    *base\n",
+        b"key:    value # why\n",
+    ];
+    for content in untouched {
+        let err = formatter
+            .format(content, &path, &ZenithConfig::default())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ZenithError::ZenithSkipped { .. }));
+        assert!(matches!(
+            FormatError::from(&err),
+            FormatError::Unsupported(Some(message)) if message.starts_with("Skipped: yaml")
+        ));
+    }
+
+    assert!(formatter
+        .format(b"key: [unclosed\n", &path, &ZenithConfig::default())
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn test_sql_zenith_formats_queries() {
+    let formatter = SqlZenith;
+    let path = PathBuf::from("query.sql");
+    let config = ZenithConfig::default();
+
+    let compact = formatter
+        .format(b"select a,b from t where x=1", &path, &config)
+        .await
+        .unwrap();
+    let compact = String::from_utf8(compact).unwrap();
+    assert_eq!(compact, "SELECT\n  a,\n  b\nFROM\n  t\nWHERE\n  x = 1\n");
+
+    let script = b"create table t (id int);\ninsert into t values (1);\n-- cleanup\ndelete from t;";
+    let formatted = formatter.format(script, &path, &config).await.unwrap();
+    let text = String::from_utf8(formatted.clone()).unwrap();
+    assert!(text.contains("(id int);\n\nINSERT INTO"));
+    assert!(text.contains("-- cleanup\nDELETE FROM"));
+
+    for output in [compact.into_bytes(), formatted] {
+        let reformatted = formatter.format(&output, &path, &config).await.unwrap();
+        assert_eq!(reformatted, output);
+    }
+}
+
+#[tokio::test]
+async fn test_sql_zenith_options() {
+    let formatter = SqlZenith;
+    let path = PathBuf::from("query.sql");
+    let config = ZenithConfig {
+        zenith_specific: serde_json::json!({ "uppercase": false, "indent_width": 4 }),
+        ..Default::default()
+    };
+
+    let formatted = formatter
+        .format(b"SELECT a FROM t", &path, &config)
+        .await
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(formatted).unwrap(),
+        "SELECT\n    a\nFROM\n    t\n"
+    );
+}
+
+#[tokio::test]
+async fn test_toml_zenith_rejects_invalid_documents() {
+    let formatter = TomlZenith;
+    let path = PathBuf::from("broken.toml");
+    let config = ZenithConfig::default();
+
+    assert!(formatter
+        .format(b"key = \"unterminated", &path, &config)
+        .await
+        .is_err());
+    assert!(!formatter.validate(b"[table\nkey = 1").await.unwrap());
+    assert_eq!(
+        formatter.format(b"\n\n", &path, &config).await.unwrap(),
+        b""
+    );
+}
+
+#[tokio::test]
+async fn test_rust_zenith_validate() {
+    let formatter = RustZenith;
+    assert!(formatter.validate(b"fn main() {}\n").await.unwrap());
+    assert!(!formatter.validate(b"fn main( {").await.unwrap());
+    assert!(!formatter.validate(&[0xff, 0xfe]).await.unwrap());
+}
+
+#[tokio::test]
+async fn test_vue_zenith_formats_each_block() {
+    let registry = Arc::new(ZenithRegistry::new());
+    registry.register(Arc::new(LineTrimFormatter::new("html", &["html"])));
+    registry.register(Arc::new(LineTrimFormatter::new("ts", &["ts"])));
+    registry.register(Arc::new(LineTrimFormatter::new("scss", &["scss"])));
+    let vue = Arc::new(VueZenith::new(Arc::downgrade(&registry)));
+    registry.register(vue.clone());
+
+    let path = PathBuf::from("Component.vue");
+    let config = ZenithConfig::default();
+    let content = "<template>\n      <div>\n   <template v-if=\"ok\"><span>hi</span></template>\n      </div>\n</template>\n\n<script setup lang=\"ts\">\n   const msg = 'hi'   \n</script>\n\n<i18n>\n  {  \"en\": {}  }\n</i18n>\n\n<style scoped lang=\"scss\">\n    .a { color: red; }\n</style>\n";
+
+    let formatted = vue
+        .format(content.as_bytes(), &path, &config)
+        .await
+        .unwrap();
+    let formatted = String::from_utf8(formatted).unwrap();
+    assert_eq!(
+        formatted,
+        "<template>\n  <div>\n  <template v-if=\"ok\"><span>hi</span></template>\n  </div>\n</template>\n\n<script setup lang=\"ts\">\nconst msg = 'hi'\n</script>\n\n<i18n>\n  {  \"en\": {}  }\n</i18n>\n\n<style scoped lang=\"scss\">\n.a { color: red; }\n</style>\n"
+    );
+
+    // Formatting is stable on its own output
+    let reformatted = vue
+        .format(formatted.as_bytes(), &path, &config)
+        .await
+        .unwrap();
+    assert_eq!(String::from_utf8(reformatted).unwrap(), formatted);
+
+    // The SFC formatter takes precedence for .vue files
+    assert_eq!(registry.get_by_extension("vue").unwrap().name(), "vue");
+}
+
+#[tokio::test]
+async fn test_vue_zenith_leaves_blocks_without_formatter() {
+    let registry = Arc::new(ZenithRegistry::new());
+    let vue = VueZenith::new(Arc::downgrade(&registry));
+    let content =
+        "<template lang=\"pug\">\n  div   hello\n</template>\n<script>\nlet   a\n</script>\n";
+
+    let formatted = vue
+        .format(
+            content.as_bytes(),
+            &PathBuf::from("A.vue"),
+            &ZenithConfig::default(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(String::from_utf8(formatted).unwrap(), content);
+
+    assert!(vue
+        .format(
+            b"<script>\nlet a",
+            &PathBuf::from("B.vue"),
+            &ZenithConfig::default()
+        )
+        .await
+        .is_err());
+}
+
+fn ini_config(options: serde_json::Value) -> ZenithConfig {
+    ZenithConfig {
+        zenith_specific: options,
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn test_ini_zenith_normalizes_spacing_and_comments() {
+    let formatter = IniZenith;
+    let path = PathBuf::from("app.ini");
+    let content = b"; global\nname=demo\n\n\n\n[server]\n; listen port\nport   =  8080\nhost=  localhost\n\n\n# duplicate keys are kept\nhost=example.org\n[empty]\nkey=\n; end\n";
+
+    let formatted = formatter
+        .format(content, &path, &ini_config(serde_json::Value::Null))
+        .await
+        .unwrap();
+    let formatted = String::from_utf8(formatted).unwrap();
+    assert_eq!(
+        formatted,
+        "; global\nname = demo\n\n[server]\n; listen port\nport = 8080\nhost = localhost\n\n# duplicate keys are kept\nhost = example.org\n\n[empty]\nkey =\n; end\n"
+    );
+
+    let reformatted = formatter
+        .format(
+            formatted.as_bytes(),
+            &path,
+            &ini_config(serde_json::Value::Null),
+        )
+        .await
+        .unwrap();
+    assert_eq!(String::from_utf8(reformatted).unwrap(), formatted);
+}
+
+#[tokio::test]
+async fn test_ini_zenith_sort_options() {
+    let formatter = IniZenith;
+    let path = PathBuf::from("app.ini");
+    let content = b"top=1\n[zeta]\nb=2\n; about a\na=1\nb=0\n[alpha]\nz=26\n\ny=25\n";
+
+    let keys_only = formatter
+        .format(
+            content,
+            &path,
+            &ini_config(serde_json::json!({ "sort_keys": true })),
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(keys_only).unwrap(),
+        "top = 1\n\n[zeta]\n; about a\na = 1\nb = 2\nb = 0\n\n[alpha]\ny = 25\nz = 26\n"
+    );
+
+    let sections_only = formatter
+        .format(
+            content,
+            &path,
+            &ini_config(serde_json::json!({ "sort_sections": true })),
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(sections_only).unwrap(),
+        "top = 1\n\n[alpha]\nz = 26\n\ny = 25\n\n[zeta]\nb = 2\n; about a\na = 1\nb = 0\n"
+    );
+}
+
+#[test]
+fn test_markdown_zenith_regexes_compile() {
+    assert!(MarkdownZenith::validate_regexes().is_ok());
+}
+
+#[test]
+fn test_markdown_rust_snippets_batched_like_single_runs() {
+    if std::process::Command::new("rustfmt")
+        .arg("--version")
+        .output()
+        .is_err()
+    {
+        return;
+    }
+
+    let snippets: Vec<String> = [
+        "fn main(){println!(\"hi\");}",
+        "let x=1;",
+        "use std::io;\nuse std::fmt;",
+        "struct Point{x:i32,y:i32}",
+        "fn open() {",
+        "use std::collections::HashMap;",
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect();
+
+    let batched = MarkdownZenith::format_rust_snippets(&snippets);
+    let single: Vec<Option<String>> = snippets
+        .iter()
+        .map(|s| MarkdownZenith::format_rust_snippets(std::slice::from_ref(s)).remove(0))
+        .collect();
+
+    assert_eq!(batched, single);
+    assert_eq!(
+        batched[0].as_deref(),
+        Some("fn main() {\n    println!(\"hi\");\n}\n")
+    );
+    assert!(batched[1].is_none());
+    assert!(batched[4].is_none());
+}
+
+#[tokio::test]
+async fn test_go_zenith_formats_with_gofmt() {
+    assert_eq!(GoZenith.name(), "gofmt");
+    assert_eq!(GoZenith.extensions(), &["go"]);
+
+    if std::process::Command::new("gofmt")
+        .arg("-l")
+        .stdin(std::process::Stdio::null())
+        .output()
+        .is_err()
+    {
+        return;
+    }
+
+    let formatted = GoZenith
+        .format(
+            b"package main\nfunc main(){\nx:=1\n_ = x}\n",
+            &PathBuf::from("main.go"),
+            &ZenithConfig::default(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(formatted).unwrap(),
+        "package main\n\nfunc main() {\n\tx := 1\n\t_ = x\n}\n"
+    );
+}
+
+#[test]
+fn test_java_zenith_name_and_extensions() {
+    assert_eq!(JavaZenith.name(), "google-java-format");
+    assert_eq!(JavaZenith.extensions(), &["java"]);
+}
+
+#[tokio::test]
+async fn test_java_zenith_formats_with_google_java_format() {
+    if !JavaZenith.is_available().await {
+        return;
+    }
+
+    let source = b"class A{void f(){int x=1;}}\n";
+    let path = PathBuf::from("A.java");
+    let google = JavaZenith
+        .format(source, &path, &ZenithConfig::default())
+        .await
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(google).unwrap(),
+        "class A {\n  void f() {\n    int x = 1;\n  }\n}\n"
+    );
+
+    let aosp = JavaZenith
+        .format(
+            source,
+            &path,
+            &ZenithConfig {
+                zenith_specific: serde_json::json!({ "style": "aosp" }),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    assert!(String::from_utf8(aosp)
+        .unwrap()
+        .contains("\n        int x = 1;"));
+}
+
+#[test]
+fn test_shell_zenith_name_and_extensions() {
+    assert_eq!(ShellZenith.name(), "shfmt");
+    let extensions = ShellZenith.extensions();
+    assert!(extensions.contains(&"sh"));
+    assert!(extensions.contains(&"bash"));
+}
+
+#[tokio::test]
+async fn test_shell_zenith_formats_with_shfmt() {
+    if !ShellZenith.is_available().await {
+        return;
+    }
+
+    let source = b"#!/bin/bash\nif true;then\necho hi &&\necho there\nfi\n";
+    let path = PathBuf::from("run.sh");
+    let formatted = ShellZenith
+        .format(source, &path, &ZenithConfig::default())
+        .await
+        .unwrap();
+    let formatted = String::from_utf8(formatted).unwrap();
+    assert!(formatted.starts_with("#!/bin/bash\nif true; then\n\techo hi &&\n"));
+
+    let configured = ShellZenith
+        .format(
+            source,
+            &path,
+            &ZenithConfig {
+                zenith_specific: serde_json::json!({ "indent_width": 2, "binary_next_line": true }),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    let configured = String::from_utf8(configured).unwrap();
+    assert!(configured.contains("\n  echo hi \\\n"));
+    assert!(configured
+        .lines()
+        .any(|line| line.trim_start() == "&& echo there"));
+}
+
+#[test]
+fn test_clang_zenith_name_and_extensions() {
+    assert_eq!(ClangZenith.name(), "clang-format");
+    let extensions = ClangZenith.extensions();
+    for ext in ["c", "cpp", "h", "hpp"] {
+        assert!(extensions.contains(&ext), "missing .{}", ext);
+    }
+}
+
+#[tokio::test]
+async fn test_clang_zenith_respects_clang_format_file() {
+    if !ClangZenith.is_available().await {
+        return;
+    }
+
+    let temp_dir = create_temp_dir();
+    let style_file = temp_dir.path().join(".clang-format");
+    std::fs::write(&style_file, "BasedOnStyle: LLVM\nIndentWidth: 8\n").unwrap();
+    let path = temp_dir.path().join("main.c");
+    let source = b"int main(){if(1){return 0;}return 1;}\n";
+
+    let formatted = ClangZenith
+        .format(
+            source,
+            &path,
+            &ZenithConfig {
+                custom_config_path: Some(style_file.clone()),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    assert!(String::from_utf8(formatted)
+        .unwrap()
+        .contains("\n        if (1) {\n"));
+
+    // Without a .clang-format the configured fallback style applies
+    std::fs::remove_file(&style_file).unwrap();
+    let formatted = ClangZenith
+        .format(
+            source,
+            &path,
+            &ZenithConfig {
+                zenith_specific: serde_json::json!({ "style": "GNU" }),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    assert!(String::from_utf8(formatted).unwrap().contains("main ()"));
+}
+
+#[tokio::test]
+async fn test_rust_zenith_formats_only_requested_lines() {
+    if !RustZenith.is_available().await {
+        return;
+    }
+
+    let temp_dir = create_temp_dir();
+    let path = temp_dir.path().join("lib.rs");
+    let source = b"fn a( ){let x=1;}\nfn b( ){let y=2;}\nfn c( ){let z=3;}\n";
+    let config = ZenithConfig {
+        range: Some((2, 2)),
+        ..Default::default()
+    };
+    let formatted = RustZenith.format(source, &path, &config).await.unwrap();
+    assert_eq!(
+        String::from_utf8(formatted).unwrap(),
+        "fn a( ){let x=1;}\nfn b() {\n    let y = 2;\n}\nfn c( ){let z=3;}\n"
+    );
+    assert!(RustZenith.supports_range());
+}
@@ -5,4 +5,5 @@
 
 mod config;
 mod core;
+#[cfg(feature = "full")]
 mod formatter;